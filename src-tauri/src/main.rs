@@ -4,9 +4,11 @@ use std::process::Command;
 use std::collections::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 use tokio::process::Command as AsyncCommand;
+use tokio::io::AsyncBufReadExt;
 use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 use tauri::Emitter;
+use tauri_plugin_notification::NotificationExt;
 use lazy_static::lazy_static;
 use portable_pty::{Child, CommandBuilder, MasterPty, PtySize, native_pty_system};
 use uuid::Uuid;
@@ -32,9 +34,64 @@ struct ProjectTodos {
 
 // Global session tracking for Claude Code
 lazy_static! {
-    static ref CURRENT_SESSION_ID: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+    // Latest Claude session ID per chat (tab ID, or project path when no tab is given),
+    // so chats in different tabs and projects don't share a conversation
+    static ref CHAT_SESSION_IDS: Arc<RwLock<HashMap<String, String>>> = Arc::new(RwLock::new(HashMap::new()));
     static ref TERMINAL_SESSIONS: Arc<RwLock<HashMap<String, TerminalSession>>> = Arc::new(RwLock::new(HashMap::new()));
     static ref ACTIVE_OUTPUT_HANDLERS: Arc<RwLock<HashSet<String>>> = Arc::new(RwLock::new(HashSet::new()));
+    // Pending prompts per project, drained sequentially by a worker task
+    static ref PROMPT_QUEUES: Arc<RwLock<HashMap<String, ProjectPromptQueue>>> = Arc::new(RwLock::new(HashMap::new()));
+    // Streaming Claude runs currently in flight, keyed by request ID
+    static ref ACTIVE_EXECUTIONS: Arc<RwLock<HashMap<String, ActiveExecution>>> = Arc::new(RwLock::new(HashMap::new()));
+    // Session ID from the system/init record of each forked run, keyed by request ID
+    static ref FORKED_SESSION_IDS: Arc<RwLock<HashMap<String, String>>> = Arc::new(RwLock::new(HashMap::new()));
+    // Cumulative USD cost per Claude session, fed by result records
+    static ref SESSION_COSTS: Arc<std::sync::Mutex<HashMap<String, f64>>> = Arc::new(std::sync::Mutex::new(HashMap::new()));
+    // Model and accumulated context tokens per Claude session
+    static ref SESSION_CONTEXT: Arc<std::sync::Mutex<HashMap<String, SessionContext>>> = Arc::new(std::sync::Mutex::new(HashMap::new()));
+    // Plans proposed in plan mode that are waiting for the user to approve or reject
+    static ref PENDING_PLANS: Arc<RwLock<HashMap<String, PendingPlan>>> = Arc::new(RwLock::new(HashMap::new()));
+    // Resolved path of the claude binary, looked up once and reset when the setting changes
+    static ref CLAUDE_BINARY: Arc<std::sync::Mutex<Option<String>>> = Arc::new(std::sync::Mutex::new(None));
+    // Environment of the user's login shell, captured on first use so spawned tools see
+    // the PATH, API keys and proxy settings from their shell profile
+    static ref LOGIN_SHELL_ENV: HashMap<String, String> = load_login_shell_env();
+    // Confirmation tokens for history deletion: token -> (Claude project directory, millis issued)
+    static ref PENDING_HISTORY_DELETIONS: Arc<std::sync::Mutex<HashMap<String, (String, u64)>>> = Arc::new(std::sync::Mutex::new(HashMap::new()));
+    // Real project directory per Claude project directory, persisted in ~/.claude/gui-project-paths.json
+    static ref PROJECT_PATH_CACHE: Arc<std::sync::Mutex<Option<HashMap<String, String>>>> = Arc::new(std::sync::Mutex::new(None));
+    // cwd read from a project dir's session files, with the dir's mtime at the time
+    static ref SESSION_CWD_CACHE: Arc<std::sync::Mutex<HashMap<std::path::PathBuf, DirCacheEntry<Option<String>>>>> = Arc::new(std::sync::Mutex::new(HashMap::new()));
+    // Real path of every project dir under a parent, with the parent's mtime at the time
+    static ref PROJECT_DIR_REAL_PATHS: std::sync::Mutex<HashMap<std::path::PathBuf, DirCacheEntry<HashMap<std::path::PathBuf, String>>>> = std::sync::Mutex::new(HashMap::new());
+    // Index of usage parsed from session JSONL files, opened on first use
+    static ref USAGE_DB: Arc<std::sync::Mutex<Option<rusqlite::Connection>>> = Arc::new(std::sync::Mutex::new(None));
+    // Signalled when a session reports new usage, so budgets get re-evaluated
+    static ref USAGE_CHANGED: Arc<tokio::sync::Notify> = Arc::new(tokio::sync::Notify::new());
+    // Claude project directory whose session files are watched for live usage updates
+    static ref WATCHED_USAGE_PROJECT: Arc<std::sync::Mutex<Option<String>>> = Arc::new(std::sync::Mutex::new(None));
+    // Budget alerts already sent, keyed by budget window, metric and threshold
+    static ref BUDGET_ALERTS_SENT: Arc<std::sync::Mutex<HashSet<String>>> = Arc::new(std::sync::Mutex::new(HashSet::new()));
+    // File watchers for opened projects, keyed by Claude project directory; dropping one stops it
+    static ref PROJECT_FILE_WATCHERS: Arc<std::sync::Mutex<HashMap<String, notify::RecommendedWatcher>>> = Arc::new(std::sync::Mutex::new(HashMap::new()));
+    // File paths per Claude project directory for fuzzy finding, kept current by the file watcher
+    static ref PROJECT_FILE_INDEX: Arc<std::sync::Mutex<HashMap<String, ProjectFileIndex>>> = Arc::new(std::sync::Mutex::new(HashMap::new()));
+    // Undo journal of file commands per project root, newest last
+    static ref FILE_UNDO_JOURNAL: Arc<std::sync::Mutex<HashMap<std::path::PathBuf, std::collections::VecDeque<FileJournalEntry>>>> = Arc::new(std::sync::Mutex::new(HashMap::new()));
+    // Context window overrides from the app settings, loaded on first use
+    static ref CONTEXT_WINDOW_SIZES: std::sync::Mutex<Option<HashMap<String, u64>>> = std::sync::Mutex::new(None);
+    // Files being written chunk by chunk, keyed by stream ID
+    static ref WRITE_STREAMS: Arc<std::sync::Mutex<HashMap<String, Arc<std::sync::Mutex<WriteStream>>>>> = Arc::new(std::sync::Mutex::new(HashMap::new()));
+    // Per-session environment overrides, kept in memory only so they never reach disk
+    static ref TERMINAL_SESSION_ENV: Arc<std::sync::Mutex<HashMap<String, HashMap<String, String>>>> = Arc::new(std::sync::Mutex::new(HashMap::new()));
+    // Serializes read-modify-write of the project registry file
+    static ref PROJECT_REGISTRY_LOCK: Arc<std::sync::Mutex<()>> = Arc::new(std::sync::Mutex::new(()));
+    // Serializes read-modify-write of the user-scoped project env file
+    static ref PROJECT_ENV_LOCK: Arc<std::sync::Mutex<()>> = Arc::new(std::sync::Mutex::new(()));
+    // CLAUDE.md watchers keyed by Claude project directory; dropping one stops it
+    static ref CLAUDE_MD_WATCHERS: Arc<std::sync::Mutex<HashMap<String, notify::RecommendedWatcher>>> = Arc::new(std::sync::Mutex::new(HashMap::new()));
+    // Last CLAUDE.md content seen per real project path, shared by the watchers and the GUI's own saves
+    static ref CLAUDE_MD_LAST_CONTENT: Arc<std::sync::Mutex<HashMap<std::path::PathBuf, Option<String>>>> = Arc::new(std::sync::Mutex::new(HashMap::new()));
 }
 
 // Terminal session management  
@@ -45,13 +102,279 @@ struct TerminalSession {
     child_process: Arc<Mutex<Box<dyn Child + Send + Sync>>>,
     project_path: String,
     active: bool,
+    // "claude" or "shell"
+    kind: String,
+    // User-facing tab name, set via rename_terminal_session
+    title: Option<String>,
+    pid: Option<u32>,
+    started_at: std::time::SystemTime,
+    // Recent output, replayed when the frontend reattaches
+    scrollback: Arc<Mutex<ScrollbackBuffer>>,
+    // Millis timestamp of the last read or write, for idle cleanup
+    last_activity: Arc<std::sync::atomic::AtomicU64>,
+    // Active asciicast recording, if the user opted in
+    recording: Arc<Mutex<Option<TerminalRecorder>>>,
+    // Hash of the last human-readable todo list parsed from this session's output
+    last_todo_hash: Arc<std::sync::atomic::AtomicU64>,
+    bytes_read: Arc<std::sync::atomic::AtomicU64>,
+    bytes_written: Arc<std::sync::atomic::AtomicU64>,
+    // Set when the user signals the process, so its exit isn't treated as a crash
+    stopped_by_user: Arc<std::sync::atomic::AtomicBool>,
+}
+
+// Writes PTY output as an asciicast v2 file: a JSON header line, then [time, code, data] events
+struct TerminalRecorder {
+    file: std::fs::File,
+    started: std::time::Instant,
+}
+
+impl TerminalRecorder {
+    fn create(path: &std::path::Path, width: u16, height: u16, title: Option<&str>) -> Result<TerminalRecorder, AppError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| AppError::io("Failed to create recordings directory", e))?;
+        }
+        
+        let mut file = std::fs::File::create(path)
+            .map_err(|e| AppError::io("Failed to create recording file", e))?;
+        let header = serde_json::json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": chrono::Utc::now().timestamp(),
+            "title": title
+        });
+        writeln!(file, "{}", header)
+            .map_err(|e| AppError::io("Failed to write recording header", e))?;
+        
+        Ok(TerminalRecorder { file, started: std::time::Instant::now() })
+    }
+    
+    // `code` is "o" for output or "r" for a resize ("COLSxROWS")
+    fn record(&mut self, code: &str, data: &str) {
+        let event = serde_json::json!([self.started.elapsed().as_secs_f64(), code, data]);
+        if let Err(e) = writeln!(self.file, "{}", event) {
+            println!("[WARN] Failed to write terminal recording: {}", e);
+        }
+    }
+}
+
+// What's needed to bring a terminal session back after the app restarts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct PersistedTerminalSession {
+    id: String,
+    project_path: String,
+    // Claude session to pass to --resume
+    resume_session_id: String,
+    title: Option<String>,
+    started_at: String,
+}
+
+const SCROLLBACK_MAX_LINES: usize = 10_000;
+
+// Turns raw PTY output into clean text lines for pattern matching: escape sequences
+// are stripped and lines split across reads are reassembled
+#[derive(Default)]
+struct PtyOutputParser {
+    partial: String,
+    recent: std::collections::VecDeque<String>,
+}
+
+const PTY_PARSER_RECENT_LINES: usize = 100;
+
+impl PtyOutputParser {
+    // Feed a chunk of output and get back the lines it completed
+    fn feed(&mut self, data: &str) -> Vec<String> {
+        self.partial.push_str(data);
+        
+        let mut lines = Vec::new();
+        while let Some(index) = self.partial.find('\n') {
+            let raw: String = self.partial.drain(..=index).collect();
+            let line = strip_ansi(raw.trim_end_matches(['\n', '\r']));
+            // A bare carriage return redraws the line, so only the last rendering counts
+            let line = line.rsplit('\r').next().unwrap_or_default().to_string();
+            
+            self.recent.push_back(line.clone());
+            if self.recent.len() > PTY_PARSER_RECENT_LINES {
+                self.recent.pop_front();
+            }
+            lines.push(line);
+        }
+        lines
+    }
+    
+    // The most recent clean lines, for patterns that span several lines
+    fn recent_text(&self) -> String {
+        self.recent.iter().map(|line| line.as_str()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+// Remove CSI, OSC and other escape sequences from terminal output
+fn strip_ansi(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters, then a final byte in @..~
+            Some('[') => {
+                for next in chars.by_ref() {
+                    if ('@'..='~').contains(&next) {
+                        break;
+                    }
+                }
+            }
+            // OSC: runs until BEL or ESC \
+            Some(']') => {
+                while let Some(next) = chars.next() {
+                    if next == '\x07' {
+                        break;
+                    }
+                    if next == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Character set selection takes one more byte
+            Some('(') | Some(')') => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    
+    output
+}
+
+// Batching of terminal_output events
+const PTY_COALESCE_WINDOW_MS: u64 = 16;
+const PTY_COALESCE_MAX_BYTES: usize = 64 * 1024;
+
+// Bounded ring of terminal output lines; the unterminated tail is kept separately
+#[derive(Default)]
+struct ScrollbackBuffer {
+    lines: std::collections::VecDeque<String>,
+    partial: String,
+}
+
+impl ScrollbackBuffer {
+    fn push(&mut self, data: &str) {
+        self.partial.push_str(data);
+        while let Some(index) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=index).collect();
+            self.lines.push_back(line);
+            if self.lines.len() > SCROLLBACK_MAX_LINES {
+                self.lines.pop_front();
+            }
+        }
+    }
+    
+    // Put an earlier session's history (and a separator) in front of this one
+    fn prepend(&mut self, earlier: &ScrollbackBuffer, separator: &str) {
+        let mut combined = ScrollbackBuffer::default();
+        combined.push(&earlier.contents());
+        combined.push(separator);
+        combined.push(&self.contents());
+        *self = combined;
+    }
+    
+    fn contents(&self) -> String {
+        let mut contents: String = self.lines.iter().map(|line| line.as_str()).collect();
+        contents.push_str(&self.partial);
+        contents
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct ResumableSession {
+    session_id: String,
+    first_prompt: Option<String>,
+    started_at: Option<String>,
+    last_active: Option<String>,
+    message_count: usize,
+    file_path: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct TerminalSessionDetails {
+    id: String,
+    kind: String,
+    title: Option<String>,
+    cwd: String,
+    pid: Option<u32>,
+    rows: u16,
+    cols: u16,
+    started_at: String,
+    uptime_secs: u64,
+    bytes_read: u64,
+    bytes_written: u64,
+    active: bool,
+    recording: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct TerminalSessionInfo {
+    id: String,
+    kind: String,
+    title: Option<String>,
+    cwd: String,
+    pid: Option<u32>,
+    uptime_secs: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Project {
     name: String,
     path: String,
+    // RFC3339 mtime of the project directory
     last_modified: String,
+    // RFC3339 time of the newest session entry
+    #[serde(default)]
+    last_activity: Option<String>,
+    // From the project registry
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    real_path: Option<String>,
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default)]
+    last_opened: Option<String>,
+    #[serde(default)]
+    open_count: u32,
+    // Other Claude project directories for the same real directory, merged into this one
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+// What the GUI knows about a project beyond its ~/.claude/projects directory,
+// stored in ~/.claude/gui-projects.json
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ProjectRecord {
+    id: String,
+    // The Claude project directory this record belongs to
+    claude_path: String,
+    #[serde(default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    real_path: Option<String>,
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default)]
+    last_opened: Option<String>,
+    // Hidden from get_claude_projects; the history stays on disk
+    #[serde(default)]
+    archived: bool,
+    // How many times mark_project_opened was called, for frecency ordering
+    #[serde(default)]
+    open_count: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -61,6 +384,33 @@ struct ChatMessage {
     timestamp: String,
 }
 
+// One message of a session transcript with its content blocks kept, so history can show
+// tool calls, their output and thinking the way the live chat does
+#[derive(Debug, Serialize)]
+struct ConversationEntry {
+    uuid: Option<String>,
+    parent_uuid: Option<String>,
+    // "user", "assistant" or "system"
+    role: String,
+    timestamp: String,
+    model: Option<String>,
+    blocks: Vec<ContentBlock>,
+    usage: Option<ClaudeUsage>,
+    // Part of a subagent's conversation rather than the main one
+    is_sidechain: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text { text: String },
+    Thinking { thinking: String },
+    ToolUse { id: String, name: String, input: serde_json::Value },
+    // Text parts of the output joined together; images are only counted
+    ToolResult { tool_use_id: String, content: String, is_error: bool, images: usize },
+    Image { media_type: Option<String> },
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct IDE {
     name: String,
@@ -78,6 +428,29 @@ struct FileInfo {
     is_directory: bool,
     modified_date: String,
     file_type: String,
+    // The path itself is a symbolic link; the other fields describe its target (or the
+    // link, when the target is missing)
+    #[serde(default)]
+    is_symlink: bool,
+    symlink_target: Option<String>,
+    // Text files up to LINE_COUNT_MAX_BYTES only, and only when requested
+    line_count: Option<usize>,
+    // Editor language id, e.g. "rust", "typescript"
+    language: Option<String>,
+    // None outside a git repository
+    git_tracked: Option<bool>,
+}
+
+// How scans treat symbolic links
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum SymlinkMode {
+    // Descend into linked directories; link cycles are detected and skipped
+    Follow,
+    Skip,
+    // List links as entries without descending into them
+    #[default]
+    Link,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -85,6 +458,15 @@ struct FileInfo {
 enum ClaudeStreamEvent {
     #[serde(rename = "status")]
     Status { message: String, timestamp: u64 },
+    #[serde(rename = "session_init")]
+    SessionInit {
+        session_id: Option<String>,
+        model: Option<String>,
+        tools: Vec<String>,
+        mcp_servers: Vec<McpServerInfo>,
+        cwd: Option<String>,
+        timestamp: u64,
+    },
     #[serde(rename = "thinking")]
     Thinking { message: String, timestamp: u64 },
     #[serde(rename = "token_usage")]
@@ -102,6 +484,28 @@ enum ClaudeStreamEvent {
     Response { content: String, timestamp: u64 },
     #[serde(rename = "error")]
     Error { message: String, timestamp: u64 },
+    #[serde(rename = "cost_report")]
+    CostReport {
+        session_id: Option<String>,
+        cost_usd: f64,
+        session_total_usd: f64,
+        duration_ms: u64,
+        timestamp: u64,
+    },
+    #[serde(rename = "budget_exceeded")]
+    BudgetExceeded { limit_usd: f64, spent_usd: f64, timestamp: u64 },
+    #[serde(rename = "retrying")]
+    Retrying {
+        attempt: u32,
+        max_attempts: u32,
+        delay_ms: u64,
+        reason: String,
+        timestamp: u64,
+    },
+    #[serde(rename = "plan_proposed")]
+    PlanProposed { plan_id: String, plan: String, steps: Vec<String>, timestamp: u64 },
+    #[serde(rename = "stderr")]
+    Stderr { line: String, kind: String, timestamp: u64 },
     #[serde(rename = "complete")]
     Complete { timestamp: u64 },
 }
@@ -119,6 +523,23 @@ struct ClaudeJsonEvent {
     total_cost_usd: Option<f64>,
     duration_ms: Option<u64>,
     error: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    tools: Option<Vec<String>>,
+    #[serde(default)]
+    mcp_servers: Option<Vec<McpServerInfo>>,
+    #[serde(default)]
+    cwd: Option<String>,
+    #[serde(default)]
+    is_error: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct McpServerInfo {
+    name: String,
+    #[serde(default)]
+    status: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -129,7 +550,9 @@ struct ClaudeMessage {
     #[serde(default)]
     message_type: Option<String>,
     role: String,
-    content: String, // This can be a JSON string containing an array of content blocks
+    // An array of content blocks in stream-json; older output sent text, or that array
+    // encoded as a JSON string
+    content: serde_json::Value,
     #[serde(default)]
     model: Option<String>,
     #[serde(default)]
@@ -138,11 +561,161 @@ struct ClaudeMessage {
     usage: Option<ClaudeUsage>,
 }
 
+impl ClaudeMessage {
+    fn content_blocks(&self) -> Option<Vec<serde_json::Value>> {
+        match &self.content {
+            serde_json::Value::Array(blocks) => Some(blocks.clone()),
+            serde_json::Value::String(text) => serde_json::from_str::<Vec<serde_json::Value>>(text).ok(),
+            _ => None,
+        }
+    }
+    
+    fn content_text(&self) -> String {
+        match &self.content {
+            serde_json::Value::String(text) => text.clone(),
+            content => content.to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct ClaudeUsage {
     input_tokens: u32,
     output_tokens: u32,
     total_tokens: Option<u32>,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<u32>,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u32>,
+}
+
+// Error returned by every command, serialized as { "kind": "...", "message": "..." }
+// so the frontend can branch on the kind instead of matching message text
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", content = "message")]
+enum AppError {
+    ClaudeNotInstalled(String),
+    AuthRequired(String),
+    RateLimited(String),
+    Overloaded(String),
+    BudgetExceeded(String),
+    PathNotFound(String),
+    AlreadyExists(String),
+    // A path outside the project roots the file commands may modify
+    AccessDenied(String),
+    InvalidInput(String),
+    NotFound(String),
+    SessionDead(String),
+    ProcessFailed(String),
+    Io(String),
+    Parse(String),
+    Other(String),
+}
+
+impl AppError {
+    fn message(&self) -> &str {
+        match self {
+            AppError::ClaudeNotInstalled(m) | AppError::AuthRequired(m) | AppError::RateLimited(m) |
+            AppError::Overloaded(m) | AppError::BudgetExceeded(m) | AppError::PathNotFound(m) |
+            AppError::AlreadyExists(m) | AppError::AccessDenied(m) | AppError::InvalidInput(m) | AppError::NotFound(m) |
+            AppError::SessionDead(m) | AppError::ProcessFailed(m) | AppError::Io(m) |
+            AppError::Parse(m) | AppError::Other(m) => m,
+        }
+    }
+    
+    // Wrap an I/O error with context, keeping missing paths and collisions distinguishable
+    fn io(context: &str, err: std::io::Error) -> AppError {
+        let message = format!("{}: {}", context, err);
+        match err.kind() {
+            std::io::ErrorKind::NotFound => AppError::PathNotFound(message),
+            std::io::ErrorKind::AlreadyExists => AppError::AlreadyExists(message),
+            _ => AppError::Io(message),
+        }
+    }
+    
+    // Failure to launch an external program; a missing claude binary gets its own kind
+    fn spawn(program: &str, err: std::io::Error) -> AppError {
+        if err.kind() == std::io::ErrorKind::NotFound && program == "claude" {
+            AppError::ClaudeNotInstalled(format!("Claude CLI not found: {}", err))
+        } else {
+            AppError::ProcessFailed(format!("Failed to execute {}: {}", program, err))
+        }
+    }
+    
+    fn parse(context: &str, err: serde_json::Error) -> AppError {
+        AppError::Parse(format!("{}: {}", context, err))
+    }
+    
+    fn db(context: &str, err: rusqlite::Error) -> AppError {
+        AppError::Io(format!("{}: {}", context, err))
+    }
+    
+    // Classify a failed Claude run: API failures by the error type and status the CLI
+    // reported, otherwise only the CLI's own login prompts count as auth errors
+    fn from_claude_failure(message: String) -> AppError {
+        if let Some(api_error) = ClaudeApiError::parse(&message) {
+            return api_error.into_app_error(message);
+        }
+        let lower = message.to_lowercase();
+        if lower.contains("invalid api key") || lower.contains("please run /login") || lower.contains("not logged in") {
+            AppError::AuthRequired(message)
+        } else {
+            AppError::ProcessFailed(message)
+        }
+    }
+}
+
+// An Anthropic API failure as the CLI reports it: "API Error: <status> <JSON body>",
+// where the body is {"type": "error", "error": {"type": "rate_limit_error", ...}}
+#[derive(Debug, Clone)]
+struct ClaudeApiError {
+    status: Option<u16>,
+    error_type: Option<String>,
+}
+
+impl ClaudeApiError {
+    fn parse(message: &str) -> Option<ClaudeApiError> {
+        let rest = &message[message.find("API Error:")? + "API Error:".len()..];
+        let rest = rest.trim_start();
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let status = rest[..digits].parse::<u16>().ok();
+        
+        let error_type = rest.find('{')
+            .and_then(|start| serde_json::Deserializer::from_str(&rest[start..]).into_iter::<serde_json::Value>().next())
+            .and_then(|body| body.ok())
+            .and_then(|body| body.get("error")?.get("type")?.as_str().map(|t| t.to_string()));
+        
+        (status.is_some() || error_type.is_some()).then_some(ClaudeApiError { status, error_type })
+    }
+    
+    fn into_app_error(self, message: String) -> AppError {
+        match (self.error_type.as_deref(), self.status) {
+            (Some("rate_limit_error"), _) | (None, Some(429)) => AppError::RateLimited(message),
+            (Some("overloaded_error"), _) | (None, Some(529)) => AppError::Overloaded(message),
+            (Some("authentication_error"), _) | (None, Some(401)) => AppError::AuthRequired(message),
+            _ => AppError::ProcessFailed(message),
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::Other(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::Other(message.to_string())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -153,7 +726,12 @@ struct PermissionResponse {
 }
 
 #[tauri::command]
-async fn get_claude_projects() -> Result<Vec<Project>, String> {
+async fn get_claude_projects() -> Result<Vec<Project>, AppError> {
+    scan_claude_projects(false)
+}
+
+// Projects under ~/.claude/projects, either the visible ones or only the archived ones
+fn scan_claude_projects(archived: bool) -> Result<Vec<Project>, AppError> {
     let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
     let claude_dir = home_dir.join(".claude").join("projects");
     
@@ -162,6 +740,7 @@ async fn get_claude_projects() -> Result<Vec<Project>, String> {
     }
     
     let mut projects = Vec::new();
+    let registry = read_project_registry();
     
     if let Ok(entries) = std::fs::read_dir(&claude_dir) {
         for entry in entries.flatten() {
@@ -172,2803 +751,11072 @@ async fn get_claude_projects() -> Result<Vec<Project>, String> {
                 // Get last modified time
                 let modified = entry.metadata()
                     .and_then(|m| m.modified())
-                    .map(|t| format!("{:?}", t))
-                    .unwrap_or_else(|_| "Unknown".to_string());
+                    .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+                    .unwrap_or_default();
                 
-                projects.push(Project {
+                let record = registry.iter().find(|record| record.claude_path == project_path);
+                if record.is_some_and(|r| r.archived) != archived {
+                    continue;
+                }
+                let real_path = known_real_project_path(&entry.path(), record.and_then(|r| r.real_path.as_deref()));
+                projects.push((real_path, Project {
                     name: project_name,
-                    path: project_path,
                     last_modified: modified,
-                });
+                    last_activity: last_session_activity(&entry.path()),
+                    id: record.map(|r| r.id.clone()),
+                    display_name: record.and_then(|r| r.display_name.clone()),
+                    real_path: record.and_then(|r| r.real_path.clone()),
+                    pinned: record.is_some_and(|r| r.pinned),
+                    last_opened: record.and_then(|r| r.last_opened.clone()),
+                    open_count: record.map(|r| r.open_count).unwrap_or(0),
+                    aliases: Vec::new(),
+                    path: project_path,
+                }));
             }
         }
     }
     
+    let mut projects = merge_duplicate_projects(projects);
+    // Pinned projects first
+    projects.sort_by_key(|project| !project.pinned);
     Ok(projects)
 }
 
-// System Information Commands
-#[tauri::command]
-async fn get_claude_version() -> Result<String, String> {
-    let output = Command::new("claude")
-        .arg("--version")
-        .output()
-        .map_err(|e| format!("Failed to get Claude version: {}", e))?;
-    
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    } else {
-        Err("Claude CLI not found or not accessible".to_string())
+// The real directory behind a Claude project dir, from what's known without guessing
+// from the encoded name
+fn known_real_project_path(project_dir: &std::path::Path, record_path: Option<&str>) -> Option<String> {
+    cached_project_path(&project_dir.to_string_lossy())
+        .or_else(|| record_path.map(|path| path.to_string()))
+        .or_else(|| cwd_from_session_files(project_dir))
+        .map(|path| path.trim_end_matches(['/', '\\']).to_string())
+        .filter(|path| !path.is_empty())
+}
+
+// The same directory can end up under several encoded names (the encoding is lossy), so
+// show one project per real directory, keeping the most recently active entry as primary
+fn merge_duplicate_projects(mut projects: Vec<(Option<String>, Project)>) -> Vec<Project> {
+    projects.sort_by(|a, b| b.1.last_activity.cmp(&a.1.last_activity));
+    
+    let mut merged: Vec<Project> = Vec::new();
+    let mut by_real_path: HashMap<String, usize> = HashMap::new();
+    for (real_path, mut project) in projects {
+        let real_path = match real_path {
+            Some(real_path) => real_path,
+            None => {
+                merged.push(project);
+                continue;
+            }
+        };
+        match by_real_path.get(&real_path) {
+            Some(&index) => {
+                let primary = &mut merged[index];
+                primary.pinned |= project.pinned;
+                primary.open_count += project.open_count;
+                primary.last_opened = primary.last_opened.take().max(project.last_opened);
+                primary.last_modified = primary.last_modified.clone().max(project.last_modified);
+                primary.display_name = primary.display_name.take().or(project.display_name);
+                primary.id = primary.id.take().or(project.id);
+                primary.aliases.push(project.path);
+            }
+            None => {
+                project.real_path = project.real_path.or_else(|| Some(real_path.clone()));
+                by_real_path.insert(real_path, merged.len());
+                merged.push(project);
+            }
+        }
     }
+    merged
 }
 
-#[tauri::command]
-async fn get_claude_config() -> Result<serde_json::Value, String> {
-    let output = Command::new("claude")
-        .args(&["config", "list"])
-        .output()
-        .map_err(|e| format!("Failed to get Claude config: {}", e))?;
+// Something worked out from a directory's contents, valid while its mtime is unchanged
+struct DirCacheEntry<T> {
+    modified: std::time::SystemTime,
+    value: T,
+}
+
+// Known real paths of the project dirs under `parent`. Rebuilt when a dir is added or
+// removed there (the parent's mtime changes) or the registry or path cache is written.
+fn project_dir_real_paths(parent: &std::path::Path) -> HashMap<std::path::PathBuf, String> {
+    let modified = std::fs::metadata(parent).and_then(|metadata| metadata.modified()).ok();
+    let mut cache = PROJECT_DIR_REAL_PATHS.lock().unwrap_or_else(|e| e.into_inner());
+    if let (Some(modified), Some(cached)) = (modified, cache.get(parent)) {
+        if cached.modified == modified {
+            return cached.value.clone();
+        }
+    }
     
-    if output.status.success() {
-        let config_str = String::from_utf8_lossy(&output.stdout);
-        serde_json::from_str(&config_str)
-            .map_err(|e| format!("Failed to parse Claude config: {}", e))
-    } else {
-        Err("Failed to get Claude configuration".to_string())
+    let registry = read_project_registry();
+    let real_paths: HashMap<std::path::PathBuf, String> = std::fs::read_dir(parent).into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|dir| dir.is_dir())
+        .filter_map(|dir| {
+            let dir_name = dir.to_string_lossy();
+            let record_path = registry.iter()
+                .find(|record| record.claude_path == dir_name)
+                .and_then(|record| record.real_path.as_deref());
+            let real_path = known_real_project_path(&dir, record_path)?;
+            Some((dir, real_path))
+        })
+        .collect();
+    if let Some(modified) = modified {
+        cache.insert(parent.to_path_buf(), DirCacheEntry { modified, value: real_paths.clone() });
     }
+    real_paths
 }
 
-#[tauri::command]
-async fn get_system_info() -> Result<serde_json::Value, String> {
-    let node_version = Command::new("node")
-        .arg("--version")
-        .output()
-        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
-        .unwrap_or_else(|_| "Not found".to_string());
-    
-    let npm_version = Command::new("npm")
-        .arg("--version")
-        .output()
-        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
-        .unwrap_or_else(|_| "Not found".to_string());
-    
-    // Check if Claude is installed via npm
-    let claude_npm_info = Command::new("npm")
-        .args(&["list", "-g", "@anthropic-ai/claude-code", "--json"])
-        .output()
-        .ok()
-        .and_then(|output| {
-            if output.status.success() {
-                serde_json::from_slice::<serde_json::Value>(&output.stdout).ok()
-            } else {
-                None
-            }
-        });
-    
-    let system_info = serde_json::json!({
-        "node_version": node_version,
-        "npm_version": npm_version,
-        "claude_npm_info": claude_npm_info,
-        "platform": std::env::consts::OS,
-        "arch": std::env::consts::ARCH
-    });
-    
-    Ok(system_info)
+fn invalidate_project_dir_real_paths() {
+    PROJECT_DIR_REAL_PATHS.lock().unwrap_or_else(|e| e.into_inner()).clear();
 }
 
-#[derive(serde::Serialize)]
-struct UsageStats {
-    total_input_tokens: u64,
-    total_output_tokens: u64,
-    total_cache_creation_tokens: u64,
-    total_cache_read_tokens: u64,
-    session_count: u32,
-    models_used: std::collections::HashMap<String, u32>,
-    daily_usage: std::collections::HashMap<String, DailyUsage>,
+// Other Claude project directories that belong to the same real directory as this one
+fn duplicate_project_dirs(project_path: &str) -> Vec<std::path::PathBuf> {
+    let project_dir = std::path::Path::new(project_path);
+    let parent = match project_dir.parent() {
+        Some(parent) => parent,
+        None => return Vec::new(),
+    };
+    let real_paths = project_dir_real_paths(parent);
+    let real_path = match real_paths.get(project_dir) {
+        Some(real_path) => real_path,
+        None => return Vec::new(),
+    };
+    
+    real_paths.iter()
+        .filter(|(dir, other)| *dir != project_dir && *other == real_path)
+        .map(|(dir, _)| dir.clone())
+        .collect()
 }
 
-#[derive(serde::Serialize)]
-struct DailyUsage {
-    input_tokens: u64,
-    output_tokens: u64,
-    sessions: u32,
+fn get_project_registry_file_path() -> Result<std::path::PathBuf, AppError> {
+    let home_dir = dirs::home_dir().ok_or(AppError::PathNotFound("Could not find home directory".to_string()))?;
+    Ok(home_dir.join(".claude").join("gui-projects.json"))
 }
 
-#[tauri::command]
-async fn get_usage_statistics(project_path: Option<String>) -> Result<UsageStats, String> {
-    let mut stats = UsageStats {
-        total_input_tokens: 0,
-        total_output_tokens: 0,
-        total_cache_creation_tokens: 0,
-        total_cache_read_tokens: 0,
-        session_count: 0,
-        models_used: std::collections::HashMap::new(),
-        daily_usage: std::collections::HashMap::new(),
+fn read_project_registry() -> Vec<ProjectRecord> {
+    let path = match get_project_registry_file_path() {
+        Ok(path) => path,
+        Err(_) => return Vec::new(),
     };
-    
-    let search_paths = if let Some(path) = project_path {
-        vec![path]
-    } else {
-        // Default to all projects - search through each project directory
-        let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
-        let projects_dir = home_dir.join(".claude").join("projects");
-        
-        let mut paths = Vec::new();
-        if let Ok(entries) = std::fs::read_dir(&projects_dir) {
-            for entry in entries.flatten() {
-                if entry.path().is_dir() {
-                    paths.push(entry.path().to_string_lossy().to_string());
-                }
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+    match serde_json::from_str(&content) {
+        Ok(records) => records,
+        Err(e) => {
+            // Keep the unreadable file for the user rather than overwriting it on the next write
+            let backup = path.with_extension(format!("json.corrupt-{}", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+            match std::fs::rename(&path, &backup) {
+                Ok(()) => println!("[WARN] Project registry is corrupt ({}), moved it to {}", e, backup.display()),
+                Err(rename_err) => println!("[WARN] Project registry is corrupt ({}) and could not be backed up: {}", e, rename_err),
             }
+            Vec::new()
         }
-        
-        if paths.is_empty() {
-            vec![projects_dir.to_string_lossy().to_string()]
-        } else {
-            paths
-        }
-    };
+    }
+}
+
+fn write_project_registry(records: &[ProjectRecord]) -> Result<(), AppError> {
+    let path = get_project_registry_file_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create registry directory", e))?;
+    }
+    let content = serde_json::to_string_pretty(records)
+        .map_err(|e| AppError::parse("Failed to serialize project registry", e))?;
+    invalidate_project_dir_real_paths();
+    // Written aside and renamed over, so a crash mid-write can't leave a truncated registry
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, content)
+        .map_err(|e| AppError::io("Failed to write project registry", e))?;
+    std::fs::rename(&temp_path, &path)
+        .map_err(|e| AppError::io("Failed to write project registry", e))
+}
+
+// Apply a change to a project's registry record, registering the project first if needed
+async fn update_project_record(project_path: String, update: impl FnOnce(&mut ProjectRecord)) -> Result<ProjectRecord, AppError> {
+    if !std::path::Path::new(&project_path).is_dir() {
+        return Err(AppError::PathNotFound(format!("Project not found: {}", project_path)));
+    }
+    let real_path = get_real_project_path(project_path.clone()).await.ok().flatten();
     
-    // Parse JSONL files for usage statistics
-    for search_path in &search_paths {
-        println!("[DEBUG] Searching for JSONL files in: {}", search_path);
-        if let Ok(entries) = std::fs::read_dir(search_path) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-                    println!("[DEBUG] Processing JSONL file: {:?}", path);
-                    if let Ok(content) = std::fs::read_to_string(&path) {
-                        stats.session_count += 1;
-                        let line_count = content.lines().count();
-                        println!("[DEBUG] File has {} lines", line_count);
-                        
-                        for line in content.lines() {
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
-                                // Check for usage data - it can be at root level or nested in message
-                                let usage_data = json.get("usage")
-                                    .or_else(|| json.get("message").and_then(|m| m.get("usage")));
-                                
-                                if let Some(usage) = usage_data {
-                                    if let Some(input_tokens) = usage.get("input_tokens").and_then(|v| v.as_u64()) {
-                                        println!("[DEBUG] Found input tokens: {}", input_tokens);
-                                        stats.total_input_tokens += input_tokens;
-                                    }
-                                    if let Some(output_tokens) = usage.get("output_tokens").and_then(|v| v.as_u64()) {
-                                        println!("[DEBUG] Found output tokens: {}", output_tokens);
-                                        stats.total_output_tokens += output_tokens;
-                                    }
-                                    if let Some(cache_creation) = usage.get("cache_creation_input_tokens").and_then(|v| v.as_u64()) {
-                                        stats.total_cache_creation_tokens += cache_creation;
-                                    }
-                                    if let Some(cache_read) = usage.get("cache_read_input_tokens").and_then(|v| v.as_u64()) {
-                                        stats.total_cache_read_tokens += cache_read;
-                                    }
-                                }
-                                
-                                // Track models used - check both root level and in message
-                                let model = json.get("model").and_then(|v| v.as_str())
-                                    .or_else(|| json.get("message").and_then(|m| m.get("model")).and_then(|v| v.as_str()));
-                                
-                                if let Some(model_str) = model {
-                                    *stats.models_used.entry(model_str.to_string()).or_insert(0) += 1;
-                                }
-                                
-                                // Track daily usage
-                                if let Some(timestamp) = json.get("timestamp").and_then(|v| v.as_str()) {
-                                    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(timestamp) {
-                                        let day = date.format("%Y-%m-%d").to_string();
-                                        let daily = stats.daily_usage.entry(day).or_insert(DailyUsage {
-                                            input_tokens: 0,
-                                            output_tokens: 0,
-                                            sessions: 0,
-                                        });
-                                        
-                                        // Add session count per day (only once per timestamp)
-                                        daily.sessions += 1;
-                                        
-                                        if let Some(usage) = usage_data {
-                                            if let Some(input_tokens) = usage.get("input_tokens").and_then(|v| v.as_u64()) {
-                                                daily.input_tokens += input_tokens;
-                                            }
-                                            if let Some(output_tokens) = usage.get("output_tokens").and_then(|v| v.as_u64()) {
-                                                daily.output_tokens += output_tokens;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    // Held from the read to the write so concurrent updates don't drop each other's changes
+    let _guard = PROJECT_REGISTRY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut records = read_project_registry();
+    let index = match records.iter().position(|record| record.claude_path == project_path) {
+        Some(index) => index,
+        None => {
+            records.push(ProjectRecord {
+                id: Uuid::new_v4().to_string(),
+                claude_path: project_path,
+                display_name: None,
+                real_path: None,
+                pinned: false,
+                last_opened: None,
+                archived: false,
+                open_count: 0,
+            });
+            records.len() - 1
         }
-    } // Close the search_paths loop
+    };
     
-    println!("[DEBUG] Final stats - Sessions: {}, Input tokens: {}, Output tokens: {}", 
-             stats.session_count, stats.total_input_tokens, stats.total_output_tokens);
+    let record = &mut records[index];
+    if real_path.is_some() {
+        record.real_path = real_path;
+    }
+    update(record);
+    let record = record.clone();
     
-    Ok(stats)
+    write_project_registry(&records)?;
+    Ok(record)
 }
 
-#[tauri::command]
-async fn update_claude_config(key: String, value: serde_json::Value) -> Result<(), String> {
-    let value_str = match value {
-        serde_json::Value::String(s) => s,
-        serde_json::Value::Bool(b) => b.to_string(),
-        serde_json::Value::Number(n) => n.to_string(),
-        _ => return Err("Unsupported config value type".to_string()),
+// How long to gather filesystem events before reporting them as one change
+const PROJECTS_WATCH_DEBOUNCE_MS: u64 = 500;
+
+// Watch ~/.claude/projects and emit projects_changed when projects or sessions appear or
+// change, including ones created by claude runs outside the GUI. Runs on its own thread.
+fn watch_claude_projects(app: tauri::AppHandle) {
+    use notify::Watcher;
+    
+    let projects_dir = match dirs::home_dir() {
+        Some(home_dir) => home_dir.join(".claude").join("projects"),
+        None => return,
     };
+    if let Err(e) = std::fs::create_dir_all(&projects_dir) {
+        println!("[WARN] Cannot watch {}: {}", projects_dir.display(), e);
+        return;
+    }
     
-    let output = Command::new("claude")
-        .args(&["config", "set", &key, &value_str])
-        .output()
-        .map_err(|e| format!("Failed to update Claude config: {}", e))?;
-    
-    if output.status.success() {
-        Ok(())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(tx) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            println!("[WARN] Failed to create projects watcher: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(&projects_dir, notify::RecursiveMode::Recursive) {
+        println!("[WARN] Failed to watch {}: {}", projects_dir.display(), e);
+        return;
+    }
+    println!("[INFO] Watching {} for project changes", projects_dir.display());
+    
+    let debounce = std::time::Duration::from_millis(PROJECTS_WATCH_DEBOUNCE_MS);
+    while let Ok(first) = rx.recv() {
+        let mut events = vec![first];
+        let deadline = std::time::Instant::now() + debounce;
+        while let Ok(event) = rx.recv_timeout(deadline.saturating_duration_since(std::time::Instant::now())) {
+            events.push(event);
+        }
+        
+        let mut changed_projects = HashSet::new();
+        let mut new_projects = HashSet::new();
+        for event in events.into_iter().flatten() {
+            if matches!(event.kind, notify::EventKind::Access(_)) {
+                continue;
+            }
+            for path in &event.paths {
+                // The project is the first path component under the projects directory
+                let project = match path.strip_prefix(&projects_dir).ok().and_then(|rel| rel.components().next()) {
+                    Some(component) => projects_dir.join(component).to_string_lossy().to_string(),
+                    None => continue,
+                };
+                if matches!(event.kind, notify::EventKind::Create(_)) && path.parent() == Some(projects_dir.as_path()) {
+                    new_projects.insert(project.clone());
+                }
+                changed_projects.insert(project);
+            }
+        }
+        
+        if !changed_projects.is_empty() {
+            let _ = app.emit("projects_changed", serde_json::json!({
+                "changedProjects": changed_projects.into_iter().collect::<Vec<_>>(),
+                "newProjects": new_projects.into_iter().collect::<Vec<_>>()
+            }));
+        }
     }
 }
 
-#[tauri::command]
-async fn check_claude_updates() -> Result<serde_json::Value, String> {
-    // Note: `claude update --check` might have TTY issues, so we'll simulate for now
-    // In a real implementation, this would check for updates
-    Ok(serde_json::json!({
-        "current_version": "1.0.56",
-        "latest_version": "1.0.56", 
-        "update_available": false,
-        "message": "Claude Code is up to date"
-    }))
+const PROJECT_FILES_WATCH_DEBOUNCE_MS: u64 = 300;
+
+// FSEvents and ReadDirectoryChangesW watch a whole tree with one subscription, so ignored
+// paths are just filtered from events there. inotify needs a watch per directory, which
+// node_modules or target alone can use up, so elsewhere only the directories that
+// aren't ignored are watched, one by one.
+const WATCH_DIRS_INDIVIDUALLY: bool = !cfg!(any(target_os = "macos", target_os = "windows"));
+
+// `dir` and the directories under it that aren't ignored (.gitignore'd ones or .git)
+fn watchable_directories(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut walker = project_walker(dir, false);
+    walker.hidden(false).filter_entry(|entry| entry.file_name() != ".git");
+    walker.build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_dir()))
+        .map(|entry| entry.into_path())
+        .collect()
 }
 
-#[tauri::command]
-async fn execute_claude_command(args: Vec<String>) -> Result<String, String> {
-    let output = Command::new("claude")
-        .args(&args)
-        .output()
-        .map_err(|e| format!("Failed to execute claude command: {}", e))?;
+fn watch_project_tree(watcher: &mut notify::RecommendedWatcher, dir: &std::path::Path) -> Result<(), AppError> {
+    use notify::Watcher;
     
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    if !WATCH_DIRS_INDIVIDUALLY {
+        return watcher.watch(dir, notify::RecursiveMode::Recursive)
+            .map_err(|e| AppError::Other(format!("Failed to watch {}: {}", dir.display(), e)));
     }
+    for subdir in watchable_directories(dir) {
+        // A directory can disappear between the walk and the watch
+        if let Err(e) = watcher.watch(&subdir, notify::RecursiveMode::NonRecursive) {
+            if subdir == dir {
+                return Err(AppError::Other(format!("Failed to watch {}: {}", dir.display(), e)));
+            }
+            println!("[WARN] Failed to watch {}: {}", subdir.display(), e);
+        }
+    }
+    Ok(())
 }
 
+// Watch the project's files (every root of its workspace) and emit debounced fs_changed
+// events, so the file tree and open editors pick up edits made by Claude
 #[tauri::command]
-async fn get_project_sessions(project_path: String) -> Result<Vec<serde_json::Value>, String> {
-    let mut sessions = Vec::new();
+async fn watch_project_files(app: tauri::AppHandle, project_path: String) -> Result<(), AppError> {
+    if PROJECT_FILE_WATCHERS.lock().unwrap().contains_key(&project_path) {
+        return Ok(());
+    }
+    let real_path = match get_real_project_path(project_path.clone()).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    let mut roots = workspace_roots(&real_path);
+    if roots.is_empty() {
+        roots.push(real_path);
+    }
+    let roots: Vec<std::path::PathBuf> = roots.iter().map(std::path::PathBuf::from).collect();
+    
+    // Walking the tree for directories to watch can take a while
+    let watch_roots = roots.clone();
+    let (watcher, rx, ignores) = tokio::task::spawn_blocking(move || {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)
+            .map_err(|e| AppError::Other(format!("Failed to create file watcher: {}", e)))?;
+        
+        // Each root's top-level .gitignore keeps build output and dependencies from flooding events
+        let mut ignores = Vec::new();
+        for root in watch_roots.iter().filter(|root| root.is_dir()) {
+            watch_project_tree(&mut watcher, root)?;
+            let mut builder = ignore::gitignore::GitignoreBuilder::new(root);
+            builder.add(root.join(".gitignore"));
+            if let Ok(gitignore) = builder.build() {
+                ignores.push(gitignore);
+            }
+        }
+        Ok::<_, AppError>((watcher, rx, ignores))
+    }).await
+        .map_err(|e| AppError::Other(format!("File watcher setup failed: {}", e)))??;
     
-    if let Ok(entries) = std::fs::read_dir(&project_path) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-                let file_name = path.file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
+    // Another call may have started watching while this one was setting up; keep the first
+    {
+        let mut watchers = PROJECT_FILE_WATCHERS.lock().unwrap();
+        if watchers.contains_key(&project_path) {
+            return Ok(());
+        }
+        watchers.insert(project_path.clone(), watcher);
+    }
+    println!("[INFO] Watching files of {}", project_path);
+    
+    std::thread::spawn(move || {
+        let is_ignored = |path: &std::path::Path| {
+            path.components().any(|c| c.as_os_str() == ".git")
+                || ignores.iter().any(|gitignore| {
+                    path.starts_with(gitignore.path())
+                        && gitignore.matched_path_or_any_parents(path, path.is_dir()).is_ignore()
+                })
+        };
+        
+        let debounce = std::time::Duration::from_millis(PROJECT_FILES_WATCH_DEBOUNCE_MS);
+        // Ends once unwatch_project_files drops the watcher and closes the channel
+        while let Ok(first) = rx.recv() {
+            let mut events = vec![first];
+            let deadline = std::time::Instant::now() + debounce;
+            while let Ok(event) = rx.recv_timeout(deadline.saturating_duration_since(std::time::Instant::now())) {
+                events.push(event);
+            }
+            
+            let mut created = HashSet::new();
+            let mut modified = HashSet::new();
+            let mut deleted = HashSet::new();
+            for event in events.into_iter().flatten() {
+                use notify::event::{EventKind, ModifyKind, RenameMode};
+                for (index, path) in event.paths.iter().enumerate() {
+                    if is_ignored(path) {
+                        continue;
+                    }
+                    let path = path.to_string_lossy().to_string();
+                    match event.kind {
+                        EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => { created.insert(path); }
+                        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => { deleted.insert(path); }
+                        // Renames reported as one event list the old path, then the new one
+                        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if index == 0 => { deleted.insert(path); }
+                        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => { created.insert(path); }
+                        EventKind::Modify(_) => { modified.insert(path); }
+                        _ => {}
+                    }
+                }
+            }
+            modified.retain(|path| !created.contains(path) && !deleted.contains(path));
+            update_project_file_index(&project_path, &roots, &created, &deleted);
+            
+            // New directories need watches of their own; they're found before taking the lock
+            if WATCH_DIRS_INDIVIDUALLY {
+                use notify::Watcher;
                 
-                // Read first and last few lines to get session info
-                if let Ok(content) = std::fs::read_to_string(&path) {
-                    let lines: Vec<&str> = content.lines().collect();
-                    let message_count = lines.len();
-                    
-                    let mut last_message = "No messages".to_string();
-                    let mut timestamp = "".to_string();
-                    
-                    // Get the last message
-                    if let Some(last_line) = lines.last() {
-                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(last_line) {
-                            if let Some(msg) = json.get("message") {
-                                if let Some(content) = msg.get("content") {
-                                    if let Some(content_str) = content.as_str() {
-                                        last_message = content_str.chars().take(100).collect::<String>();
-                                        if content_str.len() > 100 {
-                                            last_message.push_str("...");
-                                        }
-                                    }
-                                }
-                            }
-                            if let Some(ts) = json.get("timestamp") {
-                                if let Some(ts_str) = ts.as_str() {
-                                    timestamp = ts_str.to_string();
-                                }
-                            }
+                let new_dirs: Vec<std::path::PathBuf> = created.iter()
+                    .map(std::path::Path::new)
+                    .filter(|path| path.is_dir())
+                    .flat_map(watchable_directories)
+                    .collect();
+                if !new_dirs.is_empty() {
+                    let mut watchers = PROJECT_FILE_WATCHERS.lock().unwrap();
+                    let watcher = match watchers.get_mut(&project_path) {
+                        Some(watcher) => watcher,
+                        // Unwatched meanwhile
+                        None => break,
+                    };
+                    for dir in new_dirs {
+                        if let Err(e) = watcher.watch(&dir, notify::RecursiveMode::NonRecursive) {
+                            println!("[WARN] Failed to watch {}: {}", dir.display(), e);
                         }
                     }
-                    
-                    let session_info = serde_json::json!({
-                        "id": file_name,
-                        "name": file_name.replace("-", " ").replace("_", " "),
-                        "lastMessage": last_message,
-                        "timestamp": timestamp,
-                        "messageCount": message_count,
-                        "filePath": path.to_string_lossy()
-                    });
-                    
-                    sessions.push(session_info);
                 }
             }
+            
+            if created.is_empty() && modified.is_empty() && deleted.is_empty() {
+                continue;
+            }
+            let _ = app.emit("fs_changed", serde_json::json!({
+                "projectPath": project_path,
+                "created": created.into_iter().collect::<Vec<_>>(),
+                "modified": modified.into_iter().collect::<Vec<_>>(),
+                "deleted": deleted.into_iter().collect::<Vec<_>>()
+            }));
         }
-    }
-    
-    // Sort by timestamp (newest first)
-    sessions.sort_by(|a, b| {
-        let ts_a = a.get("timestamp").and_then(|t| t.as_str()).unwrap_or("");
-        let ts_b = b.get("timestamp").and_then(|t| t.as_str()).unwrap_or("");
-        ts_b.cmp(ts_a)
     });
     
-    Ok(sessions)
+    Ok(())
 }
 
 #[tauri::command]
-async fn detect_available_ides() -> Result<Vec<IDE>, String> {
-    let mut ides = Vec::new();
-    
-    // Common IDEs to detect
-    let ide_configs = vec![
-        ("Visual Studio Code", "code", vec![]),
-        ("VSCode Insiders", "code-insiders", vec![]),
-        ("Sublime Text", "subl", vec![]),
-        ("Atom", "atom", vec![]),
-        ("WebStorm", "webstorm", vec![]),
-        ("IntelliJ IDEA", "idea", vec![]),
-        ("PhpStorm", "phpstorm", vec![]),
-        ("PyCharm", "pycharm", vec![]),
-        ("Vim", "vim", vec![]),
-        ("Neovim", "nvim", vec![]),
-        ("Emacs", "emacs", vec![]),
-        ("Nano", "nano", vec![]),
-    ];
-    
-    for (name, command, default_args) in ide_configs {
-        let available = Command::new("which")
-            .arg(command)
-            .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false);
-        
-        ides.push(IDE {
-            name: name.to_string(),
-            command: command.to_string(),
-            args: default_args,
-            available,
-        });
+async fn unwatch_project_files(project_path: String) -> Result<(), AppError> {
+    if PROJECT_FILE_WATCHERS.lock().unwrap().remove(&project_path).is_some() {
+        println!("[INFO] Stopped watching files of {}", project_path);
     }
-    
-    // On macOS, also check for apps in /Applications
-    #[cfg(target_os = "macos")]
-    {
-        let app_configs = vec![
-            ("Visual Studio Code", "/Applications/Visual Studio Code.app/Contents/Resources/app/bin/code", vec![]),
-            ("Sublime Text", "/Applications/Sublime Text.app/Contents/SharedSupport/bin/subl", vec![]),
-            ("Xcode", "xed", vec![]),
-        ];
-        
-        for (name, path, default_args) in app_configs {
-            let available = std::path::Path::new(path).exists() || 
-                Command::new("which")
-                    .arg(path.split('/').last().unwrap_or(path))
-                    .output()
-                    .map(|output| output.status.success())
-                    .unwrap_or(false);
-            
-            if available && !ides.iter().any(|ide| ide.name == name) {
-                ides.push(IDE {
-                    name: name.to_string(),
-                    command: path.to_string(),
-                    args: default_args,
-                    available: true,
+    // Without the watcher the index would go stale
+    PROJECT_FILE_INDEX.lock().unwrap().remove(&project_path);
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+struct IndexedFile {
+    path: String,
+    relative_path: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FuzzyFileMatch {
+    path: String,
+    relative_path: String,
+    score: i64,
+    // Character positions in relative_path that matched the query, for highlighting
+    indices: Vec<usize>,
+}
+
+// Cap on indexed files so huge trees don't exhaust memory
+const FILE_INDEX_MAX_FILES: usize = 100_000;
+
+fn build_project_file_index(roots: &[std::path::PathBuf]) -> Vec<IndexedFile> {
+    let mut files = Vec::new();
+    for root in roots {
+        for entry in project_walker(root, false).build().flatten() {
+            if files.len() >= FILE_INDEX_MAX_FILES {
+                return files;
+            }
+            if entry.file_type().is_some_and(|ft| ft.is_file()) {
+                files.push(IndexedFile {
+                    path: entry.path().to_string_lossy().to_string(),
+                    relative_path: entry.path().strip_prefix(root).unwrap_or(entry.path()).to_string_lossy().to_string(),
                 });
             }
         }
     }
-    
-    Ok(ides)
+    files
 }
 
-#[tauri::command]
-async fn open_file_in_ide(ide_command: String, file_path: String, line: Option<u32>) -> Result<(), String> {
-    let mut cmd = Command::new(&ide_command);
-    
-    // Add line number support for common IDEs
-    if let Some(line_num) = line {
-        match ide_command.as_str() {
-            "code" | "code-insiders" => {
-                cmd.arg("--goto").arg(format!("{}:{}", file_path, line_num));
-            },
-            "subl" => {
-                cmd.arg(format!("{}:{}", file_path, line_num));
-            },
-            "atom" => {
-                cmd.arg(format!("{}:{}", file_path, line_num));
-            },
-            "vim" | "nvim" => {
-                cmd.arg(format!("+{}", line_num)).arg(&file_path);
-            },
-            _ => {
-                cmd.arg(&file_path);
+#[derive(Default)]
+struct ProjectFileIndex {
+    files: Vec<IndexedFile>,
+    // The paths in `files`, to skip duplicates without a scan
+    paths: HashSet<String>,
+    // While the first walk runs, watcher changes are queued here and replayed once it's done
+    building: bool,
+    pending_created: HashSet<String>,
+    pending_deleted: HashSet<String>,
+}
+
+impl ProjectFileIndex {
+    fn apply_changes(&mut self, roots: &[std::path::PathBuf], created: &HashSet<String>, deleted: &HashSet<String>) {
+        // A deleted directory takes everything under it along
+        if !deleted.is_empty() {
+            let paths = &mut self.paths;
+            self.files.retain(|file| {
+                let gone = deleted.iter().any(|path| file.path == *path || std::path::Path::new(&file.path).starts_with(path));
+                if gone {
+                    paths.remove(&file.path);
+                }
+                !gone
+            });
+        }
+        for path in created {
+            let file_path = std::path::Path::new(path);
+            if self.paths.contains(path) || !file_path.is_file() {
+                continue;
+            }
+            if let Some(root) = roots.iter().find(|root| file_path.starts_with(root)) {
+                self.paths.insert(path.clone());
+                self.files.push(IndexedFile {
+                    path: path.clone(),
+                    relative_path: file_path.strip_prefix(root).unwrap_or(file_path).to_string_lossy().to_string(),
+                });
             }
         }
-    } else {
-        cmd.arg(&file_path);
     }
-    
-    cmd.spawn()
-        .map_err(|e| format!("Failed to open file in IDE: {}", e))?;
-    
-    Ok(())
+}
+
+// Apply created and deleted paths reported by the file watcher to an existing index
+fn update_project_file_index(project_path: &str, roots: &[std::path::PathBuf], created: &HashSet<String>, deleted: &HashSet<String>) {
+    let mut index = PROJECT_FILE_INDEX.lock().unwrap();
+    match index.get_mut(project_path) {
+        Some(index) if index.building => {
+            index.pending_created.extend(created.iter().cloned());
+            index.pending_deleted.extend(deleted.iter().cloned());
+        }
+        Some(index) => index.apply_changes(roots, created, deleted),
+        None => {}
+    }
 }
 
 #[tauri::command]
-async fn open_project_in_ide(ide_command: String, project_path: String) -> Result<(), String> {
-    Command::new(&ide_command)
-        .arg(&project_path)
-        .spawn()
-        .map_err(|e| format!("Failed to open project in IDE: {}", e))?;
+async fn fuzzy_find_files(project_path: String, query: String, limit: Option<usize>) -> Result<Vec<FuzzyFileMatch>, AppError> {
+    use fuzzy_matcher::FuzzyMatcher;
     
-    Ok(())
-}
-
-#[tauri::command]
-async fn get_file_info(file_path: String) -> Result<FileInfo, String> {
-    let path = std::path::Path::new(&file_path);
+    let cached = match PROJECT_FILE_INDEX.lock().unwrap().get(&project_path) {
+        Some(index) if !index.building => Some(index.files.clone()),
+        _ => None,
+    };
+    let files = match cached {
+        Some(files) => files,
+        None => {
+            let real_path = match get_real_project_path(project_path.clone()).await? {
+                Some(path) => path,
+                None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+            };
+            let mut roots = workspace_roots(&real_path);
+            if roots.is_empty() {
+                roots.push(real_path);
+            }
+            let roots: Vec<std::path::PathBuf> = roots.iter().map(std::path::PathBuf::from).collect();
+            
+            // Only keep the index while a watcher is there to keep it up to date. It's
+            // registered before the walk so changes made during it are queued, not lost.
+            let keep = {
+                let watchers = PROJECT_FILE_WATCHERS.lock().unwrap();
+                let mut index = PROJECT_FILE_INDEX.lock().unwrap();
+                let keep = watchers.contains_key(&project_path) && !index.contains_key(&project_path);
+                if keep {
+                    index.insert(project_path.clone(), ProjectFileIndex { building: true, ..Default::default() });
+                }
+                keep
+            };
+            
+            let walk_roots = roots.clone();
+            let files = tokio::task::spawn_blocking(move || build_project_file_index(&walk_roots)).await
+                .map_err(|e| AppError::Other(format!("File indexing failed: {}", e)));
+            
+            let mut index = PROJECT_FILE_INDEX.lock().unwrap();
+            let files = match files {
+                Ok(files) => files,
+                Err(e) => {
+                    if keep {
+                        index.remove(&project_path);
+                    }
+                    return Err(e);
+                }
+            };
+            // Unless unwatch_project_files dropped it meanwhile
+            match index.get_mut(&project_path) {
+                Some(entry) if keep && entry.building => {
+                    entry.paths = files.iter().map(|file| file.path.clone()).collect();
+                    entry.files = files;
+                    entry.building = false;
+                    let created = std::mem::take(&mut entry.pending_created);
+                    let deleted = std::mem::take(&mut entry.pending_deleted);
+                    entry.apply_changes(&roots, &created, &deleted);
+                    entry.files.clone()
+                }
+                _ => files,
+            }
+        }
+    };
     
-    if !path.exists() {
-        return Err("File does not exist".to_string());
+    let limit = limit.unwrap_or(50);
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(files.into_iter()
+            .take(limit)
+            .map(|file| FuzzyFileMatch { path: file.path, relative_path: file.relative_path, score: 0, indices: Vec::new() })
+            .collect());
     }
     
-    let metadata = path.metadata()
-        .map_err(|e| format!("Failed to read file metadata: {}", e))?;
-    
-    let name = path.file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
-    
-    // Simple MIME type detection based on extension
-    let mime_type = match path.extension().and_then(|s| s.to_str()) {
-        Some("txt") | Some("md") | Some("markdown") => "text/plain",
-        Some("js") | Some("jsx") => "text/javascript",
-        Some("ts") | Some("tsx") => "text/typescript", 
-        Some("py") => "text/x-python",
-        Some("rs") => "text/x-rust",
-        Some("json") => "application/json",
-        Some("html") | Some("htm") => "text/html",
-        Some("css") => "text/css",
-        Some("png") => "image/png",
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("gif") => "image/gif",
-        Some("svg") => "image/svg+xml",
-        _ => "application/octet-stream",
-    }.to_string();
-    
-    let is_directory = metadata.is_dir();
-    let modified_date = metadata.modified()
-        .map(|time| {
-            let datetime: chrono::DateTime<chrono::Utc> = time.into();
-            datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+    let matcher = fuzzy_matcher::skim::SkimMatcherV2::default().smart_case();
+    let mut matches: Vec<FuzzyFileMatch> = files.into_iter()
+        .filter_map(|file| {
+            let (score, indices) = matcher.fuzzy_indices(&file.relative_path, query)?;
+            Some(FuzzyFileMatch { path: file.path, relative_path: file.relative_path, score, indices })
         })
-        .unwrap_or_else(|_| "Unknown".to_string());
-    
-    let file_type = if is_directory {
-        "directory".to_string()
-    } else {
-        path.extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("txt")
-            .to_string()
-    };
-    
-    Ok(FileInfo {
-        name,
-        path: file_path,
-        size: metadata.len(),
-        mime_type,
-        is_directory,
-        modified_date,
-        file_type,
-    })
+        .collect();
+    // Best score first; shorter paths win ties
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.relative_path.len().cmp(&b.relative_path.len())));
+    matches.truncate(limit);
+    Ok(matches)
 }
 
+#[derive(Debug, Serialize)]
+struct RecentFile {
+    path: String,
+    relative_path: String,
+    size: u64,
+    // RFC 3339
+    modified: String,
+}
+
+// Files most recently changed anywhere in the project (workspace roots included), newest
+// first. `since` is an RFC 3339 timestamp; ignored files are skipped.
 #[tauri::command]
-async fn get_project_files(project_path: String, pattern: Option<String>) -> Result<Vec<FileInfo>, String> {
-    let mut files = Vec::new();
-    
-    // First get the real project path (same as CLAUDE.md functionality)
+async fn get_recently_modified_files(project_path: String, limit: Option<usize>, since: Option<String>) -> Result<Vec<RecentFile>, AppError> {
+    let since = match since {
+        Some(since) => Some(chrono::DateTime::parse_from_rfc3339(&since)
+            .map_err(|e| AppError::InvalidInput(format!("Invalid timestamp {}: {}", since, e)))?
+            .with_timezone(&chrono::Utc)),
+        None => None,
+    };
     let real_path = match get_real_project_path(project_path).await? {
         Some(path) => path,
-        None => return Err("Could not find real project path".to_string())
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
     };
-    
-    let path = std::path::Path::new(&real_path);
-    
-    if !path.exists() {
-        return Err("Real project path does not exist".to_string());
+    let mut roots = workspace_roots(&real_path);
+    if roots.is_empty() {
+        roots.push(real_path);
     }
+    let limit = limit.unwrap_or(50);
     
-    fn scan_directory(dir: &std::path::Path, files: &mut Vec<FileInfo>, pattern: &Option<String>) -> Result<(), String> {
-        let entries = std::fs::read_dir(dir)
-            .map_err(|e| format!("Failed to read directory: {}", e))?;
-        
-        for entry in entries.flatten() {
-            let path = entry.path();
-            
-            // Skip hidden files and common ignore patterns
-            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                if name.starts_with('.') || name == "node_modules" || name == "target" || name == "dist" {
+    tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        for root in roots.iter().map(std::path::PathBuf::from) {
+            for entry in project_walker(&root, false).build().flatten() {
+                if !entry.file_type().is_some_and(|ft| ft.is_file()) {
                     continue;
                 }
-            }
-            
-            if path.is_file() {
-                if let Some(pattern_str) = pattern {
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if !name.contains(pattern_str) {
-                            continue;
-                        }
-                    }
-                }
-                
-                if let Ok(file_info) = get_file_info_sync(&path) {
-                    files.push(file_info);
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                };
+                let modified: chrono::DateTime<chrono::Utc> = match metadata.modified() {
+                    Ok(time) => time.into(),
+                    Err(_) => continue,
+                };
+                if since.is_some_and(|since| modified < since) {
+                    continue;
                 }
-            } else if path.is_dir() && files.len() < 1000 { // Limit to prevent overwhelming
-                let _ = scan_directory(&path, files, pattern);
+                files.push((modified, RecentFile {
+                    path: entry.path().to_string_lossy().to_string(),
+                    relative_path: entry.path().strip_prefix(&root).unwrap_or(entry.path()).to_string_lossy().to_string(),
+                    size: metadata.len(),
+                    modified: modified.to_rfc3339(),
+                }));
             }
         }
         
-        Ok(())
+        files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        files.truncate(limit);
+        files.into_iter().map(|(_, file)| file).collect()
+    }).await
+        .map_err(|e| AppError::Other(format!("Recent files scan failed: {}", e)))
+}
+
+#[derive(Debug, Serialize)]
+struct ProjectOverview {
+    real_path: Option<String>,
+    // None when the project isn't a git repository
+    git_branch: Option<String>,
+    git_dirty: Option<bool>,
+    has_claude_md: bool,
+    session_count: usize,
+    last_session_at: Option<String>,
+    todos_pending: usize,
+    todos_in_progress: usize,
+    todos_completed: usize,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    cost_usd: f64,
+}
+
+// Trimmed stdout of a successful git command run in `dir`
+fn run_git(dir: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
     }
-    
-    scan_directory(path, &mut files, &pattern)?;
-    files.sort_by(|a, b| a.name.cmp(&b.name));
-    
-    Ok(files)
 }
 
-// New comprehensive file system commands
+// Everything the project dashboard card shows, in one call
 #[tauri::command]
-async fn read_file_content(file_path: String) -> Result<String, String> {
-    let path = std::path::Path::new(&file_path);
+async fn get_project_overview(project_path: String) -> Result<ProjectOverview, AppError> {
+    let real_path = get_real_project_path(project_path.clone()).await?
+        .filter(|path| std::path::Path::new(path).is_dir());
+    
+    let (git_branch, git_dirty) = match &real_path {
+        Some(dir) => match run_git(dir, &["rev-parse", "--abbrev-ref", "HEAD"]) {
+            Some(branch) => (Some(branch), run_git(dir, &["status", "--porcelain"]).map(|status| !status.is_empty())),
+            None => (None, None),
+        },
+        None => (None, None),
+    };
     
-    if !path.exists() {
-        return Err("File does not exist".to_string());
+    let has_claude_md = check_claude_md_exists(project_path.clone()).await.unwrap_or(false);
+    let session_count = project_session_files(&project_path).map(|files| files.len()).unwrap_or(0);
+    let last_session_at = last_session_activity(std::path::Path::new(&project_path));
+    
+    let todos = load_project_todos(project_path.clone()).await.unwrap_or_default();
+    let count_todos = |status: &str| todos.iter().filter(|todo| todo.status == status).count();
+    
+    let search_paths = vec![project_path];
+    let files = tokio::task::spawn_blocking(move || load_usage_files(&search_paths)).await
+        .map_err(|e| AppError::Other(format!("Usage scan failed: {}", e)))?;
+    let usage = usage_totals(&model_pricing_table(), files.iter().flat_map(|file| file.entries.iter()));
+    
+    Ok(ProjectOverview {
+        real_path,
+        git_branch,
+        git_dirty,
+        has_claude_md,
+        session_count,
+        last_session_at,
+        todos_pending: count_todos("pending"),
+        todos_in_progress: count_todos("in_progress"),
+        todos_completed: count_todos("completed"),
+        input_tokens: usage.input_tokens,
+        output_tokens: usage.output_tokens,
+        cache_creation_tokens: usage.cache_creation_tokens,
+        cache_read_tokens: usage.cache_read_tokens,
+        cost_usd: usage.cost_usd,
+    })
+}
+
+#[tauri::command]
+async fn get_project_registry() -> Result<Vec<ProjectRecord>, AppError> {
+    Ok(read_project_registry())
+}
+
+#[tauri::command]
+async fn pin_project(project_path: String, pinned: bool) -> Result<ProjectRecord, AppError> {
+    update_project_record(project_path, |record| record.pinned = pinned).await
+}
+
+// An empty name clears it, falling back to the directory name
+#[tauri::command]
+async fn set_project_display_name(project_path: String, display_name: String) -> Result<ProjectRecord, AppError> {
+    let display_name = Some(display_name.trim().to_string()).filter(|name| !name.is_empty());
+    update_project_record(project_path, |record| record.display_name = display_name).await
+}
+
+#[tauri::command]
+async fn archive_project(project_path: String) -> Result<ProjectRecord, AppError> {
+    update_project_record(project_path, |record| record.archived = true).await
+}
+
+#[tauri::command]
+async fn unarchive_project(project_path: String) -> Result<ProjectRecord, AppError> {
+    update_project_record(project_path, |record| record.archived = false).await
+}
+
+#[tauri::command]
+async fn list_archived_projects() -> Result<Vec<Project>, AppError> {
+    scan_claude_projects(true)
+}
+
+// Claude names project directories after the real path with every
+// non-alphanumeric character replaced by '-'
+fn encode_claude_project_dir(real_path: &str) -> String {
+    real_path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+// Create ~/.claude/projects/<encoded> with a .claude-project file pointing back at the
+// real path, returning the Claude project directory. The path is canonicalized first so a
+// project reached through a symlink or "..", or with a trailing slash, maps to one directory.
+fn register_project_dir(real_path: &str) -> Result<String, AppError> {
+    let real_path = std::fs::canonicalize(real_path)
+        .map_err(|_| AppError::PathNotFound(format!("Directory not found: {}", real_path)))?
+        .to_string_lossy()
+        .to_string();
+    let real_path = real_path.as_str();
+    let home_dir = dirs::home_dir().ok_or(AppError::PathNotFound("Could not find home directory".to_string()))?;
+    let project_dir = home_dir.join(".claude").join("projects").join(encode_claude_project_dir(real_path));
+    std::fs::create_dir_all(&project_dir)
+        .map_err(|e| AppError::io("Failed to create project directory", e))?;
+    
+    let metadata_path = project_dir.join(".claude-project");
+    if !metadata_path.exists() {
+        let metadata = serde_json::json!({
+            "path": real_path,
+            "createdAt": chrono::Utc::now().to_rfc3339(),
+        });
+        let content = serde_json::to_string_pretty(&metadata)
+            .map_err(|e| AppError::parse("Failed to serialize project metadata", e))?;
+        std::fs::write(&metadata_path, content)
+            .map_err(|e| AppError::io("Failed to write project metadata", e))?;
     }
     
-    if !path.is_file() {
-        return Err("Path is not a file".to_string());
+    let claude_path = project_dir.to_string_lossy().to_string();
+    cache_project_path(&claude_path, real_path);
+    Ok(claude_path)
+}
+
+#[tauri::command]
+async fn import_project(real_path: String) -> Result<Project, AppError> {
+    let real_path = std::fs::canonicalize(&real_path)
+        .map_err(|_| AppError::PathNotFound(format!("Directory not found: {}", real_path)))?;
+    if !real_path.is_dir() {
+        return Err(AppError::InvalidInput(format!("Not a directory: {}", real_path.display())));
     }
+    let real_path = real_path.to_string_lossy().to_string();
     
-    // Check file size (limit to 10MB for safety)
-    if let Ok(metadata) = path.metadata() {
-        if metadata.len() > 10 * 1024 * 1024 {
-            return Err("File too large (max 10MB)".to_string());
-        }
-    }
+    let claude_path = register_project_dir(&real_path)?;
+    // Importing a project that was archived brings it back
+    update_project_record(claude_path.clone(), |record| record.archived = false).await?;
     
-    std::fs::read_to_string(path)
-        .map_err(|e| format!("Failed to read file: {}", e))
+    scan_claude_projects(false)?
+        .into_iter()
+        .find(|project| project.path == claude_path)
+        .ok_or_else(|| AppError::NotFound(format!("Imported project not found: {}", claude_path)))
 }
 
-#[tauri::command]
-async fn write_file_content(file_path: String, content: String) -> Result<(), String> {
-    let path = std::path::Path::new(&file_path);
-    
-    // Ensure parent directory exists
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create parent directory: {}", e))?;
+// How long a deletion confirmation token stays valid
+const HISTORY_DELETION_TOKEN_TTL_MS: u64 = 5 * 60 * 1000;
+
+#[derive(Debug, Serialize)]
+struct HistoryDeletionRequest {
+    confirm_token: String,
+    session_count: usize,
+    total_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct HistoryDeletionResult {
+    deleted_sessions: usize,
+    // Where the sessions were moved to, when a backup was requested
+    backup_path: Option<String>,
+}
+
+// Canonical form of a Claude project directory, which must sit directly under
+// ~/.claude/projects, so history deletion can't be pointed at any other directory
+fn claude_project_dir(project_path: &str) -> Result<String, AppError> {
+    let projects_dir = dirs::home_dir()
+        .map(|home| home.join(".claude").join("projects"))
+        .and_then(|dir| std::fs::canonicalize(dir).ok())
+        .ok_or_else(|| AppError::PathNotFound("Could not find ~/.claude/projects".to_string()))?;
+    let resolved = std::fs::canonicalize(project_path)
+        .map_err(|e| AppError::io(&format!("Failed to resolve {}", project_path), e))?;
+    if resolved.parent() != Some(projects_dir.as_path()) || !resolved.is_dir() {
+        return Err(AppError::AccessDenied(format!("{} is not a Claude project directory", project_path)));
     }
-    
-    std::fs::write(path, content)
-        .map_err(|e| format!("Failed to write file: {}", e))
+    Ok(resolved.to_string_lossy().to_string())
+}
+
+fn project_session_files(project_path: &str) -> Result<Vec<std::path::PathBuf>, AppError> {
+    let entries = std::fs::read_dir(project_path)
+        .map_err(|e| AppError::io("Failed to read project directory", e))?;
+    Ok(entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("jsonl"))
+        .collect())
+}
+
+// The project directory plus any duplicates that resolve to the same real
+// directory, each of which must itself be a Claude project directory
+fn project_history_dirs(project_path: &str) -> Vec<String> {
+    let mut dirs = vec![project_path.to_string()];
+    dirs.extend(duplicate_project_dirs(project_path).into_iter()
+        .filter_map(|dir| claude_project_dir(&dir.to_string_lossy()).ok())
+        .filter(|dir| dir != project_path));
+    dirs
+}
+
+fn project_history_files(dirs: &[String]) -> Result<Vec<std::path::PathBuf>, AppError> {
+    let mut files = Vec::new();
+    for dir in dirs {
+        files.extend(project_session_files(dir)?);
+    }
+    Ok(files)
 }
 
+// First step of deleting a project's history: returns what would be removed and a
+// short-lived token that delete_project_history requires
 #[tauri::command]
-async fn create_file(file_path: String, content: Option<String>) -> Result<(), String> {
-    let path = std::path::Path::new(&file_path);
+async fn prepare_project_history_deletion(project_path: String) -> Result<HistoryDeletionRequest, AppError> {
+    let project_path = claude_project_dir(&project_path)?;
+    let files = project_history_files(&project_history_dirs(&project_path))?;
+    let total_bytes = files.iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    
+    let confirm_token = Uuid::new_v4().to_string();
+    let mut pending = PENDING_HISTORY_DELETIONS.lock().unwrap();
+    pending.retain(|_, (_, issued)| now_millis().saturating_sub(*issued) < HISTORY_DELETION_TOKEN_TTL_MS);
+    pending.insert(confirm_token.clone(), (project_path, now_millis()));
+    
+    Ok(HistoryDeletionRequest { confirm_token, session_count: files.len(), total_bytes })
+}
+
+// Remove a project's session files, optionally moving them to
+// ~/.claude/gui-history-backups/<project>-<timestamp> instead of deleting them outright
+#[tauri::command]
+async fn delete_project_history(project_path: String, confirm_token: String, backup: Option<bool>) -> Result<HistoryDeletionResult, AppError> {
+    let project_path = claude_project_dir(&project_path)?;
+    {
+        let mut pending = PENDING_HISTORY_DELETIONS.lock().unwrap();
+        match pending.remove(&confirm_token) {
+            Some((path, issued)) if path == project_path && now_millis().saturating_sub(issued) < HISTORY_DELETION_TOKEN_TTL_MS => {}
+            _ => return Err(AppError::InvalidInput("Invalid or expired confirmation token".to_string())),
+        }
+    }
     
-    if path.exists() {
-        return Err("File already exists".to_string());
+    let history_dirs = project_history_dirs(&project_path);
+    let files = project_history_files(&history_dirs)?;
+    let backup_dir = if backup.unwrap_or(false) {
+        let home_dir = dirs::home_dir().ok_or(AppError::PathNotFound("Could not find home directory".to_string()))?;
+        let project_name = std::path::Path::new(&project_path).file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "project".to_string());
+        let dir = home_dir.join(".claude").join("gui-history-backups")
+            .join(format!("{}-{}", project_name, chrono::Local::now().format("%Y%m%d-%H%M%S")));
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| AppError::io("Failed to create backup directory", e))?;
+        Some(dir)
+    } else {
+        None
+    };
+    
+    let mut deleted_sessions = 0;
+    for file in &files {
+        let result = match &backup_dir {
+            Some(dir) => {
+                let target = dir.join(file.file_name().unwrap_or_default());
+                // Rename fails across filesystems; fall back to copying
+                std::fs::rename(file, &target)
+                    .or_else(|_| std::fs::copy(file, &target).and_then(|_| std::fs::remove_file(file)))
+            }
+            None => std::fs::remove_file(file),
+        };
+        match result {
+            Ok(()) => deleted_sessions += 1,
+            Err(e) => return Err(AppError::io(&format!("Failed to remove {} (removed {} of {} sessions)", file.display(), deleted_sessions, files.len()), e)),
+        }
     }
     
-    // Ensure parent directory exists
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create parent directory: {}", e))?;
+    // Drop the project directories too once nothing is left in them
+    for dir in &history_dirs {
+        let is_empty = std::fs::read_dir(dir).map(|mut entries| entries.next().is_none()).unwrap_or(false);
+        if is_empty {
+            let _ = std::fs::remove_dir(dir);
+        }
     }
     
-    let file_content = content.unwrap_or_default();
-    std::fs::write(path, file_content)
-        .map_err(|e| format!("Failed to create file: {}", e))
+    println!("[INFO] Deleted {} sessions from {}", deleted_sessions, project_path);
+    Ok(HistoryDeletionResult {
+        deleted_sessions,
+        backup_path: backup_dir.map(|dir| dir.to_string_lossy().to_string()),
+    })
 }
 
 #[tauri::command]
-async fn create_directory(dir_path: String) -> Result<(), String> {
-    let path = std::path::Path::new(&dir_path);
-    
-    if path.exists() {
-        return Err("Directory already exists".to_string());
-    }
-    
-    std::fs::create_dir_all(path)
-        .map_err(|e| format!("Failed to create directory: {}", e))
+async fn mark_project_opened(project_path: String) -> Result<ProjectRecord, AppError> {
+    update_project_record(project_path, |record| {
+        record.last_opened = Some(chrono::Utc::now().to_rfc3339());
+        record.open_count = record.open_count.saturating_add(1);
+    }).await
+}
+
+// Open count weighted by how recently the project was last opened
+fn frecency_score(open_count: u32, last_opened: Option<&str>) -> f64 {
+    let age_days = match last_opened.and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok()) {
+        Some(opened) => (chrono::Utc::now() - opened.with_timezone(&chrono::Utc)).num_days(),
+        None => return 0.0,
+    };
+    let recency = match age_days {
+        ..=3 => 100.0,
+        4..=14 => 70.0,
+        15..=31 => 50.0,
+        32..=90 => 30.0,
+        _ => 10.0,
+    };
+    // Older registry entries were opened before counts were kept
+    f64::from(open_count.max(1)) * recency
 }
 
+// Projects that have been opened, most frecent first
 #[tauri::command]
-async fn delete_file(file_path: String) -> Result<(), String> {
-    let path = std::path::Path::new(&file_path);
-    
-    if !path.exists() {
-        return Err("File does not exist".to_string());
-    }
+async fn get_recent_projects(limit: Option<usize>) -> Result<Vec<Project>, AppError> {
+    let mut projects: Vec<(f64, Project)> = scan_claude_projects(false)?
+        .into_iter()
+        .map(|project| (frecency_score(project.open_count, project.last_opened.as_deref()), project))
+        .filter(|(score, _)| *score > 0.0)
+        .collect();
+    
+    projects.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| b.1.last_opened.cmp(&a.1.last_opened)));
+    Ok(projects.into_iter()
+        .map(|(_, project)| project)
+        .take(limit.unwrap_or(10))
+        .collect())
+}
+
+// System Information Commands
+#[tauri::command]
+async fn get_claude_version() -> Result<String, AppError> {
+    let output = Command::new(claude_binary())
+        .envs(LOGIN_SHELL_ENV.iter())
+        .arg("--version")
+        .output()
+        .map_err(|e| AppError::spawn("claude", e))?;
     
-    if path.is_file() {
-        std::fs::remove_file(path)
-            .map_err(|e| format!("Failed to delete file: {}", e))
-    } else if path.is_dir() {
-        std::fs::remove_dir_all(path)
-            .map_err(|e| format!("Failed to delete directory: {}", e))
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
     } else {
-        Err("Path is neither file nor directory".to_string())
+        Err(AppError::ClaudeNotInstalled("Claude CLI not found or not accessible".to_string()))
     }
 }
 
 #[tauri::command]
-async fn rename_file(old_path: String, new_path: String) -> Result<(), String> {
-    let old = std::path::Path::new(&old_path);
-    let new = std::path::Path::new(&new_path);
-    
-    if !old.exists() {
-        return Err("Source file does not exist".to_string());
-    }
-    
-    if new.exists() {
-        return Err("Destination already exists".to_string());
-    }
+async fn get_claude_config() -> Result<serde_json::Value, AppError> {
+    let output = Command::new(claude_binary())
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(&["config", "list"])
+        .output()
+        .map_err(|e| AppError::spawn("claude", e))?;
     
-    // Ensure parent directory of new path exists
-    if let Some(parent) = new.parent() {
-        std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create destination directory: {}", e))?;
+    if output.status.success() {
+        let config_str = String::from_utf8_lossy(&output.stdout);
+        serde_json::from_str(&config_str)
+            .map_err(|e| AppError::parse("Failed to parse Claude config", e))
+    } else {
+        Err(AppError::ProcessFailed("Failed to get Claude configuration".to_string()))
     }
-    
-    std::fs::rename(old, new)
-        .map_err(|e| format!("Failed to rename file: {}", e))
 }
 
 #[tauri::command]
-async fn get_directory_tree(dir_path: String) -> Result<serde_json::Value, String> {
-    // Get the real project path
-    let real_path = match get_real_project_path(dir_path).await? {
-        Some(path) => path,
-        None => return Err("Could not find real project path".to_string())
-    };
-    
-    let path = std::path::Path::new(&real_path);
+async fn get_system_info() -> Result<serde_json::Value, AppError> {
+    let node_version = Command::new("node")
+        .envs(LOGIN_SHELL_ENV.iter())
+        .arg("--version")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|_| "Not found".to_string());
     
-    if !path.exists() || !path.is_dir() {
-        return Err("Directory does not exist".to_string());
-    }
+    let npm_version = Command::new("npm")
+        .envs(LOGIN_SHELL_ENV.iter())
+        .arg("--version")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|_| "Not found".to_string());
     
-    fn build_tree(dir: &std::path::Path, max_depth: usize, current_depth: usize) -> Result<serde_json::Value, String> {
-        if current_depth > max_depth {
-            return Ok(serde_json::json!({
-                "name": dir.file_name().and_then(|n| n.to_str()).unwrap_or(""),
-                "path": dir.to_string_lossy(),
-                "type": "directory",
-                "children": []
-            }));
-        }
-        
-        let mut children = Vec::new();
-        
-        if let Ok(entries) = std::fs::read_dir(dir) {
+    // Check if Claude is installed via npm
+    let claude_npm_info = Command::new("npm")
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(&["list", "-g", "@anthropic-ai/claude-code", "--json"])
+        .output()
+        .ok()
+        .and_then(|output| {
+            if output.status.success() {
+                serde_json::from_slice::<serde_json::Value>(&output.stdout).ok()
+            } else {
+                None
+            }
+        });
+    
+    let system_info = serde_json::json!({
+        "node_version": node_version,
+        "npm_version": npm_version,
+        "claude_npm_info": claude_npm_info,
+        "platform": std::env::consts::OS,
+        "arch": std::env::consts::ARCH
+    });
+    
+    Ok(system_info)
+}
+
+#[derive(serde::Serialize)]
+struct UsageStats {
+    total_input_tokens: u64,
+    total_output_tokens: u64,
+    total_cache_creation_tokens: u64,
+    total_cache_read_tokens: u64,
+    session_count: u32,
+    // Assistant messages with usage per model
+    models_used: std::collections::HashMap<String, u32>,
+    daily_usage: std::collections::HashMap<String, DailyUsage>,
+    // Keyed by ISO week ("2025-W05") and month ("2025-01"); days, weeks and months are UTC
+    weekly_usage: HashMap<String, DailyUsage>,
+    monthly_usage: HashMap<String, DailyUsage>,
+    // Messages per local weekday (Monday first) and hour of day
+    activity_heatmap: [[u32; 24]; 7],
+    // Estimated from the pricing table; models without a price count as zero
+    total_cost_usd: f64,
+    model_costs: HashMap<String, f64>,
+    // What cache reads would have cost at the full input price, minus what they did cost
+    total_cache_savings_usd: f64,
+    model_cache_savings: HashMap<String, f64>,
+    // Keyed by Claude project directory name
+    projects: HashMap<String, ProjectUsage>,
+    // Most expensive first
+    sessions: Vec<SessionUsage>,
+}
+
+#[derive(serde::Serialize, Default)]
+struct ProjectUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    cost_usd: f64,
+    session_count: u32,
+    last_active: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct SessionUsage {
+    session_id: String,
+    project: String,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    cost_usd: f64,
+    message_count: u32,
+    first_timestamp: Option<String>,
+    last_timestamp: Option<String>,
+}
+
+// Usage over one day, week or month
+#[derive(serde::Serialize)]
+struct DailyUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+    // Distinct sessions with usage in the period, not messages
+    sessions: u32,
+    cost_usd: f64,
+    cache_savings_usd: f64,
+}
+
+// USD per million tokens
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+struct ModelPricing {
+    input: f64,
+    output: f64,
+    cache_write: f64,
+    cache_read: f64,
+}
+
+impl ModelPricing {
+    const fn new(input: f64, output: f64, cache_write: f64, cache_read: f64) -> Self {
+        ModelPricing { input, output, cache_write, cache_read }
+    }
+    
+    fn cost(&self, usage: &UsageEntry) -> f64 {
+        (usage.input_tokens as f64 * self.input
+            + usage.output_tokens as f64 * self.output
+            + usage.cache_creation_tokens as f64 * self.cache_write
+            + usage.cache_read_tokens as f64 * self.cache_read) / 1_000_000.0
+    }
+    
+    fn cache_savings(&self, usage: &UsageEntry) -> f64 {
+        usage.cache_read_tokens as f64 * (self.input - self.cache_read) / 1_000_000.0
+    }
+}
+
+// Built-in prices keyed by part of the model name, most specific first
+const DEFAULT_MODEL_PRICING: &[(&str, ModelPricing)] = &[
+    ("opus-4-5", ModelPricing::new(5.0, 25.0, 6.25, 0.5)),
+    ("opus", ModelPricing::new(15.0, 75.0, 18.75, 1.5)),
+    ("sonnet", ModelPricing::new(3.0, 15.0, 3.75, 0.3)),
+    ("haiku-4-5", ModelPricing::new(1.0, 5.0, 1.25, 0.1)),
+    ("3-5-haiku", ModelPricing::new(0.8, 4.0, 1.0, 0.08)),
+    ("haiku", ModelPricing::new(0.25, 1.25, 0.3, 0.03)),
+];
+
+// User overrides from the app settings first, then the built-in prices
+fn model_pricing_table() -> Vec<(String, ModelPricing)> {
+    let mut table: Vec<(String, ModelPricing)> = read_app_settings()
+        .map(|settings| settings.model_pricing.into_iter().collect())
+        .unwrap_or_default();
+    // Longer overrides are more specific, so they get the first chance to match
+    table.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+    table.extend(DEFAULT_MODEL_PRICING.iter().map(|(name, pricing)| (name.to_string(), *pricing)));
+    table
+}
+
+// Models without a price count as zero
+fn usage_cost(table: &[(String, ModelPricing)], usage: &UsageEntry) -> f64 {
+    usage.model.as_deref()
+        .and_then(|model| pricing_for_model(table, model))
+        .map(|pricing| pricing.cost(usage))
+        .unwrap_or(0.0)
+}
+
+fn usage_cache_savings(table: &[(String, ModelPricing)], usage: &UsageEntry) -> f64 {
+    usage.model.as_deref()
+        .and_then(|model| pricing_for_model(table, model))
+        .map(|pricing| pricing.cache_savings(usage))
+        .unwrap_or(0.0)
+}
+
+fn pricing_for_model<'a>(table: &'a [(String, ModelPricing)], model: &str) -> Option<&'a ModelPricing> {
+    table.iter()
+        .find(|(name, _)| model.contains(name.as_str()))
+        .map(|(_, pricing)| pricing)
+}
+
+// Usage of a single assistant message, as recorded in a session JSONL file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct UsageEntry {
+    timestamp: Option<String>,
+    model: Option<String>,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    // API message and request IDs, used to drop records copied into resumed sessions
+    message_id: Option<String>,
+    request_id: Option<String>,
+}
+
+// Bump when the schema or UsageEntry changes so the index is rebuilt from scratch
+const USAGE_DB_VERSION: i32 = 1;
+
+fn open_usage_db() -> Result<rusqlite::Connection, AppError> {
+    let home_dir = dirs::home_dir().ok_or(AppError::PathNotFound("Could not find home directory".to_string()))?;
+    let db_path = home_dir.join(".claude").join("gui-usage.db");
+    // Superseded by the database
+    let _ = std::fs::remove_file(home_dir.join(".claude").join("gui-usage-cache.json"));
+    if let Some(parent) = db_path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create usage database directory", e))?;
+    }
+    
+    let conn = rusqlite::Connection::open(&db_path)
+        .map_err(|e| AppError::db("Failed to open usage database", e))?;
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| AppError::db("Failed to read usage database version", e))?;
+    if version != USAGE_DB_VERSION {
+        conn.execute_batch("DROP TABLE IF EXISTS usage; DROP TABLE IF EXISTS usage_files;")
+            .map_err(|e| AppError::db("Failed to reset usage database", e))?;
+    }
+    
+    conn.execute_batch(&format!("
+        PRAGMA journal_mode = WAL;
+        CREATE TABLE IF NOT EXISTS usage_files (
+            path TEXT PRIMARY KEY,
+            project TEXT NOT NULL,
+            modified INTEGER NOT NULL,
+            size INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS usage (
+            file_path TEXT NOT NULL,
+            project TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            timestamp TEXT,
+            model TEXT,
+            input_tokens INTEGER NOT NULL,
+            output_tokens INTEGER NOT NULL,
+            cache_creation_tokens INTEGER NOT NULL,
+            cache_read_tokens INTEGER NOT NULL,
+            message_id TEXT,
+            request_id TEXT
+        );
+        CREATE INDEX IF NOT EXISTS usage_by_file ON usage(file_path);
+        CREATE INDEX IF NOT EXISTS usage_by_project ON usage(project, timestamp);
+        CREATE INDEX IF NOT EXISTS usage_by_message ON usage(message_id, request_id);
+        PRAGMA user_version = {};
+    ", USAGE_DB_VERSION))
+        .map_err(|e| AppError::db("Failed to create usage database", e))?;
+    
+    Ok(conn)
+}
+
+fn with_usage_db<T>(f: impl FnOnce(&mut rusqlite::Connection) -> Result<T, AppError>) -> Result<T, AppError> {
+    let mut db = USAGE_DB.lock().unwrap();
+    if db.is_none() {
+        *db = Some(open_usage_db()?);
+    }
+    f(db.as_mut().unwrap())
+}
+
+fn usage_project_name(search_path: &str) -> String {
+    std::path::Path::new(search_path).file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| search_path.to_string())
+}
+
+// JSONL files parsed per batch; each batch is written to the index (and reported) as it finishes
+const USAGE_INDEX_BATCH_SIZE: usize = 64;
+
+// Bring the index up to date for the given project directories: parse new or changed
+// JSONL files (by mtime and size) and drop deleted ones. Files are parsed in parallel
+// outside the database lock, so queries see each batch as soon as it lands. Returns how
+// many files changed; `progress` gets (parsed, total) after every batch.
+fn index_usage_files(search_paths: &[String], progress: impl Fn(usize, usize)) -> Result<usize, AppError> {
+    use rayon::prelude::*;
+    
+    let projects: Vec<String> = search_paths.iter().map(|path| usage_project_name(path)).collect();
+    let mut indexed: HashMap<String, (u64, u64)> = with_usage_db(|conn| {
+        let mut stmt = conn.prepare_cached("SELECT path, modified, size FROM usage_files WHERE project = ?1")
+            .map_err(|e| AppError::db("Failed to read usage index", e))?;
+        let mut indexed = HashMap::new();
+        for project in &projects {
+            let rows = stmt.query_map([project], |row| Ok((row.get::<_, String>(0)?, (row.get::<_, i64>(1)? as u64, row.get::<_, i64>(2)? as u64))))
+                .map_err(|e| AppError::db("Failed to read usage index", e))?;
+            indexed.extend(rows.flatten());
+        }
+        Ok(indexed)
+    })?;
+    
+    // (path, project, modified, size) of every file that needs parsing
+    let mut stale = Vec::new();
+    for (search_path, project) in search_paths.iter().zip(&projects) {
+        if let Ok(entries) = std::fs::read_dir(search_path) {
             for entry in entries.flatten() {
                 let path = entry.path();
-                let name = path.file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("")
-                    .to_string();
-                
-                // Skip hidden files and common ignore patterns
-                if name.starts_with('.') || name == "node_modules" || name == "target" || 
-                   name == "dist" || name == ".git" || name == "build" {
+                if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
                     continue;
                 }
+                let metadata = match entry.metadata() {
+                    Ok(metadata) => metadata,
+                    Err(_) => continue,
+                };
+                let modified = metadata.modified().ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or(0);
+                let size = metadata.len();
                 
-                if path.is_dir() {
-                    children.push(build_tree(&path, max_depth, current_depth + 1)?);
-                } else {
-                    let metadata = path.metadata().ok();
-                    let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
-                    let modified = metadata.and_then(|m| m.modified().ok())
-                        .map(|time| {
-                            let datetime: chrono::DateTime<chrono::Utc> = time.into();
-                            datetime.format("%Y-%m-%d %H:%M:%S").to_string()
-                        })
-                        .unwrap_or_else(|| "Unknown".to_string());
-                    
-                    children.push(serde_json::json!({
-                        "name": name,
-                        "path": path.to_string_lossy(),
-                        "type": "file",
-                        "size": size,
-                        "modified": modified,
-                        "extension": path.extension().and_then(|ext| ext.to_str()).unwrap_or("")
-                    }));
+                if indexed.remove(path.to_string_lossy().as_ref()) != Some((modified, size)) {
+                    stale.push((path, project.clone(), modified, size));
                 }
             }
         }
+    }
+    // Whatever is left in the index no longer exists on disk
+    let removed: Vec<String> = indexed.into_keys().collect();
+    
+    let total = stale.len();
+    let mut parsed = 0;
+    for batch in stale.chunks(USAGE_INDEX_BATCH_SIZE) {
+        let results: Vec<_> = batch.par_iter()
+            .filter_map(|(path, project, modified, size)| match parse_usage_file(path) {
+                Ok(usage_entries) => Some((path, project, *modified, *size, usage_entries)),
+                Err(e) => {
+                    println!("[WARN] Failed to read {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .collect();
         
-        // Sort children: directories first, then files, both alphabetically
-        children.sort_by(|a, b| {
-            let a_type = a["type"].as_str().unwrap_or("");
-            let b_type = b["type"].as_str().unwrap_or("");
-            let a_name = a["name"].as_str().unwrap_or("");
-            let b_name = b["name"].as_str().unwrap_or("");
-            
-            match (a_type, b_type) {
-                ("directory", "file") => std::cmp::Ordering::Less,
-                ("file", "directory") => std::cmp::Ordering::Greater,
-                _ => a_name.cmp(b_name)
+        with_usage_db(|conn| {
+            let tx = conn.transaction().map_err(|e| AppError::db("Failed to update usage index", e))?;
+            for (path, project, modified, size, usage_entries) in &results {
+                let key = path.to_string_lossy().to_string();
+                let session_id = path.file_stem().and_then(|s| s.to_str()).unwrap_or("unknown").to_string();
+                
+                tx.execute("DELETE FROM usage WHERE file_path = ?1", [&key])
+                    .map_err(|e| AppError::db("Failed to update usage index", e))?;
+                let mut insert = tx.prepare_cached(
+                    "INSERT INTO usage (file_path, project, session_id, timestamp, model, input_tokens, output_tokens,
+                        cache_creation_tokens, cache_read_tokens, message_id, request_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"
+                ).map_err(|e| AppError::db("Failed to update usage index", e))?;
+                for usage in usage_entries {
+                    insert.execute(rusqlite::params![
+                        key, project, session_id, usage.timestamp, usage.model,
+                        usage.input_tokens as i64, usage.output_tokens as i64,
+                        usage.cache_creation_tokens as i64, usage.cache_read_tokens as i64,
+                        usage.message_id, usage.request_id
+                    ]).map_err(|e| AppError::db("Failed to update usage index", e))?;
+                }
+                tx.execute(
+                    "INSERT OR REPLACE INTO usage_files (path, project, modified, size) VALUES (?1, ?2, ?3, ?4)",
+                    rusqlite::params![key, project, *modified as i64, *size as i64]
+                ).map_err(|e| AppError::db("Failed to update usage index", e))?;
             }
-        });
+            tx.commit().map_err(|e| AppError::db("Failed to update usage index", e))
+        })?;
         
-        Ok(serde_json::json!({
-            "name": dir.file_name().and_then(|n| n.to_str()).unwrap_or(""),
-            "path": dir.to_string_lossy(),
-            "type": "directory",
-            "children": children
-        }))
+        parsed += batch.len();
+        progress(parsed, total);
+    }
+    
+    if !removed.is_empty() {
+        with_usage_db(|conn| {
+            let tx = conn.transaction().map_err(|e| AppError::db("Failed to update usage index", e))?;
+            for path in &removed {
+                tx.execute("DELETE FROM usage WHERE file_path = ?1", [path])
+                    .and_then(|_| tx.execute("DELETE FROM usage_files WHERE path = ?1", [path]))
+                    .map_err(|e| AppError::db("Failed to update usage index", e))?;
+            }
+            tx.commit().map_err(|e| AppError::db("Failed to update usage index", e))
+        })?;
     }
     
-    build_tree(path, 5, 0) // Limit depth to 5 levels
+    Ok(total + removed.len())
+}
+
+// Progress callback for index_usage_files that reports to the frontend
+fn emit_usage_scan_progress(app: &tauri::AppHandle) -> impl Fn(usize, usize) + '_ {
+    move |parsed, total| {
+        let _ = app.emit("usage_scan_progress", serde_json::json!({
+            "parsedFiles": parsed,
+            "totalFiles": total
+        }));
+    }
+}
+
+// Keep the index current in the background so stats calls rarely have files to parse
+async fn run_usage_indexer(app: tauri::AppHandle) {
+    loop {
+        let indexer_app = app.clone();
+        let changed = tokio::task::spawn_blocking(move || {
+            let search_paths = usage_search_paths(None)?;
+            index_usage_files(&search_paths, emit_usage_scan_progress(&indexer_app))
+        }).await;
+        
+        match changed {
+            Ok(Ok(0)) => {}
+            Ok(Ok(changed)) => {
+                println!("[DEBUG] Usage index updated ({} files)", changed);
+                let _ = app.emit("usage_index_updated", serde_json::json!({ "changedFiles": changed }));
+            }
+            Ok(Err(e)) => println!("[WARN] Usage indexing failed: {}", e),
+            Err(e) => println!("[WARN] Usage indexer task failed: {}", e),
+        }
+        
+        tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+    }
 }
 
-fn get_file_info_sync(path: &std::path::Path) -> Result<FileInfo, String> {
-    let metadata = path.metadata()
-        .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+fn parse_usage_file(path: &std::path::Path) -> std::io::Result<Vec<UsageEntry>> {
+    let content = std::fs::read_to_string(path)?;
+    let mut entries = Vec::new();
     
-    let name = path.file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+    for line in content.lines() {
+        let json = match serde_json::from_str::<serde_json::Value>(line) {
+            Ok(json) => json,
+            Err(_) => continue,
+        };
+        entries.extend(usage_entry_from_record(&json));
+    }
     
-    let mime_type = match path.extension().and_then(|s| s.to_str()) {
-        Some("txt") | Some("md") | Some("markdown") => "text/plain",
-        Some("js") | Some("jsx") => "text/javascript",
-        Some("ts") | Some("tsx") => "text/typescript",
-        Some("py") => "text/x-python",
-        Some("rs") => "text/x-rust",
-        Some("json") => "application/json",
-        Some("html") | Some("htm") => "text/html",
-        Some("css") => "text/css",
-        Some("png") => "image/png",
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("gif") => "image/gif",
-        Some("svg") => "image/svg+xml",
-        _ => "application/octet-stream",
-    }.to_string();
+    Ok(entries)
+}
+
+// Token usage of one JSONL record, if it has any
+fn usage_entry_from_record(json: &serde_json::Value) -> Option<UsageEntry> {
+    // Usage data can be at root level or nested in message
+    let usage = json.get("usage").or_else(|| json.get("message").and_then(|m| m.get("usage")))?;
+    let tokens = |field: &str| usage.get(field).and_then(|v| v.as_u64()).unwrap_or(0);
+    
+    Some(UsageEntry {
+        timestamp: json.get("timestamp").and_then(|v| v.as_str()).map(|s| s.to_string()),
+        model: json.get("model").and_then(|v| v.as_str())
+            .or_else(|| json.get("message").and_then(|m| m.get("model")).and_then(|v| v.as_str()))
+            .map(|s| s.to_string()),
+        input_tokens: tokens("input_tokens"),
+        output_tokens: tokens("output_tokens"),
+        cache_creation_tokens: tokens("cache_creation_input_tokens"),
+        cache_read_tokens: tokens("cache_read_input_tokens"),
+        message_id: json.get("message").and_then(|m| m.get("id")).and_then(|v| v.as_str()).map(|s| s.to_string()),
+        request_id: json.get("requestId").and_then(|v| v.as_str()).map(|s| s.to_string()),
+    })
+}
+
+// Usage entries of one session file
+struct UsageFile {
+    project: String,
+    session_id: String,
+    entries: Vec<UsageEntry>,
+}
+
+// The Claude project directories to scan: the given one, or every project
+fn usage_search_paths(project_path: Option<String>) -> Result<Vec<String>, AppError> {
+    if let Some(path) = project_path {
+        return Ok(vec![path]);
+    }
     
-    let is_directory = metadata.is_dir();
-    let modified_date = metadata.modified()
-        .map(|time| {
-            let datetime: chrono::DateTime<chrono::Utc> = time.into();
-            datetime.format("%Y-%m-%d %H:%M:%S").to_string()
-        })
-        .unwrap_or_else(|_| "Unknown".to_string());
+    let home_dir = dirs::home_dir().ok_or("Could not find home directory")?;
+    let projects_dir = home_dir.join(".claude").join("projects");
     
-    let file_type = if is_directory {
-        "directory".to_string()
+    let mut paths = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(&projects_dir) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                paths.push(entry.path().to_string_lossy().to_string());
+            }
+        }
+    }
+    
+    if paths.is_empty() {
+        Ok(vec![projects_dir.to_string_lossy().to_string()])
     } else {
-        path.extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("txt")
-            .to_string()
-    };
+        Ok(paths)
+    }
+}
+
+// Resumed sessions copy earlier records into their new file, so the same API message can
+// appear several times. Every usage query counts only the first copy: the earliest by
+// timestamp, then by index order, whichever project it's in.
+const USAGE_FIRST_COPY_SQL: &str = "(usage.message_id IS NULL OR NOT EXISTS (
+    SELECT 1 FROM usage AS earlier
+    WHERE earlier.message_id = usage.message_id AND earlier.request_id IS usage.request_id
+        AND (COALESCE(earlier.timestamp, '') < COALESCE(usage.timestamp, '')
+            OR (COALESCE(earlier.timestamp, '') = COALESCE(usage.timestamp, '') AND earlier.rowid < usage.rowid))
+))";
+
+// WHERE conditions and their parameters restricting the index to the given project
+// directories and filter, with copied messages left out
+fn usage_conditions(search_paths: &[String], filter: &UsageFilter) -> (Vec<String>, Vec<String>) {
+    let mut conditions = vec![USAGE_FIRST_COPY_SQL.to_string()];
+    let mut params: Vec<String> = Vec::new();
+    
+    let projects: Vec<String> = search_paths.iter().map(|path| usage_project_name(path)).collect();
+    conditions.push(format!("project IN ({})", vec!["?"; projects.len()].join(", ")));
+    params.extend(projects);
+    if let Some(from) = filter.from {
+        conditions.push("timestamp >= ?".to_string());
+        params.push(from.to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+    }
+    if let Some(to) = filter.to {
+        conditions.push("timestamp < ?".to_string());
+        params.push(to.to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+    }
+    if !filter.models.is_empty() {
+        conditions.push(format!("({})", vec!["model LIKE ?"; filter.models.len()].join(" OR ")));
+        params.extend(filter.models.iter().map(|model| format!("%{}%", model)));
+    }
+    (conditions, params)
+}
+
+// Usage of every session file under the search paths, read from the index after
+// re-parsing any files that changed since it was last updated
+fn load_usage_files(search_paths: &[String]) -> Vec<UsageFile> {
+    if let Err(e) = index_usage_files(search_paths, |_, _| {}) {
+        println!("[WARN] Failed to update usage index: {}", e);
+    }
     
-    Ok(FileInfo {
-        name,
-        path: path.to_string_lossy().to_string(),
-        size: metadata.len(),
-        mime_type,
-        is_directory,
-        modified_date,
-        file_type,
-    })
+    let result = with_usage_db(|conn| {
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT session_id, timestamp, model, input_tokens, output_tokens, cache_creation_tokens,
+                cache_read_tokens, message_id, request_id
+             FROM usage WHERE project = ?1 AND {} ORDER BY file_path, rowid",
+            USAGE_FIRST_COPY_SQL
+        )).map_err(|e| AppError::db("Failed to query usage", e))?;
+        
+        let mut files: Vec<UsageFile> = Vec::new();
+        for search_path in search_paths {
+            let project = usage_project_name(search_path);
+            let rows = stmt.query_map([&project], |row| Ok((row.get::<_, String>(0)?, UsageEntry {
+                timestamp: row.get(1)?,
+                model: row.get(2)?,
+                input_tokens: row.get::<_, i64>(3)? as u64,
+                output_tokens: row.get::<_, i64>(4)? as u64,
+                cache_creation_tokens: row.get::<_, i64>(5)? as u64,
+                cache_read_tokens: row.get::<_, i64>(6)? as u64,
+                message_id: row.get(7)?,
+                request_id: row.get(8)?,
+            }))).map_err(|e| AppError::db("Failed to query usage", e))?;
+            
+            for (session_id, usage) in rows.flatten() {
+                match files.last_mut() {
+                    Some(file) if file.project == project && file.session_id == session_id => file.entries.push(usage),
+                    _ => files.push(UsageFile { project: project.clone(), session_id, entries: vec![usage] }),
+                }
+            }
+        }
+        Ok(files)
+    });
+    
+    match result {
+        Ok(files) => files,
+        Err(e) => {
+            println!("[WARN] Failed to load usage: {}", e);
+            Vec::new()
+        }
+    }
 }
 
+#[derive(serde::Serialize)]
+struct UsageSlice {
+    // Values of the requested dimensions for this slice, e.g. {"model": "...", "day": "2025-01-31"}
+    group: HashMap<String, String>,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    cost_usd: f64,
+    message_count: u64,
+}
 
+// Slice indexed usage by any mix of "model", "day" (UTC), "project" and "session"
 #[tauri::command]
-async fn execute_claude_command_streaming(
+async fn query_usage(
     app: tauri::AppHandle,
-    args: Vec<String>, 
-    files: Vec<String>,
-    _enable_autocomplete: bool,
-    plan_mode: bool,
-    project_path: Option<String>
-) -> Result<String, String> {
-    // Use stream-json format to get detailed tool information and token usage
-    let mut command_args = vec![
-        "--print".to_string(),
-        "--output-format".to_string(),
-        "stream-json".to_string(),
-        "--verbose".to_string()
-    ];
-    
-    // Check if we have an existing session ID to continue
-    if let Ok(session_guard) = CURRENT_SESSION_ID.try_lock() {
-        if let Some(session_id) = session_guard.as_ref() {
-            command_args.push("--session-id".to_string());
-            command_args.push(session_id.clone());
+    group_by: Vec<String>,
+    project_path: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    models: Option<Vec<String>>
+) -> Result<Vec<UsageSlice>, AppError> {
+    let mut dimensions = Vec::new();
+    for dimension in &group_by {
+        let column = match dimension.as_str() {
+            "model" => "COALESCE(model, '')",
+            "day" => "COALESCE(substr(timestamp, 1, 10), '')",
+            "project" => "project",
+            "session" => "session_id",
+            other => return Err(AppError::InvalidInput(format!("Unknown usage dimension: {}", other))),
+        };
+        dimensions.push((dimension.clone(), column));
+    }
+    let filter = UsageFilter::new(from, to, models)?;
+    let search_paths = usage_search_paths(project_path)?;
+    let pricing = model_pricing_table();
+    
+    tokio::task::spawn_blocking(move || {
+        index_usage_files(&search_paths, emit_usage_scan_progress(&app))?;
+        with_usage_db(|conn| query_usage_slices(conn, &search_paths, &dimensions, &filter, &pricing))
+    }).await.map_err(|e| AppError::Other(format!("Usage query failed: {}", e)))?
+}
+
+fn query_usage_slices(
+    conn: &mut rusqlite::Connection,
+    search_paths: &[String],
+    dimensions: &[(String, &str)],
+    filter: &UsageFilter,
+    pricing: &[(String, ModelPricing)]
+) -> Result<Vec<UsageSlice>, AppError> {
+    let (conditions, params) = usage_conditions(search_paths, filter);
+    
+    // Always split by model too, since cost depends on it
+    let mut columns: Vec<&str> = dimensions.iter().map(|(_, column)| *column).collect();
+    columns.push("COALESCE(model, '')");
+    let sql = format!(
+        "SELECT {columns}, SUM(input_tokens), SUM(output_tokens), SUM(cache_creation_tokens), SUM(cache_read_tokens), COUNT(*)
+         FROM usage WHERE {conditions} GROUP BY {columns}",
+        columns = columns.join(", "),
+        conditions = conditions.join(" AND ")
+    );
+    
+    let mut stmt = conn.prepare(&sql).map_err(|e| AppError::db("Failed to query usage", e))?;
+    let group_count = columns.len();
+    let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
+        let mut values = Vec::with_capacity(group_count);
+        for i in 0..group_count {
+            values.push(row.get::<_, String>(i)?);
         }
+        Ok((values, UsageEntry {
+            timestamp: None,
+            model: None,
+            input_tokens: row.get::<_, i64>(group_count)? as u64,
+            output_tokens: row.get::<_, i64>(group_count + 1)? as u64,
+            cache_creation_tokens: row.get::<_, i64>(group_count + 2)? as u64,
+            cache_read_tokens: row.get::<_, i64>(group_count + 3)? as u64,
+            message_id: None,
+            request_id: None,
+        }, row.get::<_, i64>(group_count + 4)? as u64))
+    }).map_err(|e| AppError::db("Failed to query usage", e))?;
+    
+    let mut slices: Vec<UsageSlice> = Vec::new();
+    let mut slice_index: HashMap<Vec<String>, usize> = HashMap::new();
+    for row in rows {
+        let (mut values, mut usage, message_count) = row.map_err(|e| AppError::db("Failed to query usage", e))?;
+        let model = values.pop().unwrap_or_default();
+        usage.model = Some(model);
+        let cost = usage_cost(pricing, &usage);
+        
+        let index = *slice_index.entry(values.clone()).or_insert_with(|| {
+            slices.push(UsageSlice {
+                group: dimensions.iter().map(|(name, _)| name.clone()).zip(values.iter().cloned()).collect(),
+                input_tokens: 0,
+                output_tokens: 0,
+                cache_creation_tokens: 0,
+                cache_read_tokens: 0,
+                cost_usd: 0.0,
+                message_count: 0,
+            });
+            slices.len() - 1
+        });
+        let slice = &mut slices[index];
+        slice.input_tokens += usage.input_tokens;
+        slice.output_tokens += usage.output_tokens;
+        slice.cache_creation_tokens += usage.cache_creation_tokens;
+        slice.cache_read_tokens += usage.cache_read_tokens;
+        slice.cost_usd += cost;
+        slice.message_count += message_count;
     }
     
-    // Add plan mode flag if enabled
-    if plan_mode {
-        command_args.push("--permission-mode".to_string());
-        command_args.push("plan".to_string());
+    Ok(slices)
+}
+
+// Add one message to a day/week/month rollup, counting its session once per period
+fn add_period_usage(
+    periods: &mut HashMap<String, DailyUsage>,
+    key: String,
+    seen: &mut HashSet<String>,
+    usage: &UsageEntry,
+    cost: f64,
+    cache_savings: f64
+) {
+    let period = periods.entry(key.clone()).or_insert(DailyUsage {
+        input_tokens: 0,
+        output_tokens: 0,
+        sessions: 0,
+        cost_usd: 0.0,
+        cache_savings_usd: 0.0,
+    });
+    if seen.insert(key) {
+        period.sessions += 1;
     }
-    
-    // Add files as direct arguments before the prompt
-    for file in files {
-        command_args.push(file);
+    period.input_tokens += usage.input_tokens;
+    period.output_tokens += usage.output_tokens;
+    period.cost_usd += cost;
+    period.cache_savings_usd += cache_savings;
+}
+
+// Restricts usage to a time range and set of models
+#[derive(Default)]
+struct UsageFilter {
+    // Inclusive start, exclusive end
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    // Parts of model names, e.g. "sonnet"
+    models: Vec<String>,
+}
+
+impl UsageFilter {
+    fn new(from: Option<String>, to: Option<String>, models: Option<Vec<String>>) -> Result<Self, AppError> {
+        Ok(UsageFilter {
+            from: from.as_deref().map(|value| parse_usage_bound(value, false)).transpose()?,
+            to: to.as_deref().map(|value| parse_usage_bound(value, true)).transpose()?,
+            models: models.unwrap_or_default(),
+        })
     }
     
-    // Add the user message as the last argument
-    if let Some(message) = args.first() {
-        command_args.push(message.clone());
+    fn matches(&self, usage: &UsageEntry) -> bool {
+        if !self.models.is_empty() {
+            let model = usage.model.as_deref().unwrap_or_default();
+            if !self.models.iter().any(|wanted| model.contains(wanted.as_str())) {
+                return false;
+            }
+        }
+        
+        if self.from.is_none() && self.to.is_none() {
+            return true;
+        }
+        let ts = match usage.timestamp.as_deref().and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok()) {
+            Some(ts) => ts.with_timezone(&chrono::Utc),
+            None => return false,
+        };
+        self.from.is_none_or(|from| ts >= from) && self.to.is_none_or(|to| ts < to)
     }
+}
 
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64;
+// An RFC3339 timestamp, or a YYYY-MM-DD date in local time. A date used as the end of a
+// range includes that whole day.
+fn parse_usage_bound(value: &str, is_end: bool) -> Result<chrono::DateTime<chrono::Utc>, AppError> {
+    if let Ok(ts) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(ts.with_timezone(&chrono::Utc));
+    }
+    
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| AppError::InvalidInput(format!("Invalid date: {}", value)))?;
+    let date = if is_end { date + chrono::Duration::days(1) } else { date };
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(chrono::Local).earliest())
+        .map(|ts| ts.with_timezone(&chrono::Utc))
+        .ok_or_else(|| AppError::InvalidInput(format!("Invalid date: {}", value)))
+}
 
-    // Emit initial status
-    let _ = app.emit("claude_stream", ClaudeStreamEvent::Status {
-        message: "Starting Claude Code...".to_string(),
-        timestamp,
-    });
+// Usage of one session and model within one UTC day and local hour of the week
+struct UsageStatsRow {
+    project: String,
+    session_id: String,
+    // Summed tokens, with the model set for pricing
+    usage: UsageEntry,
+    message_count: u32,
+    day: Option<String>,
+    // Monday first
+    weekday: Option<usize>,
+    hour: Option<usize>,
+    first_timestamp: Option<String>,
+    last_timestamp: Option<String>,
+}
 
-    // Determine working directory based on project path
-    let working_dir = if let Some(proj_path) = project_path {
-        // Get the real project directory
-        match get_real_project_path(proj_path).await? {
-            Some(real_path) => {
-                let _ = app.emit("claude_stream", ClaudeStreamEvent::Status {
-                    message: format!("Using project directory: {}", real_path),
-                    timestamp: std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis() as u64,
-                });
-                std::path::PathBuf::from(real_path)
-            },
-            None => {
-                let _ = app.emit("claude_stream", ClaudeStreamEvent::Status {
-                    message: "Could not find real project path, using current directory".to_string(),
-                    timestamp: std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_millis() as u64,
-                });
-                std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."))
-            }
-        }
-    } else {
-        std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."))
-    };
+fn query_usage_stats_rows(
+    conn: &mut rusqlite::Connection,
+    search_paths: &[String],
+    filter: &UsageFilter
+) -> Result<Vec<UsageStatsRow>, AppError> {
+    let (conditions, params) = usage_conditions(search_paths, filter);
+    // strftime gives NULL for timestamps it can't parse, so those only count toward totals
+    let sql = format!(
+        "SELECT project, session_id, model, strftime('%Y-%m-%d', timestamp),
+            CAST(strftime('%w', timestamp, 'localtime') AS INTEGER), CAST(strftime('%H', timestamp, 'localtime') AS INTEGER),
+            SUM(input_tokens), SUM(output_tokens), SUM(cache_creation_tokens), SUM(cache_read_tokens), COUNT(*),
+            MIN(timestamp), MAX(timestamp)
+         FROM usage WHERE {} GROUP BY 1, 2, 3, 4, 5, 6 ORDER BY project, session_id",
+        conditions.join(" AND ")
+    );
+    
+    let mut stmt = conn.prepare(&sql).map_err(|e| AppError::db("Failed to query usage", e))?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| Ok(UsageStatsRow {
+        project: row.get(0)?,
+        session_id: row.get(1)?,
+        usage: UsageEntry {
+            timestamp: None,
+            model: row.get(2)?,
+            input_tokens: row.get::<_, i64>(6)? as u64,
+            output_tokens: row.get::<_, i64>(7)? as u64,
+            cache_creation_tokens: row.get::<_, i64>(8)? as u64,
+            cache_read_tokens: row.get::<_, i64>(9)? as u64,
+            message_id: None,
+            request_id: None,
+        },
+        message_count: row.get::<_, i64>(10)? as u32,
+        day: row.get(3)?,
+        // %w counts from Sunday
+        weekday: row.get::<_, Option<i64>>(4)?.map(|day| (day as usize + 6) % 7),
+        hour: row.get::<_, Option<i64>>(5)?.map(|hour| hour as usize),
+        first_timestamp: row.get(11)?,
+        last_timestamp: row.get(12)?,
+    })).map_err(|e| AppError::db("Failed to query usage", e))?;
+    
+    rows.collect::<Result<Vec<_>, _>>().map_err(|e| AppError::db("Failed to query usage", e))
+}
 
-    // Use simple output collection for debugging
-    let output = AsyncCommand::new("claude")
-        .args(&command_args)
-        .current_dir(&working_dir)
-        .output()
-        .await
-        .map_err(|e| {
-            let error_msg = format!("Failed to execute claude process: {}", e);
-            let _ = app.emit("claude_stream", ClaudeStreamEvent::Error {
-                message: error_msg.clone(),
-                timestamp: std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs(),
+#[tauri::command]
+async fn get_usage_statistics(
+    app: tauri::AppHandle,
+    project_path: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    models: Option<Vec<String>>
+) -> Result<UsageStats, AppError> {
+    let filter = UsageFilter::new(from, to, models)?;
+    let mut stats = UsageStats {
+        total_input_tokens: 0,
+        total_output_tokens: 0,
+        total_cache_creation_tokens: 0,
+        total_cache_read_tokens: 0,
+        session_count: 0,
+        models_used: std::collections::HashMap::new(),
+        daily_usage: std::collections::HashMap::new(),
+        weekly_usage: HashMap::new(),
+        monthly_usage: HashMap::new(),
+        activity_heatmap: [[0; 24]; 7],
+        total_cost_usd: 0.0,
+        model_costs: HashMap::new(),
+        total_cache_savings_usd: 0.0,
+        model_cache_savings: HashMap::new(),
+        projects: HashMap::new(),
+        sessions: Vec::new(),
+    };
+    let pricing = model_pricing_table();
+    
+    // Parse outstanding files up front so the dashboard can show progress; the database
+    // does the grouping, leaving only per-session, per-hour rows to price
+    let search_paths = usage_search_paths(project_path)?;
+    let rows = tokio::task::spawn_blocking(move || {
+        index_usage_files(&search_paths, emit_usage_scan_progress(&app))?;
+        with_usage_db(|conn| query_usage_stats_rows(conn, &search_paths, &filter))
+    }).await
+        .map_err(|e| AppError::Other(format!("Usage scan failed: {}", e)))??;
+    
+    // Rows arrive grouped by session, and sessions with no usage in the filtered view
+    // have no rows, so they don't count
+    // Day, week and month keys never collide, so one set covers all three
+    let mut periods_seen = HashSet::new();
+    for row in &rows {
+        let usage = &row.usage;
+        let is_new_session = stats.sessions.last()
+            .is_none_or(|session| session.project != row.project || session.session_id != row.session_id);
+        if is_new_session {
+            periods_seen.clear();
+            stats.sessions.push(SessionUsage {
+                session_id: row.session_id.clone(),
+                project: row.project.clone(),
+                input_tokens: 0,
+                output_tokens: 0,
+                cache_creation_tokens: 0,
+                cache_read_tokens: 0,
+                cost_usd: 0.0,
+                message_count: 0,
+                first_timestamp: None,
+                last_timestamp: None,
             });
-            error_msg
-        })?;
+        }
+        
+        stats.total_input_tokens += usage.input_tokens;
+        stats.total_output_tokens += usage.output_tokens;
+        stats.total_cache_creation_tokens += usage.cache_creation_tokens;
+        stats.total_cache_read_tokens += usage.cache_read_tokens;
+        
+        let cost = usage_cost(&pricing, usage);
+        stats.total_cost_usd += cost;
+        let cache_savings = usage_cache_savings(&pricing, usage);
+        stats.total_cache_savings_usd += cache_savings;
+        
+        let session = stats.sessions.last_mut().unwrap();
+        session.input_tokens += usage.input_tokens;
+        session.output_tokens += usage.output_tokens;
+        session.cache_creation_tokens += usage.cache_creation_tokens;
+        session.cache_read_tokens += usage.cache_read_tokens;
+        session.cost_usd += cost;
+        session.message_count += row.message_count;
+        if row.first_timestamp.is_some() && (session.first_timestamp.is_none() || row.first_timestamp < session.first_timestamp) {
+            session.first_timestamp = row.first_timestamp.clone();
+        }
+        if row.last_timestamp > session.last_timestamp {
+            session.last_timestamp = row.last_timestamp.clone();
+        }
+        
+        if let Some(model) = &usage.model {
+            *stats.models_used.entry(model.clone()).or_insert(0) += row.message_count;
+            *stats.model_costs.entry(model.clone()).or_insert(0.0) += cost;
+            *stats.model_cache_savings.entry(model.clone()).or_insert(0.0) += cache_savings;
+        }
+        
+        if let Some(day) = row.day.as_deref().and_then(|day| chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()) {
+            add_period_usage(&mut stats.daily_usage, day.format("%Y-%m-%d").to_string(), &mut periods_seen, usage, cost, cache_savings);
+            add_period_usage(&mut stats.weekly_usage, day.format("%G-W%V").to_string(), &mut periods_seen, usage, cost, cache_savings);
+            add_period_usage(&mut stats.monthly_usage, day.format("%Y-%m").to_string(), &mut periods_seen, usage, cost, cache_savings);
+        }
+        if let (Some(weekday), Some(hour)) = (row.weekday, row.hour) {
+            stats.activity_heatmap[weekday][hour] += row.message_count;
+        }
+    }
+    
+    for session in &stats.sessions {
+        let project = stats.projects.entry(session.project.clone()).or_default();
+        project.input_tokens += session.input_tokens;
+        project.output_tokens += session.output_tokens;
+        project.cache_creation_tokens += session.cache_creation_tokens;
+        project.cache_read_tokens += session.cache_read_tokens;
+        project.cost_usd += session.cost_usd;
+        project.session_count += 1;
+        if session.last_timestamp > project.last_active {
+            project.last_active = session.last_timestamp.clone();
+        }
+    }
+    stats.session_count = stats.sessions.len() as u32;
+    
+    stats.sessions.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd).then(b.output_tokens.cmp(&a.output_tokens)));
+    
+    println!("[DEBUG] Final stats - Sessions: {}, Input tokens: {}, Output tokens: {}", 
+             stats.session_count, stats.total_input_tokens, stats.total_output_tokens);
+    
+    Ok(stats)
+}
 
-    // Process the output
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
-
-    if !stderr.is_empty() {
-        let _ = app.emit("claude_stream", ClaudeStreamEvent::Status {
-            message: format!("Claude stderr: {}", stderr),
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64,
-        });
+#[derive(Debug, Deserialize)]
+struct UsagePeriod {
+    // Same formats as the get_usage_statistics filters
+    from: String,
+    to: String,
+}
+
+#[derive(serde::Serialize, Default)]
+struct UsagePeriodSummary {
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    total_tokens: u64,
+    cost_usd: f64,
+    sessions: u32,
+    // Share of the period's tokens per model, 0-100
+    model_mix: HashMap<String, f64>,
+}
+
+#[derive(serde::Serialize)]
+struct UsageComparison {
+    period_a: UsagePeriodSummary,
+    period_b: UsagePeriodSummary,
+    // Period B minus period A
+    total_tokens_delta: i64,
+    cost_delta_usd: f64,
+    sessions_delta: i64,
+    // Relative change from A to B; None when A had nothing to compare against
+    total_tokens_change_percent: Option<f64>,
+    cost_change_percent: Option<f64>,
+    // Change in each model's share, in percentage points
+    model_mix_delta: HashMap<String, f64>,
+}
+
+fn summarize_usage_period(files: &[UsageFile], filter: &UsageFilter, pricing: &[(String, ModelPricing)]) -> UsagePeriodSummary {
+    let mut summary = UsagePeriodSummary::default();
+    let mut model_tokens: HashMap<String, u64> = HashMap::new();
+    
+    for file in files {
+        let mut active = false;
+        for usage in file.entries.iter().filter(|usage| filter.matches(usage)) {
+            let tokens = usage.input_tokens + usage.output_tokens + usage.cache_creation_tokens + usage.cache_read_tokens;
+            summary.input_tokens += usage.input_tokens;
+            summary.output_tokens += usage.output_tokens;
+            summary.cache_creation_tokens += usage.cache_creation_tokens;
+            summary.cache_read_tokens += usage.cache_read_tokens;
+            summary.total_tokens += tokens;
+            summary.cost_usd += usage_cost(pricing, usage);
+            *model_tokens.entry(usage.model.clone().unwrap_or_else(|| "unknown".to_string())).or_insert(0) += tokens;
+            active = true;
+        }
+        if active {
+            summary.sessions += 1;
+        }
+    }
+    
+    if summary.total_tokens > 0 {
+        summary.model_mix = model_tokens.into_iter()
+            .map(|(model, tokens)| (model, tokens as f64 / summary.total_tokens as f64 * 100.0))
+            .collect();
     }
+    summary
+}
 
-    // Parse stream-json format
-    let mut assistant_response = String::new();
-    let mut processed_lines = std::collections::HashSet::new();
+#[tauri::command]
+async fn compare_usage(period_a: UsagePeriod, period_b: UsagePeriod, project_path: Option<String>) -> Result<UsageComparison, AppError> {
+    let filter_a = UsageFilter::new(Some(period_a.from), Some(period_a.to), None)?;
+    let filter_b = UsageFilter::new(Some(period_b.from), Some(period_b.to), None)?;
+    let search_paths = usage_search_paths(project_path)?;
+    let files = tokio::task::spawn_blocking(move || load_usage_files(&search_paths)).await
+        .map_err(|e| AppError::Other(format!("Usage scan failed: {}", e)))?;
+    let pricing = model_pricing_table();
+    
+    let a = summarize_usage_period(&files, &filter_a, &pricing);
+    let b = summarize_usage_period(&files, &filter_b, &pricing);
+    
+    let percent_change = |before: f64, after: f64| (before > 0.0).then(|| (after - before) / before * 100.0);
+    let mut model_mix_delta: HashMap<String, f64> = HashMap::new();
+    for (model, share) in &b.model_mix {
+        *model_mix_delta.entry(model.clone()).or_insert(0.0) += share;
+    }
+    for (model, share) in &a.model_mix {
+        *model_mix_delta.entry(model.clone()).or_insert(0.0) -= share;
+    }
     
-    for line in stdout.lines() {
-        let line_trimmed = line.trim();
+    Ok(UsageComparison {
+        total_tokens_delta: b.total_tokens as i64 - a.total_tokens as i64,
+        cost_delta_usd: b.cost_usd - a.cost_usd,
+        sessions_delta: b.sessions as i64 - a.sessions as i64,
+        total_tokens_change_percent: percent_change(a.total_tokens as f64, b.total_tokens as f64),
+        cost_change_percent: percent_change(a.cost_usd, b.cost_usd),
+        model_mix_delta,
+        period_a: a,
+        period_b: b,
+    })
+}
+
+// Subscription limits reset on 5-hour windows starting at the hour of the first message
+const USAGE_BLOCK_HOURS: i64 = 5;
+
+#[derive(serde::Serialize)]
+struct UsageBlock {
+    start: String,
+    end: String,
+    // Timestamp of the last message in the block
+    last_activity: String,
+    is_active: bool,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    total_tokens: u64,
+    cost_usd: f64,
+    message_count: u32,
+    models: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+struct UsageBlockBurnRate {
+    tokens_per_minute: f64,
+    cost_per_hour: f64,
+    // Totals the active block reaches by its end at the current rate
+    projected_tokens: u64,
+    projected_cost_usd: f64,
+    token_limit: Option<u64>,
+    // Minutes until the limit is hit at the current rate, if it's hit before the block ends
+    minutes_to_limit: Option<f64>,
+}
+
+#[derive(serde::Serialize)]
+struct UsageBlocksReport {
+    // Oldest first
+    blocks: Vec<UsageBlock>,
+    burn_rate: Option<UsageBlockBurnRate>,
+}
+
+// Usage of one model within one minute. Blocks start on the hour and last whole hours,
+// so a minute never straddles two blocks, and the gaps that end a block are measured
+// between the first and last message of each minute.
+struct UsageMinute {
+    first: chrono::DateTime<chrono::Utc>,
+    last: chrono::DateTime<chrono::Utc>,
+    usage: UsageEntry,
+    message_count: u32,
+}
+
+fn query_usage_minutes(conn: &mut rusqlite::Connection, search_paths: &[String]) -> Result<Vec<UsageMinute>, AppError> {
+    let (conditions, params) = usage_conditions(search_paths, &UsageFilter::default());
+    let sql = format!(
+        "SELECT MIN(timestamp), MAX(timestamp), model,
+            SUM(input_tokens), SUM(output_tokens), SUM(cache_creation_tokens), SUM(cache_read_tokens), COUNT(*)
+         FROM usage WHERE {} AND strftime('%Y-%m-%dT%H:%M', timestamp) IS NOT NULL
+         GROUP BY strftime('%Y-%m-%dT%H:%M', timestamp), model ORDER BY 1",
+        conditions.join(" AND ")
+    );
+    
+    let mut stmt = conn.prepare(&sql).map_err(|e| AppError::db("Failed to query usage", e))?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| Ok((
+        row.get::<_, String>(0)?,
+        row.get::<_, String>(1)?,
+        UsageEntry {
+            timestamp: None,
+            model: row.get(2)?,
+            input_tokens: row.get::<_, i64>(3)? as u64,
+            output_tokens: row.get::<_, i64>(4)? as u64,
+            cache_creation_tokens: row.get::<_, i64>(5)? as u64,
+            cache_read_tokens: row.get::<_, i64>(6)? as u64,
+            message_id: None,
+            request_id: None,
+        },
+        row.get::<_, i64>(7)? as u32,
+    ))).map_err(|e| AppError::db("Failed to query usage", e))?;
+    
+    let mut minutes = Vec::new();
+    for row in rows {
+        let (first, last, usage, message_count) = row.map_err(|e| AppError::db("Failed to query usage", e))?;
+        let parse = |ts: &str| chrono::DateTime::parse_from_rfc3339(ts).ok().map(|ts| ts.with_timezone(&chrono::Utc));
+        if let (Some(first), Some(last)) = (parse(&first), parse(&last)) {
+            minutes.push(UsageMinute { first, last, usage, message_count });
+        }
+    }
+    Ok(minutes)
+}
+
+// Groups usage into 5-hour billing blocks. Without an explicit token limit, the largest
+// completed block is used as the limit for the time-to-limit projection.
+#[tauri::command]
+async fn get_usage_blocks(project_path: Option<String>, token_limit: Option<u64>) -> Result<UsageBlocksReport, AppError> {
+    let pricing = model_pricing_table();
+    let block_length = chrono::Duration::hours(USAGE_BLOCK_HOURS);
+    let now = chrono::Utc::now();
+    
+    let search_paths = usage_search_paths(project_path)?;
+    let minutes = tokio::task::spawn_blocking(move || {
+        index_usage_files(&search_paths, |_, _| {})?;
+        with_usage_db(|conn| query_usage_minutes(conn, &search_paths))
+    }).await
+        .map_err(|e| AppError::Other(format!("Usage scan failed: {}", e)))??;
+    
+    let mut blocks: Vec<UsageBlock> = Vec::new();
+    let mut block_start = chrono::DateTime::<chrono::Utc>::MIN_UTC;
+    let mut block_first_ts = chrono::DateTime::<chrono::Utc>::MIN_UTC;
+    let mut last_ts = chrono::DateTime::<chrono::Utc>::MIN_UTC;
+    
+    for minute in &minutes {
+        let (ts, usage) = (minute.first, &minute.usage);
+        // A new block starts once the current one has run out or after a 5-hour gap
+        let starts_new_block = blocks.is_empty() || ts >= block_start + block_length || ts - last_ts >= block_length;
+        if starts_new_block {
+            let hour_start = ts.timestamp() - ts.timestamp().rem_euclid(3600);
+            block_start = chrono::DateTime::from_timestamp(hour_start, 0).unwrap_or(ts);
+            block_first_ts = ts;
+            blocks.push(UsageBlock {
+                start: block_start.to_rfc3339(),
+                end: (block_start + block_length).to_rfc3339(),
+                last_activity: ts.to_rfc3339(),
+                is_active: false,
+                input_tokens: 0,
+                output_tokens: 0,
+                cache_creation_tokens: 0,
+                cache_read_tokens: 0,
+                total_tokens: 0,
+                cost_usd: 0.0,
+                message_count: 0,
+                models: Vec::new(),
+            });
+        }
+        last_ts = last_ts.max(minute.last);
         
-        // Skip empty lines and prevent processing the same line twice
-        if line_trimmed.is_empty() || processed_lines.contains(line_trimmed) {
-            continue;
+        let block = blocks.last_mut().unwrap();
+        block.last_activity = last_ts.to_rfc3339();
+        block.input_tokens += usage.input_tokens;
+        block.output_tokens += usage.output_tokens;
+        block.cache_creation_tokens += usage.cache_creation_tokens;
+        block.cache_read_tokens += usage.cache_read_tokens;
+        block.total_tokens += usage.input_tokens + usage.output_tokens + usage.cache_creation_tokens + usage.cache_read_tokens;
+        block.cost_usd += usage_cost(&pricing, usage);
+        block.message_count += minute.message_count;
+        if let Some(model) = &usage.model {
+            if !block.models.contains(model) {
+                block.models.push(model.clone());
+            }
         }
-        processed_lines.insert(line_trimmed.to_string());
+    }
+    
+    let block_is_active = !minutes.is_empty() && now < block_start + block_length && now - last_ts < block_length;
+    let mut burn_rate = None;
+    if block_is_active {
+        let limit = token_limit.or_else(|| {
+            blocks[..blocks.len() - 1].iter().map(|block| block.total_tokens).max()
+        });
+        let block = blocks.last_mut().unwrap();
+        block.is_active = true;
         
-        if let Some(event) = parse_claude_json_event(line_trimmed) {
-            // Store assistant responses to return as final result
-            if let ClaudeStreamEvent::Response { content, .. } = &event {
-                if !assistant_response.is_empty() {
-                    assistant_response.push('\n');
+        // Rate over the span from the block's first message to now
+        let elapsed_minutes = ((now - block_first_ts).num_seconds() as f64 / 60.0).max(1.0);
+        let remaining_minutes = ((block_start + block_length - now).num_seconds() as f64 / 60.0).max(0.0);
+        let tokens_per_minute = block.total_tokens as f64 / elapsed_minutes;
+        let cost_per_minute = block.cost_usd / elapsed_minutes;
+        
+        let minutes_to_limit = limit.and_then(|limit| {
+            if block.total_tokens >= limit {
+                Some(0.0)
+            } else if tokens_per_minute > 0.0 {
+                let minutes = (limit - block.total_tokens) as f64 / tokens_per_minute;
+                (minutes <= remaining_minutes).then_some(minutes)
+            } else {
+                None
+            }
+        });
+        
+        burn_rate = Some(UsageBlockBurnRate {
+            tokens_per_minute,
+            cost_per_hour: cost_per_minute * 60.0,
+            projected_tokens: block.total_tokens + (tokens_per_minute * remaining_minutes) as u64,
+            projected_cost_usd: block.cost_usd + cost_per_minute * remaining_minutes,
+            token_limit: limit,
+            minutes_to_limit,
+        });
+    }
+    
+    Ok(UsageBlocksReport { blocks, burn_rate })
+}
+
+// A token and/or dollar limit over a calendar period in local time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct UsageBudget {
+    // "daily", "weekly" (from Monday) or "monthly"
+    period: String,
+    // Counts input, output and cache tokens
+    #[serde(default)]
+    max_tokens: Option<u64>,
+    #[serde(default)]
+    max_cost_usd: Option<f64>,
+    // Also show an OS notification when a threshold is crossed
+    #[serde(default)]
+    notify: bool,
+}
+
+const BUDGET_ALERT_THRESHOLDS: [u32; 2] = [80, 100];
+
+fn budget_period_start(period: &str, now: chrono::DateTime<chrono::Local>) -> Option<chrono::DateTime<chrono::Local>> {
+    use chrono::Datelike;
+    
+    let today = now.date_naive();
+    let start = match period {
+        "daily" => today,
+        "weekly" => today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64),
+        "monthly" => today.with_day(1)?,
+        _ => return None,
+    };
+    start.and_hms_opt(0, 0, 0)?.and_local_timezone(chrono::Local).earliest()
+}
+
+// Check every budget against its current period and alert on newly crossed thresholds
+fn evaluate_usage_budgets(app: &tauri::AppHandle) {
+    let budgets = match read_app_settings() {
+        Ok(settings) if !settings.usage_budgets.is_empty() => settings.usage_budgets,
+        _ => return,
+    };
+    let search_paths = match usage_search_paths(None) {
+        Ok(paths) => paths,
+        Err(_) => return,
+    };
+    let pricing = model_pricing_table();
+    let entries: Vec<(chrono::DateTime<chrono::Local>, UsageEntry)> = load_usage_files(&search_paths)
+        .into_iter()
+        .flat_map(|file| file.entries)
+        .filter_map(|usage| {
+            let ts = chrono::DateTime::parse_from_rfc3339(usage.timestamp.as_deref()?).ok()?;
+            Some((ts.with_timezone(&chrono::Local), usage))
+        })
+        .collect();
+    let now = chrono::Local::now();
+    
+    for budget in &budgets {
+        let start = match budget_period_start(&budget.period, now) {
+            Some(start) => start,
+            None => continue,
+        };
+        
+        let (mut tokens, mut cost) = (0u64, 0.0);
+        for (_, usage) in entries.iter().filter(|(ts, _)| *ts >= start) {
+            tokens += usage.input_tokens + usage.output_tokens + usage.cache_creation_tokens + usage.cache_read_tokens;
+            cost += usage_cost(&pricing, usage);
+        }
+        
+        let metrics = [
+            ("tokens", tokens as f64, budget.max_tokens.map(|max| max as f64)),
+            ("cost", cost, budget.max_cost_usd),
+        ];
+        for (metric, used, limit) in metrics {
+            let limit = match limit {
+                Some(limit) if limit > 0.0 => limit,
+                _ => continue,
+            };
+            let percentage = used / limit * 100.0;
+            
+            // Only the highest crossed threshold alerts; lower ones are marked as sent with it
+            let crossed = match BUDGET_ALERT_THRESHOLDS.iter().rev().find(|t| percentage >= **t as f64) {
+                Some(threshold) => *threshold,
+                None => continue,
+            };
+            let alert_key = |threshold: u32| format!("{}:{}:{}:{}", budget.period, start.to_rfc3339(), metric, threshold);
+            {
+                let mut sent = BUDGET_ALERTS_SENT.lock().unwrap();
+                if !sent.insert(alert_key(crossed)) {
+                    continue;
+                }
+                for threshold in BUDGET_ALERT_THRESHOLDS.iter().filter(|t| **t < crossed) {
+                    sent.insert(alert_key(*threshold));
                 }
-                assistant_response.push_str(content);
             }
             
-            let _ = app.emit("claude_stream", event);
+            println!("[WARN] {} {} budget at {:.0}% ({:.2} of {:.2})", budget.period, metric, percentage, used, limit);
+            let _ = app.emit("budget_alert", serde_json::json!({
+                "period": budget.period,
+                "metric": metric,
+                "used": used,
+                "limit": limit,
+                "threshold": crossed,
+                "periodStart": start.to_rfc3339()
+            }));
+            
+            if budget.notify {
+                let used_text = if metric == "cost" { format!("${:.2} of ${:.2}", used, limit) } else { format!("{} of {} tokens", used as u64, limit as u64) };
+                let title = if crossed >= 100 { "Claude usage budget exceeded" } else { "Claude usage budget almost reached" };
+                if let Err(e) = app.notification().builder()
+                    .title(title)
+                    .body(format!("{} budget: {} used", budget.period, used_text))
+                    .show()
+                {
+                    println!("[WARN] Failed to show budget notification: {}", e);
+                }
+            }
         }
     }
+}
 
-    // Emit completion
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64;
+// Re-evaluate budgets whenever a session reports usage, and periodically to catch
+// usage from terminal sessions, which don't report results to the app
+async fn monitor_usage_budgets(app: tauri::AppHandle) {
+    loop {
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(60), USAGE_CHANGED.notified()).await;
+        let app = app.clone();
+        let _ = tokio::task::spawn_blocking(move || evaluate_usage_budgets(&app)).await;
+    }
+}
 
-    let _ = app.emit("claude_stream", ClaudeStreamEvent::Complete { timestamp });
+#[derive(serde::Serialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+struct UsageTotals {
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    cost_usd: f64,
+    message_count: u32,
+}
 
-    if output.status.success() {
-        // Return the assistant response content, or fall back to raw stdout if no structured response
-        if !assistant_response.is_empty() {
-            Ok(assistant_response)
-        } else {
-            Ok(stdout.to_string())
-        }
-    } else {
-        Err(format!("Claude process exited with code: {:?}", output.status.code()))
+fn usage_totals<'a>(pricing: &[(String, ModelPricing)], entries: impl IntoIterator<Item = &'a UsageEntry>) -> UsageTotals {
+    let mut totals = UsageTotals::default();
+    for usage in entries {
+        totals.input_tokens += usage.input_tokens;
+        totals.output_tokens += usage.output_tokens;
+        totals.cache_creation_tokens += usage.cache_creation_tokens;
+        totals.cache_read_tokens += usage.cache_read_tokens;
+        totals.cost_usd += usage_cost(pricing, usage);
+        totals.message_count += 1;
     }
+    totals
 }
 
-fn parse_claude_json_event(line: &str) -> Option<ClaudeStreamEvent> {
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64;
+// Poll the watched project's session files and emit usage_updated when one grows, so
+// token counters stay current for chats and terminal sessions alike
+async fn monitor_project_usage(app: tauri::AppHandle) {
+    let mut current_project: Option<String> = None;
+    let mut message_counts: HashMap<String, usize> = HashMap::new();
+    
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
         
-    // Skip empty lines
-    let trimmed = line.trim();
-    if trimmed.is_empty() {
-        return None;
+        let project = WATCHED_USAGE_PROJECT.lock().unwrap().clone();
+        let project_path = match project {
+            Some(path) => path,
+            None => {
+                current_project = None;
+                continue;
+            }
+        };
+        let switched = current_project.as_ref() != Some(&project_path);
+        if switched {
+            message_counts.clear();
+            current_project = Some(project_path.clone());
+        }
+        
+        let search_paths = vec![project_path.clone()];
+        let files = match tokio::task::spawn_blocking(move || load_usage_files(&search_paths)).await {
+            Ok(files) => files,
+            Err(_) => continue,
+        };
+        let pricing = model_pricing_table();
+        let project_totals = usage_totals(&pricing, files.iter().flat_map(|file| file.entries.iter()));
+        
+        let mut changed = false;
+        for file in &files {
+            let previous = message_counts.insert(file.session_id.clone(), file.entries.len());
+            if switched || previous == Some(file.entries.len()) {
+                continue;
+            }
+            changed = true;
+            let _ = app.emit("usage_updated", serde_json::json!({
+                "projectPath": project_path,
+                "sessionId": file.session_id,
+                "session": usage_totals(&pricing, &file.entries),
+                "project": project_totals
+            }));
+        }
+        
+        if switched {
+            let _ = app.emit("usage_updated", serde_json::json!({
+                "projectPath": project_path,
+                "sessionId": null,
+                "session": null,
+                "project": project_totals
+            }));
+        } else if changed {
+            USAGE_CHANGED.notify_one();
+        }
+    }
+}
+
+// Start live usage updates for a Claude project directory, or stop them with None
+#[tauri::command]
+async fn watch_project_usage(project_path: Option<String>) -> Result<(), AppError> {
+    if let Some(path) = &project_path {
+        if !std::path::Path::new(path).is_dir() {
+            return Err(AppError::PathNotFound(format!("Project directory not found: {}", path)));
+        }
+    }
+    *WATCHED_USAGE_PROJECT.lock().unwrap() = project_path;
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_usage_budgets() -> Result<Vec<UsageBudget>, AppError> {
+    Ok(read_app_settings()?.usage_budgets)
+}
+
+#[tauri::command]
+async fn set_usage_budgets(app: tauri::AppHandle, budgets: Vec<UsageBudget>) -> Result<(), AppError> {
+    for budget in &budgets {
+        if !["daily", "weekly", "monthly"].contains(&budget.period.as_str()) {
+            return Err(AppError::InvalidInput(format!("Unknown budget period: {}", budget.period)));
+        }
+        if budget.max_tokens.is_none() && budget.max_cost_usd.is_none() {
+            return Err(AppError::InvalidInput("A budget needs a token or dollar limit".to_string()));
+        }
     }
     
-    // Try to parse as Claude stream-json format
-    if let Ok(claude_event) = serde_json::from_str::<ClaudeJsonEvent>(trimmed) {
-        match claude_event.event_type.as_str() {
-            "system" => {
-                if let Some(subtype) = &claude_event.subtype {
-                    match subtype.as_str() {
-                        "init" => Some(ClaudeStreamEvent::Status {
-                            message: "Claude Code initialized".to_string(),
-                            timestamp,
-                        }),
-                        "permission_request" => {
-                            // Handle permission requests
-                            let prompt = if let Some(msg) = &claude_event.message {
-                                // Try to extract a readable prompt from the message
-                                format!("Claude is requesting permission: {}", msg.content)
-                            } else {
-                                "Claude is requesting permission to proceed".to_string()
-                            };
-                            
-                            Some(ClaudeStreamEvent::PermissionRequest {
-                                id: format!("perm_{}", timestamp),
-                                prompt,
-                                options: vec![
-                                    "1: Allow".to_string(),
-                                    "2: Allow and remember".to_string(),
-                                    "3: Deny".to_string(),
-                                ],
-                                timestamp,
-                            })
-                        },
-                        _ => Some(ClaudeStreamEvent::Status {
-                            message: format!("System: {}", subtype),
-                            timestamp,
-                        }),
-                    }
-                } else {
-                    None
-                }
-            },
-            "assistant" => {
-                if let Some(message) = &claude_event.message {
-                    // Parse message content to extract text and tool usage
-                    if let Ok(content_value) = serde_json::from_str::<serde_json::Value>(&message.content) {
-                        if let Some(content_array) = content_value.as_array() {
-                            let mut text_content = String::new();
-                            let mut tool_usage = Vec::new();
-                            
-                            for item in content_array {
-                                if let Some(item_type) = item.get("type").and_then(|t| t.as_str()) {
-                                    match item_type {
-                                        "text" => {
-                                            if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
-                                                if !text_content.is_empty() {
-                                                    text_content.push('\n');
-                                                }
-                                                text_content.push_str(text);
-                                            }
-                                        },
-                                        "tool_use" => {
-                                            if let (Some(name), Some(input)) = (
-                                                item.get("name").and_then(|n| n.as_str()),
-                                                item.get("input")
-                                            ) {
-                                                tool_usage.push(format!("🔧 Using tool: {}", name));
-                                                
-                                                // Add tool parameters for common tools
-                                                match name {
-                                                    "Glob" => {
-                                                        if let Some(pattern) = input.get("pattern").and_then(|p| p.as_str()) {
-                                                            tool_usage.push(format!("   Searching for pattern: {}", pattern));
-                                                        }
-                                                    },
-                                                    "Grep" => {
-                                                        if let Some(pattern) = input.get("pattern").and_then(|p| p.as_str()) {
-                                                            tool_usage.push(format!("   Searching for: {}", pattern));
-                                                        }
-                                                    },
-                                                    "Read" => {
-                                                        if let Some(path) = input.get("file_path").and_then(|p| p.as_str()) {
-                                                            tool_usage.push(format!("   Reading file: {}", path.split('/').last().unwrap_or(path)));
-                                                        }
-                                                    },
-                                                    "Task" => {
-                                                        if let Some(desc) = input.get("description").and_then(|d| d.as_str()) {
-                                                            tool_usage.push(format!("   Task: {}", desc));
-                                                        }
-                                                    },
-                                                    "TodoWrite" => {
-                                                        if let Some(todos_array) = input.get("todos").and_then(|t| t.as_array()) {
-                                                            tool_usage.push(format!("📝 Updating todos ({} items)", todos_array.len()));
-                                                            
-                                                            // Extract and emit todo data for real-time sync
-                                                            // This will be handled by a separate function
-                                                            // to avoid blocking the stream parsing
-                                                        }
-                                                    },
-                                                    _ => {
-                                                        tool_usage.push(format!("   Executing {}", name));
-                                                    }
-                                                }
+    let mut settings = read_app_settings()?;
+    settings.usage_budgets = budgets;
+    write_app_settings(&settings)?;
+    
+    // New limits may already be crossed
+    tokio::task::spawn_blocking(move || evaluate_usage_budgets(&app)).await
+        .map_err(|e| AppError::Other(format!("Budget check failed: {}", e)))
+}
+
+#[tauri::command]
+async fn update_claude_config(key: String, value: serde_json::Value) -> Result<(), AppError> {
+    let value_str = match value {
+        serde_json::Value::String(s) => s,
+        serde_json::Value::Bool(b) => b.to_string(),
+        serde_json::Value::Number(n) => n.to_string(),
+        _ => return Err(AppError::InvalidInput("Unsupported config value type".to_string())),
+    };
+    
+    let output = Command::new(claude_binary())
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(&["config", "set", &key, &value_str])
+        .output()
+        .map_err(|e| AppError::spawn("claude", e))?;
+    
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(AppError::from_claude_failure(String::from_utf8_lossy(&output.stderr).to_string()))
+    }
+}
+
+#[tauri::command]
+async fn check_claude_updates() -> Result<serde_json::Value, AppError> {
+    // Note: `claude update --check` might have TTY issues, so we'll simulate for now
+    // In a real implementation, this would check for updates
+    Ok(serde_json::json!({
+        "current_version": "1.0.56",
+        "latest_version": "1.0.56", 
+        "update_available": false,
+        "message": "Claude Code is up to date"
+    }))
+}
+
+#[tauri::command]
+async fn execute_claude_command(args: Vec<String>) -> Result<String, AppError> {
+    let output = Command::new(claude_binary())
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(&args)
+        .output()
+        .map_err(|e| AppError::spawn("claude", e))?;
+    
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(AppError::from_claude_failure(String::from_utf8_lossy(&output.stderr).to_string()))
+    }
+}
+
+#[tauri::command]
+async fn get_project_sessions(project_path: String) -> Result<Vec<serde_json::Value>, AppError> {
+    let mut sessions = Vec::new();
+
+    // Include sessions stored under duplicate encodings of the same directory
+    let mut dirs = vec![std::path::PathBuf::from(&project_path)];
+    dirs.extend(duplicate_project_dirs(&project_path));
+
+    for dir in dirs {
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
+                    let file_name = path.file_stem()
+                        .and_then(|s| s.to_str())
+                        .unwrap_or("unknown")
+                        .to_string();
+
+                    // Read first and last few lines to get session info
+                    if let Ok(content) = std::fs::read_to_string(&path) {
+                        let lines: Vec<&str> = content.lines().filter(|line| !line.trim().is_empty()).collect();
+                        let message_count = lines.len();
+
+                        let mut last_message = "No messages".to_string();
+                        let mut timestamp = "".to_string();
+
+                        // Get the last message
+                        if let Some(last_line) = lines.last() {
+                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(last_line) {
+                                if let Some(msg) = json.get("message") {
+                                    if let Some(content) = msg.get("content") {
+                                        if let Some(content_str) = content.as_str() {
+                                            last_message = content_str.chars().take(100).collect::<String>();
+                                            if content_str.len() > 100 {
+                                                last_message.push_str("...");
                                             }
-                                        },
-                                        _ => {}
+                                        }
+                                    }
+                                }
+                                if let Some(ts) = json.get("timestamp") {
+                                    if let Some(ts_str) = ts.as_str() {
+                                        timestamp = ts_str.to_string();
                                     }
                                 }
-                            }
-                            
-                            // Emit tool usage as thinking events
-                            if !tool_usage.is_empty() {
-                                return Some(ClaudeStreamEvent::Thinking {
-                                    message: tool_usage.join("\n"),
-                                    timestamp,
-                                });
-                            }
-                            
-                            // Emit text content as response
-                            if !text_content.is_empty() {
-                                return Some(ClaudeStreamEvent::Response {
-                                    content: text_content,
-                                    timestamp,
-                                });
                             }
                         }
+
+                        let session_info = serde_json::json!({
+                            "id": file_name,
+                            "name": file_name.replace("-", " ").replace("_", " "),
+                            "lastMessage": last_message,
+                            "timestamp": timestamp,
+                            "messageCount": message_count,
+                            "filePath": path.to_string_lossy()
+                        });
+
+                        sessions.push(session_info);
                     }
-                    
-                    // Fallback to raw content if parsing fails
-                    Some(ClaudeStreamEvent::Response {
-                        content: message.content.clone(),
-                        timestamp,
-                    })
-                } else {
-                    None
                 }
-            },
-            "user" => {
-                // Don't emit user messages as events (they're already in the UI)
-                None
-            },
-            "result" => {
-                // Store session ID if present
-                if let Some(session_id) = &claude_event.session_id {
-                    if let Ok(mut current_session) = CURRENT_SESSION_ID.try_lock() {
-                        *current_session = Some(session_id.clone());
-                    }
+            }
+        }
+    }
+
+    // Sort by timestamp (newest first)
+    sessions.sort_by(|a, b| {
+        let ts_a = a.get("timestamp").and_then(|t| t.as_str()).unwrap_or("");
+        let ts_b = b.get("timestamp").and_then(|t| t.as_str()).unwrap_or("");
+        ts_b.cmp(ts_a)
+    });
+    
+    Ok(sessions)
+}
+
+// Text of a user message, skipping tool results and other non-prompt content
+fn user_prompt_text(message: &serde_json::Value) -> Option<String> {
+    let text = match message.get("content")? {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(blocks) => blocks.iter()
+            .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+    let text = text.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
+#[tauri::command]
+async fn list_resumable_sessions(project_path: String) -> Result<Vec<ResumableSession>, AppError> {
+    let entries = std::fs::read_dir(&project_path)
+        .map_err(|e| AppError::io("Failed to read project directory", e))?;
+    
+    // A session can span several files (e.g. after --resume), so group by the sessionId in the lines
+    let mut sessions: HashMap<String, ResumableSession> = HashMap::new();
+    
+    let duplicate_entries = duplicate_project_dirs(&project_path).into_iter()
+        .flat_map(|dir| std::fs::read_dir(dir).into_iter().flatten().flatten());
+    
+    for entry in entries.flatten().chain(duplicate_entries) {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+            continue;
+        }
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                println!("[WARN] Failed to read {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        
+        for line in content.lines() {
+            let json: serde_json::Value = match serde_json::from_str(line) {
+                Ok(json) => json,
+                Err(_) => continue,
+            };
+            let session_id = match json.get("sessionId").and_then(|s| s.as_str()) {
+                Some(id) => id.to_string(),
+                None => continue,
+            };
+            let timestamp = json.get("timestamp").and_then(|t| t.as_str()).map(|t| t.to_string());
+            
+            let session = sessions.entry(session_id.clone()).or_insert_with(|| ResumableSession {
+                session_id,
+                first_prompt: None,
+                started_at: None,
+                last_active: None,
+                message_count: 0,
+                file_path: path.to_string_lossy().to_string(),
+            });
+            
+            let is_user = json.get("type").and_then(|t| t.as_str()) == Some("user");
+            let is_assistant = json.get("type").and_then(|t| t.as_str()) == Some("assistant");
+            if is_user || is_assistant {
+                session.message_count += 1;
+            }
+            
+            if let Some(ts) = timestamp {
+                // RFC3339 timestamps compare correctly as strings
+                if session.started_at.as_ref().is_none_or(|start| ts < *start) {
+                    session.started_at = Some(ts.clone());
                 }
-                
-                if let Some(subtype) = &claude_event.subtype {
-                    match subtype.as_str() {
-                        "success" => {
-                            // Extract usage information if available
-                            if let Some(usage) = &claude_event.usage {
-                                Some(ClaudeStreamEvent::TokenUsage {
-                                    input: usage.input_tokens,
-                                    output: usage.output_tokens,
-                                    total: usage.input_tokens + usage.output_tokens,
-                                    timestamp,
-                                })
-                            } else {
-                                Some(ClaudeStreamEvent::Complete { timestamp })
-                            }
-                        },
-                        "error" => Some(ClaudeStreamEvent::Error {
-                            message: claude_event.error.unwrap_or_else(|| "Unknown error".to_string()),
-                            timestamp,
-                        }),
-                        _ => Some(ClaudeStreamEvent::Complete { timestamp }),
-                    }
-                } else {
-                    Some(ClaudeStreamEvent::Complete { timestamp })
+                if session.last_active.as_ref().is_none_or(|last| ts > *last) {
+                    session.last_active = Some(ts);
                 }
+            }
+            
+            let is_meta = json.get("isMeta").and_then(|m| m.as_bool()).unwrap_or(false);
+            if session.first_prompt.is_none() && is_user && !is_meta {
+                if let Some(text) = json.get("message").and_then(user_prompt_text) {
+                    session.first_prompt = Some(text.chars().take(200).collect());
+                }
+            }
+        }
+    }
+    
+    let mut sessions: Vec<ResumableSession> = sessions.into_values()
+        .filter(|session| session.message_count > 0)
+        .collect();
+    sessions.sort_by(|a, b| b.last_active.cmp(&a.last_active));
+    
+    Ok(sessions)
+}
+
+#[tauri::command]
+async fn detect_available_ides() -> Result<Vec<IDE>, AppError> {
+    let mut ides = Vec::new();
+    
+    // Common IDEs to detect
+    let ide_configs = vec![
+        ("Visual Studio Code", "code", vec![]),
+        ("VSCode Insiders", "code-insiders", vec![]),
+        ("Sublime Text", "subl", vec![]),
+        ("Atom", "atom", vec![]),
+        ("WebStorm", "webstorm", vec![]),
+        ("IntelliJ IDEA", "idea", vec![]),
+        ("PhpStorm", "phpstorm", vec![]),
+        ("PyCharm", "pycharm", vec![]),
+        ("Vim", "vim", vec![]),
+        ("Neovim", "nvim", vec![]),
+        ("Emacs", "emacs", vec![]),
+        ("Nano", "nano", vec![]),
+    ];
+    
+    for (name, command, default_args) in ide_configs {
+        let available = command_exists(command);
+        
+        ides.push(IDE {
+            name: name.to_string(),
+            command: command.to_string(),
+            args: default_args,
+            available,
+        });
+    }
+    
+    // On macOS, also check for apps in /Applications
+    #[cfg(target_os = "macos")]
+    {
+        let app_configs = vec![
+            ("Visual Studio Code", "/Applications/Visual Studio Code.app/Contents/Resources/app/bin/code", vec![]),
+            ("Sublime Text", "/Applications/Sublime Text.app/Contents/SharedSupport/bin/subl", vec![]),
+            ("Xcode", "xed", vec![]),
+        ];
+        
+        for (name, path, default_args) in app_configs {
+            let available = std::path::Path::new(path).exists() || 
+                command_exists(path.split('/').last().unwrap_or(path));
+            
+            if available && !ides.iter().any(|ide| ide.name == name) {
+                ides.push(IDE {
+                    name: name.to_string(),
+                    command: path.to_string(),
+                    args: default_args,
+                    available: true,
+                });
+            }
+        }
+    }
+    
+    Ok(ides)
+}
+
+// Look a program up on PATH (`where` on Windows, `which` elsewhere) and return its full path
+fn find_on_path(command: &str) -> Option<String> {
+    let locator = if cfg!(target_os = "windows") { "where.exe" } else { "which" };
+    let output = Command::new(locator)
+        .envs(LOGIN_SHELL_ENV.iter())
+        .arg(command)
+        .output()
+        .ok()?;
+    
+    if !output.status.success() {
+        return None;
+    }
+    
+    // `where` lists every match, one per line; the first is what would run
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim().to_string())
+        .find(|line| !line.is_empty())
+}
+
+fn command_exists(command: &str) -> bool {
+    find_on_path(command).is_some()
+}
+
+#[tauri::command]
+async fn open_file_in_ide(ide_command: String, file_path: String, line: Option<u32>) -> Result<(), AppError> {
+    let mut cmd = Command::new(&ide_command);
+    cmd.envs(LOGIN_SHELL_ENV.iter());
+    
+    // Add line number support for common IDEs
+    if let Some(line_num) = line {
+        match ide_command.as_str() {
+            "code" | "code-insiders" => {
+                cmd.arg("--goto").arg(format!("{}:{}", file_path, line_num));
+            },
+            "subl" => {
+                cmd.arg(format!("{}:{}", file_path, line_num));
+            },
+            "atom" => {
+                cmd.arg(format!("{}:{}", file_path, line_num));
+            },
+            "vim" | "nvim" => {
+                cmd.arg(format!("+{}", line_num)).arg(&file_path);
             },
             _ => {
-                // Don't emit unknown events as status to reduce noise
-                None
+                cmd.arg(&file_path);
             }
         }
     } else {
-        // Check if this might be a permission-related message
-        if trimmed.starts_with("Claude requested permissions") || 
-           trimmed.contains("permission") && (trimmed.contains("Allow") || trimmed.contains("Deny")) {
-            // This looks like a permission request
-            Some(ClaudeStreamEvent::PermissionRequest {
-                id: format!("perm_{}", timestamp),
-                prompt: "Claude is requesting permission to access files or perform operations".to_string(),
-                options: vec![
-                    "1: Allow".to_string(),
-                    "2: Allow and remember".to_string(), 
-                    "3: Deny".to_string(),
-                ],
-                timestamp,
-            })
-        } else {
-            // If it's not valid JSON, only process specific patterns to reduce noise
-            let line_lower = trimmed.to_lowercase();
-            
-            if line_lower.contains("thinking") || line_lower.contains("processing") {
-                Some(ClaudeStreamEvent::Thinking {
-                    message: trimmed.to_string(),
-                    timestamp,
-                })
+        cmd.arg(&file_path);
+    }
+    
+    cmd.spawn()
+        .map_err(|e| AppError::spawn(&ide_command, e))?;
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn open_project_in_ide(ide_command: String, project_path: String) -> Result<(), AppError> {
+    Command::new(&ide_command)
+        .envs(LOGIN_SHELL_ENV.iter())
+        .arg(&project_path)
+        .spawn()
+        .map_err(|e| AppError::spawn(&ide_command, e))?;
+    
+    Ok(())
+}
+
+// Terminal emulators tried in order on Linux, with the flag that sets their working directory
+#[cfg(target_os = "linux")]
+const LINUX_TERMINALS: &[(&str, &[&str])] = &[
+    ("gnome-terminal", &["--working-directory"]),
+    ("konsole", &["--workdir"]),
+    ("xfce4-terminal", &["--working-directory"]),
+    ("kitty", &["--directory"]),
+    ("alacritty", &["--working-directory"]),
+    ("wezterm", &["start", "--cwd"]),
+    ("x-terminal-emulator", &[]),
+    ("xterm", &[]),
+];
+
+// Open the platform terminal in the project's real directory. `terminal` picks a specific
+// app (e.g. "iTerm" on macOS or "konsole" on Linux); otherwise the usual default is used.
+#[tauri::command]
+async fn open_project_in_terminal(project_path: String, terminal: Option<String>) -> Result<(), AppError> {
+    let claude_projects_dir = dirs::home_dir().map(|home| home.join(".claude").join("projects"));
+    let is_claude_project = claude_projects_dir.is_some_and(|dir| std::path::Path::new(&project_path).starts_with(dir));
+    let dir = if is_claude_project {
+        match get_real_project_path(project_path).await? {
+            Some(path) => path,
+            None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+        }
+    } else {
+        project_path
+    };
+    if !std::path::Path::new(&dir).is_dir() {
+        return Err(AppError::PathNotFound(format!("Directory not found: {}", dir)));
+    }
+    let terminal = terminal.filter(|t| !t.trim().is_empty());
+    
+    #[cfg(target_os = "macos")]
+    {
+        let app_name = terminal.unwrap_or_else(|| "Terminal".to_string());
+        Command::new("open")
+            .args(["-a", &app_name, &dir])
+            .spawn()
+            .map_err(|e| AppError::spawn("open", e))?;
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        let use_wt = match terminal.as_deref() {
+            Some(t) => t == "wt",
+            None => command_exists("wt"),
+        };
+        if use_wt {
+            Command::new("wt")
+                .args(["-d", &dir])
+                .spawn()
+                .map_err(|e| AppError::spawn("wt", e))?;
+        } else {
+            Command::new("cmd")
+                .args(["/C", "start", "", "cmd", "/K"])
+                .current_dir(&dir)
+                .spawn()
+                .map_err(|e| AppError::spawn("cmd", e))?;
+        }
+    }
+    
+    #[cfg(target_os = "linux")]
+    {
+        // An explicit choice, then $TERMINAL, then the first known terminal installed
+        let preferred = terminal.or_else(|| std::env::var("TERMINAL").ok().filter(|t| !t.trim().is_empty()));
+        let program = match preferred {
+            Some(program) => program,
+            None => LINUX_TERMINALS.iter()
+                .map(|(name, _)| name.to_string())
+                .find(|name| command_exists(name))
+                .ok_or_else(|| AppError::NotFound("No terminal emulator found".to_string()))?,
+        };
+        
+        let mut cmd = Command::new(&program);
+        if let Some((_, flags)) = LINUX_TERMINALS.iter().find(|(name, _)| *name == program) {
+            if !flags.is_empty() {
+                cmd.args(*flags).arg(&dir);
+            }
+        }
+        cmd.current_dir(&dir)
+            .spawn()
+            .map_err(|e| AppError::spawn(&program, e))?;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_file_info(file_path: String) -> Result<FileInfo, AppError> {
+    let path = std::path::Path::new(&file_path);
+    
+    // symlink_metadata so broken links can still be inspected
+    if path.symlink_metadata().is_err() {
+        return Err(AppError::PathNotFound("File does not exist".to_string()));
+    }
+    
+    let mut info = get_file_info_sync(path, true)?;
+    if let (Some(parent), Some(name)) = (path.parent(), path.file_name()) {
+        info.git_tracked = git_tracked_paths(parent, &name.to_string_lossy()).map(|tracked| !tracked.is_empty());
+    }
+    Ok(info)
+}
+
+// Files git tracks matching `pathspec` in `dir`, as absolute paths; None outside a repository
+fn git_tracked_paths(dir: &std::path::Path, pathspec: &str) -> Option<HashSet<std::path::PathBuf>> {
+    let output = Command::new("git")
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(["ls-files", "-z", "--", pathspec])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| dir.join(path))
+        .collect())
+}
+
+// Walks a directory honoring .gitignore, .ignore and global git excludes (even outside a git
+// repository). Hidden files are always skipped; include_ignored lists ignored files too.
+fn project_walker(root: &std::path::Path, include_ignored: bool) -> ignore::WalkBuilder {
+    let mut builder = ignore::WalkBuilder::new(root);
+    builder.require_git(false);
+    if include_ignored {
+        builder.git_ignore(false)
+            .git_global(false)
+            .git_exclude(false)
+            .ignore(false)
+            .parents(false);
+    }
+    builder
+}
+
+// Line counts mean reading every file, so listings only include them when asked
+#[tauri::command]
+async fn get_project_files(project_path: String, pattern: Option<String>, include_ignored: Option<bool>, symlinks: Option<SymlinkMode>, include_line_counts: Option<bool>) -> Result<Vec<FileInfo>, AppError> {
+    let mut files = Vec::new();
+    
+    // First get the real project path (same as CLAUDE.md functionality)
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    
+    let path = std::path::Path::new(&real_path);
+    
+    if !path.exists() {
+        return Err(AppError::PathNotFound("Real project path does not exist".to_string()));
+    }
+    
+    fn scan_directory(dir: &std::path::Path, files: &mut Vec<FileInfo>, pattern: &Option<String>, include_ignored: bool, symlinks: SymlinkMode, line_counts: bool) -> Result<(), AppError> {
+        if !dir.is_dir() {
+            return Err(AppError::PathNotFound(format!("Directory does not exist: {}", dir.display())));
+        }
+        
+        let tracked = git_tracked_paths(dir, ".");
+        let mut walker = project_walker(dir, include_ignored);
+        walker.follow_links(symlinks == SymlinkMode::Follow);
+        for entry in walker.build() {
+            if files.len() >= 1000 { // Limit to prevent overwhelming
+                break;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(ignore::Error::WithDepth { err, .. }) if matches!(*err, ignore::Error::Loop { .. }) => {
+                    println!("[WARN] Skipping symlink cycle: {}", err);
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            let listed = match entry.file_type() {
+                Some(ft) if ft.is_symlink() => symlinks == SymlinkMode::Link,
+                Some(ft) => ft.is_file() && (symlinks != SymlinkMode::Skip || !entry.path_is_symlink()),
+                None => false,
+            };
+            if !listed {
+                continue;
+            }
+            let path = entry.path();
+            
+            if let Some(pattern_str) = pattern {
+                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                    if !name.contains(pattern_str) {
+                        continue;
+                    }
+                }
+            }
+            
+            if let Ok(mut file_info) = get_file_info_sync(path, line_counts) {
+                file_info.git_tracked = tracked.as_ref().map(|tracked| tracked.contains(path));
+                files.push(file_info);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    let include_ignored = include_ignored.unwrap_or(false);
+    let symlinks = symlinks.unwrap_or_default();
+    let line_counts = include_line_counts.unwrap_or(false);
+    scan_directory(path, &mut files, &pattern, include_ignored, symlinks, line_counts)?;
+    // The other roots of a multi-root workspace
+    for root in workspace_roots(&real_path).iter().skip(1) {
+        if let Err(e) = scan_directory(std::path::Path::new(root), &mut files, &pattern, include_ignored, symlinks, line_counts) {
+            println!("[WARN] Skipping workspace root {}: {}", root, e);
+        }
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    Ok(files)
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct ContentSearchOptions {
+    // Treat the query as a regular expression rather than literal text
+    #[serde(default)]
+    regex: bool,
+    #[serde(default)]
+    case_sensitive: bool,
+    // Globs relative to the project root, e.g. "src/**/*.ts"; "!"-prefixed excludes work too
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    max_results: Option<usize>,
+    #[serde(default)]
+    include_ignored: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ContentMatch {
+    file: String,
+    relative_path: String,
+    // 1-based line and character column of the match
+    line: usize,
+    column: usize,
+    preview: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ContentSearchResult {
+    matches: Vec<ContentMatch>,
+    files_searched: usize,
+    // More matches exist beyond max_results
+    truncated: bool,
+}
+
+// Files bigger than this are skipped by content search
+const CONTENT_SEARCH_MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+const CONTENT_SEARCH_PREVIEW_CHARS: usize = 200;
+
+fn search_content_in_root(
+    root: &std::path::Path,
+    pattern: &regex::Regex,
+    options: &ContentSearchOptions,
+    max_results: usize,
+    result: &mut ContentSearchResult,
+) -> Result<(), AppError> {
+    let mut overrides = ignore::overrides::OverrideBuilder::new(root);
+    for glob in &options.include {
+        overrides.add(glob)
+            .map_err(|e| AppError::InvalidInput(format!("Invalid include glob {}: {}", glob, e)))?;
+    }
+    for glob in &options.exclude {
+        let glob = if glob.starts_with('!') { glob.clone() } else { format!("!{}", glob) };
+        overrides.add(&glob)
+            .map_err(|e| AppError::InvalidInput(format!("Invalid exclude glob {}: {}", glob, e)))?;
+    }
+    let overrides = overrides.build()
+        .map_err(|e| AppError::InvalidInput(format!("Invalid glob: {}", e)))?;
+    
+    for entry in project_walker(root, options.include_ignored).overrides(overrides).build().flatten() {
+        if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+            continue;
+        }
+        if entry.metadata().map(|m| m.len() > CONTENT_SEARCH_MAX_FILE_BYTES).unwrap_or(true) {
+            continue;
+        }
+        let bytes = match std::fs::read(entry.path()) {
+            Ok(bytes) => bytes,
+            Err(_) => continue,
+        };
+        // Skip binary files
+        if bytes.iter().take(8192).any(|b| *b == 0) {
+            continue;
+        }
+        result.files_searched += 1;
+        
+        let content = String::from_utf8_lossy(&bytes);
+        let relative_path = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_string_lossy().to_string();
+        for (index, line) in content.lines().enumerate() {
+            for found in pattern.find_iter(line) {
+                if result.matches.len() >= max_results {
+                    result.truncated = true;
+                    return Ok(());
+                }
+                result.matches.push(ContentMatch {
+                    file: entry.path().to_string_lossy().to_string(),
+                    relative_path: relative_path.clone(),
+                    line: index + 1,
+                    column: line[..found.start()].chars().count() + 1,
+                    preview: line.trim().chars().take(CONTENT_SEARCH_PREVIEW_CHARS).collect(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn search_project_content(
+    project_path: String,
+    query: String,
+    options: Option<ContentSearchOptions>
+) -> Result<ContentSearchResult, AppError> {
+    if query.is_empty() {
+        return Err(AppError::InvalidInput("Search query is required".to_string()));
+    }
+    let options = options.unwrap_or_default();
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    
+    let source = if options.regex { query.clone() } else { regex::escape(&query) };
+    let pattern = regex::RegexBuilder::new(&source)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| AppError::InvalidInput(format!("Invalid regular expression: {}", e)))?;
+    
+    let mut roots = workspace_roots(&real_path);
+    if roots.is_empty() {
+        roots.push(real_path);
+    }
+    
+    tokio::task::spawn_blocking(move || {
+        let max_results = options.max_results.unwrap_or(500).max(1);
+        let mut result = ContentSearchResult {
+            matches: Vec::new(),
+            files_searched: 0,
+            truncated: false,
+        };
+        for root in &roots {
+            search_content_in_root(std::path::Path::new(root), &pattern, &options, max_results, &mut result)?;
+            if result.truncated {
+                break;
+            }
+        }
+        Ok(result)
+    }).await
+        .map_err(|e| AppError::Other(format!("Content search failed: {}", e)))?
+}
+
+// Directories the file commands may modify: the real paths of registered projects and
+// workspace roots, plus the paths the user granted in a native dialog. Session working
+// directories aren't trusted, and the filesystem root and home directory never count.
+fn writable_roots() -> Vec<std::path::PathBuf> {
+    let mut roots: Vec<String> = read_project_registry().into_iter()
+        .filter_map(|record| record.real_path)
+        .collect();
+    roots.extend(read_workspaces().into_iter().flat_map(|workspace| workspace.roots));
+    roots.extend(read_app_settings().map(|settings| settings.granted_paths).unwrap_or_default());
+    
+    roots.iter()
+        .filter_map(|root| std::fs::canonicalize(root).ok())
+        .filter(|root| !is_protected_root(root))
+        .collect()
+}
+
+// Too broad to be a writable root: granting it would open up ~/.ssh, shell profiles, etc.
+fn is_protected_root(path: &std::path::Path) -> bool {
+    path.parent().is_none()
+        || dirs::home_dir()
+            .and_then(|home| std::fs::canonicalize(home).ok())
+            .is_some_and(|home| home.starts_with(path))
+}
+
+// Ask the user for a folder in a native dialog. Paths that come from the webview are only
+// used as the starting point, so an injected call can't pick the folder itself.
+async fn pick_folder_natively(app: &tauri::AppHandle, title: &str, start: Option<&str>) -> Option<std::path::PathBuf> {
+    use tauri_plugin_dialog::DialogExt;
+    
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let mut dialog = app.dialog().file().set_title(title);
+    if let Some(start) = start.filter(|start| std::path::Path::new(start).is_dir()) {
+        dialog = dialog.set_directory(start);
+    }
+    dialog.pick_folder(move |result| {
+        let _ = tx.send(result);
+    });
+    rx.await.ok().flatten().and_then(|path| path.into_path().ok())
+}
+
+// Ask the user where to save a file in a native dialog, for exports that may go anywhere
+async fn pick_save_path_natively(app: &tauri::AppHandle, title: &str, file_name: &str) -> Option<std::path::PathBuf> {
+    use tauri_plugin_dialog::DialogExt;
+    
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title(title)
+        .set_file_name(file_name)
+        .save_file(move |result| {
+            let _ = tx.send(result);
+        });
+    rx.await.ok().flatten().and_then(|path| path.into_path().ok())
+}
+
+// Absolute path with symlinks and ".." resolved, including paths that don't exist yet
+fn normalize_path(path: &std::path::Path) -> Result<std::path::PathBuf, AppError> {
+    if !path.is_absolute() {
+        return Err(AppError::InvalidInput(format!("Path must be absolute: {}", path.display())));
+    }
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(mut resolved) = std::fs::canonicalize(existing) {
+            resolved.extend(missing.iter().rev());
+            return Ok(resolved);
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return Err(AppError::InvalidInput(format!("Cannot resolve path: {}", path.display()))),
+        }
+    }
+}
+
+// Guard for commands that create, change or delete files: the path must lie inside a
+// writable root (and not be the root itself). Returns the innermost root containing it.
+fn ensure_writable_path(path: &str) -> Result<std::path::PathBuf, AppError> {
+    let resolved = normalize_path(std::path::Path::new(path))?;
+    let root = writable_roots().into_iter()
+        .filter(|root| resolved.starts_with(root) && resolved != *root)
+        .max_by_key(|root| root.components().count());
+    match root {
+        Some(root) => Ok(root),
+        None => {
+            println!("[WARN] Blocked file operation outside project roots: {}", path);
+            Err(AppError::AccessDenied(format!("{} is outside the open projects", path)))
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_granted_paths() -> Result<Vec<String>, AppError> {
+    Ok(read_app_settings()?.granted_paths)
+}
+
+// Let the file commands modify a directory that isn't part of any project. The directory
+// is chosen by the user in a native dialog; `suggested` only sets where it opens.
+#[tauri::command]
+async fn grant_path_access(app: tauri::AppHandle, suggested: Option<String>) -> Result<Vec<String>, AppError> {
+    let picked = match pick_folder_natively(&app, "Allow file changes in folder", suggested.as_deref()).await {
+        Some(picked) => picked,
+        None => return Ok(read_app_settings()?.granted_paths),
+    };
+    let resolved = std::fs::canonicalize(&picked)
+        .map_err(|e| AppError::io(&format!("Cannot grant access to {}", picked.display()), e))?;
+    if is_protected_root(&resolved) {
+        return Err(AppError::AccessDenied(format!("{} is too broad to allow file changes in", resolved.display())));
+    }
+    let resolved = resolved.to_string_lossy().to_string();
+    
+    let mut settings = read_app_settings()?;
+    if !settings.granted_paths.contains(&resolved) {
+        settings.granted_paths.push(resolved);
+        write_app_settings(&settings)?;
+    }
+    Ok(settings.granted_paths)
+}
+
+#[tauri::command]
+async fn revoke_path_access(path: String) -> Result<Vec<String>, AppError> {
+    let resolved = std::fs::canonicalize(&path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.clone());
+    
+    let mut settings = read_app_settings()?;
+    settings.granted_paths.retain(|granted| *granted != path && *granted != resolved);
+    write_app_settings(&settings)?;
+    Ok(settings.granted_paths)
+}
+
+// New comprehensive file system commands
+#[tauri::command]
+async fn read_file_content(file_path: String) -> Result<String, AppError> {
+    let path = std::path::Path::new(&file_path);
+    
+    if !path.exists() {
+        return Err(AppError::PathNotFound("File does not exist".to_string()));
+    }
+    
+    if !path.is_file() {
+        return Err(AppError::InvalidInput("Path is not a file".to_string()));
+    }
+    
+    // Check file size (limit to 10MB for safety)
+    if let Ok(metadata) = path.metadata() {
+        if metadata.len() > 10 * 1024 * 1024 {
+            return Err(AppError::InvalidInput("File too large (max 10MB), use read_file_range or read_file_chunked".to_string()));
+        }
+    }
+    
+    // Only UTF-8 text; other encodings go through read_file_with_encoding so they can be
+    // saved back in the same encoding
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| AppError::io("Failed to read file", e))?;
+    if content.contains('\0') {
+        return Err(AppError::InvalidInput("Binary file, use read_file_binary".to_string()));
+    }
+    Ok(content)
+}
+
+#[derive(Debug, Serialize)]
+struct DecodedFile {
+    content: String,
+    // WHATWG encoding name, e.g. "UTF-8", "windows-1252", "UTF-16LE"; pass it back to
+    // write_file_content to save in the same encoding
+    encoding: String,
+    has_bom: bool,
+    // Some bytes weren't valid in the detected encoding and were replaced
+    had_errors: bool,
+}
+
+// Decode file contents to UTF-8: a byte order mark wins, then valid UTF-8, then a guess
+// from the byte statistics (which covers Latin-1 and the other legacy code pages)
+fn decode_text(bytes: &[u8]) -> DecodedFile {
+    let (encoding, bom_length) = match encoding_rs::Encoding::for_bom(bytes) {
+        Some((encoding, length)) => (encoding, length),
+        None if std::str::from_utf8(bytes).is_ok() => (encoding_rs::UTF_8, 0),
+        None => (guess_bomless_encoding(bytes), 0),
+    };
+    let (content, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+    DecodedFile {
+        content: content.into_owned(),
+        encoding: encoding.name().to_string(),
+        has_bom: bom_length > 0,
+        had_errors,
+    }
+}
+
+fn guess_bomless_encoding(bytes: &[u8]) -> &'static encoding_rs::Encoding {
+    // UTF-16 text without a BOM is mostly ASCII with every other byte zero
+    let sample = &bytes[..bytes.len().min(4096) & !1];
+    if !sample.is_empty() {
+        let pairs = sample.len() / 2;
+        let even_zeros = sample.iter().step_by(2).filter(|byte| **byte == 0).count();
+        let odd_zeros = sample.iter().skip(1).step_by(2).filter(|byte| **byte == 0).count();
+        if odd_zeros * 10 >= pairs * 3 && even_zeros * 10 < pairs {
+            return encoding_rs::UTF_16LE;
+        }
+        if even_zeros * 10 >= pairs * 3 && odd_zeros * 10 < pairs {
+            return encoding_rs::UTF_16BE;
+        }
+    }
+    
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+// Encode text for saving in `encoding`; encoding_rs only decodes UTF-16, so that's done here
+fn encode_text(content: &str, encoding: &str, bom: bool) -> Result<Vec<u8>, AppError> {
+    let encoding = encoding_rs::Encoding::for_label(encoding.as_bytes())
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown encoding: {}", encoding)))?;
+    
+    let mut bytes = Vec::with_capacity(content.len() + 3);
+    if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+        let little_endian = encoding == encoding_rs::UTF_16LE;
+        if bom {
+            bytes.extend_from_slice(if little_endian { &[0xFF, 0xFE] } else { &[0xFE, 0xFF] });
+        }
+        for unit in content.encode_utf16() {
+            bytes.extend_from_slice(&if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() });
+        }
+        return Ok(bytes);
+    }
+    
+    if bom && encoding == encoding_rs::UTF_8 {
+        bytes.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+    }
+    let (encoded, _, unmappable) = encoding.encode(content);
+    if unmappable {
+        return Err(AppError::InvalidInput(format!("The text contains characters that can't be saved as {}", encoding.name())));
+    }
+    bytes.extend_from_slice(&encoded);
+    Ok(bytes)
+}
+
+// Like read_file_content, also reporting which encoding the file was decoded from
+#[tauri::command]
+async fn read_file_with_encoding(file_path: String) -> Result<DecodedFile, AppError> {
+    let path = std::path::Path::new(&file_path);
+    if !path.is_file() {
+        return Err(AppError::PathNotFound(format!("File does not exist: {}", file_path)));
+    }
+    if path.metadata().map(|metadata| metadata.len()).unwrap_or(0) > 10 * 1024 * 1024 {
+        return Err(AppError::InvalidInput("File too large (max 10MB), use read_file_range or read_file_chunked".to_string()));
+    }
+    
+    let bytes = std::fs::read(path)
+        .map_err(|e| AppError::io("Failed to read file", e))?;
+    let decoded = decode_text(&bytes);
+    // NULs outside UTF-16 mean binary data, which no text encoding round-trips
+    if decoded.content.contains('\0') {
+        return Err(AppError::InvalidInput("Binary file, use read_file_binary".to_string()));
+    }
+    Ok(decoded)
+}
+
+#[derive(Debug, Serialize)]
+struct FileRange {
+    content: String,
+    // 1-based, inclusive; end_line is the last line actually returned
+    start_line: usize,
+    end_line: usize,
+    // Lines exist past end_line
+    has_more: bool,
+}
+
+const FILE_RANGE_MAX_LINES: usize = 50_000;
+
+// Lines start_line..=end_line of a file of any size, without loading the rest of it
+#[tauri::command]
+async fn read_file_range(file_path: String, start_line: usize, end_line: usize) -> Result<FileRange, AppError> {
+    use std::io::BufRead;
+    
+    if start_line == 0 || end_line < start_line {
+        return Err(AppError::InvalidInput(format!("Invalid line range {}-{}", start_line, end_line)));
+    }
+    let end_line = end_line.min(start_line + FILE_RANGE_MAX_LINES - 1);
+    let file = std::fs::File::open(&file_path)
+        .map_err(|e| AppError::io(&format!("Failed to open {}", file_path), e))?;
+    
+    tokio::task::spawn_blocking(move || {
+        let mut reader = std::io::BufReader::new(file);
+        let mut line = Vec::new();
+        let mut content = String::new();
+        let mut line_number = 0;
+        let mut last_line = start_line - 1;
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line)
+                .map_err(|e| AppError::io("Failed to read file", e))?;
+            if read == 0 {
+                break;
+            }
+            line_number += 1;
+            if line_number < start_line {
+                continue;
+            }
+            if line_number > end_line {
+                return Ok(FileRange { content, start_line, end_line: last_line, has_more: true });
+            }
+            content.push_str(&String::from_utf8_lossy(&line));
+            last_line = line_number;
+        }
+        Ok(FileRange { content, start_line, end_line: last_line, has_more: false })
+    }).await
+        .map_err(|e| AppError::Other(format!("File read failed: {}", e)))?
+}
+
+#[derive(Debug, Serialize)]
+struct FileChunk {
+    content: String,
+    offset: u64,
+    // Where the following chunk starts; content never splits a UTF-8 character
+    next_offset: u64,
+    total_size: u64,
+    eof: bool,
+}
+
+const FILE_CHUNK_DEFAULT_BYTES: usize = 1024 * 1024;
+const FILE_CHUNK_MAX_BYTES: usize = 8 * 1024 * 1024;
+
+// Page through a file by byte offset, for huge logs and generated files
+#[tauri::command]
+async fn read_file_chunked(file_path: String, offset: Option<u64>, chunk_size: Option<usize>) -> Result<FileChunk, AppError> {
+    use std::io::{Seek, SeekFrom};
+    
+    let offset = offset.unwrap_or(0);
+    let chunk_size = chunk_size.unwrap_or(FILE_CHUNK_DEFAULT_BYTES).clamp(1, FILE_CHUNK_MAX_BYTES);
+    let mut file = std::fs::File::open(&file_path)
+        .map_err(|e| AppError::io(&format!("Failed to open {}", file_path), e))?;
+    let total_size = file.metadata()
+        .map_err(|e| AppError::io("Failed to read file metadata", e))?
+        .len();
+    if offset > total_size {
+        return Err(AppError::InvalidInput(format!("Offset {} is past the end of the file ({} bytes)", offset, total_size)));
+    }
+    
+    file.seek(SeekFrom::Start(offset))
+        .map_err(|e| AppError::io("Failed to seek file", e))?;
+    let mut buffer = Vec::with_capacity(chunk_size);
+    file.take(chunk_size as u64).read_to_end(&mut buffer)
+        .map_err(|e| AppError::io("Failed to read file", e))?;
+    
+    // Leave a character cut off at the end of the chunk for the next one
+    let at_end = offset + buffer.len() as u64 >= total_size;
+    if !at_end {
+        if let Err(e) = std::str::from_utf8(&buffer) {
+            if e.error_len().is_none() && e.valid_up_to() > 0 {
+                buffer.truncate(e.valid_up_to());
+            }
+        }
+    }
+    
+    let next_offset = offset + buffer.len() as u64;
+    Ok(FileChunk {
+        content: String::from_utf8_lossy(&buffer).to_string(),
+        offset,
+        next_offset,
+        total_size,
+        eof: next_offset >= total_size,
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct BinaryFile {
+    // Base64-encoded file contents
+    data: String,
+    mime_type: String,
+    size: u64,
+}
+
+const BINARY_READ_MAX_BYTES: u64 = 25 * 1024 * 1024;
+
+// Raw file contents for previews of images, PDFs and other non-text files. The MIME type
+// comes from the file's magic bytes, falling back to the extension-based guess.
+#[tauri::command]
+async fn read_file_binary(file_path: String) -> Result<BinaryFile, AppError> {
+    use base64::Engine;
+    
+    let path = std::path::Path::new(&file_path);
+    if !path.is_file() {
+        return Err(AppError::PathNotFound("File does not exist".to_string()));
+    }
+    let size = path.metadata()
+        .map_err(|e| AppError::io("Failed to read file metadata", e))?
+        .len();
+    if size > BINARY_READ_MAX_BYTES {
+        return Err(AppError::InvalidInput(format!("File too large (max {}MB)", BINARY_READ_MAX_BYTES / 1024 / 1024)));
+    }
+    
+    let bytes = std::fs::read(path)
+        .map_err(|e| AppError::io("Failed to read file", e))?;
+    let mime_type = match infer::get(&bytes) {
+        Some(kind) => kind.mime_type().to_string(),
+        None => get_file_info_sync(path, false).map(|info| info.mime_type).unwrap_or_else(|_| "application/octet-stream".to_string()),
+    };
+    
+    Ok(BinaryFile {
+        data: base64::engine::general_purpose::STANDARD.encode(&bytes),
+        mime_type,
+        size,
+    })
+}
+
+#[derive(Debug, Serialize)]
+struct ImageThumbnail {
+    // Base64-encoded PNG, or JPEG for images without transparency
+    data: String,
+    mime_type: String,
+    width: u32,
+    height: u32,
+    original_width: u32,
+    original_height: u32,
+}
+
+const THUMBNAIL_DEFAULT_DIMENSION: u32 = 256;
+const THUMBNAIL_JPEG_QUALITY: u8 = 80;
+
+// Downscaled preview of an image (PNG, JPEG, GIF, WebP, BMP, ICO, TIFF) that fits in
+// max_dimension x max_dimension, so previews don't send the original over IPC
+#[tauri::command]
+async fn get_image_thumbnail(path: String, max_dimension: Option<u32>) -> Result<ImageThumbnail, AppError> {
+    use base64::Engine;
+    
+    let file = std::path::PathBuf::from(&path);
+    if !file.is_file() {
+        return Err(AppError::PathNotFound(format!("File does not exist: {}", path)));
+    }
+    let size = file.metadata()
+        .map_err(|e| AppError::io("Failed to read file metadata", e))?
+        .len();
+    if size > BINARY_READ_MAX_BYTES {
+        return Err(AppError::InvalidInput(format!("Image too large (max {}MB)", BINARY_READ_MAX_BYTES / 1024 / 1024)));
+    }
+    let max_dimension = max_dimension.unwrap_or(THUMBNAIL_DEFAULT_DIMENSION).max(1);
+    
+    tokio::task::spawn_blocking(move || {
+        let image = image::ImageReader::open(&file)
+            .map_err(|e| AppError::io("Failed to open image", e))?
+            .with_guessed_format()
+            .map_err(|e| AppError::io("Failed to read image", e))?
+            .decode()
+            .map_err(|e| AppError::InvalidInput(format!("Cannot decode {}: {}", path, e)))?;
+        let (original_width, original_height) = (image.width(), image.height());
+        let thumbnail = if original_width > max_dimension || original_height > max_dimension {
+            image.thumbnail(max_dimension, max_dimension)
+        } else {
+            image
+        };
+        
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        let mime_type = if thumbnail.color().has_alpha() {
+            thumbnail.write_to(&mut bytes, image::ImageFormat::Png)
+                .map_err(|e| AppError::Other(format!("Failed to encode thumbnail: {}", e)))?;
+            "image/png"
+        } else {
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, THUMBNAIL_JPEG_QUALITY);
+            image::DynamicImage::ImageRgb8(thumbnail.to_rgb8()).write_with_encoder(encoder)
+                .map_err(|e| AppError::Other(format!("Failed to encode thumbnail: {}", e)))?;
+            "image/jpeg"
+        };
+        
+        Ok(ImageThumbnail {
+            data: base64::engine::general_purpose::STANDARD.encode(bytes.into_inner()),
+            mime_type: mime_type.to_string(),
+            width: thumbnail.width(),
+            height: thumbnail.height(),
+            original_width,
+            original_height,
+        })
+    }).await
+        .map_err(|e| AppError::Other(format!("Thumbnail generation failed: {}", e)))?
+}
+
+#[tauri::command]
+async fn write_file_content(file_path: String, content: String, encoding: Option<String>, bom: Option<bool>) -> Result<(), AppError> {
+    let root = ensure_writable_path(&file_path)?;
+    let path = std::path::Path::new(&file_path);
+    // UTF-8 unless saving back a file read in another encoding
+    let bytes = match encoding {
+        Some(encoding) => encode_text(&content, &encoding, bom.unwrap_or(false))?,
+        None => content.into_bytes(),
+    };
+    
+    // Ensure parent directory exists
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create parent directory", e))?;
+    }
+    
+    let existed = path.exists();
+    let previous = previous_content_for_undo(path);
+    std::fs::write(path, bytes)
+        .map_err(|e| AppError::io("Failed to write file", e))?;
+    
+    match previous {
+        Some(bytes) => record_file_undo(root, format!("Write {}", file_path), FileOpUndo::RestoreContent(path.to_path_buf(), bytes)),
+        None if !existed => record_file_undo(root, format!("Create {}", file_path), FileOpUndo::RemoveFile(path.to_path_buf())),
+        None => record_file_overwrite_barrier(root, path),
+    }
+    Ok(())
+}
+
+// Add to the end of a file, creating it if needed
+#[tauri::command]
+async fn append_file_content(file_path: String, chunk: String) -> Result<(), AppError> {
+    use std::io::Write;
+    
+    let root = ensure_writable_path(&file_path)?;
+    let path = std::path::Path::new(&file_path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create parent directory", e))?;
+    }
+    
+    let existed = path.exists();
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| AppError::io("Failed to open file", e))?;
+    file.write_all(chunk.as_bytes())
+        .map_err(|e| AppError::io("Failed to append to file", e))?;
+    
+    if !existed {
+        record_file_undo(root, format!("Create {}", file_path), FileOpUndo::RemoveFile(path.to_path_buf()));
+    }
+    Ok(())
+}
+
+// A file written in chunks to a temporary file next to it, which replaces the target when
+// the stream is closed. `writer` is taken when the stream is closed or discarded.
+struct WriteStream {
+    writer: Option<std::io::BufWriter<std::fs::File>>,
+    temp_path: std::path::PathBuf,
+    target: std::path::PathBuf,
+    root: std::path::PathBuf,
+    bytes_written: u64,
+    last_used: std::time::Instant,
+}
+
+// Streams left open this long without a chunk are discarded by sweep_write_streams
+const WRITE_STREAM_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+// Each stream has its own lock so writes never hold WRITE_STREAMS during disk I/O
+fn get_write_stream(stream_id: &str) -> Result<Arc<std::sync::Mutex<WriteStream>>, AppError> {
+    WRITE_STREAMS.lock().unwrap().get(stream_id).cloned()
+        .ok_or_else(|| AppError::NotFound(format!("Write stream {} not found", stream_id)))
+}
+
+fn take_write_stream(stream_id: &str) -> Result<Arc<std::sync::Mutex<WriteStream>>, AppError> {
+    WRITE_STREAMS.lock().unwrap().remove(stream_id)
+        .ok_or_else(|| AppError::NotFound(format!("Write stream {} not found", stream_id)))
+}
+
+// Start writing a large file without building it in memory; returns the stream ID for
+// write_stream_chunk and close_write_stream
+#[tauri::command]
+async fn open_write_stream(file_path: String) -> Result<String, AppError> {
+    let root = ensure_writable_path(&file_path)?;
+    let target = std::path::PathBuf::from(&file_path);
+    if target.is_dir() {
+        return Err(AppError::InvalidInput(format!("{} is a directory", file_path)));
+    }
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create parent directory", e))?;
+    }
+    
+    let id = Uuid::new_v4().to_string();
+    let name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let temp_path = target.with_file_name(format!(".{}.gui-write-{}", name, Uuid::new_v4().simple()));
+    let file = std::fs::File::create(&temp_path)
+        .map_err(|e| AppError::io(&format!("Failed to create {}", temp_path.display()), e))?;
+    
+    WRITE_STREAMS.lock().unwrap().insert(id.clone(), Arc::new(std::sync::Mutex::new(WriteStream {
+        writer: Some(std::io::BufWriter::new(file)),
+        temp_path,
+        target,
+        root,
+        bytes_written: 0,
+        last_used: std::time::Instant::now(),
+    })));
+    Ok(id)
+}
+
+#[tauri::command]
+async fn write_stream_chunk(stream_id: String, chunk: String) -> Result<u64, AppError> {
+    use std::io::Write;
+    
+    let stream = get_write_stream(&stream_id)?;
+    let mut stream = stream.lock().unwrap();
+    let target = stream.target.clone();
+    let writer = stream.writer.as_mut()
+        .ok_or_else(|| AppError::NotFound(format!("Write stream {} not found", stream_id)))?;
+    writer.write_all(chunk.as_bytes())
+        .map_err(|e| AppError::io(&format!("Failed to write {}", target.display()), e))?;
+    stream.bytes_written += chunk.len() as u64;
+    stream.last_used = std::time::Instant::now();
+    Ok(stream.bytes_written)
+}
+
+// Flush the stream and move it into place, returning the number of bytes written
+#[tauri::command]
+async fn close_write_stream(stream_id: String) -> Result<u64, AppError> {
+    use std::io::Write;
+    
+    let stream = take_write_stream(&stream_id)?;
+    let mut stream = stream.lock().unwrap();
+    let mut writer = stream.writer.take()
+        .ok_or_else(|| AppError::NotFound(format!("Write stream {} not found", stream_id)))?;
+    let flushed = writer.flush();
+    drop(writer);
+    if let Err(e) = flushed {
+        let _ = std::fs::remove_file(&stream.temp_path);
+        return Err(AppError::io(&format!("Failed to write {}", stream.target.display()), e));
+    }
+    
+    // Keep the target's mode (e.g. executable scripts) rather than the temp file's default
+    let existed = stream.target.exists();
+    if let Ok(metadata) = std::fs::metadata(&stream.target) {
+        if let Err(e) = std::fs::set_permissions(&stream.temp_path, metadata.permissions()) {
+            println!("[WARN] Failed to copy permissions to {}: {}", stream.temp_path.display(), e);
+        }
+    }
+    let previous = previous_content_for_undo(&stream.target);
+    if let Err(e) = std::fs::rename(&stream.temp_path, &stream.target) {
+        let _ = std::fs::remove_file(&stream.temp_path);
+        return Err(AppError::io(&format!("Failed to write {}", stream.target.display()), e));
+    }
+    
+    let description = format!("Write {}", stream.target.display());
+    let (root, target) = (stream.root.clone(), stream.target.clone());
+    match previous {
+        Some(bytes) => record_file_undo(root, description, FileOpUndo::RestoreContent(target, bytes)),
+        None if !existed => record_file_undo(root, description, FileOpUndo::RemoveFile(target)),
+        None => record_file_overwrite_barrier(root, &target),
+    }
+    Ok(stream.bytes_written)
+}
+
+// Discard a stream, leaving the target file untouched
+#[tauri::command]
+async fn abort_write_stream(stream_id: String) -> Result<(), AppError> {
+    let stream = take_write_stream(&stream_id)?;
+    let mut stream = stream.lock().unwrap();
+    drop(stream.writer.take());
+    std::fs::remove_file(&stream.temp_path)
+        .map_err(|e| AppError::io(&format!("Failed to remove {}", stream.temp_path.display()), e))
+}
+
+// Discard streams the frontend opened but never closed or aborted, and their temp files
+async fn sweep_write_streams() {
+    loop {
+        tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
+        
+        let idle: Vec<_> = {
+            let mut streams = WRITE_STREAMS.lock().unwrap();
+            let ids: Vec<String> = streams.iter()
+                // A stream someone is writing to right now isn't idle
+                .filter(|(_, stream)| stream.try_lock().is_ok_and(|s| s.last_used.elapsed() > WRITE_STREAM_IDLE_TIMEOUT))
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.into_iter().filter_map(|id| streams.remove(&id).map(|stream| (id, stream))).collect()
+        };
+        
+        for (id, stream) in idle {
+            let mut stream = stream.lock().unwrap();
+            drop(stream.writer.take());
+            println!("[INFO] Discarding idle write stream {} for {}", id, stream.target.display());
+            if let Err(e) = std::fs::remove_file(&stream.temp_path) {
+                println!("[WARN] Failed to remove {}: {}", stream.temp_path.display(), e);
+            }
+        }
+    }
+}
+
+#[tauri::command]
+async fn create_file(file_path: String, content: Option<String>) -> Result<(), AppError> {
+    let root = ensure_writable_path(&file_path)?;
+    let path = std::path::Path::new(&file_path);
+    
+    if path.exists() {
+        return Err(AppError::AlreadyExists("File already exists".to_string()));
+    }
+    
+    // Ensure parent directory exists
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create parent directory", e))?;
+    }
+    
+    let file_content = content.unwrap_or_default();
+    std::fs::write(path, file_content)
+        .map_err(|e| AppError::io("Failed to create file", e))?;
+    
+    record_file_undo(root, format!("Create {}", file_path), FileOpUndo::RemoveFile(path.to_path_buf()));
+    Ok(())
+}
+
+#[tauri::command]
+async fn create_directory(dir_path: String) -> Result<(), AppError> {
+    let root = ensure_writable_path(&dir_path)?;
+    let path = std::path::Path::new(&dir_path);
+    
+    if path.exists() {
+        return Err(AppError::AlreadyExists("Directory already exists".to_string()));
+    }
+    
+    // Undo removes the outermost directory this call created
+    let created = path.ancestors()
+        .take_while(|dir| !dir.exists())
+        .last()
+        .unwrap_or(path)
+        .to_path_buf();
+    std::fs::create_dir_all(path)
+        .map_err(|e| AppError::io("Failed to create directory", e))?;
+    
+    record_file_undo(root, format!("Create directory {}", dir_path), FileOpUndo::RemoveDir { top: created, deepest: path.to_path_buf() });
+    Ok(())
+}
+
+// Moves the file or directory to the platform trash; `permanent` removes it for good
+#[tauri::command]
+async fn delete_file(file_path: String, permanent: Option<bool>) -> Result<(), AppError> {
+    let root = ensure_writable_path(&file_path)?;
+    let path = std::path::Path::new(&file_path);
+    
+    if !path.exists() {
+        return Err(AppError::PathNotFound("File does not exist".to_string()));
+    }
+    
+    // Trashed entries can be restored; anything else leaves a barrier so undo stops here
+    // rather than reverting an older, unrelated change
+    let description = format!("Delete {}", file_path);
+    if !permanent.unwrap_or(false) {
+        trash::delete(path)
+            .map_err(|e| AppError::Io(format!("Failed to move {} to the trash: {}", file_path, e)))?;
+        match find_trashed_item(&normalize_path(path)?) {
+            Some(item) => record_file_undo(root, description, FileOpUndo::Untrash(item)),
+            None => record_file_undo(root, description, FileOpUndo::Barrier(
+                format!("Can't undo deleting {}: restore it from the trash instead", file_path)
+            )),
+        }
+        return Ok(());
+    }
+    
+    if path.is_file() {
+        std::fs::remove_file(path)
+            .map_err(|e| AppError::io("Failed to delete file", e))?;
+    } else if path.is_dir() {
+        std::fs::remove_dir_all(path)
+            .map_err(|e| AppError::io("Failed to delete directory", e))?;
+    } else {
+        return Err(AppError::InvalidInput("Path is neither file nor directory".to_string()));
+    }
+    record_file_undo(root, description, FileOpUndo::Barrier(format!("Can't undo deleting {}: it was deleted permanently", file_path)));
+    Ok(())
+}
+
+#[tauri::command]
+async fn rename_file(old_path: String, new_path: String) -> Result<(), AppError> {
+    let root = ensure_writable_path(&old_path)?;
+    ensure_writable_path(&new_path)?;
+    let old = std::path::Path::new(&old_path);
+    let new = std::path::Path::new(&new_path);
+    
+    if !old.exists() {
+        return Err(AppError::PathNotFound("Source file does not exist".to_string()));
+    }
+    
+    if new.exists() {
+        return Err(AppError::AlreadyExists("Destination already exists".to_string()));
+    }
+    
+    // Ensure parent directory of new path exists
+    if let Some(parent) = new.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create destination directory", e))?;
+    }
+    
+    std::fs::rename(old, new)
+        .map_err(|e| AppError::io("Failed to rename file", e))?;
+    
+    record_file_undo(root, format!("Rename {} to {}", old_path, new_path), FileOpUndo::Move { from: new.to_path_buf(), to: old.to_path_buf() });
+    Ok(())
+}
+
+// The immediate, non-ignored entries of a directory
+fn directory_entries(dir: &std::path::Path, include_ignored: bool) -> impl Iterator<Item = ignore::DirEntry> {
+    project_walker(dir, include_ignored)
+        .max_depth(Some(1))
+        .build()
+        .flatten()
+        .filter(|entry| entry.depth() == 1)
+}
+
+// `git status` of everything under a directory, for coloring the file tree
+struct GitStatusMap {
+    base: std::path::PathBuf,
+    // `base` relative to the repository root, with a trailing slash ("" at the root)
+    prefix: String,
+    // Repository-relative paths; untracked and ignored directories end in '/'
+    entries: HashMap<String, &'static str>,
+    // Every directory ("dir/", or "" for the root) containing a non-ignored entry
+    dirty_dirs: HashSet<String>,
+}
+
+impl GitStatusMap {
+    // None outside a git repository
+    fn load(dir: &std::path::Path) -> Option<GitStatusMap> {
+        let dir_str = dir.to_string_lossy();
+        let prefix = run_git(&dir_str, &["rev-parse", "--show-prefix"])?;
+        let output = Command::new("git")
+            .envs(LOGIN_SHELL_ENV.iter())
+            .args(["status", "--porcelain=v1", "-z", "--ignored", "--", "."])
+            .current_dir(dir)
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        
+        let mut entries = HashMap::new();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut records = stdout.split('\0');
+        while let Some(record) = records.next() {
+            if record.len() < 4 {
+                continue;
+            }
+            let (code, path) = record.split_at(3);
+            let mut code = code.chars();
+            let (index, worktree) = (code.next().unwrap_or(' '), code.next().unwrap_or(' '));
+            let status = match (index, worktree) {
+                ('?', '?') => "untracked",
+                ('!', '!') => "ignored",
+                (_, 'M') | (_, 'D') | ('U', _) | (_, 'U') => "modified",
+                _ => "staged",
+            };
+            // Renames and copies are followed by the original path
+            if index == 'R' || index == 'C' {
+                records.next();
+            }
+            entries.insert(path.to_string(), status);
+        }
+        
+        let mut dirty_dirs = HashSet::new();
+        for (path, _) in entries.iter().filter(|(_, status)| **status != "ignored") {
+            dirty_dirs.insert(String::new());
+            for (i, _) in path.match_indices('/') {
+                dirty_dirs.insert(path[..=i].to_string());
+            }
+        }
+        Some(GitStatusMap { base: dir.to_path_buf(), prefix, entries, dirty_dirs })
+    }
+    
+    // Status of one file or directory under `base`; directories containing changes count
+    // as modified. None when clean.
+    fn status(&self, path: &std::path::Path, is_dir: bool) -> Option<&'static str> {
+        let relative = path.strip_prefix(&self.base).ok()?.to_string_lossy().replace('\\', "/");
+        let key = format!("{}{}", self.prefix, relative);
+        let dir_key = if key.is_empty() { key.clone() } else { format!("{}/", key.trim_end_matches('/')) };
+        let own = if is_dir { self.entries.get(&dir_key) } else { self.entries.get(&key) };
+        if let Some(status) = own {
+            return Some(status);
+        }
+        
+        // Inside an untracked or ignored directory
+        let mut ancestor = String::new();
+        for component in key.split('/') {
+            ancestor.push_str(component);
+            ancestor.push('/');
+            if ancestor.len() >= key.len() {
+                break;
+            }
+            if let Some(status) = self.entries.get(&ancestor) {
+                return Some(status);
+            }
+        }
+        
+        if is_dir && self.dirty_dirs.contains(&dir_key) {
+            return Some("modified");
+        }
+        None
+    }
+}
+
+// Add gitStatus to a file tree node when the tree is part of a repository
+fn annotate_git_status(node: &mut serde_json::Value, git: Option<&GitStatusMap>, path: &std::path::Path, is_dir: bool) {
+    if let Some(git) = git {
+        node["gitStatus"] = serde_json::json!(git.status(path, is_dir));
+    }
+}
+
+fn file_tree_node(path: &std::path::Path) -> serde_json::Value {
+    let metadata = path.metadata().ok();
+    let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+    let modified = metadata.and_then(|m| m.modified().ok())
+        .map(|time| {
+            let datetime: chrono::DateTime<chrono::Utc> = time.into();
+            datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+        })
+        .unwrap_or_else(|| "Unknown".to_string());
+    
+    serde_json::json!({
+        "name": path.file_name().and_then(|n| n.to_str()).unwrap_or(""),
+        "path": path.to_string_lossy(),
+        "type": "file",
+        "size": size,
+        "modified": modified,
+        "extension": path.extension().and_then(|ext| ext.to_str()).unwrap_or("")
+    })
+}
+
+// Directories first, then files, both alphabetically
+fn sort_tree_nodes(nodes: &mut [serde_json::Value]) {
+    nodes.sort_by(|a, b| {
+        let a_type = a["type"].as_str().unwrap_or("");
+        let b_type = b["type"].as_str().unwrap_or("");
+        let a_name = a["name"].as_str().unwrap_or("");
+        let b_name = b["name"].as_str().unwrap_or("");
+        
+        match (a_type, b_type) {
+            ("directory", "file") => std::cmp::Ordering::Less,
+            ("file", "directory") => std::cmp::Ordering::Greater,
+            _ => a_name.cmp(b_name)
+        }
+    });
+}
+
+// One level of a directory for lazily expanding the file tree. Directory nodes carry
+// hasChildren instead of their children. Inside a git repository every node also has
+// gitStatus ("modified", "staged", "untracked", "ignored" or null), as in get_directory_tree.
+#[tauri::command]
+async fn get_directory_children(dir_path: String, include_ignored: Option<bool>) -> Result<Vec<serde_json::Value>, AppError> {
+    let dir = std::path::Path::new(&dir_path);
+    if !dir.is_dir() {
+        return Err(AppError::PathNotFound(format!("Directory does not exist: {}", dir_path)));
+    }
+    let include_ignored = include_ignored.unwrap_or(false);
+    
+    // git status and the directory walk both block
+    tokio::task::spawn_blocking(move || {
+        let dir = std::path::Path::new(&dir_path);
+        let git = GitStatusMap::load(dir);
+        
+        let mut children: Vec<serde_json::Value> = directory_entries(dir, include_ignored)
+            .map(|entry| {
+                let path = entry.path();
+                let mut node = if path.is_dir() {
+                    serde_json::json!({
+                        "name": path.file_name().and_then(|n| n.to_str()).unwrap_or(""),
+                        "path": path.to_string_lossy(),
+                        "type": "directory",
+                        "hasChildren": directory_entries(path, include_ignored).next().is_some()
+                    })
+                } else {
+                    file_tree_node(path)
+                };
+                annotate_git_status(&mut node, git.as_ref(), path, path.is_dir());
+                node
+            })
+            .collect();
+        
+        sort_tree_nodes(&mut children);
+        children
+    }).await
+        .map_err(|e| AppError::Other(format!("Directory listing failed: {}", e)))
+}
+
+#[derive(Debug, Serialize)]
+struct CopyResult {
+    files_copied: u64,
+    bytes_copied: u64,
+}
+
+// Minimum time between copy_progress events
+const COPY_PROGRESS_INTERVAL_MS: u128 = 200;
+
+enum CopyKind {
+    Dir,
+    File(u64),
+    Symlink,
+}
+
+// Every entry under `src` (or `src` itself) paired with its destination. Symlinks are
+// copied as links, never followed.
+fn copy_plan(src: &std::path::Path, dest: &std::path::Path, plan: &mut Vec<(std::path::PathBuf, std::path::PathBuf, CopyKind)>) -> Result<(), AppError> {
+    let metadata = std::fs::symlink_metadata(src)
+        .map_err(|e| AppError::io(&format!("Failed to read {}", src.display()), e))?;
+    if metadata.file_type().is_symlink() {
+        plan.push((src.to_path_buf(), dest.to_path_buf(), CopyKind::Symlink));
+    } else if metadata.is_dir() {
+        // Directories come before their contents so they're created top-down
+        plan.push((src.to_path_buf(), dest.to_path_buf(), CopyKind::Dir));
+        let entries = std::fs::read_dir(src)
+            .map_err(|e| AppError::io(&format!("Failed to read directory {}", src.display()), e))?;
+        for entry in entries.flatten() {
+            copy_plan(&entry.path(), &dest.join(entry.file_name()), plan)?;
+        }
+    } else {
+        plan.push((src.to_path_buf(), dest.to_path_buf(), CopyKind::File(metadata.len())));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &std::path::Path, to: &std::path::Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &std::path::Path, to: &std::path::Path) -> std::io::Result<()> {
+    let target = std::fs::read_link(from)?;
+    if from.is_dir() {
+        std::os::windows::fs::symlink_dir(target, to)
+    } else {
+        std::os::windows::fs::symlink_file(target, to)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn copy_symlink(from: &std::path::Path, _to: &std::path::Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, format!("Cannot copy symlink {}", from.display())))
+}
+
+// Create `dir` and any missing parents, remembering each one created so a failed copy
+// can remove it again
+fn create_copy_dirs(dir: &std::path::Path, created: &mut Vec<std::path::PathBuf>) -> Result<(), AppError> {
+    let missing: Vec<_> = dir.ancestors()
+        .take_while(|ancestor| std::fs::symlink_metadata(ancestor).is_err())
+        .map(|ancestor| ancestor.to_path_buf())
+        .collect();
+    std::fs::create_dir_all(dir)
+        .map_err(|e| AppError::io(&format!("Failed to create {}", dir.display()), e))?;
+    created.extend(missing.into_iter().rev());
+    Ok(())
+}
+
+// Copy a file or a whole directory tree, emitting copy_progress while it runs. With
+// `overwrite`, existing files are replaced and directories merged. If the copy fails
+// part way, everything it created is removed again.
+#[tauri::command]
+async fn copy_path(app: tauri::AppHandle, src: String, dest: String, overwrite: Option<bool>) -> Result<CopyResult, AppError> {
+    ensure_writable_path(&dest)?;
+    let overwrite = overwrite.unwrap_or(false);
+    let src_path = std::fs::canonicalize(&src)
+        .map_err(|e| AppError::io(&format!("Failed to read {}", src), e))?;
+    let dest_path = normalize_path(std::path::Path::new(&dest))?;
+    
+    if dest_path.exists() && !overwrite {
+        return Err(AppError::AlreadyExists(format!("{} already exists", dest)));
+    }
+    if src_path.is_dir() && dest_path.starts_with(&src_path) {
+        return Err(AppError::InvalidInput("Cannot copy a directory into itself".to_string()));
+    }
+    if src_path.is_dir() != dest_path.is_dir() && dest_path.exists() {
+        return Err(AppError::InvalidInput(format!("Cannot replace {} with a different kind of entry", dest)));
+    }
+    
+    tokio::task::spawn_blocking(move || {
+        let mut plan = Vec::new();
+        copy_plan(&src_path, &dest_path, &mut plan)?;
+        let total_bytes: u64 = plan.iter()
+            .map(|(_, _, kind)| if let CopyKind::File(size) = kind { *size } else { 0 })
+            .sum();
+        let total_files = plan.iter().filter(|(_, _, kind)| !matches!(kind, CopyKind::Dir)).count() as u64;
+        
+        let emit_progress = |files: u64, bytes: u64| {
+            let _ = app.emit("copy_progress", serde_json::json!({
+                "src": src,
+                "dest": dest,
+                "copiedFiles": files,
+                "totalFiles": total_files,
+                "copiedBytes": bytes,
+                "totalBytes": total_bytes
+            }));
+        };
+        
+        let mut result = CopyResult { files_copied: 0, bytes_copied: 0 };
+        let mut last_emit = std::time::Instant::now();
+        // Entries that didn't exist before, in creation order
+        let mut created: Vec<std::path::PathBuf> = Vec::new();
+        let outcome = plan.iter().try_for_each(|(from, to, kind)| {
+            let size = match kind {
+                CopyKind::Dir if to.is_symlink() => {
+                    return Err(AppError::InvalidInput(format!("Cannot replace {} with a different kind of entry", to.display())));
+                }
+                CopyKind::Dir => return create_copy_dirs(to, &mut created),
+                CopyKind::File(size) => *size,
+                CopyKind::Symlink => 0,
+            };
+            if let Some(parent) = to.parent() {
+                create_copy_dirs(parent, &mut created)?;
+            }
+            let existed = std::fs::symlink_metadata(to).is_ok();
+            if existed && to.is_dir() && !to.is_symlink() {
+                return Err(AppError::InvalidInput(format!("Cannot replace {} with a different kind of entry", to.display())));
+            }
+            // Replace an existing link itself rather than writing through it
+            if to.is_symlink() || (existed && matches!(kind, CopyKind::Symlink)) {
+                std::fs::remove_file(to)
+                    .map_err(|e| AppError::io(&format!("Failed to replace {}", to.display()), e))?;
+            }
+            if let CopyKind::Symlink = kind {
+                copy_symlink(from, to)
+                    .map_err(|e| AppError::io(&format!("Failed to copy link {}", from.display()), e))?;
+            } else {
+                std::fs::copy(from, to)
+                    .map_err(|e| AppError::io(&format!("Failed to copy {}", from.display()), e))?;
+            }
+            if !existed {
+                created.push(to.clone());
+            }
+            result.files_copied += 1;
+            result.bytes_copied += size;
+            
+            if last_emit.elapsed().as_millis() >= COPY_PROGRESS_INTERVAL_MS {
+                emit_progress(result.files_copied, result.bytes_copied);
+                last_emit = std::time::Instant::now();
+            }
+            Ok(())
+        });
+        
+        if let Err(e) = outcome {
+            // Children were created after their parents, so removing in reverse empties
+            // each directory before it's removed
+            for path in created.iter().rev() {
+                let removed = match std::fs::symlink_metadata(path) {
+                    Ok(metadata) if metadata.is_dir() => std::fs::remove_dir(path),
+                    Ok(_) => std::fs::remove_file(path),
+                    Err(_) => continue,
+                };
+                if let Err(err) = removed {
+                    println!("[WARN] Failed to clean up {} after failed copy: {}", path.display(), err);
+                }
+            }
+            return Err(e);
+        }
+        emit_progress(result.files_copied, result.bytes_copied);
+        Ok(result)
+    }).await
+        .map_err(|e| AppError::Other(format!("Copy failed: {}", e)))?
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum FileOp {
+    Create { path: String, #[serde(default)] content: String },
+    Write { path: String, content: String },
+    Rename { from: String, to: String },
+    Delete { path: String },
+}
+
+// How to reverse one file change, for batch rollback and the undo journal
+enum FileOpUndo {
+    RemoveFile(std::path::PathBuf),
+    // Directories created by one call, removed from `deepest` up to `top` while they're empty
+    RemoveDir { top: std::path::PathBuf, deepest: std::path::PathBuf },
+    RestoreContent(std::path::PathBuf, Vec<u8>),
+    Move { from: std::path::PathBuf, to: std::path::PathBuf },
+    Untrash(trash::TrashItem),
+    // A change that can't be reversed; nothing before it can be undone either
+    Barrier(String),
+}
+
+impl FileOpUndo {
+    fn apply(&self) -> Result<(), AppError> {
+        match self {
+            FileOpUndo::RemoveFile(path) => std::fs::remove_file(path)
+                .map_err(|e| AppError::io(&format!("Failed to remove {}", path.display()), e)),
+            // remove_dir refuses non-empty directories, so files added since are never lost
+            FileOpUndo::RemoveDir { top, deepest } => {
+                for dir in deepest.ancestors() {
+                    std::fs::remove_dir(dir)
+                        .map_err(|e| AppError::io(&format!("Failed to remove {}", dir.display()), e))?;
+                    if dir == top {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            FileOpUndo::RestoreContent(path, bytes) => std::fs::write(path, bytes)
+                .map_err(|e| AppError::io(&format!("Failed to restore {}", path.display()), e)),
+            FileOpUndo::Move { from, to } => std::fs::rename(from, to)
+                .map_err(|e| AppError::io(&format!("Failed to move {} back", from.display()), e)),
+            FileOpUndo::Untrash(item) => restore_trashed_item(item.clone()),
+            FileOpUndo::Barrier(reason) => Err(AppError::Other(reason.clone())),
+        }
+    }
+}
+
+// The trash entry for a path that was just moved to the trash, so it can be restored.
+// The trash can't be read programmatically on macOS.
+#[cfg(not(target_os = "macos"))]
+fn find_trashed_item(path: &std::path::Path) -> Option<trash::TrashItem> {
+    trash::os_limited::list().ok()?
+        .into_iter()
+        .filter(|item| item.original_path() == path)
+        .max_by_key(|item| item.time_deleted)
+}
+
+#[cfg(target_os = "macos")]
+fn find_trashed_item(_path: &std::path::Path) -> Option<trash::TrashItem> {
+    None
+}
+
+#[cfg(not(target_os = "macos"))]
+fn restore_trashed_item(item: trash::TrashItem) -> Result<(), AppError> {
+    trash::os_limited::restore_all([item])
+        .map_err(|e| AppError::Io(format!("Failed to restore from the trash: {}", e)))
+}
+
+#[cfg(target_os = "macos")]
+fn restore_trashed_item(item: trash::TrashItem) -> Result<(), AppError> {
+    Err(AppError::Other(format!("Restore {} from the Trash in Finder", item.original_path().display())))
+}
+
+// Undo entries kept per project root
+const FILE_UNDO_JOURNAL_LIMIT: usize = 50;
+// Previous contents larger than this aren't kept, so overwriting such files can't be undone
+const FILE_UNDO_MAX_CONTENT_BYTES: u64 = 10 * 1024 * 1024;
+
+struct FileJournalEntry {
+    description: String,
+    undo: FileOpUndo,
+    // Orders entries across the roots of one project
+    recorded_at: std::time::Instant,
+}
+
+fn record_file_undo(root: std::path::PathBuf, description: String, undo: FileOpUndo) {
+    let mut journal = FILE_UNDO_JOURNAL.lock().unwrap();
+    let entries = journal.entry(root).or_default();
+    if matches!(undo, FileOpUndo::Barrier(_)) {
+        entries.clear();
+    }
+    entries.push_back(FileJournalEntry { description, undo, recorded_at: std::time::Instant::now() });
+    while entries.len() > FILE_UNDO_JOURNAL_LIMIT {
+        entries.pop_front();
+    }
+}
+
+// Record an overwrite whose previous contents weren't kept, so undo stops there instead of
+// reverting older changes underneath it
+fn record_file_overwrite_barrier(root: std::path::PathBuf, path: &std::path::Path) {
+    let description = format!("Write {}", path.display());
+    let reason = format!("Can't undo the write to {}: its previous contents were too large to keep", path.display());
+    record_file_undo(root, description, FileOpUndo::Barrier(reason));
+}
+
+// Writable roots whose journal belongs to a project: its own directory and anything nested in
+// it, the other roots of workspaces it's in, and folders granted outside any project
+fn undo_roots_for_project(root: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let canonical = |paths: Vec<String>| -> Vec<std::path::PathBuf> {
+        paths.iter().filter_map(|path| std::fs::canonicalize(path).ok()).collect()
+    };
+    let mut roots = vec![root.to_path_buf()];
+    for workspace in read_workspaces() {
+        let workspace_roots = canonical(workspace.roots);
+        if workspace_roots.iter().any(|workspace_root| workspace_root == root) {
+            roots.extend(workspace_roots);
+        }
+    }
+    roots.extend(canonical(read_app_settings().map(|settings| settings.granted_paths).unwrap_or_default()));
+    roots
+}
+
+// Current contents of a file that's about to be overwritten, when small enough to keep
+fn previous_content_for_undo(path: &std::path::Path) -> Option<Vec<u8>> {
+    let metadata = path.metadata().ok()?;
+    if !metadata.is_file() || metadata.len() > FILE_UNDO_MAX_CONTENT_BYTES {
+        return None;
+    }
+    std::fs::read(path).ok()
+}
+
+// Reverse the most recent change made through the file commands in this project (the
+// Claude project directory or its real path), returning what was undone
+#[tauri::command]
+async fn undo_last_file_operation(project_path: String) -> Result<String, AppError> {
+    let real_path = match get_real_project_path(project_path.clone()).await {
+        Ok(Some(real_path)) => real_path,
+        _ => project_path.clone(),
+    };
+    let root = std::fs::canonicalize(&real_path)
+        .map_err(|e| AppError::io(&format!("Failed to resolve {}", real_path), e))?;
+    let scope = undo_roots_for_project(&root);
+    
+    // Changes are journaled under the innermost writable root they were made in, so take the
+    // newest entry across every root belonging to this project
+    let entry = {
+        let mut journal = FILE_UNDO_JOURNAL.lock().unwrap();
+        let latest = journal.iter()
+            .filter(|(journal_root, _)| journal_root.starts_with(&root) || scope.contains(journal_root))
+            .filter_map(|(journal_root, entries)| entries.back().map(|entry| (journal_root.clone(), entry.recorded_at)))
+            .max_by_key(|(_, recorded_at)| *recorded_at)
+            .map(|(journal_root, _)| journal_root);
+        latest.and_then(|journal_root| journal.get_mut(&journal_root)?.pop_back())
+    }.ok_or_else(|| AppError::NotFound("Nothing to undo".to_string()))?;
+    entry.undo.apply()?;
+    
+    println!("[INFO] Undid file operation: {}", entry.description);
+    Ok(entry.description)
+}
+
+// Create the missing parents of `path`, recording the topmost one so rollback can remove it
+fn create_parent_dirs(path: &std::path::Path, journal: &mut Vec<FileOpUndo>) -> Result<(), AppError> {
+    let parent = match path.parent() {
+        Some(parent) => parent,
+        None => return Ok(()),
+    };
+    if let Some(top) = parent.ancestors().take_while(|dir| !dir.exists()).last() {
+        let top = top.to_path_buf();
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io(&format!("Failed to create {}", parent.display()), e))?;
+        journal.push(FileOpUndo::RemoveDir { top, deepest: parent.to_path_buf() });
+    }
+    Ok(())
+}
+
+fn apply_file_op(op: &FileOp, journal: &mut Vec<FileOpUndo>, deleted: &mut Vec<(std::path::PathBuf, std::path::PathBuf)>) -> Result<(), AppError> {
+    match op {
+        FileOp::Create { path, content } | FileOp::Write { path, content } => {
+            let path = std::path::PathBuf::from(path);
+            let previous = match std::fs::read(&path) {
+                Ok(bytes) => Some(bytes),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(AppError::io(&format!("Failed to read {}", path.display()), e)),
+            };
+            create_parent_dirs(&path, journal)?;
+            std::fs::write(&path, content)
+                .map_err(|e| AppError::io(&format!("Failed to write {}", path.display()), e))?;
+            journal.push(match previous {
+                Some(bytes) => FileOpUndo::RestoreContent(path, bytes),
+                None => FileOpUndo::RemoveFile(path),
+            });
+        }
+        FileOp::Rename { from, to } => {
+            let (from, to) = (std::path::PathBuf::from(from), std::path::PathBuf::from(to));
+            create_parent_dirs(&to, journal)?;
+            std::fs::rename(&from, &to)
+                .map_err(|e| AppError::io(&format!("Failed to rename {}", from.display()), e))?;
+            journal.push(FileOpUndo::Move { from: to, to: from });
+        }
+        FileOp::Delete { path } => {
+            // Set aside next to the original until the whole batch has succeeded
+            let path = std::path::PathBuf::from(path);
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let backup = path.with_file_name(format!(".{}.gui-batch-{}", name, Uuid::new_v4().simple()));
+            std::fs::rename(&path, &backup)
+                .map_err(|e| AppError::io(&format!("Failed to delete {}", path.display()), e))?;
+            journal.push(FileOpUndo::Move { from: backup.clone(), to: path.clone() });
+            deleted.push((backup, path));
+        }
+    }
+    Ok(())
+}
+
+fn rollback_file_ops(journal: Vec<FileOpUndo>) {
+    for undo in journal.into_iter().rev() {
+        if let Err(e) = undo.apply() {
+            println!("[ERROR] Rollback step failed: {}", e);
+        }
+    }
+}
+
+// Check an operation against the batch so far, returning it with normalized paths
+fn validate_file_op(op: &FileOp, exists: &mut HashMap<std::path::PathBuf, bool>) -> Result<FileOp, AppError> {
+    let resolve = |path: &str| -> Result<std::path::PathBuf, AppError> {
+        ensure_writable_path(path)?;
+        normalize_path(std::path::Path::new(path))
+    };
+    let is_present = |exists: &HashMap<std::path::PathBuf, bool>, path: &std::path::PathBuf| {
+        exists.get(path).copied().unwrap_or_else(|| path.exists())
+    };
+    
+    let op = match op {
+        FileOp::Create { path, content } => {
+            let resolved = resolve(path)?;
+            if is_present(exists, &resolved) {
+                return Err(AppError::AlreadyExists(format!("{} already exists", path)));
+            }
+            exists.insert(resolved.clone(), true);
+            FileOp::Create { path: resolved.to_string_lossy().to_string(), content: content.clone() }
+        }
+        FileOp::Write { path, content } => {
+            let resolved = resolve(path)?;
+            if resolved.is_dir() {
+                return Err(AppError::InvalidInput(format!("{} is a directory", path)));
+            }
+            exists.insert(resolved.clone(), true);
+            FileOp::Write { path: resolved.to_string_lossy().to_string(), content: content.clone() }
+        }
+        FileOp::Rename { from, to } => {
+            let (resolved_from, resolved_to) = (resolve(from)?, resolve(to)?);
+            if !is_present(exists, &resolved_from) {
+                return Err(AppError::PathNotFound(format!("{} does not exist", from)));
+            }
+            if is_present(exists, &resolved_to) {
+                return Err(AppError::AlreadyExists(format!("{} already exists", to)));
+            }
+            exists.insert(resolved_from.clone(), false);
+            exists.insert(resolved_to.clone(), true);
+            FileOp::Rename { from: resolved_from.to_string_lossy().to_string(), to: resolved_to.to_string_lossy().to_string() }
+        }
+        FileOp::Delete { path } => {
+            let resolved = resolve(path)?;
+            if !is_present(exists, &resolved) {
+                return Err(AppError::PathNotFound(format!("{} does not exist", path)));
+            }
+            exists.insert(resolved.clone(), false);
+            FileOp::Delete { path: resolved.to_string_lossy().to_string() }
+        }
+    };
+    Ok(op)
+}
+
+// Validate every operation up front, then apply them in order; if one fails, the steps
+// already applied are reversed so the project is left as it was. Deleted entries go to
+// the trash once the whole batch has succeeded.
+#[tauri::command]
+async fn apply_file_operations(ops: Vec<FileOp>) -> Result<usize, AppError> {
+    let mut exists = HashMap::new();
+    let mut validated = Vec::with_capacity(ops.len());
+    for (index, op) in ops.iter().enumerate() {
+        match validate_file_op(op, &mut exists) {
+            Ok(op) => validated.push(op),
+            Err(e) => {
+                println!("[WARN] File operation {} rejected: {}", index + 1, e);
+                return Err(e);
+            }
+        }
+    }
+    
+    tokio::task::spawn_blocking(move || {
+        let mut journal = Vec::new();
+        let mut deleted = Vec::new();
+        for (index, op) in validated.iter().enumerate() {
+            if let Err(e) = apply_file_op(op, &mut journal, &mut deleted) {
+                println!("[WARN] File operation {} failed, rolling back {} steps: {}", index + 1, journal.len(), e);
+                rollback_file_ops(journal);
+                return Err(AppError::Io(format!("Operation {} failed, no changes were kept: {}", index + 1, e)));
+            }
+        }
+        
+        let mut not_deleted = Vec::new();
+        for (backup, original) in deleted {
+            // Trash under the original name when nothing has taken its place
+            let target = if original.exists() {
+                backup
+            } else {
+                match std::fs::rename(&backup, &original) {
+                    Ok(()) => original.clone(),
+                    Err(_) => backup,
+                }
+            };
+            // Without a usable trash, delete for real rather than leave the entry (or its
+            // .gui-batch backup) behind
+            if let Err(e) = trash::delete(&target) {
+                println!("[WARN] Failed to move {} to the trash, deleting it instead: {}", target.display(), e);
+                let is_dir = std::fs::symlink_metadata(&target).map(|m| m.is_dir()).unwrap_or(false);
+                let removed = if is_dir { std::fs::remove_dir_all(&target) } else { std::fs::remove_file(&target) };
+                if let Err(e) = removed {
+                    println!("[ERROR] Failed to delete {}: {}", target.display(), e);
+                    not_deleted.push(original.display().to_string());
+                }
+            }
+        }
+        if !not_deleted.is_empty() {
+            return Err(AppError::Io(format!(
+                "The other changes were applied, but these could not be deleted: {}",
+                not_deleted.join(", ")
+            )));
+        }
+        Ok(validated.len())
+    }).await
+        .map_err(|e| AppError::Other(format!("File operations failed: {}", e)))?
+}
+
+#[derive(Debug, Serialize, Default)]
+struct DirectorySize {
+    path: String,
+    total_bytes: u64,
+    file_count: u64,
+    dir_count: u64,
+    // Immediate children by size, largest first (e.g. node_modules, target)
+    children: Vec<ChildSize>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChildSize {
+    name: String,
+    bytes: u64,
+    is_directory: bool,
+}
+
+const DIRECTORY_SIZE_PROGRESS_INTERVAL_MS: u128 = 250;
+
+// Total size of everything under a directory, ignored and hidden files included, emitting
+// directory_size_progress while it counts. Symlinks aren't followed.
+#[tauri::command]
+async fn get_directory_size(app: tauri::AppHandle, path: String) -> Result<DirectorySize, AppError> {
+    let root = std::path::PathBuf::from(&path);
+    if !root.is_dir() {
+        return Err(AppError::PathNotFound(format!("Directory does not exist: {}", path)));
+    }
+    
+    tokio::task::spawn_blocking(move || {
+        let mut size = DirectorySize { path: path.clone(), ..Default::default() };
+        let mut children: HashMap<std::ffi::OsString, ChildSize> = HashMap::new();
+        let mut last_emit = std::time::Instant::now();
+        
+        let walker = ignore::WalkBuilder::new(&root).standard_filters(false).build();
+        for entry in walker.flatten().filter(|entry| entry.depth() > 0) {
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            let bytes = if metadata.is_dir() {
+                size.dir_count += 1;
+                0
+            } else {
+                size.file_count += 1;
+                metadata.len()
+            };
+            size.total_bytes += bytes;
+            
+            if let Some(child) = entry.path().strip_prefix(&root).ok().and_then(|rel| rel.components().next()) {
+                let name = child.as_os_str().to_os_string();
+                let child_size = children.entry(name.clone()).or_insert_with(|| ChildSize {
+                    name: name.to_string_lossy().to_string(),
+                    bytes: 0,
+                    is_directory: entry.depth() > 1 || metadata.is_dir(),
+                });
+                child_size.bytes += bytes;
+            }
+            
+            if last_emit.elapsed().as_millis() >= DIRECTORY_SIZE_PROGRESS_INTERVAL_MS {
+                let _ = app.emit("directory_size_progress", serde_json::json!({
+                    "path": path,
+                    "totalBytes": size.total_bytes,
+                    "fileCount": size.file_count
+                }));
+                last_emit = std::time::Instant::now();
+            }
+        }
+        
+        size.children = children.into_values().collect();
+        size.children.sort_by_key(|child| std::cmp::Reverse(child.bytes));
+        size
+    }).await
+        .map_err(|e| AppError::Other(format!("Directory size scan failed: {}", e)))
+}
+
+#[derive(Debug, Serialize)]
+struct ProjectArchive {
+    path: String,
+    files: u64,
+    // Uncompressed size of the archived files
+    bytes: u64,
+    archive_bytes: u64,
+}
+
+const ARCHIVE_PROGRESS_INTERVAL_MS: u128 = 200;
+
+// Permission bits to store in archives, so executables stay executable
+#[cfg(unix)]
+fn unix_file_mode(path: &std::path::Path) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().ok().map(|metadata| metadata.permissions().mode())
+}
+
+#[cfg(not(unix))]
+fn unix_file_mode(_path: &std::path::Path) -> Option<u32> {
+    None
+}
+
+// Zip the real project directory as a snapshot before a big change, to a file the user picks
+// in a native save dialog (None if cancelled). Ignored files and .git are left out unless
+// include_ignored is set; the archive is written next to the destination and only moved
+// into place once complete.
+#[tauri::command]
+async fn export_project_archive(app: tauri::AppHandle, project_path: String, include_ignored: Option<bool>) -> Result<Option<ProjectArchive>, AppError> {
+    use std::io::Write;
+    
+    let real_path = match get_real_project_path(project_path.clone()).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    let root = std::fs::canonicalize(&real_path)
+        .map_err(|e| AppError::io(&format!("Failed to resolve {}", real_path), e))?;
+    let folder = root.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| "project".to_string());
+    let file_name = format!("{}-{}.zip", folder, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    let dest_path = match pick_save_path_natively(&app, "Export project archive", &file_name).await {
+        Some(dest) => dest,
+        None => return Ok(None),
+    };
+    let dest = dest_path.to_string_lossy().to_string();
+    if dest_path.is_dir() {
+        return Err(AppError::InvalidInput(format!("{} is a directory", dest)));
+    }
+    let include_ignored = include_ignored.unwrap_or(false);
+    
+    tokio::task::spawn_blocking(move || {
+        let dest_resolved = normalize_path(&dest_path)?;
+        let mut walker = project_walker(&root, include_ignored);
+        walker.hidden(false);
+        if !include_ignored {
+            walker.filter_entry(|entry| entry.file_name() != ".git");
+        }
+        let entries: Vec<(std::path::PathBuf, bool, u64)> = walker.build().flatten()
+            .filter(|entry| entry.depth() > 0 && entry.path() != dest_resolved)
+            .filter_map(|entry| {
+                let file_type = entry.file_type()?;
+                let size = if file_type.is_file() { entry.metadata().ok()?.len() } else { 0 };
+                (file_type.is_file() || file_type.is_dir()).then(|| (entry.into_path(), file_type.is_dir(), size))
+            })
+            .collect();
+        let total_files = entries.iter().filter(|(_, is_dir, _)| !is_dir).count() as u64;
+        let total_bytes: u64 = entries.iter().map(|(_, _, size)| size).sum();
+        
+        // Entries go under a folder named after the project, so extracting makes one folder
+        let partial_path = dest_path.with_file_name(format!(".{}.partial", dest_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()));
+        let file = std::fs::File::create(&partial_path)
+            .map_err(|e| AppError::io(&format!("Failed to create {}", dest), e))?;
+        let mut zip = zip::ZipWriter::new(std::io::BufWriter::new(file));
+        
+        let mut archive = ProjectArchive { path: dest.clone(), files: 0, bytes: 0, archive_bytes: 0 };
+        let mut last_emit = std::time::Instant::now();
+        let emit_progress = |archive: &ProjectArchive| {
+            let _ = app.emit("project_export_progress", serde_json::json!({
+                "projectPath": project_path,
+                "dest": dest,
+                "filesWritten": archive.files,
+                "totalFiles": total_files,
+                "bytesWritten": archive.bytes,
+                "totalBytes": total_bytes
+            }));
+        };
+        
+        let result = (|| -> Result<(), AppError> {
+            let zip_error = |e: zip::result::ZipError| AppError::Io(format!("Failed to write archive: {}", e));
+            for (path, is_dir, size) in &entries {
+                let relative = path.strip_prefix(&root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+                let name = format!("{}/{}", folder, relative);
+                let mut options = zip::write::SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Deflated)
+                    .large_file(*size >= u32::MAX as u64);
+                if let Some(mode) = unix_file_mode(path) {
+                    options = options.unix_permissions(mode);
+                }
+                
+                if *is_dir {
+                    zip.add_directory(name, options).map_err(zip_error)?;
+                    continue;
+                }
+                let mut source = match std::fs::File::open(path) {
+                    Ok(source) => source,
+                    Err(e) => {
+                        println!("[WARN] Skipping {} in archive: {}", path.display(), e);
+                        continue;
+                    }
+                };
+                zip.start_file(name, options).map_err(zip_error)?;
+                std::io::copy(&mut source, &mut zip)
+                    .map_err(|e| AppError::io(&format!("Failed to archive {}", path.display()), e))?;
+                archive.files += 1;
+                archive.bytes += size;
+                
+                if last_emit.elapsed().as_millis() >= ARCHIVE_PROGRESS_INTERVAL_MS {
+                    emit_progress(&archive);
+                    last_emit = std::time::Instant::now();
+                }
+            }
+            let mut writer = zip.finish().map_err(zip_error)?;
+            writer.flush().map_err(|e| AppError::io("Failed to write archive", e))?;
+            Ok(())
+        })();
+        
+        if let Err(e) = result.and_then(|_| std::fs::rename(&partial_path, &dest_path)
+            .map_err(|e| AppError::io(&format!("Failed to write {}", dest), e))) {
+            let _ = std::fs::remove_file(&partial_path);
+            return Err(e);
+        }
+        
+        emit_progress(&archive);
+        archive.archive_bytes = dest_path.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        println!("[INFO] Exported {} files from {} to {}", archive.files, real_path, archive.path);
+        Ok(Some(archive))
+    }).await
+        .map_err(|e| AppError::Other(format!("Project export failed: {}", e)))?
+}
+
+// What diff_file compares the working copy against
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum DiffBase {
+    // Last committed version
+    Head,
+    // Staged version
+    Index,
+    File { path: String },
+}
+
+#[derive(Debug, Serialize)]
+struct DiffLine {
+    // "context", "add" or "delete"
+    kind: String,
+    content: String,
+    // 1-based; None on the side the line doesn't exist in
+    old_line: Option<usize>,
+    new_line: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct DiffHunk {
+    header: String,
+    old_start: usize,
+    old_lines: usize,
+    new_start: usize,
+    new_lines: usize,
+    lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Serialize)]
+struct FileDiff {
+    old_path: String,
+    new_path: String,
+    additions: usize,
+    deletions: usize,
+    hunks: Vec<DiffHunk>,
+    // Plain `diff -u` text, for copying or feeding back to Claude
+    unified: String,
+    // Either side isn't text; hunks are left empty
+    binary: bool,
+}
+
+const DIFF_CONTEXT_LINES: usize = 3;
+
+fn read_diff_side(path: &str) -> Result<Vec<u8>, AppError> {
+    std::fs::read(path).map_err(|e| AppError::io(&format!("Failed to read {}", path), e))
+}
+
+// Contents of a file as of HEAD (or the index), empty when the file is new
+fn git_file_content(path: &str, staged: bool) -> Result<Vec<u8>, AppError> {
+    let file = std::path::Path::new(path);
+    let (dir, name) = match (file.parent(), file.file_name()) {
+        (Some(dir), Some(name)) => (dir.to_string_lossy().to_string(), name.to_string_lossy().to_string()),
+        _ => return Err(AppError::InvalidInput(format!("Invalid file path: {}", path))),
+    };
+    let dir = if dir.is_empty() { ".".to_string() } else { dir };
+    if run_git(&dir, &["rev-parse", "--is-inside-work-tree"]).as_deref() != Some("true") {
+        return Err(AppError::InvalidInput(format!("{} is not inside a git repository", path)));
+    }
+    
+    let spec = format!("{}:./{}", if staged { "" } else { "HEAD" }, name);
+    let output = Command::new("git")
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(["show", &spec])
+        .current_dir(&dir)
+        .output()
+        .map_err(|e| AppError::spawn("git", e))?;
+    if output.status.success() {
+        return Ok(output.stdout);
+    }
+    
+    // Untracked files have an empty base; --error-unmatch exits 1 when git doesn't know the path
+    let path_spec = format!("./{}", name);
+    let tracked = Command::new("git")
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(["ls-files", "--error-unmatch", "--", &path_spec])
+        .current_dir(&dir)
+        .output()
+        .map_err(|e| AppError::spawn("git", e))?;
+    if tracked.status.code() == Some(1) {
+        return Ok(Vec::new());
+    }
+    // So do files added since the last commit, or before the first one
+    if tracked.status.success() && !staged {
+        let unborn = run_git(&dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_none();
+        let in_head = run_git(&dir, &["ls-tree", "--name-only", "HEAD", "--", &path_spec]);
+        if unborn || in_head.as_deref() == Some("") {
+            return Ok(Vec::new());
+        }
+    }
+    
+    let stderr = String::from_utf8_lossy(if tracked.status.success() { &output.stderr } else { &tracked.stderr });
+    Err(AppError::ProcessFailed(format!("Failed to read {} from git: {}", spec, stderr.trim())))
+}
+
+fn build_file_diff(old_path: String, new_path: String, old: &[u8], new: &[u8]) -> FileDiff {
+    let mut file_diff = FileDiff {
+        old_path,
+        new_path,
+        additions: 0,
+        deletions: 0,
+        hunks: Vec::new(),
+        unified: String::new(),
+        binary: false,
+    };
+    let (old, new) = match (std::str::from_utf8(old), std::str::from_utf8(new)) {
+        (Ok(old), Ok(new)) if !old.contains('\0') && !new.contains('\0') => (old, new),
+        _ => {
+            file_diff.binary = old != new;
+            return file_diff;
+        }
+    };
+    
+    let diff = similar::TextDiff::from_lines(old, new);
+    let mut unified = diff.unified_diff();
+    unified.context_radius(DIFF_CONTEXT_LINES).header(&file_diff.old_path, &file_diff.new_path);
+    
+    for hunk in unified.iter_hunks() {
+        let ops = hunk.ops();
+        let (first, last) = match (ops.first(), ops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => continue,
+        };
+        let old_lines = last.old_range().end - first.old_range().start;
+        let new_lines = last.new_range().end - first.new_range().start;
+        // Same convention as `diff -u`: an empty side starts at the line before it
+        let old_start = first.old_range().start + usize::from(old_lines > 0);
+        let new_start = first.new_range().start + usize::from(new_lines > 0);
+        
+        let lines = hunk.iter_changes().map(|change| {
+            let kind = match change.tag() {
+                similar::ChangeTag::Equal => "context",
+                similar::ChangeTag::Insert => {
+                    file_diff.additions += 1;
+                    "add"
+                }
+                similar::ChangeTag::Delete => {
+                    file_diff.deletions += 1;
+                    "delete"
+                }
+            };
+            DiffLine {
+                kind: kind.to_string(),
+                content: change.value().trim_end_matches(['\n', '\r']).to_string(),
+                old_line: change.old_index().map(|index| index + 1),
+                new_line: change.new_index().map(|index| index + 1),
+            }
+        }).collect();
+        
+        file_diff.hunks.push(DiffHunk {
+            header: hunk.header().to_string(),
+            old_start,
+            old_lines,
+            new_start,
+            new_lines,
+            lines,
+        });
+    }
+    
+    file_diff.unified = unified.to_string();
+    file_diff
+}
+
+// What changed in a file relative to git HEAD, the index, or another file
+#[tauri::command]
+async fn diff_file(path: String, base: DiffBase) -> Result<FileDiff, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let new = read_diff_side(&path)?;
+        let (old_path, old) = match base {
+            DiffBase::Head => (format!("HEAD:{}", path), git_file_content(&path, false)?),
+            DiffBase::Index => (format!("index:{}", path), git_file_content(&path, true)?),
+            DiffBase::File { path: other } => {
+                let old = read_diff_side(&other)?;
+                (other, old)
+            }
+        };
+        Ok(build_file_diff(old_path, path, &old, &new))
+    }).await
+        .map_err(|e| AppError::Other(format!("Diff failed: {}", e)))?
+}
+
+// Compare any two files, `a` being the old side
+#[tauri::command]
+async fn diff_paths(a: String, b: String) -> Result<FileDiff, AppError> {
+    tokio::task::spawn_blocking(move || {
+        let old = read_diff_side(&a)?;
+        let new = read_diff_side(&b)?;
+        Ok(build_file_diff(a, b, &old, &new))
+    }).await
+        .map_err(|e| AppError::Other(format!("Diff failed: {}", e)))?
+}
+
+#[derive(Debug, Serialize)]
+struct PatchFileResult {
+    // Relative to the project root
+    path: String,
+    // "modify", "create" or "delete"
+    action: String,
+    hunks: usize,
+    additions: usize,
+    deletions: usize,
+    // Why this file's changes don't apply; nothing is written while any file has one
+    conflict: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PatchResult {
+    applied: bool,
+    files: Vec<PatchFileResult>,
+}
+
+// One section per file of a multi-file unified diff. Markdown code fences around the diff
+// are dropped, as in diffs copied out of a chat response.
+fn split_patch_files(patch_text: &str) -> Vec<String> {
+    let lines: Vec<&str> = patch_text.split_inclusive('\n')
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .collect();
+    let mut files = Vec::new();
+    let mut current = String::new();
+    let mut in_hunks = false;
+    
+    for (index, line) in lines.iter().enumerate() {
+        let file_header = line.starts_with("--- ") && in_hunks
+            && lines.get(index + 1).is_some_and(|next| next.starts_with("+++ "));
+        if (line.starts_with("diff --git ") || file_header) && !current.trim().is_empty() {
+            files.push(std::mem::take(&mut current));
+            in_hunks = false;
+        }
+        if line.starts_with("@@ ") {
+            in_hunks = true;
+        }
+        current.push_str(line);
+    }
+    if !current.trim().is_empty() {
+        files.push(current);
+    }
+    
+    for file in files.iter_mut() {
+        if !file.ends_with('\n') {
+            file.push('\n');
+        }
+    }
+    files.retain(|file| file.lines().any(|line| line.starts_with("@@ ")));
+    files
+}
+
+// Project file named in a patch header, dropping git's a/ and b/ prefixes
+fn patch_target_path(root: &std::path::Path, name: &str) -> Result<std::path::PathBuf, AppError> {
+    let name = name.trim();
+    let stripped = name.strip_prefix("a/").or_else(|| name.strip_prefix("b/"));
+    let relative = match stripped {
+        Some(stripped) if root.join(stripped).exists() || !root.join(name).exists() => stripped,
+        _ => name,
+    };
+    let path = normalize_path(&root.join(relative))?;
+    if !path.starts_with(root) || path == root {
+        return Err(AppError::AccessDenied(format!("Patch touches {} outside the project", name)));
+    }
+    Ok(path)
+}
+
+// The file operation that applies one file's patch, or why it can't be applied
+fn patch_file_op(path: &std::path::Path, patch: &diffy::Patch<'_, str>, creates: bool, deletes: bool) -> Result<FileOp, String> {
+    let path_string = path.to_string_lossy().to_string();
+    if creates {
+        if path.exists() {
+            return Err("File already exists".to_string());
+        }
+        let content = diffy::apply("", patch)
+            .map_err(|e| format!("Patch doesn't apply: {}", e))?;
+        return Ok(FileOp::Create { path: path_string, content });
+    }
+    
+    let current = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    // Diffs copied from a response use \n even when the file uses \r\n
+    let crlf = current.contains("\r\n");
+    let base = if crlf { current.replace("\r\n", "\n") } else { current };
+    let patched = diffy::apply(&base, patch)
+        .map_err(|e| format!("Changes don't match the current file ({})", e))?;
+    
+    if deletes {
+        if !patched.is_empty() {
+            return Err("File has changed since the patch was made".to_string());
+        }
+        return Ok(FileOp::Delete { path: path_string });
+    }
+    let content = if crlf { patched.replace('\n', "\r\n") } else { patched };
+    Ok(FileOp::Write { path: path_string, content })
+}
+
+// Apply a unified diff (single or multi-file) to the project. Every file is checked first;
+// if any hunk doesn't match, nothing is written and the conflicts are reported. With
+// dry_run the check is all that happens.
+#[tauri::command]
+async fn apply_patch(project_path: String, patch_text: String, dry_run: Option<bool>) -> Result<PatchResult, AppError> {
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    let root = std::fs::canonicalize(&real_path)
+        .map_err(|e| AppError::io(&format!("Failed to resolve {}", real_path), e))?;
+    
+    let sections = split_patch_files(&patch_text);
+    if sections.is_empty() {
+        return Err(AppError::InvalidInput("No file changes found in the patch".to_string()));
+    }
+    
+    let mut files = Vec::new();
+    let mut ops = Vec::new();
+    for section in &sections {
+        let patch = diffy::Patch::from_str(section)
+            .map_err(|e| AppError::Parse(format!("Invalid patch: {}", e)))?;
+        let original = patch.original().filter(|name| *name != "/dev/null");
+        let modified = patch.modified().filter(|name| *name != "/dev/null");
+        let name = original.or(modified)
+            .ok_or_else(|| AppError::InvalidInput("Patch is missing its file names".to_string()))?;
+        let path = patch_target_path(&root, name)?;
+        
+        let (mut additions, mut deletions) = (0, 0);
+        for line in patch.hunks().iter().flat_map(|hunk| hunk.lines()) {
+            match line {
+                diffy::Line::Insert(_) => additions += 1,
+                diffy::Line::Delete(_) => deletions += 1,
+                diffy::Line::Context(_) => {}
+            }
+        }
+        let (creates, deletes) = (original.is_none(), modified.is_none());
+        let action = if creates { "create" } else if deletes { "delete" } else { "modify" };
+        
+        let conflict = match patch_file_op(&path, &patch, creates, deletes) {
+            Ok(op) => {
+                ops.push(op);
+                None
+            }
+            Err(reason) => Some(reason),
+        };
+        files.push(PatchFileResult {
+            path: path.strip_prefix(&root).unwrap_or(&path).to_string_lossy().to_string(),
+            action: action.to_string(),
+            hunks: patch.hunks().len(),
+            additions,
+            deletions,
+            conflict,
+        });
+    }
+    
+    let conflicts = files.iter().filter(|file| file.conflict.is_some()).count();
+    if conflicts > 0 {
+        println!("[WARN] Patch doesn't apply to {}: {} of {} files conflict", real_path, conflicts, files.len());
+        return Ok(PatchResult { applied: false, files });
+    }
+    if dry_run.unwrap_or(false) {
+        return Ok(PatchResult { applied: false, files });
+    }
+    
+    apply_file_operations(ops).await?;
+    println!("[INFO] Applied patch to {} files in {}", files.len(), real_path);
+    Ok(PatchResult { applied: true, files })
+}
+
+#[tauri::command]
+async fn get_directory_tree(dir_path: String, include_ignored: Option<bool>) -> Result<serde_json::Value, AppError> {
+    // Get the real project path
+    let real_path = match get_real_project_path(dir_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    
+    let path = std::path::Path::new(&real_path);
+    
+    if !path.exists() || !path.is_dir() {
+        return Err(AppError::PathNotFound("Directory does not exist".to_string()));
+    }
+    
+    fn build_tree(dir: &std::path::Path, max_depth: usize, current_depth: usize, include_ignored: bool, git: Option<&GitStatusMap>) -> Result<serde_json::Value, AppError> {
+        if current_depth > max_depth {
+            let mut node = serde_json::json!({
+                "name": dir.file_name().and_then(|n| n.to_str()).unwrap_or(""),
+                "path": dir.to_string_lossy(),
+                "type": "directory",
+                "children": []
+            });
+            annotate_git_status(&mut node, git, dir, true);
+            return Ok(node);
+        }
+        
+        let mut children = Vec::new();
+        
+        for entry in directory_entries(dir, include_ignored) {
+            let path = entry.path();
+            if path.is_dir() {
+                children.push(build_tree(path, max_depth, current_depth + 1, include_ignored, git)?);
+            } else {
+                let mut node = file_tree_node(path);
+                annotate_git_status(&mut node, git, path, false);
+                children.push(node);
+            }
+        }
+        
+        sort_tree_nodes(&mut children);
+        
+        let mut node = serde_json::json!({
+            "name": dir.file_name().and_then(|n| n.to_str()).unwrap_or(""),
+            "path": dir.to_string_lossy(),
+            "type": "directory",
+            "children": children
+        });
+        annotate_git_status(&mut node, git, dir, true);
+        Ok(node)
+    }
+    
+    let include_ignored = include_ignored.unwrap_or(false);
+    
+    // git status and the directory walk both block
+    tokio::task::spawn_blocking(move || {
+        // A multi-root workspace gets a synthetic root with one tree per directory
+        if let Some(workspace) = workspace_for_path(&real_path) {
+            let mut roots = Vec::new();
+            for root in &workspace.roots {
+                let root_path = std::path::Path::new(root);
+                if root_path.is_dir() {
+                    let git = GitStatusMap::load(root_path);
+                    roots.push(build_tree(root_path, 5, 0, include_ignored, git.as_ref())?);
+                }
+            }
+            return Ok(serde_json::json!({
+                "name": workspace.name,
+                "path": real_path,
+                "type": "workspace",
+                "children": roots
+            }));
+        }
+        
+        let path = std::path::Path::new(&real_path);
+        let git = GitStatusMap::load(path);
+        build_tree(path, 5, 0, include_ignored, git.as_ref()) // Limit depth to 5 levels
+    }).await
+        .map_err(|e| AppError::Other(format!("Directory tree failed: {}", e)))?
+}
+
+// Several directories worked on together, e.g. a frontend and a backend repo. The first
+// root is the primary one: its Claude project is where the workspace is opened from.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Workspace {
+    id: String,
+    name: String,
+    roots: Vec<String>,
+}
+
+fn get_workspaces_file_path() -> Result<std::path::PathBuf, AppError> {
+    let home_dir = dirs::home_dir().ok_or(AppError::PathNotFound("Could not find home directory".to_string()))?;
+    Ok(home_dir.join(".claude").join("gui-workspaces.json"))
+}
+
+fn read_workspaces() -> Vec<Workspace> {
+    get_workspaces_file_path().ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_workspaces(workspaces: &[Workspace]) -> Result<(), AppError> {
+    let path = get_workspaces_file_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create workspaces directory", e))?;
+    }
+    let content = serde_json::to_string_pretty(workspaces)
+        .map_err(|e| AppError::parse("Failed to serialize workspaces", e))?;
+    std::fs::write(&path, content)
+        .map_err(|e| AppError::io("Failed to write workspaces", e))
+}
+
+// The workspace whose primary root is this real project directory
+fn workspace_for_path(real_path: &str) -> Option<Workspace> {
+    let real_path = std::path::Path::new(real_path);
+    read_workspaces().into_iter()
+        .find(|workspace| workspace.roots.first().is_some_and(|root| std::path::Path::new(root) == real_path))
+}
+
+// Every root of the project's workspace (primary first), or none outside a workspace
+fn workspace_roots(real_path: &str) -> Vec<String> {
+    workspace_for_path(real_path).map(|workspace| workspace.roots).unwrap_or_default()
+}
+
+#[tauri::command]
+async fn list_workspaces() -> Result<Vec<Workspace>, AppError> {
+    Ok(read_workspaces())
+}
+
+// Create a workspace (empty id) or replace an existing one
+#[tauri::command]
+async fn save_workspace(workspace: Workspace) -> Result<Workspace, AppError> {
+    let mut workspace = workspace;
+    if workspace.name.trim().is_empty() {
+        return Err(AppError::InvalidInput("Workspace name cannot be empty".to_string()));
+    }
+    if workspace.roots.is_empty() {
+        return Err(AppError::InvalidInput("A workspace needs at least one directory".to_string()));
+    }
+    for root in &workspace.roots {
+        if !std::path::Path::new(root).is_absolute() || !std::path::Path::new(root).is_dir() {
+            return Err(AppError::PathNotFound(format!("Directory does not exist: {}", root)));
+        }
+    }
+    
+    let mut workspaces = read_workspaces();
+    let primary = std::path::Path::new(&workspace.roots[0]);
+    if workspaces.iter().any(|other| other.id != workspace.id && other.roots.first().is_some_and(|root| std::path::Path::new(root) == primary)) {
+        return Err(AppError::AlreadyExists(format!("{} is already the primary directory of another workspace", workspace.roots[0])));
+    }
+    
+    if workspace.id.is_empty() {
+        workspace.id = Uuid::new_v4().to_string();
+    }
+    match workspaces.iter_mut().find(|existing| existing.id == workspace.id) {
+        Some(existing) => *existing = workspace.clone(),
+        None => workspaces.push(workspace.clone()),
+    }
+    write_workspaces(&workspaces)?;
+    Ok(workspace)
+}
+
+#[tauri::command]
+async fn delete_workspace(workspace_id: String) -> Result<(), AppError> {
+    let mut workspaces = read_workspaces();
+    let before = workspaces.len();
+    workspaces.retain(|workspace| workspace.id != workspace_id);
+    if workspaces.len() == before {
+        return Err(AppError::NotFound(format!("Workspace {} not found", workspace_id)));
+    }
+    write_workspaces(&workspaces)
+}
+
+const LINE_COUNT_MAX_BYTES: u64 = 1024 * 1024;
+
+// File details for listings; git_tracked is left for the caller, which can look it up for
+// many files at once. Lines are only counted with `line_counts`, since that reads the file.
+fn get_file_info_sync(path: &std::path::Path, line_counts: bool) -> Result<FileInfo, AppError> {
+    let link_metadata = path.symlink_metadata()
+        .map_err(|e| AppError::io("Failed to read file metadata", e))?;
+    let is_symlink = link_metadata.file_type().is_symlink();
+    let symlink_target = if is_symlink {
+        std::fs::read_link(path).ok().map(|target| target.to_string_lossy().to_string())
+    } else {
+        None
+    };
+    let metadata = path.metadata().unwrap_or(link_metadata);
+    
+    let line_count = if line_counts && metadata.is_file() && metadata.len() <= LINE_COUNT_MAX_BYTES {
+        std::fs::read(path).ok()
+            .filter(|content| !content.contains(&0))
+            .map(|content| count_lines(&content))
+    } else {
+        None
+    };
+    
+    let name = path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+    
+    let mime_type = match path.extension().and_then(|s| s.to_str()) {
+        Some("txt") | Some("md") | Some("markdown") => "text/plain",
+        Some("js") | Some("jsx") => "text/javascript",
+        Some("ts") | Some("tsx") => "text/typescript",
+        Some("py") => "text/x-python",
+        Some("rs") => "text/x-rust",
+        Some("json") => "application/json",
+        Some("html") | Some("htm") => "text/html",
+        Some("css") => "text/css",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        _ => "application/octet-stream",
+    }.to_string();
+    
+    let is_directory = metadata.is_dir();
+    let modified_date = metadata.modified()
+        .map(|time| {
+            let datetime: chrono::DateTime<chrono::Utc> = time.into();
+            datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+        })
+        .unwrap_or_else(|_| "Unknown".to_string());
+    
+    let file_type = if is_directory {
+        "directory".to_string()
+    } else {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("txt")
+            .to_string()
+    };
+    
+    Ok(FileInfo {
+        name,
+        path: path.to_string_lossy().to_string(),
+        size: metadata.len(),
+        mime_type,
+        is_directory,
+        modified_date,
+        file_type,
+        is_symlink,
+        symlink_target,
+        line_count,
+        language: language_id_for_path(path),
+        git_tracked: None,
+    })
+}
+
+
+// Per-request knobs for streaming Claude runs
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct ClaudeRequestOptions {
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    max_turns: Option<u32>,
+    #[serde(default)]
+    max_cost_usd: Option<f64>,
+    #[serde(default)]
+    allowed_tools: Vec<String>,
+    #[serde(default)]
+    disallowed_tools: Vec<String>,
+    #[serde(default)]
+    extra_dirs: Vec<String>,
+    #[serde(default)]
+    system_prompt: Option<String>,
+    #[serde(default)]
+    append_system_prompt: Option<String>,
+    // Identifies the chat (e.g. a tab ID) whose conversation this request continues
+    #[serde(default)]
+    session_key: Option<String>,
+    // Caller-chosen ID echoed on every event; generated when not provided
+    #[serde(default)]
+    request_id: Option<String>,
+    // Total attempts for rate-limited or overloaded runs (default 3)
+    #[serde(default)]
+    retry_attempts: Option<u32>,
+    // Historical session to pick up with full history (from get_project_sessions)
+    #[serde(default)]
+    resume_session_id: Option<String>,
+    // Pick up the most recent conversation in the project
+    #[serde(default)]
+    continue_latest: bool,
+    // Branch the resumed session into a new session ID instead of appending to it
+    #[serde(default)]
+    fork_session: bool,
+    // "json" returns the complete structured result instead of the response text
+    #[serde(default)]
+    output_format: Option<String>,
+}
+
+#[tauri::command]
+async fn get_chat_session_id(session_key: String) -> Result<Option<String>, AppError> {
+    Ok(CHAT_SESSION_IDS.read().await.get(&session_key).cloned())
+}
+
+#[tauri::command]
+async fn clear_chat_session(session_key: String) -> Result<(), AppError> {
+    CHAT_SESSION_IDS.write().await.remove(&session_key);
+    Ok(())
+}
+
+// Every claude_stream event carries the request it belongs to, so concurrent runs can be told apart
+#[derive(Debug, Serialize, Clone)]
+struct ClaudeStreamMessage {
+    request_id: String,
+    #[serde(flatten)]
+    event: ClaudeStreamEvent,
+}
+
+fn emit_claude_stream(app: &tauri::AppHandle, request_id: &str, event: ClaudeStreamEvent) {
+    let _ = app.emit("claude_stream", ClaudeStreamMessage {
+        request_id: request_id.to_string(),
+        event,
+    });
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct ActiveExecution {
+    request_id: String,
+    project_path: Option<String>,
+    prompt: String,
+    pid: Option<u32>,
+    started_at: String,
+}
+
+#[tauri::command]
+async fn list_active_executions() -> Result<Vec<ActiveExecution>, AppError> {
+    let executions = ACTIVE_EXECUTIONS.read().await;
+    let mut list: Vec<ActiveExecution> = executions.values().cloned().collect();
+    list.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    Ok(list)
+}
+
+#[tauri::command]
+async fn execute_claude_command_streaming(
+    app: tauri::AppHandle,
+    args: Vec<String>, 
+    files: Vec<String>,
+    _enable_autocomplete: bool,
+    plan_mode: Option<bool>,
+    project_path: Option<String>,
+    options: Option<ClaudeRequestOptions>
+) -> Result<String, AppError> {
+    execute_claude_request(app, args, files, plan_mode, project_path, options.unwrap_or_default()).await
+}
+
+// A proposed plan along with what's needed to continue the run that produced it
+#[derive(Debug, Clone)]
+struct PendingPlan {
+    project_path: Option<String>,
+    options: ClaudeRequestOptions,
+}
+
+#[tauri::command]
+async fn approve_plan(app: tauri::AppHandle, plan_id: String) -> Result<String, AppError> {
+    let pending = PENDING_PLANS.write().await.remove(&plan_id)
+        .ok_or_else(|| AppError::NotFound(format!("No pending plan with ID {}", plan_id)))?;
+    
+    println!("[INFO] Plan {} approved, continuing execution", plan_id);
+    execute_claude_request(
+        app,
+        vec!["The plan is approved. Proceed with implementing it.".to_string()],
+        vec![],
+        Some(false),
+        pending.project_path,
+        pending.options
+    ).await
+}
+
+#[tauri::command]
+async fn reject_plan(plan_id: String) -> Result<(), AppError> {
+    PENDING_PLANS.write().await.remove(&plan_id)
+        .ok_or_else(|| AppError::NotFound(format!("No pending plan with ID {}", plan_id)))?;
+    
+    println!("[INFO] Plan {} rejected", plan_id);
+    Ok(())
+}
+
+// Parent/child relationship of a forked conversation, kept in the project's sidecar file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct SessionFork {
+    session_id: String,
+    parent_session_id: String,
+    created_at: String,
+}
+
+async fn get_session_forks_file_path(project_path: String) -> Result<std::path::PathBuf, AppError> {
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    
+    Ok(std::path::Path::new(&real_path).join(".claude").join("gui-session-forks.json"))
+}
+
+#[tauri::command]
+async fn get_session_forks(project_path: String) -> Result<Vec<SessionFork>, AppError> {
+    let forks_file = get_session_forks_file_path(project_path).await?;
+    
+    if !forks_file.exists() {
+        return Ok(vec![]);
+    }
+    
+    let content = std::fs::read_to_string(&forks_file)
+        .map_err(|e| AppError::io("Failed to read session forks", e))?;
+    
+    serde_json::from_str(&content)
+        .map_err(|e| AppError::parse("Failed to parse session forks", e))
+}
+
+#[tauri::command]
+async fn fork_session(
+    app: tauri::AppHandle,
+    project_path: String,
+    source_session_id: String,
+    prompt: String,
+    options: Option<ClaudeRequestOptions>
+) -> Result<SessionFork, AppError> {
+    if source_session_id.trim().is_empty() {
+        return Err(AppError::InvalidInput("Source session ID is required".to_string()));
+    }
+    
+    // The branch gets its own chat key so follow-ups continue the fork, not the original
+    let mut options = options.unwrap_or_default();
+    let session_key = options.session_key.clone()
+        .unwrap_or_else(|| format!("fork-{}", Uuid::new_v4()));
+    options.session_key = Some(session_key);
+    options.resume_session_id = Some(source_session_id.clone());
+    options.fork_session = true;
+    // The new session ID only shows up in the stream's init record
+    options.output_format = None;
+    let request_id = options.request_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    options.request_id = Some(request_id.clone());
+    
+    let result = execute_claude_request(app, vec![prompt], vec![], None, Some(project_path.clone()), options).await;
+    let forked_session_id = FORKED_SESSION_IDS.write().await.remove(&request_id);
+    result?;
+    
+    let session_id = forked_session_id
+        .filter(|id| *id != source_session_id)
+        .ok_or_else(|| AppError::ProcessFailed("Claude did not report a new session ID for the fork".to_string()))?;
+    
+    let fork = SessionFork {
+        session_id,
+        parent_session_id: source_session_id,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    
+    let mut forks = get_session_forks(project_path.clone()).await?;
+    forks.push(fork.clone());
+    
+    let forks_file = get_session_forks_file_path(project_path).await?;
+    if let Some(parent) = forks_file.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create settings directory", e))?;
+    }
+    
+    let content = serde_json::to_string_pretty(&forks)
+        .map_err(|e| AppError::parse("Failed to serialize session forks", e))?;
+    std::fs::write(&forks_file, content)
+        .map_err(|e| AppError::io("Failed to write session forks", e))?;
+    
+    println!("[INFO] Forked session {} into {}", fork.parent_session_id, fork.session_id);
+    Ok(fork)
+}
+
+// A plan_mode of None falls back to the project's saved default
+async fn execute_claude_request(
+    app: tauri::AppHandle,
+    args: Vec<String>,
+    files: Vec<String>,
+    plan_mode: Option<bool>,
+    project_path: Option<String>,
+    options: ClaudeRequestOptions
+) -> Result<String, AppError> {
+    let plan_mode = match (plan_mode, &project_path) {
+        (Some(plan_mode), _) => plan_mode,
+        (None, Some(path)) => read_project_settings(path.clone()).await.map(|s| s.plan_mode).unwrap_or(false),
+        (None, None) => false,
+    };
+    let request_id = options.request_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    
+    // Track the run while it's in flight so the UI can list what's still going
+    {
+        let mut executions = ACTIVE_EXECUTIONS.write().await;
+        if executions.contains_key(&request_id) {
+            return Err(AppError::AlreadyExists(format!("Request {} is already running", request_id)));
+        }
+        executions.insert(request_id.clone(), ActiveExecution {
+            request_id: request_id.clone(),
+            project_path: project_path.clone(),
+            prompt: args.first().map(|p| p.chars().take(200).collect()).unwrap_or_default(),
+            pid: None,
+            started_at: chrono::Utc::now().to_rfc3339(),
+        });
+    }
+    
+    // Retry rate-limit and overload failures (by the API's error type or status) with
+    // exponential backoff
+    let max_attempts = options.retry_attempts.unwrap_or(3).max(1);
+    let mut attempt = 1;
+    let result = loop {
+        let result = run_claude_request(
+            app.clone(),
+            request_id.clone(),
+            args.clone(),
+            files.clone(),
+            plan_mode,
+            project_path.clone(),
+            options.clone()
+        ).await;
+        
+        match &result {
+            Err(e @ (AppError::RateLimited(_) | AppError::Overloaded(_))) if attempt < max_attempts => {
+                let delay_ms = (2000u64 << (attempt - 1)).min(60_000);
+                println!("[WARN] Request {} failed (attempt {}/{}), retrying in {}ms: {}", request_id, attempt, max_attempts, delay_ms, e);
+                emit_claude_stream(&app, &request_id, ClaudeStreamEvent::Retrying {
+                    attempt: attempt + 1,
+                    max_attempts,
+                    delay_ms,
+                    reason: e.to_string(),
+                    timestamp: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                });
+                tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)).await;
+                attempt += 1;
+            }
+            _ => break result,
+        }
+    };
+    
+    ACTIVE_EXECUTIONS.write().await.remove(&request_id);
+    result
+}
+
+// Prompt queue: prompts waiting to run in a project, executed one at a time
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct QueuedPrompt {
+    id: String,
+    project_path: String,
+    prompt: String,
+    files: Vec<String>,
+    // None uses the project's default
+    plan_mode: Option<bool>,
+    options: ClaudeRequestOptions,
+    enqueued_at: String,
+}
+
+#[derive(Default)]
+struct ProjectPromptQueue {
+    pending: std::collections::VecDeque<QueuedPrompt>,
+    running: bool,
+}
+
+#[tauri::command]
+async fn enqueue_prompt(
+    app: tauri::AppHandle,
+    project_path: String,
+    prompt: String,
+    files: Option<Vec<String>>,
+    plan_mode: Option<bool>,
+    options: Option<ClaudeRequestOptions>
+) -> Result<QueuedPrompt, AppError> {
+    let queued = QueuedPrompt {
+        id: Uuid::new_v4().to_string(),
+        project_path: project_path.clone(),
+        prompt,
+        files: files.unwrap_or_default(),
+        plan_mode,
+        options: options.unwrap_or_default(),
+        enqueued_at: chrono::Utc::now().to_rfc3339(),
+    };
+    
+    let start_worker = {
+        let mut queues = PROMPT_QUEUES.write().await;
+        let queue = queues.entry(project_path.clone()).or_default();
+        queue.pending.push_back(queued.clone());
+        
+        let _ = app.emit("prompt_queue", serde_json::json!({
+            "type": "queued",
+            "projectPath": project_path,
+            "queueId": queued.id,
+            "position": queue.pending.len()
+        }));
+        
+        // Only one worker drains each project's queue
+        !std::mem::replace(&mut queue.running, true)
+    };
+    
+    if start_worker {
+        tokio::spawn(run_prompt_queue(app, project_path));
+    }
+    
+    Ok(queued)
+}
+
+#[tauri::command]
+async fn list_prompt_queue(project_path: String) -> Result<Vec<QueuedPrompt>, AppError> {
+    let queues = PROMPT_QUEUES.read().await;
+    Ok(queues.get(&project_path)
+        .map(|queue| queue.pending.iter().cloned().collect())
+        .unwrap_or_default())
+}
+
+#[tauri::command]
+async fn remove_queued_prompt(app: tauri::AppHandle, project_path: String, queue_id: String) -> Result<(), AppError> {
+    let mut queues = PROMPT_QUEUES.write().await;
+    let queue = queues.get_mut(&project_path).ok_or_else(|| AppError::NotFound("No queue for this project".to_string()))?;
+    
+    let before = queue.pending.len();
+    queue.pending.retain(|item| item.id != queue_id);
+    if queue.pending.len() == before {
+        return Err(AppError::NotFound(format!("Queued prompt {} not found", queue_id)));
+    }
+    
+    let _ = app.emit("prompt_queue", serde_json::json!({
+        "type": "removed",
+        "projectPath": project_path,
+        "queueId": queue_id
+    }));
+    emit_queue_positions(&app, &project_path, queue);
+    Ok(())
+}
+
+fn emit_queue_positions(app: &tauri::AppHandle, project_path: &str, queue: &ProjectPromptQueue) {
+    for (index, item) in queue.pending.iter().enumerate() {
+        let _ = app.emit("prompt_queue", serde_json::json!({
+            "type": "position",
+            "projectPath": project_path,
+            "queueId": item.id,
+            "position": index + 1
+        }));
+    }
+}
+
+async fn run_prompt_queue(app: tauri::AppHandle, project_path: String) {
+    println!("[INFO] Prompt queue worker started for project: {}", project_path);
+    
+    loop {
+        // Take the next prompt, or mark the queue idle while still holding the lock
+        let next = {
+            let mut queues = PROMPT_QUEUES.write().await;
+            let queue = match queues.get_mut(&project_path) {
+                Some(queue) => queue,
+                None => break,
+            };
+            match queue.pending.pop_front() {
+                Some(item) => {
+                    emit_queue_positions(&app, &project_path, queue);
+                    item
+                }
+                None => {
+                    queues.remove(&project_path);
+                    break;
+                }
+            }
+        };
+        
+        let _ = app.emit("prompt_queue", serde_json::json!({
+            "type": "started",
+            "projectPath": project_path,
+            "queueId": next.id
+        }));
+        
+        let mut options = next.options.clone();
+        options.request_id = Some(next.id.clone());
+        let result = execute_claude_request(
+            app.clone(),
+            vec![next.prompt.clone()],
+            next.files.clone(),
+            next.plan_mode,
+            Some(project_path.clone()),
+            options
+        ).await;
+        
+        let _ = app.emit("prompt_queue", serde_json::json!({
+            "type": "finished",
+            "projectPath": project_path,
+            "queueId": next.id,
+            "success": result.is_ok(),
+            "result": result.as_ref().ok(),
+            "error": result.as_ref().err()
+        }));
+    }
+    
+    println!("[INFO] Prompt queue worker finished for project: {}", project_path);
+}
+
+// Outcome of one project's run in a multi-project fan-out
+#[derive(Debug, Serialize, Clone)]
+struct MultiPromptResult {
+    project_path: String,
+    request_id: String,
+    success: bool,
+    result: Option<String>,
+    error: Option<AppError>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+struct MultiPromptSummary {
+    batch_id: String,
+    succeeded: usize,
+    failed: usize,
+    results: Vec<MultiPromptResult>,
+}
+
+// Run the same prompt in several projects, a few at a time
+#[tauri::command]
+async fn execute_prompt_multi(
+    app: tauri::AppHandle,
+    projects: Vec<String>,
+    prompt: String,
+    max_concurrency: Option<usize>,
+    options: Option<ClaudeRequestOptions>
+) -> Result<MultiPromptSummary, AppError> {
+    if projects.is_empty() {
+        return Err(AppError::InvalidInput("No projects selected".to_string()));
+    }
+    
+    let batch_id = Uuid::new_v4().to_string();
+    let options = options.unwrap_or_default();
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(max_concurrency.unwrap_or(3).max(1)));
+    println!("[INFO] Fanning out prompt to {} projects (batch {})", projects.len(), batch_id);
+    
+    let mut handles = Vec::new();
+    for (index, project_path) in projects.into_iter().enumerate() {
+        let app = app.clone();
+        let prompt = prompt.clone();
+        let batch_id = batch_id.clone();
+        let semaphore = semaphore.clone();
+        
+        // Each project continues its own chat, so per-request identity is reset
+        let mut options = options.clone();
+        options.request_id = Some(format!("{}-{}", batch_id, index));
+        options.session_key = None;
+        options.resume_session_id = None;
+        options.fork_session = false;
+        
+        handles.push(tokio::spawn(async move {
+            let request_id = options.request_id.clone().unwrap_or_default();
+            let _permit = semaphore.acquire_owned().await;
+            
+            let _ = app.emit("prompt_multi", serde_json::json!({
+                "type": "started",
+                "batchId": batch_id,
+                "projectPath": project_path,
+                "requestId": request_id
+            }));
+            
+            let result = execute_claude_request(
+                app.clone(),
+                vec![prompt],
+                vec![],
+                None,
+                Some(project_path.clone()),
+                options
+            ).await;
+            
+            let _ = app.emit("prompt_multi", serde_json::json!({
+                "type": "finished",
+                "batchId": batch_id,
+                "projectPath": project_path,
+                "requestId": request_id,
+                "success": result.is_ok(),
+                "error": result.as_ref().err()
+            }));
+            
+            MultiPromptResult {
+                project_path,
+                request_id,
+                success: result.is_ok(),
+                error: result.as_ref().err().cloned(),
+                result: result.ok(),
+            }
+        }));
+    }
+    
+    let mut results = Vec::new();
+    for handle in handles {
+        match handle.await {
+            Ok(result) => results.push(result),
+            Err(e) => println!("[ERROR] Fan-out task failed: {}", e),
+        }
+    }
+    
+    let succeeded = results.iter().filter(|r| r.success).count();
+    let summary = MultiPromptSummary {
+        batch_id,
+        succeeded,
+        failed: results.len() - succeeded,
+        results,
+    };
+    
+    let _ = app.emit("prompt_multi", serde_json::json!({
+        "type": "summary",
+        "batchId": summary.batch_id,
+        "succeeded": summary.succeeded,
+        "failed": summary.failed
+    }));
+    
+    println!("[INFO] Batch {} finished: {} succeeded, {} failed", summary.batch_id, summary.succeeded, summary.failed);
+    Ok(summary)
+}
+
+async fn run_claude_request(
+    app: tauri::AppHandle,
+    request_id: String,
+    args: Vec<String>,
+    files: Vec<String>,
+    plan_mode: bool,
+    project_path: Option<String>,
+    options: ClaudeRequestOptions
+) -> Result<String, AppError> {
+    // Load the project's saved GUI defaults (missing or unreadable settings just mean no defaults)
+    let project_settings = match &project_path {
+        Some(path) => read_project_settings(path.clone()).await.unwrap_or_default(),
+        None => ProjectGuiSettings::default(),
+    };
+    
+    // Use stream-json format to get detailed tool information and token usage,
+    // unless the caller asked for the single structured JSON result
+    let json_output = options.output_format.as_deref() == Some("json");
+    let mut command_args = if json_output {
+        vec![
+            "--print".to_string(),
+            "--output-format".to_string(),
+            "json".to_string()
+        ]
+    } else {
+        vec![
+            "--print".to_string(),
+            "--output-format".to_string(),
+            "stream-json".to_string(),
+            "--verbose".to_string()
+        ]
+    };
+    
+    // Check if this chat has an existing session ID to continue
+    let session_key = options.session_key.clone()
+        .or_else(|| project_path.clone())
+        .unwrap_or_default();
+    if let Some(resume_id) = options.resume_session_id.as_ref().filter(|id| !id.trim().is_empty()) {
+        // Resuming an explicit session takes precedence over the chat's current one
+        command_args.push("--resume".to_string());
+        command_args.push(resume_id.clone());
+        if options.fork_session {
+            command_args.push("--fork-session".to_string());
+        }
+    } else if options.continue_latest {
+        command_args.push("--continue".to_string());
+    } else if let Some(session_id) = CHAT_SESSION_IDS.read().await.get(&session_key) {
+        command_args.push("--session-id".to_string());
+        command_args.push(session_id.clone());
+    }
+    
+    // Use the requested model, falling back to the project's default
+    if let Some(model) = options.model.clone().or(project_settings.default_model) {
+        command_args.push("--model".to_string());
+        command_args.push(model);
+    }
+    
+    // Limit how many agentic turns Claude may take
+    if let Some(turns) = options.max_turns {
+        command_args.push("--max-turns".to_string());
+        command_args.push(turns.to_string());
+    }
+    
+    // Combine the project's saved tool policy with any tools named for this request
+    let tool_policy = project_settings.tool_policy.merged_with(&options.allowed_tools, &options.disallowed_tools);
+    if !tool_policy.allowed_tools.is_empty() {
+        command_args.push("--allowedTools".to_string());
+        command_args.push(tool_policy.allowed_tools.join(","));
+    }
+    if !tool_policy.disallowed_tools.is_empty() {
+        command_args.push("--disallowedTools".to_string());
+        command_args.push(tool_policy.disallowed_tools.join(","));
+    }
+    
+    // Give Claude access to additional directories (e.g. sibling packages in a monorepo,
+    // or the other roots of the project's workspace)
+    let mut extra_dirs = project_settings.extra_dirs.clone();
+    if let Some(path) = &project_path {
+        if let Ok(Some(real_path)) = get_real_project_path(path.clone()).await {
+            for root in workspace_roots(&real_path).into_iter().skip(1) {
+                if !extra_dirs.contains(&root) {
+                    extra_dirs.push(root);
+                }
+            }
+        }
+    }
+    for dir in &options.extra_dirs {
+        if !extra_dirs.contains(dir) {
+            extra_dirs.push(dir.clone());
+        }
+    }
+    for dir in extra_dirs {
+        if std::path::Path::new(&dir).is_dir() {
+            command_args.push("--add-dir".to_string());
+            command_args.push(dir);
+        } else {
+            println!("[WARN] Skipping extra directory that does not exist: {}", dir);
+        }
+    }
+    
+    // Custom system prompts from the request, falling back to the project's saved prompts
+    if let Some(prompt) = options.system_prompt.clone().or(project_settings.system_prompt.clone()) {
+        command_args.push("--system-prompt".to_string());
+        command_args.push(prompt);
+    }
+    if let Some(prompt) = options.append_system_prompt.clone().or(project_settings.append_system_prompt.clone()) {
+        command_args.push("--append-system-prompt".to_string());
+        command_args.push(prompt);
+    }
+    
+    // Add plan mode flag if enabled
+    if plan_mode {
+        command_args.push("--permission-mode".to_string());
+        command_args.push("plan".to_string());
+    }
+    
+    // Add files as direct arguments before the prompt
+    for file in files {
+        command_args.push(file);
+    }
+    
+    // Add the user message as the last argument
+    if let Some(message) = args.first() {
+        command_args.push(message.clone());
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+
+    // Emit initial status
+    emit_claude_stream(&app, &request_id, ClaudeStreamEvent::Status {
+        message: "Starting Claude Code...".to_string(),
+        timestamp,
+    });
+
+    // Determine working directory based on project path
+    let working_dir = if let Some(proj_path) = project_path.clone() {
+        // Get the real project directory
+        match get_real_project_path(proj_path).await? {
+            Some(real_path) => {
+                emit_claude_stream(&app, &request_id, ClaudeStreamEvent::Status {
+                    message: format!("Using project directory: {}", real_path),
+                    timestamp: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                });
+                std::path::PathBuf::from(real_path)
+            },
+            None => {
+                emit_claude_stream(&app, &request_id, ClaudeStreamEvent::Status {
+                    message: "Could not find real project path, using current directory".to_string(),
+                    timestamp: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64,
+                });
+                std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."))
+            }
+        }
+    } else {
+        std::env::current_dir().unwrap_or_else(|_| std::path::PathBuf::from("."))
+    };
+
+    // Spawn with piped output so events reach the UI as Claude produces them
+    let mut child = AsyncCommand::new(claude_binary())
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(&command_args)
+        .current_dir(&working_dir)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            let error = AppError::spawn("claude", e);
+            emit_claude_stream(&app, &request_id, ClaudeStreamEvent::Error {
+                message: error.to_string(),
+                timestamp: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+            });
+            error
+        })?;
+
+    if let Some(execution) = ACTIVE_EXECUTIONS.write().await.get_mut(&request_id) {
+        execution.pid = child.id();
+    }
+
+    let stdout = child.stdout.take().ok_or_else(|| AppError::ProcessFailed("Failed to capture claude stdout".to_string()))?;
+    let stderr = child.stderr.take().ok_or_else(|| AppError::ProcessFailed("Failed to capture claude stderr".to_string()))?;
+    
+    // Drain stderr in the background so a chatty process can't block on a full pipe,
+    // forwarding each line on its own channel instead of mixing it into the transcript
+    let stderr_app = app.clone();
+    let stderr_request_id = request_id.clone();
+    let stderr_task = tokio::spawn(async move {
+        let mut buffer = String::new();
+        let mut stderr_lines = tokio::io::BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = stderr_lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            emit_claude_stream(&stderr_app, &stderr_request_id, ClaudeStreamEvent::Stderr {
+                kind: classify_stderr_line(&line).to_string(),
+                line: line.clone(),
+                timestamp: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_millis() as u64,
+            });
+            buffer.push_str(&line);
+            buffer.push('\n');
+        }
+        buffer
+    });
+
+    // Parse stream-json format
+    let mut assistant_response = String::new();
+    let mut raw_stdout = String::new();
+    let mut processed_lines = std::collections::HashSet::new();
+    let mut budget_overrun = None;
+    // The result record only arrives at the end, so the budget is also checked against a
+    // running estimate priced from each assistant message's usage. A message can be split
+    // over several records, so only its latest usage counts.
+    let pricing = model_pricing_table();
+    let mut message_costs: HashMap<String, f64> = HashMap::new();
+    let mut unidentified_cost = 0.0;
+    let mut last_error = None;
+    let mut lines = tokio::io::BufReader::new(stdout).lines();
+    
+    while let Some(line) = lines.next_line().await.map_err(|e| AppError::io("Failed to read claude output", e))? {
+        raw_stdout.push_str(&line);
+        raw_stdout.push('\n');
+        let line_trimmed = line.trim();
+        
+        // Skip empty lines and prevent processing the same line twice
+        if line_trimmed.is_empty() || processed_lines.contains(line_trimmed) {
+            continue;
+        }
+        processed_lines.insert(line_trimmed.to_string());
+        
+        if let Some(limit) = options.max_cost_usd {
+            let usage = serde_json::from_str::<serde_json::Value>(line_trimmed).ok()
+                .filter(|json| json.get("type").and_then(|t| t.as_str()) == Some("assistant"))
+                .and_then(|json| usage_entry_from_record(&json));
+            if let Some(usage) = usage {
+                let cost = usage_cost(&pricing, &usage);
+                match usage.message_id {
+                    Some(message_id) => { message_costs.insert(message_id, cost); }
+                    None => unidentified_cost += cost,
+                }
+                let spent = unidentified_cost + message_costs.values().sum::<f64>();
+                if spent > limit {
+                    budget_overrun = Some((limit, spent));
+                }
+            }
+        }
+        
+        for event in parse_claude_json_event(line_trimmed) {
+            // Remember the session so the next request in this chat continues it
+            match &event {
+                ClaudeStreamEvent::SessionInit { session_id: Some(session_id), .. } |
+                ClaudeStreamEvent::CostReport { session_id: Some(session_id), .. } => {
+                    CHAT_SESSION_IDS.write().await.insert(session_key.clone(), session_id.clone());
+                }
+                _ => {}
+            }
+            if let (true, ClaudeStreamEvent::SessionInit { session_id: Some(session_id), .. }) = (options.fork_session, &event) {
+                FORKED_SESSION_IDS.write().await.insert(request_id.clone(), session_id.clone());
+            }
+            
+            // Check what this run cost against the budget
+            if let (Some(limit), ClaudeStreamEvent::CostReport { cost_usd, .. }) = (options.max_cost_usd, &event) {
+                if *cost_usd > limit {
+                    budget_overrun = Some((limit, *cost_usd));
+                }
+            }
+            
+            if let ClaudeStreamEvent::Error { message, .. } = &event {
+                last_error = Some(message.clone());
+            }
+            
+            // Hold on to the plan so approve_plan can carry on in the same conversation
+            if let ClaudeStreamEvent::PlanProposed { plan_id, .. } = &event {
+                PENDING_PLANS.write().await.insert(plan_id.clone(), PendingPlan {
+                    project_path: project_path.clone(),
+                    options: ClaudeRequestOptions {
+                        session_key: Some(session_key.clone()),
+                        request_id: None,
+                        resume_session_id: None,
+                        continue_latest: false,
+                        fork_session: false,
+                        ..options.clone()
+                    },
+                });
+            }
+            
+            // Store assistant responses to return as final result
+            if let ClaudeStreamEvent::Response { content, .. } = &event {
+                if !assistant_response.is_empty() {
+                    assistant_response.push('\n');
+                }
+                assistant_response.push_str(content);
+            }
+            
+            emit_claude_stream(&app, &request_id, event);
+        }
+        
+        if let Some((limit, spent)) = budget_overrun {
+            println!("[WARN] Budget of ${:.2} exceeded (${:.2} spent), stopping claude", limit, spent);
+            let _ = child.kill().await;
+            emit_claude_stream(&app, &request_id, ClaudeStreamEvent::BudgetExceeded {
+                limit_usd: limit,
+                spent_usd: spent,
+                timestamp: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_millis() as u64,
+            });
+            break;
+        }
+    }
+
+    let status = child.wait().await
+        .map_err(|e| AppError::io("Failed to wait for claude process", e))?;
+    let stderr = stderr_task.await.unwrap_or_default();
+
+    // Emit completion
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+
+    emit_claude_stream(&app, &request_id, ClaudeStreamEvent::Complete { timestamp });
+
+    if let Some((limit, spent)) = budget_overrun {
+        return Err(AppError::BudgetExceeded(format!("Budget of ${:.2} exceeded (${:.2} spent)", limit, spent)));
+    }
+
+    // Keep the structured result next to the chat so it can be inspected later
+    if json_output && project_path.is_some() && !raw_stdout.trim().is_empty() {
+        let session_id = CHAT_SESSION_IDS.read().await.get(&session_key).cloned()
+            .unwrap_or_else(|| request_id.clone());
+        if let Err(e) = save_raw_json_output(&working_dir, &session_id, &raw_stdout) {
+            println!("[WARN] Failed to store raw JSON output: {}", e);
+        }
+    }
+
+    if status.success() {
+        // Return the assistant response content, or fall back to raw stdout if no structured response
+        if json_output {
+            Ok(raw_stdout)
+        } else if !assistant_response.is_empty() {
+            Ok(assistant_response)
+        } else {
+            Ok(raw_stdout)
+        }
+    } else {
+        // Include the reported error (or stderr) so callers can tell what went wrong
+        let detail = last_error.unwrap_or_else(|| stderr.trim().to_string());
+        if detail.is_empty() {
+            Err(AppError::ProcessFailed(format!("Claude process exited with code: {:?}", status.code())))
+        } else {
+            Err(AppError::from_claude_failure(format!("Claude process exited with code {:?}: {}", status.code(), detail)))
+        }
+    }
+}
+
+fn get_raw_json_output_path(project_dir: &std::path::Path, session_id: &str) -> std::path::PathBuf {
+    project_dir.join(".claude").join("gui-raw-output").join(format!("{}.jsonl", session_id))
+}
+
+// Append one run's raw JSON result to the session's capture file
+fn save_raw_json_output(project_dir: &std::path::Path, session_id: &str, raw: &str) -> Result<(), AppError> {
+    let output_file = get_raw_json_output_path(project_dir, session_id);
+    
+    if let Some(parent) = output_file.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create raw output directory", e))?;
+    }
+    
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&output_file)
+        .map_err(|e| AppError::io("Failed to open raw output file", e))?;
+    
+    for line in raw.lines().filter(|line| !line.trim().is_empty()) {
+        writeln!(file, "{}", line.trim())
+            .map_err(|e| AppError::io("Failed to write raw output", e))?;
+    }
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_raw_json_outputs(project_path: String, session_id: String) -> Result<Vec<serde_json::Value>, AppError> {
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    
+    let output_file = get_raw_json_output_path(std::path::Path::new(&real_path), &session_id);
+    if !output_file.exists() {
+        return Ok(vec![]);
+    }
+    
+    let content = std::fs::read_to_string(&output_file)
+        .map_err(|e| AppError::io("Failed to read raw output", e))?;
+    
+    Ok(content.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+// Pull the actionable steps (bullets and numbered items) out of a markdown plan
+fn extract_plan_steps(plan: &str) -> Vec<String> {
+    let steps: Vec<String> = plan.lines()
+        .map(|line| line.trim())
+        .filter_map(|line| {
+            if let Some(step) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+                return Some(step.trim().to_string());
+            }
+            let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 0 {
+                if let Some(step) = line[digits..].strip_prefix(". ").or_else(|| line[digits..].strip_prefix(") ")) {
+                    return Some(step.trim().to_string());
+                }
+            }
+            None
+        })
+        .filter(|step| !step.is_empty())
+        .collect();
+    
+    if steps.is_empty() {
+        // Free-form plan: fall back to its non-heading lines
+        plan.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.to_string())
+            .collect()
+    } else {
+        steps
+    }
+}
+
+// Sort stderr output into buckets the UI can filter or surface differently
+fn classify_stderr_line(line: &str) -> &'static str {
+    let lower = line.to_lowercase();
+    
+    if lower.contains("/login") || lower.contains("not logged in") || lower.contains("invalid api key") || lower.contains("authentication") {
+        "auth_required"
+    } else if lower.contains("update available") || lower.contains("new version") || lower.contains("claude update") {
+        "update_notice"
+    } else if lower.contains("(node:") || lower.contains("deprecationwarning") || lower.contains("experimentalwarning") || lower.contains("--trace-warnings") {
+        "node_warning"
+    } else if lower.contains("error") {
+        "error"
+    } else {
+        "info"
+    }
+}
+
+fn parse_claude_json_event(line: &str) -> Vec<ClaudeStreamEvent> {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+        
+    // Skip empty lines
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return Vec::new();
+    }
+    
+    // Try to parse as Claude stream-json format
+    if let Ok(claude_event) = serde_json::from_str::<ClaudeJsonEvent>(trimmed) {
+        match claude_event.event_type.as_str() {
+            "system" => {
+                if let Some(subtype) = &claude_event.subtype {
+                    match subtype.as_str() {
+                        "init" => {
+                            if let (Some(session_id), Some(model)) = (&claude_event.session_id, &claude_event.model) {
+                                record_session_model(session_id, model);
+                            }
+                            vec![ClaudeStreamEvent::SessionInit {
+                                session_id: claude_event.session_id.clone(),
+                                model: claude_event.model.clone(),
+                                tools: claude_event.tools.clone().unwrap_or_default(),
+                                mcp_servers: claude_event.mcp_servers.clone().unwrap_or_default(),
+                                cwd: claude_event.cwd.clone(),
+                                timestamp,
+                            }]
+                        },
+                        "permission_request" => {
+                            // Handle permission requests
+                            let prompt = if let Some(msg) = &claude_event.message {
+                                // Try to extract a readable prompt from the message
+                                format!("Claude is requesting permission: {}", msg.content_text())
+                            } else {
+                                "Claude is requesting permission to proceed".to_string()
+                            };
+                            
+                            vec![ClaudeStreamEvent::PermissionRequest {
+                                id: format!("perm_{}", timestamp),
+                                prompt,
+                                options: vec![
+                                    "1: Allow".to_string(),
+                                    "2: Allow and remember".to_string(),
+                                    "3: Deny".to_string(),
+                                ],
+                                timestamp,
+                            }]
+                        },
+                        _ => vec![ClaudeStreamEvent::Status {
+                            message: format!("System: {}", subtype),
+                            timestamp,
+                        }],
+                    }
+                } else {
+                    Vec::new()
+                }
+            },
+            "assistant" => {
+                if let Some(message) = &claude_event.message {
+                    // Parse message content to extract text and tool usage
+                    if let Some(content_array) = message.content_blocks() {
+                        let mut text_content = String::new();
+                        let mut tool_usage = Vec::new();
+                        let mut plan_events = Vec::new();
+                        
+                        for item in &content_array {
+                            if let Some(item_type) = item.get("type").and_then(|t| t.as_str()) {
+                                match item_type {
+                                    "text" => {
+                                        if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
+                                            if !text_content.is_empty() {
+                                                text_content.push('\n');
+                                            }
+                                            text_content.push_str(text);
+                                        }
+                                    },
+                                    "tool_use" => {
+                                        if let (Some(name), Some(input)) = (
+                                            item.get("name").and_then(|n| n.as_str()),
+                                            item.get("input")
+                                        ) {
+                                            tool_usage.push(format!("🔧 Using tool: {}", name));
+                                            
+                                            // Add tool parameters for common tools
+                                            match name {
+                                                "Glob" => {
+                                                    if let Some(pattern) = input.get("pattern").and_then(|p| p.as_str()) {
+                                                        tool_usage.push(format!("   Searching for pattern: {}", pattern));
+                                                    }
+                                                },
+                                                "Grep" => {
+                                                    if let Some(pattern) = input.get("pattern").and_then(|p| p.as_str()) {
+                                                        tool_usage.push(format!("   Searching for: {}", pattern));
+                                                    }
+                                                },
+                                                "Read" => {
+                                                    if let Some(path) = input.get("file_path").and_then(|p| p.as_str()) {
+                                                        tool_usage.push(format!("   Reading file: {}", path.split('/').last().unwrap_or(path)));
+                                                    }
+                                                },
+                                                "Task" => {
+                                                    if let Some(desc) = input.get("description").and_then(|d| d.as_str()) {
+                                                        tool_usage.push(format!("   Task: {}", desc));
+                                                    }
+                                                },
+                                                "ExitPlanMode" => {
+                                                    // Plan mode hands its plan back through this tool
+                                                    if let Some(plan) = input.get("plan").and_then(|p| p.as_str()) {
+                                                        plan_events.push(ClaudeStreamEvent::PlanProposed {
+                                                            plan_id: item.get("id")
+                                                                .and_then(|i| i.as_str())
+                                                                .map(|i| i.to_string())
+                                                                .unwrap_or_else(|| format!("plan_{}", timestamp)),
+                                                            plan: plan.to_string(),
+                                                            steps: extract_plan_steps(plan),
+                                                            timestamp,
+                                                        });
+                                                    }
+                                                },
+                                                "TodoWrite" => {
+                                                    if let Some(todos_array) = input.get("todos").and_then(|t| t.as_array()) {
+                                                        tool_usage.push(format!("📝 Updating todos ({} items)", todos_array.len()));
+                                                        
+                                                        // Extract and emit todo data for real-time sync
+                                                        // This will be handled by a separate function
+                                                        // to avoid blocking the stream parsing
+                                                    }
+                                                },
+                                                _ => {
+                                                    tool_usage.push(format!("   Executing {}", name));
+                                                }
+                                            }
+                                        }
+                                    },
+                                    _ => {}
+                                }
+                            }
+                        }
+                        
+                        // Emit tool usage as thinking events, followed by any proposed plan
+                        if !tool_usage.is_empty() {
+                            let mut events = vec![ClaudeStreamEvent::Thinking {
+                                message: tool_usage.join("\n"),
+                                timestamp,
+                            }];
+                            events.extend(plan_events);
+                            return events;
+                        }
+                        
+                        // Emit text content as response
+                        if !text_content.is_empty() {
+                            return vec![ClaudeStreamEvent::Response {
+                                content: text_content,
+                                timestamp,
+                            }];
+                        }
+                    }
+                    
+                    // Fallback to raw content if parsing fails
+                    vec![ClaudeStreamEvent::Response {
+                        content: message.content_text(),
+                        timestamp,
+                    }]
+                } else {
+                    Vec::new()
+                }
+            },
+            "user" => {
+                // Don't emit user messages as events (they're already in the UI)
+                Vec::new()
+            },
+            "result" => {
+                let mut events = if claude_event.is_error == Some(true) {
+                    // API failures (e.g. "API Error: 429 ...") arrive as error results
+                    vec![ClaudeStreamEvent::Error {
+                        message: claude_event.result.clone()
+                            .or_else(|| claude_event.error.clone())
+                            .unwrap_or_else(|| "Unknown error".to_string()),
+                        timestamp,
+                    }]
+                } else if let Some(subtype) = &claude_event.subtype {
+                    match subtype.as_str() {
+                        "success" => {
+                            // Extract usage information if available
+                            if let Some(usage) = &claude_event.usage {
+                                vec![ClaudeStreamEvent::TokenUsage {
+                                    input: usage.input_tokens,
+                                    output: usage.output_tokens,
+                                    total: usage.input_tokens + usage.output_tokens,
+                                    timestamp,
+                                }]
+                            } else {
+                                vec![ClaudeStreamEvent::Complete { timestamp }]
+                            }
+                        },
+                        "error" => vec![ClaudeStreamEvent::Error {
+                            message: claude_event.error.clone().unwrap_or_else(|| "Unknown error".to_string()),
+                            timestamp,
+                        }],
+                        _ => vec![ClaudeStreamEvent::Complete { timestamp }],
+                    }
+                } else {
+                    vec![ClaudeStreamEvent::Complete { timestamp }]
+                };
+                
+                // Report what this run cost and how long it took, plus the running total for the session
+                if claude_event.total_cost_usd.is_some() || claude_event.duration_ms.is_some() {
+                    let cost_usd = claude_event.total_cost_usd.unwrap_or(0.0);
+                    let session_total_usd = match &claude_event.session_id {
+                        Some(session_id) => record_session_cost(session_id, cost_usd),
+                        None => cost_usd,
+                    };
+                    
+                    events.push(ClaudeStreamEvent::CostReport {
+                        session_id: claude_event.session_id.clone(),
+                        cost_usd,
+                        session_total_usd,
+                        duration_ms: claude_event.duration_ms.unwrap_or(0),
+                        timestamp,
+                    });
+                }
+                
+                // Session usage changed; let the budget checker re-evaluate
+                USAGE_CHANGED.notify_one();
+                
+                // Report how full the context window is getting after this turn
+                if let (Some(session_id), Some(usage)) = (&claude_event.session_id, &claude_event.usage) {
+                    let turn_tokens = usage.input_tokens as u64
+                        + usage.cache_creation_input_tokens.unwrap_or(0) as u64
+                        + usage.output_tokens as u64;
+                    let (used, window) = record_session_context(session_id, turn_tokens);
+                    events.push(ClaudeStreamEvent::ContextStatus {
+                        percentage: ((used as f64 / window as f64) * 100.0).min(100.0) as f32,
+                        remaining: format!("{} tokens", window.saturating_sub(used)),
+                        timestamp,
+                    });
+                }
+                
+                events
+            },
+            _ => {
+                // Don't emit unknown events as status to reduce noise
+                Vec::new()
+            }
+        }
+    } else {
+        // Check if this might be a permission-related message
+        if trimmed.starts_with("Claude requested permissions") || 
+           trimmed.contains("permission") && (trimmed.contains("Allow") || trimmed.contains("Deny")) {
+            // This looks like a permission request
+            vec![ClaudeStreamEvent::PermissionRequest {
+                id: format!("perm_{}", timestamp),
+                prompt: "Claude is requesting permission to access files or perform operations".to_string(),
+                options: vec![
+                    "1: Allow".to_string(),
+                    "2: Allow and remember".to_string(), 
+                    "3: Deny".to_string(),
+                ],
+                timestamp,
+            }]
+        } else {
+            // If it's not valid JSON, only process specific patterns to reduce noise
+            let line_lower = trimmed.to_lowercase();
+            
+            if line_lower.contains("thinking") || line_lower.contains("processing") {
+                vec![ClaudeStreamEvent::Thinking {
+                    message: trimmed.to_string(),
+                    timestamp,
+                }]
             } else if line_lower.contains("error") && line_lower.contains("failed") {
-                Some(ClaudeStreamEvent::Error {
+                vec![ClaudeStreamEvent::Error {
                     message: trimmed.to_string(),
                     timestamp,
+                }]
+            } else {
+                // Skip non-JSON content to reduce noise
+                Vec::new()
+            }
+        }
+    }
+}
+
+// Add a run's cost to the session total and return the new total
+fn record_session_cost(session_id: &str, cost_usd: f64) -> f64 {
+    let mut costs = SESSION_COSTS.lock().unwrap_or_else(|e| e.into_inner());
+    let total = costs.entry(session_id.to_string()).or_insert(0.0);
+    *total += cost_usd;
+    *total
+}
+
+#[derive(Debug, Clone, Default)]
+struct SessionContext {
+    model: Option<String>,
+    tokens: u64,
+}
+
+fn record_session_model(session_id: &str, model: &str) {
+    let mut contexts = SESSION_CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+    contexts.entry(session_id.to_string()).or_default().model = Some(model.to_string());
+}
+
+// Add a turn's new tokens to the session and return (tokens used, context window size)
+fn record_session_context(session_id: &str, tokens: u64) -> (u64, u64) {
+    let mut contexts = SESSION_CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+    let context = contexts.entry(session_id.to_string()).or_default();
+    context.tokens += tokens;
+    (context.tokens, context_window_for_model(context.model.as_deref()))
+}
+
+// Context window size for a model, with overrides from the app settings table. The most
+// specific (longest) matching name wins, so "claude-opus-4-1" beats "claude-opus".
+fn context_window_for_model(model: Option<&str>) -> u64 {
+    let model = model.unwrap_or_default();
+    
+    let mut sizes = CONTEXT_WINDOW_SIZES.lock().unwrap();
+    let sizes = sizes.get_or_insert_with(|| read_app_settings().map(|s| s.context_window_sizes).unwrap_or_default());
+    let size = sizes.iter()
+        .filter(|(name, _)| model.contains(name.as_str()))
+        .max_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| b.cmp(a)))
+        .map(|(_, size)| *size);
+    if let Some(size) = size {
+        return size;
+    }
+    
+    if model.contains("[1m]") {
+        1_000_000
+    } else {
+        200_000
+    }
+}
+
+#[tauri::command]
+async fn get_session_cost(session_id: String) -> Result<f64, AppError> {
+    let costs = SESSION_COSTS.lock().map_err(|e| AppError::Other(format!("Failed to read session costs: {}", e)))?;
+    Ok(costs.get(&session_id).copied().unwrap_or(0.0))
+}
+
+#[tauri::command]
+async fn execute_claude_command_with_files(
+    args: Vec<String>, 
+    files: Vec<String>,
+    enable_autocomplete: bool,
+    plan_mode: bool
+) -> Result<String, AppError> {
+    let mut command_args = args;
+    
+    // Add plan mode flag if enabled
+    if plan_mode {
+        command_args.insert(0, "--plan".to_string());
+    }
+    
+    // Add autocomplete flag if disabled
+    if !enable_autocomplete {
+        command_args.insert(0, "--no-autocomplete".to_string());
+    }
+    
+    // Add files as direct arguments (Claude Code accepts file paths as arguments)
+    for file in files {
+        command_args.push(file);
+    }
+    
+    let output = Command::new(claude_binary())
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(&command_args)
+        .output()
+        .map_err(|e| AppError::spawn("claude", e))?;
+    
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(AppError::from_claude_failure(String::from_utf8_lossy(&output.stderr).to_string()))
+    }
+}
+
+
+// Decode a drive-letter project directory name, using the file system to tell
+// path separators apart from dashes that belong to a directory name. Only a directory
+// that exists counts, so names like "C--x" elsewhere aren't mistaken for Windows paths.
+fn decode_windows_project_dir(encoded: &str) -> Option<String> {
+    let mut chars = encoded.chars();
+    let drive = chars.next().filter(|c| c.is_ascii_alphabetic())?;
+    let rest = encoded.get(1..)?.strip_prefix("--")?;
+    
+    let mut path = std::path::PathBuf::from(format!("{}:\\", drive));
+    let mut component = String::new();
+    for part in rest.split('-') {
+        if component.is_empty() {
+            component = part.to_string();
+        } else if path.join(&component).is_dir() {
+            path.push(&component);
+            component = part.to_string();
+        } else {
+            component = format!("{}-{}", component, part);
+        }
+    }
+    if !component.is_empty() {
+        path.push(&component);
+    }
+    
+    path.is_dir().then(|| path.to_string_lossy().to_string())
+}
+
+fn get_project_path_cache_file() -> Result<std::path::PathBuf, AppError> {
+    let home_dir = dirs::home_dir().ok_or(AppError::PathNotFound("Could not find home directory".to_string()))?;
+    Ok(home_dir.join(".claude").join("gui-project-paths.json"))
+}
+
+fn read_project_path_cache() -> HashMap<String, String> {
+    get_project_path_cache_file().ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn cached_project_path(claude_project_path: &str) -> Option<String> {
+    let mut cache = PROJECT_PATH_CACHE.lock().unwrap();
+    cache.get_or_insert_with(read_project_path_cache).get(claude_project_path).cloned()
+}
+
+fn cache_project_path(claude_project_path: &str, real_path: &str) {
+    let mut cache = PROJECT_PATH_CACHE.lock().unwrap();
+    let cache = cache.get_or_insert_with(read_project_path_cache);
+    if cache.get(claude_project_path).map(|p| p.as_str()) == Some(real_path) {
+        return;
+    }
+    cache.insert(claude_project_path.to_string(), real_path.to_string());
+    invalidate_project_dir_real_paths();
+    
+    let result = get_project_path_cache_file().and_then(|file| {
+        let content = serde_json::to_string_pretty(&*cache)
+            .map_err(|e| AppError::parse("Failed to serialize project path cache", e))?;
+        std::fs::write(&file, content).map_err(|e| AppError::io("Failed to write project path cache", e))
+    });
+    if let Err(e) = result {
+        println!("[WARN] {}", e);
+    }
+}
+
+// Search session files newest first, and each file from its last entry backwards, for
+// the first value `extract` accepts
+fn find_in_newest_session_entries(
+    project_dir: &std::path::Path,
+    max_files: usize,
+    extract: impl Fn(&serde_json::Value) -> Option<String>
+) -> Option<String> {
+    let mut files: Vec<(std::time::SystemTime, std::path::PathBuf)> = std::fs::read_dir(project_dir).ok()?
+        .flatten()
+        .filter(|entry| entry.path().extension().and_then(|s| s.to_str()) == Some("jsonl"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    
+    for (_, path) in files.iter().take(max_files) {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let found = content.lines().rev()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .find_map(|json| extract(&json));
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+// The working directory Claude recorded most recently for this project. Project lists
+// ask for every project, so the answer is kept until a session file is added or removed.
+fn cwd_from_session_files(project_dir: &std::path::Path) -> Option<String> {
+    let modified = std::fs::metadata(project_dir).and_then(|metadata| metadata.modified()).ok();
+    if let Some(modified) = modified {
+        let cache = SESSION_CWD_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = cache.get(project_dir) {
+            if cached.modified == modified && cached.value.as_deref().is_none_or(|cwd| std::path::Path::new(cwd).is_dir()) {
+                return cached.value.clone();
+            }
+        }
+    }
+    
+    let cwd = find_in_newest_session_entries(project_dir, 5, |json| {
+        json.get("cwd").and_then(|cwd| cwd.as_str())
+            .filter(|cwd| std::path::Path::new(cwd).is_dir())
+            .map(|cwd| cwd.to_string())
+    });
+    if let Some(modified) = modified {
+        SESSION_CWD_CACHE.lock().unwrap_or_else(|e| e.into_inner())
+            .insert(project_dir.to_path_buf(), DirCacheEntry { modified, value: cwd.clone() });
+    }
+    cwd
+}
+
+// Timestamp of the newest session entry, as RFC3339
+fn last_session_activity(project_dir: &std::path::Path) -> Option<String> {
+    find_in_newest_session_entries(project_dir, 1, |json| {
+        json.get("timestamp").and_then(|ts| ts.as_str())
+            .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+            .map(|ts| ts.with_timezone(&chrono::Utc).to_rfc3339())
+    })
+}
+
+#[tauri::command]
+async fn get_real_project_path(claude_project_path: String) -> Result<Option<String>, AppError> {
+    if let Some(real_path) = cached_project_path(&claude_project_path) {
+        if std::path::Path::new(&real_path).is_dir() {
+            return Ok(Some(real_path));
+        }
+    }
+    
+    let resolved = match cwd_from_session_files(std::path::Path::new(&claude_project_path)) {
+        Some(cwd) => Some(cwd),
+        None => guess_real_project_path(claude_project_path.clone()).await?,
+    };
+    
+    // Only remember paths that exist; guesses from the directory name may not
+    if let Some(real_path) = &resolved {
+        if std::path::Path::new(real_path).is_dir() {
+            cache_project_path(&claude_project_path, real_path);
+        }
+    }
+    Ok(resolved)
+}
+
+// Fallbacks for projects without a usable cwd: metadata files, then decoding the directory name
+async fn guess_real_project_path(claude_project_path: String) -> Result<Option<String>, AppError> {
+    let project_dir = std::path::Path::new(&claude_project_path);
+    
+    // Try to read various metadata files that might contain the real path
+    let possible_files = vec![
+        ".claude-project",
+        "project.json",
+        ".project",
+        "config.json",
+        ".claude",
+    ];
+    
+    for file_name in possible_files {
+        let file_path = project_dir.join(file_name);
+        if file_path.exists() {
+            if let Ok(content) = std::fs::read_to_string(&file_path) {
+                // Try to parse as JSON and look for path-like fields
+                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
+                    // Check various field names that might contain the path
+                    let path_fields = vec!["path", "projectPath", "directory", "root", "workingDirectory"];
+                    for field in path_fields {
+                        if let Some(path) = json.get(field).and_then(|p| p.as_str()) {
+                            return Ok(Some(path.to_string()));
+                        }
+                    }
+                }
+                
+                // If not JSON, maybe it's just a plain text file with the path
+                let trimmed_content = content.trim();
+                if std::path::Path::new(trimmed_content).is_absolute() && std::path::Path::new(trimmed_content).exists() {
+                    return Ok(Some(trimmed_content.to_string()));
+                }
+            }
+        }
+    }
+    
+    // Check if there are any files that look like they contain path information
+    if let Ok(entries) = std::fs::read_dir(&project_dir) {
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            if let Some(name_str) = file_name.to_str() {
+                // Look for any JSON or JSONL files that might contain metadata
+                if name_str.ends_with(".json") || name_str.ends_with(".jsonl") {
+                    if let Ok(content) = std::fs::read_to_string(entry.path()) {
+                        // For .jsonl files, check each line
+                        let lines_to_check = if name_str.ends_with(".jsonl") {
+                            content.lines().take(10).collect::<Vec<_>>()
+                        } else {
+                            vec![content.as_str()]
+                        };
+                        
+                        for line in lines_to_check {
+                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
+                                let path_fields = vec!["path", "projectPath", "directory", "root", "workingDirectory", "cwd"];
+                                for field in path_fields {
+                                    if let Some(path) = json.get(field).and_then(|p| p.as_str()) {
+                                        if std::path::Path::new(path).exists() {
+                                            return Ok(Some(path.to_string()));
+                                        }
+                                    }
+                                }
+                                
+                                // Also search for any path-like strings in the JSON
+                                if let Some(obj) = json.as_object() {
+                                    for (_, value) in obj {
+                                        if let Some(str_val) = value.as_str() {
+                                            // Check if it looks like an absolute path and exists
+                                            if std::path::Path::new(str_val).is_absolute() && std::path::Path::new(str_val).exists() {
+                                                return Ok(Some(str_val.to_string()));
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    // Fallback: decode the directory name to get the real path
+    // Claude projects encode paths by replacing '/' with '-' and adding a leading '-'
+    // Example: /Users/username/repos/project-name -> -Users-username-repos-project-name
+    if let Some(dir_name) = std::path::Path::new(&claude_project_path).file_name() {
+        if let Some(encoded_path) = dir_name.to_str() {
+            // Windows paths encode the drive too: C:\Users\me\repo -> C--Users-me-repo
+            if let Some(decoded) = decode_windows_project_dir(encoded_path) {
+                return Ok(Some(decoded));
+            }
+            
+            if encoded_path.starts_with('-') {
+                let path_part = &encoded_path[1..];
+                
+                // Strategy: Try to intelligently decode by looking for known path patterns
+                // Common pattern: Users-username-repos-project-name
+                if let Some(repos_pos) = path_part.find("-repos-") {
+                    // Split at "repos" - everything before is directory structure
+                    let before_repos = &path_part[..repos_pos];
+                    let after_repos_with_dash = &path_part[repos_pos + 6..]; // +6 for "-repos-"
+                    
+                    // Before repos: replace dashes with slashes
+                    let dir_structure = before_repos.replace('-', "/");
+                    
+                    // After repos: remove the leading dash if present, then keep remaining dashes
+                    let after_repos = if after_repos_with_dash.starts_with('-') {
+                        &after_repos_with_dash[1..]
+                    } else {
+                        after_repos_with_dash
+                    };
+                    
+                    // Try both the original project name and with dashes converted to underscores
+                    // since project names might use underscores but Claude encodes them as dashes
+                    let project_with_dashes = format!("/{}/repos/{}", dir_structure, after_repos);
+                    let project_with_underscores = format!("/{}/repos/{}", dir_structure, after_repos.replace('-', "_"));
+                    
+                    // Check which one actually exists
+                    if std::path::Path::new(&project_with_underscores).exists() {
+                        return Ok(Some(project_with_underscores));
+                    } else if std::path::Path::new(&project_with_dashes).exists() {
+                        return Ok(Some(project_with_dashes));
+                    } else {
+                        // Return the underscore version as it's more likely for project names
+                        return Ok(Some(project_with_underscores));
+                    }
+                }
+                
+                // Fallback: look for other common patterns
+                if path_part.starts_with("Users-") {
+                    let parts: Vec<&str> = path_part.split('-').collect();
+                    if parts.len() >= 3 {
+                        // Assume first 3 parts are Users/username/something, rest is project name
+                        let base_path = format!("/{}/{}/{}", parts[0], parts[1], parts[2]);
+                        if parts.len() > 3 {
+                            let project_name = parts[3..].join("-");
+                            return Ok(Some(format!("{}/{}", base_path, project_name)));
+                        } else {
+                            return Ok(Some(base_path));
+                        }
+                    }
+                }
+                
+                // Last resort: replace all dashes with slashes
+                let decoded_path = format!("/{}", path_part.replace('-', "/"));
+                return Ok(Some(decoded_path));
+            }
+        }
+    }
+    
+    Ok(None)
+}
+
+// Spellings of CLAUDE.md accepted in a project, in order of preference
+const CLAUDE_MD_NAMES: [&str; 3] = ["CLAUDE.md", "claude.md", "Claude.md"];
+
+fn find_claude_md(dir: &std::path::Path) -> Option<std::path::PathBuf> {
+    CLAUDE_MD_NAMES.iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+}
+
+#[tauri::command]
+async fn get_claude_md_content(project_path: String) -> Result<Option<String>, AppError> {
+    // First get the real project path
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Ok(None)
+    };
+    
+    match find_claude_md(std::path::Path::new(&real_path)) {
+        Some(claude_md_path) => match std::fs::read_to_string(&claude_md_path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) => Err(AppError::io(&format!("Failed to read CLAUDE.md at {}", claude_md_path.display()), e))
+        },
+        None => Ok(None)
+    }
+}
+
+#[tauri::command]
+async fn save_claude_md_content(project_path: String, content: String) -> Result<(), AppError> {
+    // First get the real project path
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    
+    let claude_md_path = std::path::Path::new(&real_path).join("CLAUDE.md");
+    
+    // Recorded first so the watcher doesn't report our own save back as an outside edit
+    CLAUDE_MD_LAST_CONTENT.lock().unwrap().insert(std::path::PathBuf::from(&real_path), Some(content.clone()));
+    std::fs::write(&claude_md_path, content)
+        .map_err(|e| AppError::io("Failed to save CLAUDE.md", e))?;
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn check_claude_md_exists(project_path: String) -> Result<bool, AppError> {
+    // First get the real project path
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Ok(false)
+    };
+    
+    Ok(find_claude_md(std::path::Path::new(&real_path)).is_some())
+}
+
+const CLAUDE_MD_WATCH_DEBOUNCE_MS: u64 = 200;
+
+// Watch the project's CLAUDE.md (any of its spellings) and emit claude_md_changed with the
+// new content when it's edited, created or deleted outside the GUI. The directory is
+// watched rather than the file, so editors that save by replacing the file are caught.
+#[tauri::command]
+async fn watch_claude_md(app: tauri::AppHandle, project_path: String) -> Result<(), AppError> {
+    use notify::Watcher;
+    
+    if CLAUDE_MD_WATCHERS.lock().unwrap().contains_key(&project_path) {
+        return Ok(());
+    }
+    let real_path = match get_real_project_path(project_path.clone()).await? {
+        Some(path) => std::path::PathBuf::from(path),
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| AppError::Other(format!("Failed to create CLAUDE.md watcher: {}", e)))?;
+    watcher.watch(&real_path, notify::RecursiveMode::NonRecursive)
+        .map_err(|e| AppError::Other(format!("Failed to watch {}: {}", real_path.display(), e)))?;
+    CLAUDE_MD_WATCHERS.lock().unwrap().insert(project_path.clone(), watcher);
+    
+    std::thread::spawn(move || {
+        let read_current = || {
+            let path = find_claude_md(&real_path);
+            let content = path.as_ref().and_then(|path| std::fs::read_to_string(path).ok());
+            (path, content)
+        };
+        CLAUDE_MD_LAST_CONTENT.lock().unwrap().insert(real_path.clone(), read_current().1);
+        
+        let debounce = std::time::Duration::from_millis(CLAUDE_MD_WATCH_DEBOUNCE_MS);
+        // Ends once unwatch_claude_md drops the watcher and closes the channel
+        while let Ok(first) = rx.recv() {
+            let mut events = vec![first];
+            let deadline = std::time::Instant::now() + debounce;
+            while let Ok(event) = rx.recv_timeout(deadline.saturating_duration_since(std::time::Instant::now())) {
+                events.push(event);
+            }
+            
+            let touched = events.into_iter().flatten()
+                .filter(|event| !matches!(event.kind, notify::EventKind::Access(_)))
+                .flat_map(|event| event.paths)
+                .any(|path| path.file_name().and_then(|name| name.to_str()).is_some_and(|name| CLAUDE_MD_NAMES.contains(&name)));
+            if !touched {
+                continue;
+            }
+            
+            // Saves that didn't change anything, and the GUI's own saves, aren't reported
+            let (path, content) = read_current();
+            {
+                let mut last_content = CLAUDE_MD_LAST_CONTENT.lock().unwrap();
+                let last = last_content.entry(real_path.clone()).or_default();
+                if *last == content {
+                    continue;
+                }
+                *last = content.clone();
+            }
+            let _ = app.emit("claude_md_changed", serde_json::json!({
+                "projectPath": project_path,
+                "path": path.map(|path| path.to_string_lossy().to_string()),
+                "content": content
+            }));
+        }
+    });
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn unwatch_claude_md(project_path: String) -> Result<(), AppError> {
+    CLAUDE_MD_WATCHERS.lock().unwrap().remove(&project_path);
+    Ok(())
+}
+
+#[tauri::command]
+async fn debug_project_path(project_path: String) -> Result<String, AppError> {
+    let mut debug_info = format!("Claude project path: {}\n", project_path);
+    
+    // First, show what's in the Claude project directory
+    let claude_path = std::path::Path::new(&project_path);
+    debug_info.push_str(&format!("Claude project directory exists: {}\n", claude_path.exists()));
+    
+    if claude_path.exists() {
+        debug_info.push_str("Files in Claude project directory:\n");
+        if let Ok(entries) = std::fs::read_dir(claude_path) {
+            for entry in entries.flatten() {
+                let file_name = entry.file_name();
+                let name = file_name.to_string_lossy();
+                let is_file = entry.path().is_file();
+                debug_info.push_str(&format!("  - {} ({})\n", name, if is_file { "file" } else { "dir" }));
+                
+                // If it's a small file, try to read its content
+                if is_file {
+                    let path = entry.path();
+                    if let Ok(metadata) = path.metadata() {
+                        if metadata.len() < 5120 { // Less than 5KB - check jsonl files too
+                            if let Ok(content) = std::fs::read_to_string(&path) {
+                                // For .jsonl files, try to find project path information
+                                if name.ends_with(".jsonl") {
+                                    // Read first few lines to look for project info
+                                    let lines: Vec<&str> = content.lines().take(5).collect();
+                                    for line in lines {
+                                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
+                                            // Look for common fields that might contain the project path
+                                            let search_fields = vec!["workingDirectory", "cwd", "projectPath", "path", "directory"];
+                                            for field in search_fields {
+                                                if let Some(value) = json.get(field) {
+                                                    debug_info.push_str(&format!("    Found {}: {}\n", field, value));
+                                                }
+                                            }
+                                            // Also check if there's any path-like string in the JSON
+                                            if let Some(obj) = json.as_object() {
+                                                for (key, value) in obj {
+                                                    if let Some(str_val) = value.as_str() {
+                                                        // Log any absolute paths found in the JSON for debugging
+                                                        if std::path::Path::new(str_val).is_absolute() && std::path::Path::new(str_val).exists() {
+                                                            debug_info.push_str(&format!("    Found path in {}: {}\n", key, str_val));
+                                                        }
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                } else {
+                                    let preview = if content.len() > 200 { 
+                                        format!("{}...", &content[..200])
+                                    } else { 
+                                        content 
+                                    };
+                                    debug_info.push_str(&format!("    Content: {}\n", preview.replace('\n', "\\n")));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    // Try to get the real project path
+    match get_real_project_path(project_path.clone()).await {
+        Ok(Some(real_path)) => {
+            debug_info.push_str(&format!("Real project path: {}\n", real_path));
+            
+            let path = std::path::Path::new(&real_path);
+            debug_info.push_str(&format!("Real path exists: {}\n", path.exists()));
+            debug_info.push_str(&format!("Real path is directory: {}\n", path.is_dir()));
+            
+            if path.exists() && path.is_dir() {
+                debug_info.push_str("Real directory contents:\n");
+                if let Ok(entries) = std::fs::read_dir(path) {
+                    for entry in entries.flatten() {
+                        let file_name = entry.file_name();
+                        let name = file_name.to_string_lossy();
+                        debug_info.push_str(&format!("  - {}\n", name));
+                    }
+                }
+            }
+            
+            // Check specifically for CLAUDE.md variants in real path
+            let possible_paths = vec![
+                path.join("CLAUDE.md"),
+                path.join("claude.md"),
+                path.join("Claude.md"),
+            ];
+            
+            debug_info.push_str("\nCLAUDE.md file checks in real path:\n");
+            for claude_path in possible_paths {
+                debug_info.push_str(&format!("  {} exists: {}\n", claude_path.display(), claude_path.exists()));
+            }
+        }
+        Ok(None) => {
+            debug_info.push_str("Could not find real project path (no .claude-project file)\n");
+        }
+        Err(e) => {
+            debug_info.push_str(&format!("Error getting real project path: {}\n", e));
+        }
+    }
+    
+    Ok(debug_info)
+}
+
+#[tauri::command]
+async fn create_claude_md_template(project_path: String) -> Result<(), AppError> {
+    // First get the real project path
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    
+    let claude_md_path = std::path::Path::new(&real_path).join("CLAUDE.md");
+    
+    if claude_md_path.exists() {
+        return Err(AppError::AlreadyExists("CLAUDE.md already exists".to_string()));
+    }
+    
+    let template = r#"# Project Instructions for Claude
+
+## Project Overview
+Brief description of what this project does and its main purpose.
+
+## Development Guidelines
+- Coding standards and conventions to follow
+- Preferred libraries and frameworks
+- Architecture patterns to maintain
+
+## Key Files and Directories
+- `src/` - Main source code
+- `tests/` - Test files
+- `docs/` - Documentation
+
+## Important Notes
+- Any specific requirements or constraints
+- Known issues or gotchas
+- Deployment considerations
+
+## Testing
+- How to run tests
+- Test coverage expectations
+- Any special testing requirements
+
+## Build & Deployment
+- Build commands
+- Environment setup
+- Deployment process
+"#;
+    
+    std::fs::write(&claude_md_path, template)
+        .map_err(|e| AppError::io("Failed to create CLAUDE.md template", e))?;
+    
+    Ok(())
+}
+
+#[tauri::command]
+async fn open_file_in_system(file_path: String) -> Result<(), AppError> {
+    #[cfg(target_os = "macos")]
+    {
+        Command::new("open")
+            .arg(&file_path)
+            .spawn()
+            .map_err(|e| AppError::spawn("open", e))?;
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        Command::new("cmd")
+            .args(["/C", "start", "", &file_path])
+            .spawn()
+            .map_err(|e| AppError::spawn("cmd", e))?;
+    }
+    
+    #[cfg(target_os = "linux")]
+    {
+        Command::new("xdg-open")
+            .arg(&file_path)
+            .spawn()
+            .map_err(|e| AppError::spawn("xdg-open", e))?;
+    }
+    
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+struct LanguageStats {
+    language: String,
+    files: usize,
+    lines: usize,
+    bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct ProjectStats {
+    total_files: usize,
+    total_lines: usize,
+    total_bytes: u64,
+    // Rough size of the text files in tokens (about 4 bytes per token)
+    estimated_tokens: u64,
+    // Sorted by line count, largest first
+    languages: Vec<LanguageStats>,
+}
+
+// Files larger than this are counted but not read for line counts
+const STATS_MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+fn language_for_path(path: &std::path::Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    match name {
+        "Dockerfile" => return Some("Dockerfile"),
+        "Makefile" => return Some("Makefile"),
+        _ => {}
+    }
+    let language = match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "rs" => "Rust",
+        "ts" | "mts" | "cts" => "TypeScript",
+        "tsx" => "TSX",
+        "js" | "mjs" | "cjs" => "JavaScript",
+        "jsx" => "JSX",
+        "py" | "pyi" => "Python",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "swift" => "Swift",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "C++",
+        "cs" => "C#",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "scala" => "Scala",
+        "dart" => "Dart",
+        "lua" => "Lua",
+        "sh" | "bash" | "zsh" => "Shell",
+        "ps1" => "PowerShell",
+        "sql" => "SQL",
+        "html" | "htm" => "HTML",
+        "css" => "CSS",
+        "scss" | "sass" => "SCSS",
+        "vue" => "Vue",
+        "svelte" => "Svelte",
+        "json" => "JSON",
+        "yaml" | "yml" => "YAML",
+        "toml" => "TOML",
+        "xml" => "XML",
+        "md" | "mdx" => "Markdown",
+        "txt" => "Text",
+        _ => return None,
+    };
+    Some(language)
+}
+
+// Language id as used by the code editor, for the languages language_for_path knows
+fn language_id_for_path(path: &std::path::Path) -> Option<String> {
+    let id = match language_for_path(path)? {
+        "TypeScript" | "TSX" => "typescript",
+        "JavaScript" | "JSX" => "javascript",
+        "C++" => "cpp",
+        "C#" => "csharp",
+        "Text" => "plaintext",
+        language => return Some(language.to_lowercase()),
+    };
+    Some(id.to_string())
+}
+
+// Lines in a text file, counting a last line without a trailing newline
+fn count_lines(content: &[u8]) -> usize {
+    content.iter().filter(|b| **b == b'\n').count()
+        + usize::from(content.last().is_some_and(|b| *b != b'\n'))
+}
+
+fn collect_project_stats(root: &str) -> ProjectStats {
+    let mut languages: HashMap<&'static str, LanguageStats> = HashMap::new();
+    let mut stats = ProjectStats {
+        total_files: 0,
+        total_lines: 0,
+        total_bytes: 0,
+        estimated_tokens: 0,
+        languages: Vec::new(),
+    };
+    
+    // Respects .gitignore, .ignore and global git excludes; hidden files are skipped
+    for entry in ignore::WalkBuilder::new(root).build().flatten() {
+        if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+            continue;
+        }
+        let path = entry.path();
+        let bytes = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        stats.total_files += 1;
+        stats.total_bytes += bytes;
+        
+        let language = match language_for_path(path) {
+            Some(language) => language,
+            None => continue,
+        };
+        let lines = if bytes <= STATS_MAX_FILE_BYTES {
+            match std::fs::read(path) {
+                Ok(content) if !content.contains(&0) => {
+                    stats.estimated_tokens += bytes / 4;
+                    count_lines(&content)
+                }
+                _ => 0,
+            }
+        } else {
+            0
+        };
+        stats.total_lines += lines;
+        
+        let entry = languages.entry(language).or_insert_with(|| LanguageStats {
+            language: language.to_string(),
+            ..Default::default()
+        });
+        entry.files += 1;
+        entry.lines += lines;
+        entry.bytes += bytes;
+    }
+    
+    stats.languages = languages.into_values().collect();
+    stats.languages.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.language.cmp(&b.language)));
+    stats
+}
+
+#[tauri::command]
+async fn get_project_stats(project_path: String) -> Result<ProjectStats, AppError> {
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    if !std::path::Path::new(&real_path).is_dir() {
+        return Err(AppError::PathNotFound(format!("Directory not found: {}", real_path)));
+    }
+    
+    tokio::task::spawn_blocking(move || collect_project_stats(&real_path)).await
+        .map_err(|e| AppError::Other(format!("Project scan failed: {}", e)))
+}
+
+// .env files in the project root: values are masked unless explicitly revealed
+const ENV_VALUE_MASK: &str = "********";
+
+#[derive(Debug, Serialize)]
+struct EnvFile {
+    name: String,
+    path: String,
+    variable_count: usize,
+    modified: String,
+}
+
+#[derive(Debug, Serialize)]
+struct EnvVariable {
+    key: String,
+    value: String,
+    masked: bool,
+    line: usize,
+}
+
+// Only ".env" and ".env.<suffix>" directly in the project root
+fn is_env_file_name(name: &str) -> bool {
+    (name == ".env" || name.starts_with(".env."))
+        && !name.contains('/')
+        && !name.contains('\\')
+        && !name.contains("..")
+}
+
+async fn resolve_env_file(project_path: String, file_name: &str) -> Result<std::path::PathBuf, AppError> {
+    if !is_env_file_name(file_name) {
+        return Err(AppError::InvalidInput(format!("Not an env file: {}", file_name)));
+    }
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    Ok(std::path::Path::new(&real_path).join(file_name))
+}
+
+// Index in `s` of the quote closing a value opened with `quote`; backslash escapes only
+// count inside double quotes
+fn env_closing_quote(s: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in s.char_indices() {
+        match c {
+            '\\' if quote == '"' && !escaped => escaped = true,
+            c if c == quote && !escaped => return Some(index),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+// The KEY and raw value of a "KEY=value" assignment, optionally prefixed with "export"
+fn split_env_assignment(text: &str) -> Option<(&str, &str)> {
+    let trimmed = text.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return None;
+    }
+    let trimmed = trimmed.strip_prefix("export ").unwrap_or(trimmed);
+    let (key, value) = trimmed.split_once('=')?;
+    let key = key.trim();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((key, value))
+}
+
+// Undo format_env_line's escaping in one pass, so "\\n" stays a backslash and an 'n'
+fn unescape_env_value(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some(escaped @ ('\\' | '"')) => unescaped.push(escaped),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// Parse an assignment, which may span several lines when its value is quoted, unquoting
+// the value
+fn parse_env_assignment(text: &str) -> Option<(String, String)> {
+    let (key, value) = split_env_assignment(text)?;
+    let value = value.trim();
+    let value = match value.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let rest = &value[1..];
+            // Anything after the closing quote (e.g. a comment) is dropped
+            let inner = match env_closing_quote(rest, quote) {
+                Some(end) => &rest[..end],
+                None => rest,
+            };
+            if quote == '"' {
+                unescape_env_value(inner)
+            } else {
+                inner.to_string()
+            }
+        }
+        // Drop trailing comments from unquoted values
+        _ => value.split(" #").next().unwrap_or("").trim_end().to_string(),
+    };
+    Some((key.to_string(), value))
+}
+
+// One entry of a .env file: an assignment, possibly spanning several lines, or any other line
+struct EnvEntry {
+    // 1-based line the entry starts on
+    line: usize,
+    // The entry's lines as they appear in the file
+    lines: Vec<String>,
+    variable: Option<(String, String)>,
+}
+
+fn parse_env_entries(content: &str) -> Vec<EnvEntry> {
+    let mut entries = Vec::new();
+    // lines() drops the \r of CRLF endings too
+    let mut lines = content.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let mut entry_lines = vec![line.to_string()];
+        
+        // A quoted value not closed on its first line continues until the closing quote
+        let open_quote = split_env_assignment(line).and_then(|(_, value)| {
+            let value = value.trim_start();
+            let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+            env_closing_quote(&value[1..], quote).is_none().then_some(quote)
+        });
+        if let Some(quote) = open_quote {
+            for (_, next) in lines.by_ref() {
+                entry_lines.push(next.to_string());
+                if env_closing_quote(next, quote).is_some() {
+                    break;
+                }
+            }
+        }
+        
+        let variable = parse_env_assignment(&entry_lines.join("\n"));
+        entries.push(EnvEntry { line: index + 1, lines: entry_lines, variable });
+    }
+    entries
+}
+
+fn format_env_line(key: &str, value: &str) -> String {
+    let needs_quotes = value.is_empty()
+        || value.chars().any(|c| c.is_whitespace() || matches!(c, '#' | '"' | '\'' | '\\' | '='));
+    if needs_quotes {
+        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+        format!("{}=\"{}\"", key, escaped)
+    } else {
+        format!("{}={}", key, value)
+    }
+}
+
+#[tauri::command]
+async fn list_env_files(project_path: String) -> Result<Vec<EnvFile>, AppError> {
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    
+    let entries = std::fs::read_dir(&real_path)
+        .map_err(|e| AppError::io("Failed to read project directory", e))?;
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !is_env_file_name(&name) || !entry.path().is_file() {
+            continue;
+        }
+        let variable_count = std::fs::read_to_string(entry.path())
+            .map(|content| parse_env_entries(&content).iter().filter(|entry| entry.variable.is_some()).count())
+            .unwrap_or(0);
+        let modified = entry.metadata()
+            .and_then(|m| m.modified())
+            .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+            .unwrap_or_default();
+        files.push(EnvFile {
+            name,
+            path: entry.path().to_string_lossy().to_string(),
+            variable_count,
+            modified,
+        });
+    }
+    
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+#[tauri::command]
+async fn read_env_file(project_path: String, file_name: String) -> Result<Vec<EnvVariable>, AppError> {
+    let path = resolve_env_file(project_path, &file_name).await?;
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io(&format!("Failed to read {}", file_name), e))?;
+    
+    Ok(parse_env_entries(&content).into_iter()
+        .filter_map(|entry| entry.variable.map(|(key, value)| EnvVariable {
+            key,
+            masked: !value.is_empty(),
+            value: if value.is_empty() { value } else { ENV_VALUE_MASK.to_string() },
+            line: entry.line,
+        }))
+        .collect())
+}
+
+#[tauri::command]
+async fn reveal_env_value(project_path: String, file_name: String, key: String) -> Result<String, AppError> {
+    let path = resolve_env_file(project_path, &file_name).await?;
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io(&format!("Failed to read {}", file_name), e))?;
+    
+    // Later assignments win, as they do when the file is loaded
+    parse_env_entries(&content).into_iter()
+        .rev()
+        .filter_map(|entry| entry.variable)
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| value)
+        .ok_or_else(|| AppError::NotFound(format!("{} is not set in {}", key, file_name)))
+}
+
+// Set (Some) or remove (None) variables, keeping comments, ordering and untouched lines;
+// creates the file if it doesn't exist yet
+#[tauri::command]
+async fn update_env_file(
+    project_path: String,
+    file_name: String,
+    updates: HashMap<String, Option<String>>
+) -> Result<(), AppError> {
+    if let Some(key) = updates.keys().find(|key| key.is_empty() || key.contains('=') || key.contains(char::is_whitespace)) {
+        return Err(AppError::InvalidInput(format!("Invalid environment variable name: {:?}", key)));
+    }
+    
+    let path = resolve_env_file(project_path, &file_name).await?;
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(AppError::io(&format!("Failed to read {}", file_name), e)),
+    };
+    
+    let mut written = HashSet::new();
+    let mut lines = Vec::new();
+    for entry in parse_env_entries(&content) {
+        let key = match entry.variable {
+            Some((key, _)) => key,
+            None => {
+                lines.extend(entry.lines);
+                continue;
+            }
+        };
+        match updates.get(&key) {
+            None => lines.extend(entry.lines),
+            Some(None) => {}
+            Some(Some(value)) => {
+                // Collapse duplicate assignments into the first one
+                if written.insert(key.clone()) {
+                    lines.push(format_env_line(&key, value));
+                }
+            }
+        }
+    }
+    
+    let mut new_keys: Vec<_> = updates.iter()
+        .filter_map(|(key, value)| value.as_ref().map(|value| (key, value)))
+        .filter(|(key, _)| !written.contains(*key))
+        .collect();
+    new_keys.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, value) in new_keys {
+        lines.push(format_env_line(key, value));
+    }
+    
+    // Keep the file's line endings
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut output = lines.join(newline);
+    output.push_str(newline);
+    std::fs::write(&path, output)
+        .map_err(|e| AppError::io(&format!("Failed to write {}", file_name), e))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProjectSetupOptions {
+    path: String,
+    project_name: String,
+    init_git: bool,
+    create_claude: bool,
+    project_type: String,
+    open_in_ide: bool,
+    selected_ide: Option<String>,
+    #[serde(default)]
+    package_manager: Option<String>,
+    #[serde(default)]
+    create_devcontainer: bool,
+    // "node", "python", "rust", "go", "generic" or "custom" (uses gitignore_content);
+    // defaults to one matching the project type
+    #[serde(default)]
+    gitignore_template: Option<String>,
+    #[serde(default)]
+    gitignore_content: Option<String>,
+}
+
+const PACKAGE_MANAGERS: &[&str] = &["npm", "pnpm", "yarn", "bun"];
+const JS_PROJECT_TYPES: &[&str] = &["react", "vue", "svelte", "nextjs", "tauri", "node"];
+
+// Validate the requested package manager, or pick the first one installed
+fn resolve_package_manager(requested: Option<&str>) -> Result<String, AppError> {
+    match requested.map(str::trim).filter(|pm| !pm.is_empty()) {
+        Some(pm) => {
+            if !PACKAGE_MANAGERS.contains(&pm) {
+                return Err(AppError::InvalidInput(format!("Unsupported package manager: {}", pm)));
+            }
+            if !command_exists(pm) {
+                return Err(AppError::NotFound(format!("{} is not installed", pm)));
+            }
+            Ok(pm.to_string())
+        }
+        None => PACKAGE_MANAGERS.iter()
+            .find(|pm| command_exists(pm))
+            .map(|pm| pm.to_string())
+            .ok_or_else(|| AppError::NotFound("No JavaScript package manager found (npm, pnpm, yarn or bun)".to_string())),
+    }
+}
+
+// A Go module path from a project name: characters go doesn't allow become '-', e.g.
+// "My App!" -> "my-app"
+fn go_module_name(project_name: &str) -> String {
+    let mut name = String::new();
+    for c in project_name.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '~') {
+            name.push(c);
+        } else if !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    let name = name.trim_matches(|c| c == '-' || c == '.');
+    if name.is_empty() { "app".to_string() } else { name.to_string() }
+}
+
+// `<pm> create <package>` with the flags routed past npm's own option parsing
+fn package_create_command(package_manager: &str, package: &str, positional: &[&str], flags: &[&str]) -> (String, Vec<String>) {
+    let mut args = vec!["create".to_string()];
+    args.push(match package_manager {
+        "yarn" => package.to_string(),
+        _ => format!("{}@latest", package),
+    });
+    args.extend(positional.iter().map(|a| a.to_string()));
+    if package_manager == "npm" {
+        args.push("--".to_string());
+    }
+    args.extend(flags.iter().map(|a| a.to_string()));
+    (package_manager.to_string(), args)
+}
+
+// Run a package binary without installing it, like npx
+fn package_exec_command(package_manager: &str, package: &str) -> (String, Vec<String>) {
+    match package_manager {
+        "pnpm" | "yarn" => (package_manager.to_string(), vec!["dlx".to_string(), package.to_string()]),
+        "bun" => ("bunx".to_string(), vec![package.to_string()]),
+        _ => ("npx".to_string(), vec!["--yes".to_string(), package.to_string()]),
+    }
+}
+
+const GITIGNORE_COMMON: &str = ".DS_Store\nThumbs.db\n.idea/\n.vscode/*\n!.vscode/extensions.json\n*.log\n.env\n.env.*\n!.env.example\n";
+
+fn gitignore_template(name: &str) -> Option<String> {
+    let specific = match name {
+        "node" => "node_modules/\ndist/\nbuild/\n.next/\n.svelte-kit/\ncoverage/\nnpm-debug.log*\nyarn-error.log*\npnpm-debug.log*\n*.tsbuildinfo\n",
+        "python" => "__pycache__/\n*.py[cod]\n.venv/\nvenv/\n*.egg-info/\ndist/\nbuild/\n.pytest_cache/\n.mypy_cache/\n.ruff_cache/\n.coverage\n",
+        "rust" => "target/\n**/*.rs.bk\n",
+        "go" => "bin/\n*.exe\n*.test\n*.out\nvendor/\n",
+        "generic" => "",
+        _ => return None,
+    };
+    Some(format!("{}{}", specific, GITIGNORE_COMMON))
+}
+
+fn default_gitignore_template(project_type: &str) -> &'static str {
+    match project_type {
+        "python" | "fastapi" => "python",
+        "rust" => "rust",
+        "go" => "go",
+        _ if JS_PROJECT_TYPES.contains(&project_type) => "node",
+        _ => "generic",
+    }
+}
+
+// Write .gitignore, or add any missing rules to one the scaffold already created
+fn write_gitignore(project_path: &str, content: &str) -> Result<(), AppError> {
+    let path = std::path::Path::new(project_path).join(".gitignore");
+    let existing = std::fs::read_to_string(&path).unwrap_or_default();
+    let existing_rules: HashSet<&str> = existing.lines().map(str::trim).collect();
+    let missing: Vec<&str> = content.lines()
+        .filter(|line| !line.trim().is_empty() && !existing_rules.contains(line.trim()))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    
+    let mut output = existing.clone();
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+    if !existing.is_empty() {
+        output.push('\n');
+    }
+    for line in missing {
+        output.push_str(line);
+        output.push('\n');
+    }
+    std::fs::write(&path, output)
+        .map_err(|e| AppError::io("Failed to write .gitignore", e))
+}
+
+// .devcontainer/devcontainer.json for the project type, with Claude available in the container
+fn devcontainer_config(project_name: &str, project_type: &str, package_manager: &str) -> serde_json::Value {
+    let node_project = JS_PROJECT_TYPES.contains(&project_type);
+    let image = match project_type {
+        _ if node_project => "mcr.microsoft.com/devcontainers/typescript-node:1-22-bookworm",
+        "python" | "fastapi" => "mcr.microsoft.com/devcontainers/python:1-3.12-bookworm",
+        "rust" => "mcr.microsoft.com/devcontainers/rust:1-bookworm",
+        "go" => "mcr.microsoft.com/devcontainers/go:1-bookworm",
+        _ => "mcr.microsoft.com/devcontainers/base:bookworm",
+    };
+    
+    let mut features = serde_json::Map::new();
+    // The Claude feature needs Node, which only the Node image ships with
+    if !node_project {
+        features.insert("ghcr.io/devcontainers/features/node:1".to_string(), serde_json::json!({}));
+    }
+    if project_type == "tauri" {
+        features.insert("ghcr.io/devcontainers/features/rust:1".to_string(), serde_json::json!({}));
+    }
+    features.insert("ghcr.io/anthropics/devcontainer-features/claude-code:1".to_string(), serde_json::json!({}));
+    
+    let mut config = serde_json::json!({
+        "name": project_name,
+        "image": image,
+        "features": features,
+    });
+    
+    let post_create = match project_type {
+        _ if node_project => Some(format!("{} install", package_manager)),
+        "python" | "fastapi" => Some("pip install -r requirements.txt".to_string()),
+        "go" => Some("go mod download".to_string()),
+        _ => None,
+    };
+    if let Some(command) = post_create {
+        config["postCreateCommand"] = serde_json::json!(command);
+    }
+    
+    let ports: &[u16] = match project_type {
+        "react" | "vue" | "svelte" | "tauri" => &[5173],
+        "nextjs" => &[3000],
+        "fastapi" => &[8000],
+        _ => &[],
+    };
+    if !ports.is_empty() {
+        config["forwardPorts"] = serde_json::json!(ports);
+    }
+    config
+}
+
+fn emit_setup_progress(app: &tauri::AppHandle, project_path: &str, step: &str, line: Option<&str>) {
+    let _ = app.emit("project_setup_progress", serde_json::json!({
+        "path": project_path,
+        "step": step,
+        "line": line,
+    }));
+}
+
+// Lines of a tool's output, split on the bare \r that progress bars redraw with as well
+// as on \n
+struct ProgressLines<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl<R: tokio::io::AsyncRead + Unpin> ProgressLines<R> {
+    fn new(reader: R) -> Self {
+        ProgressLines { reader, buffer: Vec::new(), done: false }
+    }
+    
+    async fn next_line(&mut self) -> Option<String> {
+        use tokio::io::AsyncReadExt;
+        
+        loop {
+            if let Some(end) = self.buffer.iter().position(|b| *b == b'\n' || *b == b'\r') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                return Some(String::from_utf8_lossy(&line[..end]).to_string());
+            }
+            if self.done {
+                if self.buffer.is_empty() {
+                    return None;
+                }
+                return Some(String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).to_string());
+            }
+            let mut chunk = [0u8; 4096];
+            match self.reader.read(&mut chunk).await {
+                Ok(0) | Err(_) => self.done = true,
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+}
+
+// Run a scaffolding command, streaming its stdout and stderr as setup progress
+async fn run_setup_command(app: &tauri::AppHandle, step: &str, program: &str, args: &[String], project_path: &str) -> Result<(), AppError> {
+    emit_setup_progress(app, project_path, step, Some(&format!("$ {} {}", program, args.join(" "))));
+    
+    let mut child = AsyncCommand::new(program)
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(args)
+        .current_dir(project_path)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::spawn(program, e))?;
+    
+    let stdout = child.stdout.take().ok_or_else(|| AppError::ProcessFailed(format!("Failed to capture {} stdout", program)))?;
+    let stderr = child.stderr.take().ok_or_else(|| AppError::ProcessFailed(format!("Failed to capture {} stderr", program)))?;
+    
+    let stderr_app = app.clone();
+    let stderr_step = step.to_string();
+    let stderr_path = project_path.to_string();
+    let stderr_task = tokio::spawn(async move {
+        let mut buffer = String::new();
+        let mut stderr_lines = ProgressLines::new(stderr);
+        while let Some(line) = stderr_lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            emit_setup_progress(&stderr_app, &stderr_path, &stderr_step, Some(&line));
+            buffer.push_str(&line);
+            buffer.push('\n');
+        }
+        buffer
+    });
+    
+    let mut lines = ProgressLines::new(stdout);
+    while let Some(line) = lines.next_line().await {
+        if !line.trim().is_empty() {
+            emit_setup_progress(app, project_path, step, Some(&line));
+        }
+    }
+    
+    let status = child.wait().await
+        .map_err(|e| AppError::io(&format!("Failed to wait for {}", program), e))?;
+    let stderr_output = stderr_task.await.unwrap_or_default();
+    
+    if !status.success() {
+        return Err(AppError::ProcessFailed(stderr_output));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn select_directory(app: tauri::AppHandle) -> Result<Option<String>, AppError> {
+    use tauri_plugin_dialog::DialogExt;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::oneshot;
+    
+    let (tx, rx) = oneshot::channel();
+    let tx = Arc::new(Mutex::new(Some(tx)));
+    
+    app.dialog()
+        .file()
+        .set_directory(dirs::home_dir().unwrap_or_default())
+        .pick_folder(move |result| {
+            if let Some(tx) = tx.lock().unwrap().take() {
+                let _ = tx.send(result);
+            }
+        });
+    
+    match rx.await {
+        Ok(Some(path)) => Ok(Some(path.to_string())),
+        Ok(None) => Ok(None),
+        Err(_) => Err(AppError::Other("Dialog was cancelled or failed".to_string()))
+    }
+}
+
+fn write_claude_md_template(project_path: &str, project_name: &str, project_type: &str) -> Result<(), AppError> {
+    let claude_md_path = std::path::Path::new(project_path).join("CLAUDE.md");
+    let template = format!(r#"# {} - Claude Instructions
+
+## Project Overview
+Brief description of what this project does and its main purpose.
+
+## Development Guidelines
+- Coding standards and conventions to follow
+- Preferred libraries and frameworks
+- Architecture patterns to maintain
+
+## Key Files and Directories
+- `src/` - Main source code
+- `tests/` - Test files
+- `docs/` - Documentation
+
+## Project Type
+This is a {} project.
+
+## Important Notes
+- Any specific requirements or constraints
+- Known issues or gotchas
+- Deployment considerations
+
+## Testing
+- How to run tests
+- Test coverage expectations
+- Any special testing requirements
+
+## Build & Deployment
+- Build commands
+- Environment setup
+- Deployment process
+"#, project_name, project_type);
+    
+    std::fs::write(&claude_md_path, template)
+        .map_err(|e| AppError::io("Failed to create CLAUDE.md", e))
+}
+
+#[tauri::command]
+async fn create_enhanced_project(app: tauri::AppHandle, options: ProjectSetupOptions) -> Result<String, AppError> {
+    let project_path = &options.path;
+    
+    // Resolve the package manager up front so a missing tool fails before anything is created
+    let package_manager = if JS_PROJECT_TYPES.contains(&options.project_type.as_str()) {
+        resolve_package_manager(options.package_manager.as_deref())?
+    } else {
+        String::new()
+    };
+    
+    let gitignore = if options.init_git {
+        let template = options.gitignore_template.as_deref()
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or_else(|| default_gitignore_template(&options.project_type));
+        match template {
+            "custom" => options.gitignore_content.clone().filter(|c| !c.trim().is_empty()),
+            name => Some(gitignore_template(name)
+                .ok_or_else(|| AppError::InvalidInput(format!("Unknown gitignore template: {}", name)))?),
+        }
+    } else {
+        None
+    };
+    
+    // Create directory if it doesn't exist
+    if !std::path::Path::new(project_path).exists() {
+        std::fs::create_dir_all(project_path)
+            .map_err(|e| AppError::io("Failed to create directory", e))?;
+    }
+    
+    // Initialize Git repository if requested
+    if options.init_git {
+        emit_setup_progress(&app, project_path, "git_init", None);
+        let git_output = Command::new("git")
+            .envs(LOGIN_SHELL_ENV.iter())
+            .args(["init"])
+            .current_dir(project_path)
+            .output()
+            .map_err(|e| AppError::spawn("git", e))?;
+        
+        if !git_output.status.success() {
+            eprintln!("Warning: Failed to initialize git repository");
+        }
+    }
+    
+    // Create project based on type
+    emit_setup_progress(&app, project_path, "scaffold", None);
+    match options.project_type.as_str() {
+        "react" => {
+            // Create React app with Vite
+            let (program, args) = package_create_command(&package_manager, "vite", &["."], &["--template", "react-ts"]);
+            run_setup_command(&app, "scaffold", &program, &args, project_path).await?;
+        },
+        "vue" => {
+            // Create Vue app with create-vue
+            let (program, args) = package_create_command(&package_manager, "vue", &["."], &["--ts", "--force"]);
+            run_setup_command(&app, "scaffold", &program, &args, project_path).await?;
+        },
+        "svelte" => {
+            // Create SvelteKit app with the sv CLI
+            let (program, mut args) = package_exec_command(&package_manager, "sv");
+            args.extend(["create", ".", "--template", "minimal", "--types", "ts", "--no-add-ons", "--install", package_manager.as_str()].iter().map(|a| a.to_string()));
+            run_setup_command(&app, "scaffold", &program, &args, project_path).await?;
+        },
+        "nextjs" => {
+            // Create Next.js app
+            let use_flag = format!("--use-{}", package_manager);
+            let (program, args) = package_create_command(&package_manager, "next-app", &["."], &["--typescript", "--tailwind", "--eslint", "--yes", &use_flag]);
+            run_setup_command(&app, "scaffold", &program, &args, project_path).await?;
+        },
+        "tauri" => {
+            // Create Tauri app with create-tauri-app
+            let (program, args) = package_create_command(&package_manager, "tauri-app", &["."], &["--template", "react-ts", "--manager", &package_manager, "--yes", "--force"]);
+            run_setup_command(&app, "scaffold", &program, &args, project_path).await?;
+        },
+        "python" => {
+            // Create Python project structure
+            let dirs = ["src", "tests", "docs"];
+            for dir in &dirs {
+                let dir_path = std::path::Path::new(project_path).join(dir);
+                std::fs::create_dir_all(&dir_path)
+                    .map_err(|e| AppError::io(&format!("Failed to create directory {}", dir), e))?;
+            }
+            
+            // Create requirements.txt
+            let requirements_path = std::path::Path::new(project_path).join("requirements.txt");
+            std::fs::write(&requirements_path, "# Add your dependencies here\n")
+                .map_err(|e| AppError::io("Failed to create requirements.txt", e))?;
+        },
+        "fastapi" => {
+            // Create FastAPI project structure
+            let root = std::path::Path::new(project_path);
+            for dir in ["app", "tests"] {
+                std::fs::create_dir_all(root.join(dir))
+                    .map_err(|e| AppError::io(&format!("Failed to create directory {}", dir), e))?;
+            }
+            let files = [
+                ("requirements.txt", "fastapi\nuvicorn[standard]\n"),
+                ("app/__init__.py", ""),
+                ("app/main.py", "from fastapi import FastAPI\n\napp = FastAPI()\n\n\n@app.get(\"/\")\ndef read_root():\n    return {\"status\": \"ok\"}\n"),
+                ("tests/__init__.py", ""),
+            ];
+            for (name, contents) in files {
+                std::fs::write(root.join(name), contents)
+                    .map_err(|e| AppError::io(&format!("Failed to create {}", name), e))?;
+            }
+        },
+        "go" => {
+            // Initialize Go module
+            if !command_exists("go") {
+                return Err(AppError::NotFound("go is not installed".to_string()));
+            }
+            run_setup_command(&app, "scaffold", "go", &["mod".to_string(), "init".to_string(), go_module_name(&options.project_name)], project_path).await?;
+            let main_path = std::path::Path::new(project_path).join("main.go");
+            std::fs::write(&main_path, "package main\n\nimport \"fmt\"\n\nfunc main() {\n\tfmt.Println(\"Hello, world!\")\n}\n")
+                .map_err(|e| AppError::io("Failed to create main.go", e))?;
+        },
+        "node" => {
+            // Initialize package.json
+            let args = match package_manager.as_str() {
+                "pnpm" => vec!["init".to_string()],
+                _ => vec!["init".to_string(), "-y".to_string()],
+            };
+            run_setup_command(&app, "scaffold", &package_manager, &args, project_path).await?;
+        },
+        "rust" => {
+            // Create Rust project with Cargo
+            run_setup_command(&app, "scaffold", "cargo", &["init".to_string(), ".".to_string(), "--name".to_string(), options.project_name.clone()], project_path).await?;
+        },
+        _ => {
+            // Empty project or custom - just create basic structure
+        }
+    }
+    
+    if let Some(content) = &gitignore {
+        emit_setup_progress(&app, project_path, "gitignore", None);
+        write_gitignore(project_path, content)?;
+    }
+    
+    // Add a devcontainer if requested, keeping one the scaffold may have provided
+    let devcontainer_path = std::path::Path::new(project_path).join(".devcontainer").join("devcontainer.json");
+    if options.create_devcontainer && !devcontainer_path.exists() {
+        emit_setup_progress(&app, project_path, "devcontainer", None);
+        let config = devcontainer_config(&options.project_name, &options.project_type, &package_manager);
+        let content = serde_json::to_string_pretty(&config)
+            .map_err(|e| AppError::parse("Failed to serialize devcontainer.json", e))?;
+        std::fs::create_dir_all(std::path::Path::new(project_path).join(".devcontainer"))
+            .map_err(|e| AppError::io("Failed to create .devcontainer directory", e))?;
+        std::fs::write(&devcontainer_path, content)
+            .map_err(|e| AppError::io("Failed to create devcontainer.json", e))?;
+    }
+    
+    // Create CLAUDE.md template if requested
+    if options.create_claude {
+        emit_setup_progress(&app, project_path, "claude_md", None);
+        write_claude_md_template(project_path, &options.project_name, &options.project_type)?;
+    }
+    
+    emit_setup_progress(&app, project_path, "register", None);
+    register_project_dir(project_path)?;
+    
+    // Open in IDE if requested
+    if options.open_in_ide {
+        if let Some(ide_command) = options.selected_ide {
+            let _ide_output = Command::new(&ide_command)
+                .envs(LOGIN_SHELL_ENV.iter())
+                .arg(project_path)
+                .spawn();
+            // Don't fail if IDE opening fails
+        }
+    }
+    
+    emit_setup_progress(&app, project_path, "done", None);
+    Ok(format!("Project '{}' created successfully at {}", options.project_name, project_path))
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GitCloneOptions {
+    #[serde(default)]
+    branch: Option<String>,
+    #[serde(default)]
+    depth: Option<u32>,
+    #[serde(default)]
+    create_claude: bool,
+    #[serde(default)]
+    open_in_ide: bool,
+    #[serde(default)]
+    selected_ide: Option<String>,
+}
+
+// Pull the phase and percentage out of a git progress line such as
+// "Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s"
+fn parse_git_progress(line: &str) -> (Option<String>, Option<u32>) {
+    let line = line.trim_start_matches("remote:").trim();
+    let (phase, rest) = match line.split_once(':') {
+        Some((phase, rest)) => (phase.trim(), rest),
+        None => return (None, None),
+    };
+    let percent = rest.split_whitespace()
+        .next()
+        .and_then(|p| p.strip_suffix('%'))
+        .and_then(|p| p.parse::<u32>().ok());
+    if percent.is_none() {
+        return (None, None);
+    }
+    (Some(phase.to_string()), percent)
+}
+
+#[tauri::command]
+async fn create_project_from_git(
+    app: tauri::AppHandle,
+    url: String,
+    path: String,
+    options: Option<GitCloneOptions>,
+) -> Result<String, AppError> {
+    use tokio::io::AsyncReadExt;
+    
+    let options = options.unwrap_or_default();
+    if url.trim().is_empty() {
+        return Err(AppError::InvalidInput("Repository URL is required".to_string()));
+    }
+    if !command_exists("git") {
+        return Err(AppError::NotFound("git is not installed".to_string()));
+    }
+    
+    let target = std::path::Path::new(&path);
+    let not_empty = std::fs::read_dir(target)
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+    if not_empty {
+        return Err(AppError::AlreadyExists(format!("{} already exists and is not empty", path)));
+    }
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create directory", e))?;
+    }
+    
+    let mut args = vec!["clone".to_string(), "--progress".to_string()];
+    if let Some(branch) = options.branch.as_ref().filter(|b| !b.trim().is_empty()) {
+        args.push("--branch".to_string());
+        args.push(branch.clone());
+    }
+    if let Some(depth) = options.depth.filter(|d| *d > 0) {
+        args.push("--depth".to_string());
+        args.push(depth.to_string());
+    }
+    args.push("--".to_string());
+    args.push(url.clone());
+    args.push(path.clone());
+    
+    let mut child = AsyncCommand::new("git")
+        .envs(LOGIN_SHELL_ENV.iter())
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .args(&args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::spawn("git", e))?;
+    
+    let mut stderr = child.stderr.take()
+        .ok_or_else(|| AppError::ProcessFailed("Failed to capture git stderr".to_string()))?;
+    
+    // git redraws progress in place with \r, so split on both line endings
+    let mut output = String::new();
+    let mut pending = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let read = stderr.read(&mut chunk).await
+            .map_err(|e| AppError::io("Failed to read git output", e))?;
+        if read == 0 {
+            break;
+        }
+        pending.extend_from_slice(&chunk[..read]);
+        while let Some(pos) = pending.iter().position(|b| *b == b'\r' || *b == b'\n') {
+            let raw: Vec<u8> = pending.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&raw[..raw.len() - 1]).trim().to_string();
+            if line.is_empty() {
+                continue;
+            }
+            let (phase, percent) = parse_git_progress(&line);
+            let _ = app.emit("project_clone_progress", serde_json::json!({
+                "path": path,
+                "url": url,
+                "phase": phase,
+                "percent": percent,
+                "line": line,
+            }));
+            output.push_str(&line);
+            output.push('\n');
+        }
+    }
+    
+    let status = child.wait().await
+        .map_err(|e| AppError::io("Failed to wait for git clone", e))?;
+    if !status.success() {
+        let _ = app.emit("project_clone_finished", serde_json::json!({
+            "path": path,
+            "url": url,
+            "success": false,
+        }));
+        return Err(AppError::ProcessFailed(format!("git clone failed:\n{}", output.trim_end())));
+    }
+    
+    let project_name = target.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.clone());
+    
+    // Leave an existing CLAUDE.md from the repository alone
+    if options.create_claude && !target.join("CLAUDE.md").exists() {
+        write_claude_md_template(&path, &project_name, "cloned")?;
+    }
+    
+    register_project_dir(&path)?;
+    
+    let _ = app.emit("project_clone_finished", serde_json::json!({
+        "path": path,
+        "url": url,
+        "success": true,
+    }));
+    
+    if options.open_in_ide {
+        if let Some(ide_command) = options.selected_ide {
+            let _ide_output = Command::new(&ide_command)
+                .envs(LOGIN_SHELL_ENV.iter())
+                .arg(&path)
+                .spawn();
+        }
+    }
+    
+    Ok(format!("Project '{}' cloned successfully to {}", project_name, path))
+}
+
+// Create the directory if needed and register it, returning the Claude project directory
+#[tauri::command]
+async fn create_new_project(project_path: String) -> Result<String, AppError> {
+    std::fs::create_dir_all(&project_path)
+        .map_err(|e| AppError::io(&format!("Failed to create {}", project_path), e))?;
+    register_project_dir(&project_path)
+}
+
+fn parse_content_block(block: &serde_json::Value) -> Option<ContentBlock> {
+    let text = |field: &str| block.get(field).and_then(|v| v.as_str()).unwrap_or("").to_string();
+    let content_block = match block.get("type")?.as_str()? {
+        "text" => ContentBlock::Text { text: text("text") },
+        "thinking" => ContentBlock::Thinking { thinking: text("thinking") },
+        "tool_use" => ContentBlock::ToolUse {
+            id: text("id"),
+            name: text("name"),
+            input: block.get("input").cloned().unwrap_or(serde_json::Value::Null),
+        },
+        "tool_result" => {
+            // Output is either a string or a list of text and image parts
+            let (content, images) = match block.get("content") {
+                Some(serde_json::Value::String(content)) => (content.clone(), 0),
+                Some(serde_json::Value::Array(parts)) => {
+                    let texts: Vec<&str> = parts.iter()
+                        .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+                        .collect();
+                    let images = parts.iter()
+                        .filter(|part| part.get("type").and_then(|t| t.as_str()) == Some("image"))
+                        .count();
+                    (texts.join("\n"), images)
+                }
+                _ => (String::new(), 0),
+            };
+            ContentBlock::ToolResult {
+                tool_use_id: text("tool_use_id"),
+                content,
+                is_error: block.get("is_error").and_then(|v| v.as_bool()).unwrap_or(false),
+                images,
+            }
+        }
+        "image" => ContentBlock::Image {
+            media_type: block.get("source").and_then(|s| s.get("media_type")).and_then(|m| m.as_str()).map(|m| m.to_string()),
+        },
+        _ => return None,
+    };
+    Some(content_block)
+}
+
+// A transcript line as a ConversationEntry; None for lines that aren't messages
+// (summaries, snapshots and the like)
+fn parse_conversation_entry(json: &serde_json::Value) -> Option<ConversationEntry> {
+    let role = match json.get("type").and_then(|t| t.as_str())? {
+        role @ ("user" | "assistant" | "system") => role.to_string(),
+        _ => return None,
+    };
+    let field = |name: &str| json.get(name).and_then(|v| v.as_str()).map(|v| v.to_string());
+    let message = json.get("message");
+    
+    let blocks = match message.and_then(|m| m.get("content")).or_else(|| json.get("content")) {
+        Some(serde_json::Value::String(text)) => vec![ContentBlock::Text { text: text.clone() }],
+        Some(serde_json::Value::Array(blocks)) => blocks.iter().filter_map(parse_content_block).collect(),
+        _ => Vec::new(),
+    };
+    
+    Some(ConversationEntry {
+        uuid: field("uuid"),
+        parent_uuid: field("parentUuid"),
+        role,
+        timestamp: field("timestamp").unwrap_or_default(),
+        model: message.and_then(|m| m.get("model")).and_then(|m| m.as_str()).map(|m| m.to_string()),
+        blocks,
+        usage: message.and_then(|m| m.get("usage")).and_then(|usage| serde_json::from_value(usage.clone()).ok()),
+        is_sidechain: json.get("isSidechain").and_then(|v| v.as_bool()).unwrap_or(false),
+    })
+}
+
+fn read_conversation_entries_sync(file_path: &str) -> Result<Vec<ConversationEntry>, AppError> {
+    let content = std::fs::read_to_string(file_path)
+        .map_err(|e| AppError::io(&format!("Failed to read file {}", file_path), e))?;
+    
+    Ok(content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter_map(|json| parse_conversation_entry(&json))
+        .collect())
+}
+
+// Every message of a session transcript with typed content blocks and token usage
+#[tauri::command]
+async fn read_conversation_entries(file_path: String) -> Result<Vec<ConversationEntry>, AppError> {
+    read_conversation_entries_sync(&file_path)
+}
+
+// Plain-text view of a transcript: the text blocks of each message, skipping messages
+// that have none (such as tool results)
+#[tauri::command]
+async fn read_conversation_file(file_path: String) -> Result<Vec<ChatMessage>, AppError> {
+    let messages = read_conversation_entries_sync(&file_path)?
+        .into_iter()
+        .filter_map(|entry| {
+            let texts: Vec<String> = entry.blocks.into_iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text),
+                    _ => None,
                 })
-            } else {
-                // Skip non-JSON content to reduce noise
-                None
+                .collect();
+            let content = texts.join("\n");
+            if content.trim().is_empty() {
+                return None;
+            }
+            Some(ChatMessage { role: entry.role, content, timestamp: entry.timestamp })
+        })
+        .collect();
+    
+    Ok(messages)
+}
+
+// Poll every session's child process and tell the UI when one starts or stops running
+async fn monitor_terminal_sessions(app: tauri::AppHandle) {
+    let mut last_status: HashMap<String, (String, Option<u32>)> = HashMap::new();
+    let mut restarts: HashMap<String, u32> = HashMap::new();
+    let mut rounds: u64 = 0;
+    
+    loop {
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        rounds += 1;
+        
+        // Idle cleanup doesn't need to be as responsive as status changes
+        if rounds.is_multiple_of(15) {
+            cleanup_idle_terminal_sessions(&app).await;
+        }
+        
+        let mut current_status = HashMap::new();
+        let mut stopped_by_user = HashSet::new();
+        {
+            let sessions = TERMINAL_SESSIONS.read().await;
+            for (session_id, session) in sessions.iter() {
+                if session.stopped_by_user.load(std::sync::atomic::Ordering::Relaxed) {
+                    stopped_by_user.insert(session_id.clone());
+                }
+                // Skip sessions someone else is busy with; we'll catch them next round
+                let status = match session.child_process.try_lock() {
+                    Ok(mut child) => match child.try_wait() {
+                        Ok(Some(exit)) => ("exited".to_string(), Some(exit.exit_code())),
+                        Ok(None) => ("running".to_string(), None),
+                        Err(_) => ("exited".to_string(), None),
+                    },
+                    Err(_) => match last_status.get(session_id) {
+                        Some(status) => status.clone(),
+                        None => continue,
+                    },
+                };
+                current_status.insert(session_id.clone(), status);
+            }
+        }
+        
+        for (session_id, (status, exit_code)) in current_status.iter() {
+            if last_status.get(session_id).map(|(s, _)| s) != Some(status) {
+                if status == "exited" {
+                    println!("[HEALTH] Session {} exited with code {:?}", session_id, exit_code);
+                }
+                let _ = app.emit("terminal_session_status", serde_json::json!({
+                    "sessionId": session_id,
+                    "status": status,
+                    "exitCode": exit_code
+                }));
+                
+                // A non-zero exit is a crash, unless the user stopped the process themselves
+                if status == "exited" && exit_code.is_some_and(|code| code != 0) && !stopped_by_user.contains(session_id) {
+                    let attempts = restarts.entry(session_id.clone()).or_insert(0);
+                    handle_terminal_crash(&app, session_id, *exit_code, attempts).await;
+                }
+            }
+        }
+        
+        last_status = current_status;
+    }
+}
+
+const MAX_AUTO_RESTARTS: u32 = 3;
+
+// Report a crashed session and, if the project opted in, bring Claude back with --resume,
+// keeping the old scrollback in front of the new output
+async fn handle_terminal_crash(app: &tauri::AppHandle, session_id: &str, exit_code: Option<u32>, attempts: &mut u32) {
+    let (kind, old_scrollback) = {
+        let sessions = TERMINAL_SESSIONS.read().await;
+        match sessions.get(session_id) {
+            Some(session) => (session.kind.clone(), session.scrollback.clone()),
+            None => return,
+        }
+    };
+    
+    let record = read_persisted_terminal_sessions().into_iter().find(|record| record.id == session_id);
+    let auto_restart = match &record {
+        Some(record) if kind == "claude" => read_project_settings(record.project_path.clone()).await
+            .map(|settings| settings.auto_restart_claude)
+            .unwrap_or(false),
+        _ => false,
+    };
+    let restarting = auto_restart && *attempts < MAX_AUTO_RESTARTS;
+    
+    println!("[WARN] Session {} crashed with code {:?} (restarting: {})", session_id, exit_code, restarting);
+    let _ = app.emit("terminal_session_crashed", serde_json::json!({
+        "sessionId": session_id,
+        "exitCode": exit_code,
+        "restarting": restarting
+    }));
+    
+    let record = match record {
+        Some(record) if restarting => record,
+        _ => return,
+    };
+    *attempts += 1;
+    
+    let env = TERMINAL_SESSION_ENV.lock().unwrap_or_else(|e| e.into_inner()).get(session_id).cloned();
+    match resume_claude_session(app.clone(), record.resume_session_id.clone(), record.project_path.clone(), env).await {
+        Ok(new_session_id) => {
+            let mut sessions = TERMINAL_SESSIONS.write().await;
+            if let Some(session) = sessions.get_mut(&new_session_id) {
+                let old = old_scrollback.lock().await;
+                session.scrollback.lock().await.prepend(&old, "\r\n[Session restarted after a crash]\r\n");
+                session.title.clone_from(&record.title);
+            }
+            drop(sessions);
+            
+            let _ = app.emit("terminal_session_restarted", serde_json::json!({
+                "sessionId": new_session_id,
+                "attempt": *attempts
+            }));
+        }
+        Err(e) => println!("[ERROR] Failed to restart session {}: {}", session_id, e),
+    }
+}
+
+const SHUTDOWN_GRACE_PERIOD_MS: u64 = 3000;
+
+// Ask every PTY child to exit (SIGTERM), then kill whatever is still running after a grace period.
+// Persisted session records are kept so the sessions can be restored on next launch.
+async fn shutdown_terminal_sessions() {
+    let mut sessions = TERMINAL_SESSIONS.write().await;
+    if sessions.is_empty() {
+        return;
+    }
+    println!("[INFO] Shutting down {} terminal sessions", sessions.len());
+    
+    for (session_id, session) in sessions.iter() {
+        // Close recordings so the files are complete
+        session.recording.lock().await.take();
+        
+        #[cfg(unix)]
+        if let Some(pid) = session.pid {
+            unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM); }
+        }
+        #[cfg(not(unix))]
+        if let Err(e) = session.child_process.lock().await.kill() {
+            println!("[WARN] Failed to stop session {}: {}", session_id, e);
+        }
+        
+        println!("[DEBUG] Asked session {} to exit", session_id);
+    }
+    
+    let deadline = std::time::Instant::now() + std::time::Duration::from_millis(SHUTDOWN_GRACE_PERIOD_MS);
+    loop {
+        let mut still_running = false;
+        for session in sessions.values() {
+            if let Ok(None) = session.child_process.lock().await.try_wait() {
+                still_running = true;
+            }
+        }
+        if !still_running || std::time::Instant::now() >= deadline {
+            break;
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+    
+    for (session_id, session) in sessions.drain() {
+        let mut child = session.child_process.lock().await;
+        if let Ok(None) = child.try_wait() {
+            println!("[WARN] Session {} did not exit in time, killing it", session_id);
+            let _ = child.kill();
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+// Terminate sessions with no reads or writes for longer than the configured timeout
+async fn cleanup_idle_terminal_sessions(app: &tauri::AppHandle) {
+    let timeout_minutes = match read_app_settings().ok().and_then(|s| s.terminal_idle_timeout_minutes) {
+        Some(minutes) if minutes > 0 => minutes,
+        _ => return,
+    };
+    let cutoff = now_millis().saturating_sub(timeout_minutes * 60 * 1000);
+    
+    let mut sessions = TERMINAL_SESSIONS.write().await;
+    let idle: Vec<String> = sessions.iter()
+        .filter(|(_, session)| session.last_activity.load(std::sync::atomic::Ordering::Relaxed) < cutoff)
+        .map(|(id, _)| id.clone())
+        .collect();
+    
+    for session_id in idle {
+        // If the process is busy elsewhere, leave the session for the next sweep rather than
+        // dropping it while the process keeps running
+        let killed = match sessions.get(&session_id).map(|session| session.child_process.clone()) {
+            Some(child_process) => match child_process.try_lock() {
+                Ok(mut child) => {
+                    let _ = child.kill();
+                    true
+                }
+                Err(_) => false,
+            },
+            None => false,
+        };
+        if !killed {
+            println!("[DEBUG] Terminal session {} is busy, retrying idle cleanup later", session_id);
+            continue;
+        }
+        
+        if let Some(session) = sessions.remove(&session_id) {
+            println!("[INFO] Closing terminal session {} after {} idle minutes", session_id, timeout_minutes);
+            forget_terminal_session(&session_id);
+            
+            // Killing the client leaves a tmux-backed conversation running in the background
+            let tmux_name = tmux_session_name(&session_id);
+            if session.kind == "claude" && command_exists("tmux") && tmux_has_session(&tmux_name) {
+                tmux_kill_session(&tmux_name);
+            }
+            
+            let _ = app.emit("terminal_session_cleanup", serde_json::json!({
+                "sessionId": session_id,
+                "reason": "idle",
+                "idleMinutes": timeout_minutes
+            }));
+        }
+    }
+}
+
+async fn verify_claude_health(session_id: &str) -> bool {
+    if let Ok(sessions) = TERMINAL_SESSIONS.try_read() {
+        if let Some(session) = sessions.get(session_id) {
+            // Check if child process is still alive
+            if let Ok(mut child_guard) = session.child_process.try_lock() {
+                match child_guard.try_wait() {
+                    Ok(Some(_)) => {
+                        println!("[HEALTH] Session {} process has exited", session_id);
+                        return false;
+                    }
+                    Ok(None) => {
+                        println!("[HEALTH] Session {} process is still running", session_id);
+                        return true;
+                    }
+                    Err(e) => {
+                        println!("[HEALTH] Session {} process check failed: {}", session_id, e);
+                        return false;
+                    }
+                }
             }
         }
     }
+    false
+}
+
+// Open a PTY, run `cmd` in it and start streaming its output; shared by Claude and shell sessions
+async fn spawn_pty_session(
+    app: tauri::AppHandle,
+    session_id: String,
+    working_dir: String,
+    cmd: CommandBuilder,
+    kind: &str
+) -> Result<(), AppError> {
+    // Create PTY system
+    let pty_system = native_pty_system();
+    
+    // Create PTY with appropriate size
+    let pty_pair = pty_system
+        .openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|e| AppError::ProcessFailed(format!("Failed to create PTY: {}", e)))?;
+
+    // Start the child process
+    let child = pty_pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|e| AppError::ProcessFailed(format!("Failed to spawn process: {}", e)))?;
+
+    let pid = child.process_id();
+
+    // Get the writer ONCE and store it permanently
+    println!("[DEBUG] Getting PTY writer for session: {}", session_id);
+    let writer = pty_pair.master.take_writer()
+        .map_err(|e| {
+            let error_msg = format!("Failed to get PTY writer: {}", e);
+            println!("[ERROR] {}", error_msg);
+            AppError::ProcessFailed(error_msg)
+        })?;
+    println!("[DEBUG] Successfully got PTY writer");
+        
+    // Create session with separate writer storage
+    let session = TerminalSession {
+        id: session_id.clone(),
+        pty_master: Arc::new(Mutex::new(pty_pair.master)),
+        pty_writer: Arc::new(Mutex::new(writer)),
+        child_process: Arc::new(Mutex::new(child)),
+        project_path: working_dir,
+        active: true,
+        kind: kind.to_string(),
+        title: None,
+        pid,
+        started_at: std::time::SystemTime::now(),
+        scrollback: Arc::new(Mutex::new(ScrollbackBuffer::default())),
+        last_activity: Arc::new(std::sync::atomic::AtomicU64::new(now_millis())),
+        recording: Arc::new(Mutex::new(None)),
+        last_todo_hash: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+        bytes_read: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+        bytes_written: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+        stopped_by_user: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+    };
+
+    // Store session
+    {
+        let mut sessions = TERMINAL_SESSIONS.write().await;
+        println!("[DEBUG] Storing session with ID: {}", session_id);
+        sessions.insert(session_id.clone(), session);
+        println!("[DEBUG] Session stored. Total sessions: {}", sessions.len());
+    }
+
+    // Start reading from PTY and sending output to frontend (only if not already running)
+    {
+        let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
+        if !handlers.contains(&session_id) {
+            // Reserve the handler slot immediately to prevent race conditions
+            handlers.insert(session_id.clone());
+            let session_id_clone = session_id.clone();
+            let session_id_for_cleanup = session_id.clone();
+            let app_clone = app.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_pty_output_no_check(app_clone, session_id_clone).await {
+                    eprintln!("PTY output handler error: {}", e);
+                    // Remove from handlers on error
+                    let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
+                    handlers.remove(&session_id_for_cleanup);
+                }
+            });
+            println!("[DEBUG] Spawned new PTY handler for session: {}", session_id);
+        } else {
+            println!("[DEBUG] PTY handler already exists for session: {}", session_id);
+        }
+    }
+
+    Ok(())
+}
+
+// Project env first, then the per-session overrides on top
+async fn session_env(project_path: &str, env: Option<&HashMap<String, String>>) -> Vec<(String, String)> {
+    let project_settings = read_project_settings(project_path.to_string()).await.unwrap_or_default();
+    project_settings.env.into_iter()
+        .chain(env.into_iter().flatten().map(|(key, value)| (key.clone(), value.clone())))
+        .collect()
+}
+
+async fn apply_session_env(cmd: &mut CommandBuilder, project_path: &str, env: Option<&HashMap<String, String>>) {
+    for (key, value) in session_env(project_path, env).await {
+        cmd.env(key, value);
+    }
+}
+
+fn tmux_session_name(session_id: &str) -> String {
+    format!("claude-gui-{}", session_id)
+}
+
+fn tmux_enabled() -> bool {
+    !cfg!(target_os = "windows")
+        && read_app_settings().map(|s| s.tmux_sessions).unwrap_or(false)
+        && command_exists("tmux")
+}
+
+fn tmux_has_session(name: &str) -> bool {
+    Command::new("tmux")
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(["has-session", "-t", name])
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn tmux_kill_session(name: &str) {
+    let _ = Command::new("tmux")
+        .envs(LOGIN_SHELL_ENV.iter())
+        .args(["kill-session", "-t", name])
+        .output();
+}
+
+// Shell-quote with single quotes, which keep everything literal except ' itself
+fn shell_single_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn is_shell_identifier(key: &str) -> bool {
+    key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// `export KEY='value'` lines for a tmux session's shell to source, in a file only we can read
+fn write_tmux_env_file(env: &[(String, String)]) -> Result<std::path::PathBuf, AppError> {
+    let mut script = String::new();
+    for (key, value) in env {
+        if !is_shell_identifier(key) {
+            println!("[WARN] Skipping environment variable {:?}: not a valid shell name", key);
+            continue;
+        }
+        script.push_str(&format!("export {}={}\n", key, shell_single_quote(value)));
+    }
+    
+    let path = std::env::temp_dir().join(format!("claude-gui-env-{}", Uuid::new_v4()));
+    let mut file = create_private_file(&path, true)
+        .map_err(|e| AppError::io("Failed to write tmux environment", e))?;
+    if let Err(e) = file.write_all(script.as_bytes()) {
+        let _ = std::fs::remove_file(&path);
+        return Err(AppError::io("Failed to write tmux environment", e));
+    }
+    Ok(path)
 }
 
-#[tauri::command]
-async fn execute_claude_command_with_files(
-    args: Vec<String>, 
-    files: Vec<String>,
-    enable_autocomplete: bool,
-    plan_mode: bool
-) -> Result<String, String> {
-    let mut command_args = args;
+// Command for a Claude terminal session. With the tmux backend, claude runs in a detached
+// tmux session (created unless it's still alive) and the PTY only hosts the tmux client,
+// so closing the app detaches instead of killing the conversation.
+async fn claude_session_command(
+    session_id: &str,
+    working_dir: &str,
+    project_path: &str,
+    env: Option<&HashMap<String, String>>,
+    claude_args: &[&str]
+) -> Result<CommandBuilder, AppError> {
+    if !tmux_enabled() {
+        let mut cmd = claude_pty_command();
+        cmd.cwd(working_dir);
+        for (key, value) in LOGIN_SHELL_ENV.iter() {
+            cmd.env(key, value);
+        }
+        apply_session_env(&mut cmd, project_path, env).await;
+        cmd.args(claude_args);
+        return Ok(cmd);
+    }
     
-    // Add plan mode flag if enabled
-    if plan_mode {
-        command_args.insert(0, "--plan".to_string());
+    let name = tmux_session_name(session_id);
+    if tmux_has_session(&name) {
+        println!("[DEBUG] Reattaching to tmux session {}", name);
+    } else {
+        // The tmux server may already be running with its own environment, so ours has to be
+        // handed over explicitly. Values can be secrets, so they go through an owner-only file
+        // that the session's shell sources and deletes, rather than through argv
+        let env_file = write_tmux_env_file(&session_env(project_path, env).await)?;
+        let mut tmux = Command::new("tmux");
+        tmux.envs(LOGIN_SHELL_ENV.iter())
+            .args(["new-session", "-d", "-s", &name, "-c", working_dir, "-x", "80", "-y", "24"])
+            .args(["--", "/bin/sh", "-c", ". \"$1\"; rm -f -- \"$1\"; shift; exec \"$@\"", "sh"])
+            .arg(&env_file)
+            .arg(claude_binary())
+            .args(claude_args);
+        
+        let output = tmux.output().map_err(|e| {
+            let _ = std::fs::remove_file(&env_file);
+            AppError::spawn("tmux", e)
+        })?;
+        if !output.status.success() {
+            let _ = std::fs::remove_file(&env_file);
+            return Err(AppError::ProcessFailed(format!(
+                "Failed to start tmux session: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        println!("[DEBUG] Started tmux session {}", name);
     }
     
-    // Add autocomplete flag if disabled
-    if !enable_autocomplete {
-        command_args.insert(0, "--no-autocomplete".to_string());
+    let mut cmd = CommandBuilder::new("tmux");
+    cmd.cwd(working_dir);
+    for (key, value) in LOGIN_SHELL_ENV.iter() {
+        cmd.env(key, value);
+    }
+    cmd.env("TERM", "xterm-256color");
+    cmd.args(["attach-session", "-t", &name]);
+    Ok(cmd)
+}
+
+#[tauri::command]
+async fn set_tmux_sessions_enabled(enabled: bool) -> Result<(), AppError> {
+    if enabled && !command_exists("tmux") {
+        return Err(AppError::NotFound("tmux is not installed".to_string()));
     }
+    let mut settings = read_app_settings()?;
+    settings.tmux_sessions = enabled;
+    write_app_settings(&settings)
+}
+
+// Built-in project terminal: the user's shell, or a specific command run through it
+#[tauri::command]
+async fn start_terminal_session(
+    app: tauri::AppHandle,
+    project_path: String,
+    command: Option<String>,
+    env: Option<HashMap<String, String>>
+) -> Result<String, AppError> {
+    let session_id = Uuid::new_v4().to_string();
+    println!("[INFO] Starting new terminal session: {}", session_id);
     
-    // Add files as direct arguments (Claude Code accepts file paths as arguments)
-    for file in files {
-        command_args.push(file);
+    let working_dir = match get_real_project_path(project_path.clone()).await? {
+        Some(real_path) => real_path,
+        None => {
+            return Err(AppError::PathNotFound("Could not find real project path".to_string()));
+        }
+    };
+    
+    let shell = default_shell();
+    let mut cmd = CommandBuilder::new(&shell);
+    cmd.cwd(&working_dir);
+    for (key, value) in LOGIN_SHELL_ENV.iter() {
+        cmd.env(key, value);
+    }
+    apply_session_env(&mut cmd, &project_path, env.as_ref()).await;
+    if let Some(command) = command.filter(|c| !c.trim().is_empty()) {
+        if cfg!(target_os = "windows") {
+            cmd.arg("-Command");
+        } else {
+            cmd.arg("-l");
+            cmd.arg("-c");
+        }
+        cmd.arg(command);
+    } else if !cfg!(target_os = "windows") {
+        cmd.arg("-l");
     }
+    println!("[DEBUG] Starting {} in directory: {}", shell, working_dir);
     
-    let output = Command::new("claude")
-        .args(&command_args)
-        .output()
-        .map_err(|e| format!("Failed to execute claude command: {}", e))?;
+    spawn_pty_session(app, session_id.clone(), working_dir, cmd, "shell").await?;
     
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    Ok(session_id)
+}
+
+fn default_shell() -> String {
+    if cfg!(target_os = "windows") {
+        "powershell.exe".to_string()
     } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
+        std::env::var("SHELL").unwrap_or_else(|_| {
+            if cfg!(target_os = "macos") { "/bin/zsh".to_string() } else { "/bin/bash".to_string() }
+        })
     }
 }
 
-
 #[tauri::command]
-async fn get_real_project_path(claude_project_path: String) -> Result<Option<String>, String> {
-    let project_dir = std::path::Path::new(&claude_project_path);
+async fn start_claude_session(app: tauri::AppHandle, project_path: String, env: Option<HashMap<String, String>>) -> Result<String, AppError> {
+    let session_id = Uuid::new_v4().to_string();
+    println!("[INFO] Starting new Claude session: {}", session_id);
     
-    // Try to read various metadata files that might contain the real path
-    let possible_files = vec![
-        ".claude-project",
-        "project.json",
-        ".project",
-        "config.json",
-        ".claude",
-    ];
+    // Get the real project path for the working directory
+    let working_dir = match get_real_project_path(project_path.clone()).await? {
+        Some(real_path) => real_path,
+        None => {
+            return Err(AppError::PathNotFound("Could not find real project path".to_string()));
+        }
+    };
+
+    // Reuse our ID as Claude's session ID so the session can be resumed later
+    let cmd = claude_session_command(&session_id, &working_dir, &project_path, env.as_ref(), &["--session-id", &session_id]).await?;
+    println!("[DEBUG] Starting Claude in directory: {}", working_dir);
     
-    for file_name in possible_files {
-        let file_path = project_dir.join(file_name);
-        if file_path.exists() {
-            if let Ok(content) = std::fs::read_to_string(&file_path) {
-                // Try to parse as JSON and look for path-like fields
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                    // Check various field names that might contain the path
-                    let path_fields = vec!["path", "projectPath", "directory", "root", "workingDirectory"];
-                    for field in path_fields {
-                        if let Some(path) = json.get(field).and_then(|p| p.as_str()) {
-                            return Ok(Some(path.to_string()));
-                        }
-                    }
-                }
-                
-                // If not JSON, maybe it's just a plain text file with the path
-                let trimmed_content = content.trim();
-                if trimmed_content.starts_with('/') && std::path::Path::new(trimmed_content).exists() {
-                    return Ok(Some(trimmed_content.to_string()));
-                }
+    spawn_pty_session(app, session_id.clone(), working_dir, cmd, "claude").await?;
+
+    remember_terminal_session(PersistedTerminalSession {
+        id: session_id.clone(),
+        project_path: project_path.clone(),
+        resume_session_id: session_id.clone(),
+        title: None,
+        started_at: chrono::Utc::now().to_rfc3339(),
+    });
+    remember_session_env(&session_id, env);
+
+    Ok(session_id)
+}
+
+#[tauri::command]
+async fn resume_claude_session(
+    app: tauri::AppHandle,
+    session_id: String,
+    project_path: String,
+    env: Option<HashMap<String, String>>
+) -> Result<String, AppError> {
+    println!("[INFO] Resume request for session: {}", session_id);
+    
+    // Check if session already exists and is healthy
+    {
+        let sessions = TERMINAL_SESSIONS.read().await;
+        if sessions.contains_key(&session_id) {
+            println!("[DEBUG] Session {} already exists, verifying health", session_id);
+            if verify_claude_health(&session_id).await {
+                println!("[DEBUG] Session {} is healthy, returning existing session", session_id);
+                return Ok(session_id);
+            } else {
+                println!("[DEBUG] Session {} is not healthy, will recreate", session_id);
+                // Don't return early - let it recreate the session
             }
         }
     }
     
-    // Check if there are any files that look like they contain path information
-    if let Ok(entries) = std::fs::read_dir(&project_dir) {
-        for entry in entries.flatten() {
-            let file_name = entry.file_name();
-            if let Some(name_str) = file_name.to_str() {
-                // Look for any JSON or JSONL files that might contain metadata
-                if name_str.ends_with(".json") || name_str.ends_with(".jsonl") {
-                    if let Ok(content) = std::fs::read_to_string(entry.path()) {
-                        // For .jsonl files, check each line
-                        let lines_to_check = if name_str.ends_with(".jsonl") {
-                            content.lines().take(10).collect::<Vec<_>>()
-                        } else {
-                            vec![content.as_str()]
-                        };
-                        
-                        for line in lines_to_check {
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
-                                let path_fields = vec!["path", "projectPath", "directory", "root", "workingDirectory", "cwd"];
-                                for field in path_fields {
-                                    if let Some(path) = json.get(field).and_then(|p| p.as_str()) {
-                                        if std::path::Path::new(path).exists() {
-                                            return Ok(Some(path.to_string()));
-                                        }
-                                    }
-                                }
-                                
-                                // Also search for any path-like strings in the JSON
-                                if let Some(obj) = json.as_object() {
-                                    for (_, value) in obj {
-                                        if let Some(str_val) = value.as_str() {
-                                            // Check if it looks like an absolute path and exists
-                                            if str_val.starts_with("/") && std::path::Path::new(str_val).exists() {
-                                                return Ok(Some(str_val.to_string()));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+    // Clean up any existing unhealthy session
+    {
+        let mut sessions = TERMINAL_SESSIONS.write().await;
+        if let Some(old_session) = sessions.remove(&session_id) {
+            println!("[DEBUG] Removing unhealthy session and terminating process: {}", session_id);
+            
+            // Terminate the old Claude process
+            if let Ok(mut child) = old_session.child_process.try_lock() {
+                match child.kill() {
+                    Ok(_) => println!("[DEBUG] Successfully killed old Claude process for session: {}", session_id),
+                    Err(e) => println!("[WARN] Failed to kill old Claude process for session {}: {}", session_id, e)
                 }
+            } else {
+                println!("[WARN] Could not acquire lock on old Claude process for session: {}", session_id);
+            }
+            
+            // Remove from active handlers
+            {
+                let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
+                handlers.remove(&session_id);
+                println!("[DEBUG] Removed old session {} from active handlers during cleanup", session_id);
             }
         }
     }
     
-    // Fallback: decode the directory name to get the real path
-    // Claude projects encode paths by replacing '/' with '-' and adding a leading '-'
-    // Example: /Users/username/repos/project-name -> -Users-username-repos-project-name
-    if let Some(dir_name) = std::path::Path::new(&claude_project_path).file_name() {
-        if let Some(encoded_path) = dir_name.to_str() {
-            if encoded_path.starts_with('-') {
-                let path_part = &encoded_path[1..];
-                
-                // Strategy: Try to intelligently decode by looking for known path patterns
-                // Common pattern: Users-username-repos-project-name
-                if let Some(repos_pos) = path_part.find("-repos-") {
-                    // Split at "repos" - everything before is directory structure
-                    let before_repos = &path_part[..repos_pos];
-                    let after_repos_with_dash = &path_part[repos_pos + 6..]; // +6 for "-repos-"
-                    
-                    // Before repos: replace dashes with slashes
-                    let dir_structure = before_repos.replace('-', "/");
-                    
-                    // After repos: remove the leading dash if present, then keep remaining dashes
-                    let after_repos = if after_repos_with_dash.starts_with('-') {
-                        &after_repos_with_dash[1..]
-                    } else {
-                        after_repos_with_dash
-                    };
-                    
-                    // Try both the original project name and with dashes converted to underscores
-                    // since project names might use underscores but Claude encodes them as dashes
-                    let project_with_dashes = format!("/{}/repos/{}", dir_structure, after_repos);
-                    let project_with_underscores = format!("/{}/repos/{}", dir_structure, after_repos.replace('-', "_"));
-                    
-                    // Check which one actually exists
-                    if std::path::Path::new(&project_with_underscores).exists() {
-                        return Ok(Some(project_with_underscores));
-                    } else if std::path::Path::new(&project_with_dashes).exists() {
-                        return Ok(Some(project_with_dashes));
-                    } else {
-                        // Return the underscore version as it's more likely for project names
-                        return Ok(Some(project_with_underscores));
-                    }
-                }
-                
-                // Fallback: look for other common patterns
-                if path_part.starts_with("Users-") {
-                    let parts: Vec<&str> = path_part.split('-').collect();
-                    if parts.len() >= 3 {
-                        // Assume first 3 parts are Users/username/something, rest is project name
-                        let base_path = format!("/{}/{}/{}", parts[0], parts[1], parts[2]);
-                        if parts.len() > 3 {
-                            let project_name = parts[3..].join("-");
-                            return Ok(Some(format!("{}/{}", base_path, project_name)));
-                        } else {
-                            return Ok(Some(base_path));
-                        }
-                    }
-                }
-                
-                // Last resort: replace all dashes with slashes
-                let decoded_path = format!("/{}", path_part.replace('-', "/"));
-                return Ok(Some(decoded_path));
-            }
+    // Get the real project path for the working directory
+    let working_dir = match get_real_project_path(project_path.clone()).await? {
+        Some(real_path) => real_path,
+        None => {
+            return Err(AppError::PathNotFound("Could not find real project path".to_string()));
         }
-    }
+    };
+
+    // Set up Claude command with resume flag (a live tmux session is reattached as-is)
+    let cmd = claude_session_command(&session_id, &working_dir, &project_path, env.as_ref(), &["--resume", &session_id]).await?;
+    println!("[DEBUG] Starting Claude with resume for session {} in directory: {}", session_id, working_dir);
+    
+    spawn_pty_session(app, session_id.clone(), working_dir, cmd, "claude").await?;
+
+    remember_terminal_session(PersistedTerminalSession {
+        id: session_id.clone(),
+        project_path: project_path.clone(),
+        resume_session_id: session_id.clone(),
+        title: None,
+        started_at: chrono::Utc::now().to_rfc3339(),
+    });
+    remember_session_env(&session_id, env);
+
+    Ok(session_id)
+}
+
+#[tauri::command]
+async fn list_terminal_sessions(project_path: String) -> Result<Vec<TerminalSessionInfo>, AppError> {
+    // Sessions store the real working directory, so match against that when we can resolve it
+    let real_path = get_real_project_path(project_path.clone()).await.ok().flatten()
+        .unwrap_or(project_path);
+    
+    let sessions = TERMINAL_SESSIONS.read().await;
+    let mut infos: Vec<TerminalSessionInfo> = sessions.iter()
+        .filter(|(_, session)| session.project_path == real_path)
+        .map(|(id, session)| TerminalSessionInfo {
+            id: id.clone(),
+            kind: session.kind.clone(),
+            title: session.title.clone(),
+            cwd: session.project_path.clone(),
+            pid: session.pid,
+            uptime_secs: session.started_at.elapsed().map(|d| d.as_secs()).unwrap_or(0),
+        })
+        .collect();
     
-    Ok(None)
+    // Oldest first, matching the order tabs were opened in
+    infos.sort_by_key(|info| std::cmp::Reverse(info.uptime_secs));
+    Ok(infos)
 }
 
 #[tauri::command]
-async fn get_claude_md_content(project_path: String) -> Result<Option<String>, String> {
-    // First get the real project path
-    let real_path = match get_real_project_path(project_path).await? {
-        Some(path) => path,
-        None => return Ok(None)
-    };
+async fn get_terminal_session_info(session_id: String) -> Result<TerminalSessionDetails, AppError> {
+    let sessions = TERMINAL_SESSIONS.read().await;
+    let session = sessions.get(&session_id)
+        .ok_or_else(|| AppError::SessionDead(format!("Session {} not found", session_id)))?;
     
-    // Try multiple possible paths for CLAUDE.md in the real project directory
-    let possible_paths = vec![
-        std::path::Path::new(&real_path).join("CLAUDE.md"),
-        std::path::Path::new(&real_path).join("claude.md"),
-        std::path::Path::new(&real_path).join("Claude.md"),
-    ];
+    let size = session.pty_master.lock().await.get_size()
+        .map_err(|e| AppError::Other(format!("Failed to read terminal size: {}", e)))?;
     
-    for claude_md_path in possible_paths {
-        if claude_md_path.exists() {
-            match std::fs::read_to_string(&claude_md_path) {
-                Ok(content) => return Ok(Some(content)),
-                Err(e) => return Err(format!("Failed to read CLAUDE.md at {}: {}", claude_md_path.display(), e))
-            }
-        }
-    }
+    // Active means the session hasn't been retired and its process is still alive
+    let alive = matches!(session.child_process.lock().await.try_wait(), Ok(None));
+    let recording = session.recording.lock().await.is_some();
     
-    Ok(None)
+    Ok(TerminalSessionDetails {
+        id: session_id.clone(),
+        kind: session.kind.clone(),
+        title: session.title.clone(),
+        cwd: session.project_path.clone(),
+        pid: session.pid,
+        rows: size.rows,
+        cols: size.cols,
+        started_at: chrono::DateTime::<chrono::Utc>::from(session.started_at).to_rfc3339(),
+        uptime_secs: session.started_at.elapsed().map(|d| d.as_secs()).unwrap_or(0),
+        bytes_read: session.bytes_read.load(std::sync::atomic::Ordering::Relaxed),
+        bytes_written: session.bytes_written.load(std::sync::atomic::Ordering::Relaxed),
+        active: session.active && alive,
+        recording,
+    })
 }
 
 #[tauri::command]
-async fn save_claude_md_content(project_path: String, content: String) -> Result<(), String> {
-    // First get the real project path
-    let real_path = match get_real_project_path(project_path).await? {
-        Some(path) => path,
-        None => return Err("Could not find real project path".to_string())
-    };
-    
-    let claude_md_path = std::path::Path::new(&real_path).join("CLAUDE.md");
-    
-    std::fs::write(&claude_md_path, content)
-        .map_err(|e| format!("Failed to save CLAUDE.md: {}", e))?;
+async fn rename_terminal_session(session_id: String, title: Option<String>) -> Result<(), AppError> {
+    let mut sessions = TERMINAL_SESSIONS.write().await;
     
-    Ok(())
+    match sessions.get_mut(&session_id) {
+        Some(session) => {
+            session.title = title.filter(|t| !t.trim().is_empty());
+            
+            let mut persisted = read_persisted_terminal_sessions();
+            if let Some(record) = persisted.iter_mut().find(|record| record.id == session_id) {
+                record.title = session.title.clone();
+                write_persisted_terminal_sessions(&persisted);
+            }
+            Ok(())
+        }
+        None => Err(AppError::SessionDead(format!("Session {} not found", session_id)))
+    }
 }
 
-#[tauri::command]
-async fn check_claude_md_exists(project_path: String) -> Result<bool, String> {
-    // First get the real project path
-    let real_path = match get_real_project_path(project_path).await? {
+fn get_terminal_sessions_file_path() -> Option<std::path::PathBuf> {
+    dirs::home_dir().map(|home| home.join(".claude").join("gui-terminal-sessions.json"))
+}
+
+fn read_persisted_terminal_sessions() -> Vec<PersistedTerminalSession> {
+    get_terminal_sessions_file_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_persisted_terminal_sessions(records: &[PersistedTerminalSession]) {
+    let path = match get_terminal_sessions_file_path() {
         Some(path) => path,
-        None => return Ok(false)
+        None => return,
     };
     
-    // Try multiple possible paths for CLAUDE.md in the real project directory
-    let possible_paths = vec![
-        std::path::Path::new(&real_path).join("CLAUDE.md"),
-        std::path::Path::new(&real_path).join("claude.md"),
-        std::path::Path::new(&real_path).join("Claude.md"),
-    ];
-    
-    for claude_md_path in possible_paths {
-        if claude_md_path.exists() {
-            return Ok(true);
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match serde_json::to_string_pretty(records) {
+        Ok(content) => {
+            if let Err(e) = std::fs::write(&path, content) {
+                println!("[WARN] Failed to persist terminal sessions: {}", e);
+            }
         }
+        Err(e) => println!("[WARN] Failed to serialize terminal sessions: {}", e),
     }
-    
-    Ok(false)
 }
 
-#[tauri::command]
-async fn debug_project_path(project_path: String) -> Result<String, String> {
-    let mut debug_info = format!("Claude project path: {}\n", project_path);
-    
-    // First, show what's in the Claude project directory
-    let claude_path = std::path::Path::new(&project_path);
-    debug_info.push_str(&format!("Claude project directory exists: {}\n", claude_path.exists()));
-    
-    if claude_path.exists() {
-        debug_info.push_str("Files in Claude project directory:\n");
-        if let Ok(entries) = std::fs::read_dir(claude_path) {
-            for entry in entries.flatten() {
-                let file_name = entry.file_name();
-                let name = file_name.to_string_lossy();
-                let is_file = entry.path().is_file();
-                debug_info.push_str(&format!("  - {} ({})\n", name, if is_file { "file" } else { "dir" }));
-                
-                // If it's a small file, try to read its content
-                if is_file {
-                    let path = entry.path();
-                    if let Ok(metadata) = path.metadata() {
-                        if metadata.len() < 5120 { // Less than 5KB - check jsonl files too
-                            if let Ok(content) = std::fs::read_to_string(&path) {
-                                // For .jsonl files, try to find project path information
-                                if name.ends_with(".jsonl") {
-                                    // Read first few lines to look for project info
-                                    let lines: Vec<&str> = content.lines().take(5).collect();
-                                    for line in lines {
-                                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
-                                            // Look for common fields that might contain the project path
-                                            let search_fields = vec!["workingDirectory", "cwd", "projectPath", "path", "directory"];
-                                            for field in search_fields {
-                                                if let Some(value) = json.get(field) {
-                                                    debug_info.push_str(&format!("    Found {}: {}\n", field, value));
-                                                }
-                                            }
-                                            // Also check if there's any path-like string in the JSON
-                                            if let Some(obj) = json.as_object() {
-                                                for (key, value) in obj {
-                                                    if let Some(str_val) = value.as_str() {
-                                                        // Log any absolute paths found in the JSON for debugging
-                                                        if str_val.starts_with("/") && std::path::Path::new(str_val).exists() {
-                                                            debug_info.push_str(&format!("    Found path in {}: {}\n", key, str_val));
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    let preview = if content.len() > 200 { 
-                                        format!("{}...", &content[..200])
-                                    } else { 
-                                        content 
-                                    };
-                                    debug_info.push_str(&format!("    Content: {}\n", preview.replace('\n', "\\n")));
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+fn remember_terminal_session(record: PersistedTerminalSession) {
+    let mut records = read_persisted_terminal_sessions();
+    match records.iter_mut().find(|existing| existing.id == record.id) {
+        // Keep the title the user gave it across resumes
+        Some(existing) => {
+            existing.project_path = record.project_path;
+            existing.resume_session_id = record.resume_session_id;
         }
+        None => records.push(record),
+    }
+    write_persisted_terminal_sessions(&records);
+}
+
+fn remember_session_env(session_id: &str, env: Option<HashMap<String, String>>) {
+    let mut envs = TERMINAL_SESSION_ENV.lock().unwrap_or_else(|e| e.into_inner());
+    match env {
+        Some(env) => { envs.insert(session_id.to_string(), env); }
+        None => { envs.remove(session_id); }
     }
+}
+
+fn forget_terminal_session(session_id: &str) {
+    TERMINAL_SESSION_ENV.lock().unwrap_or_else(|e| e.into_inner()).remove(session_id);
+    let mut records = read_persisted_terminal_sessions();
+    records.retain(|record| record.id != session_id);
+    write_persisted_terminal_sessions(&records);
+}
+
+// Re-spawn `claude --resume` for every session that was open when the app last quit
+#[tauri::command]
+async fn restore_terminal_sessions(app: tauri::AppHandle) -> Result<Vec<String>, AppError> {
+    let mut restored = Vec::new();
     
-    // Try to get the real project path
-    match get_real_project_path(project_path.clone()).await {
-        Ok(Some(real_path)) => {
-            debug_info.push_str(&format!("Real project path: {}\n", real_path));
-            
-            let path = std::path::Path::new(&real_path);
-            debug_info.push_str(&format!("Real path exists: {}\n", path.exists()));
-            debug_info.push_str(&format!("Real path is directory: {}\n", path.is_dir()));
-            
-            if path.exists() && path.is_dir() {
-                debug_info.push_str("Real directory contents:\n");
-                if let Ok(entries) = std::fs::read_dir(path) {
-                    for entry in entries.flatten() {
-                        let file_name = entry.file_name();
-                        let name = file_name.to_string_lossy();
-                        debug_info.push_str(&format!("  - {}\n", name));
-                    }
+    for record in read_persisted_terminal_sessions() {
+        if TERMINAL_SESSIONS.read().await.contains_key(&record.id) {
+            restored.push(record.id);
+            continue;
+        }
+        
+        // Session env overrides aren't persisted, so a restored session gets the project env only
+        match resume_claude_session(app.clone(), record.resume_session_id.clone(), record.project_path.clone(), None).await {
+            Ok(session_id) => {
+                if let Some(session) = TERMINAL_SESSIONS.write().await.get_mut(&session_id) {
+                    session.title = record.title.clone();
                 }
+                println!("[INFO] Restored terminal session {}", session_id);
+                restored.push(session_id);
             }
-            
-            // Check specifically for CLAUDE.md variants in real path
-            let possible_paths = vec![
-                path.join("CLAUDE.md"),
-                path.join("claude.md"),
-                path.join("Claude.md"),
-            ];
-            
-            debug_info.push_str("\nCLAUDE.md file checks in real path:\n");
-            for claude_path in possible_paths {
-                debug_info.push_str(&format!("  {} exists: {}\n", claude_path.display(), claude_path.exists()));
+            Err(e) => {
+                println!("[WARN] Could not restore terminal session {}: {}", record.id, e);
+                forget_terminal_session(&record.id);
             }
         }
-        Ok(None) => {
-            debug_info.push_str("Could not find real project path (no .claude-project file)\n");
-        }
-        Err(e) => {
-            debug_info.push_str(&format!("Error getting real project path: {}\n", e));
-        }
     }
     
-    Ok(debug_info)
+    Ok(restored)
+}
+
+fn get_terminal_recording_path(session_id: &str) -> Result<std::path::PathBuf, AppError> {
+    let home_dir = dirs::home_dir().ok_or(AppError::PathNotFound("Could not find home directory".to_string()))?;
+    Ok(home_dir.join(".claude").join("gui-recordings").join(format!("{}.cast", session_id)))
 }
 
 #[tauri::command]
-async fn create_claude_md_template(project_path: String) -> Result<(), String> {
-    // First get the real project path
-    let real_path = match get_real_project_path(project_path).await? {
-        Some(path) => path,
-        None => return Err("Could not find real project path".to_string())
-    };
+async fn start_terminal_recording(session_id: String) -> Result<String, AppError> {
+    let sessions = TERMINAL_SESSIONS.read().await;
+    let session = sessions.get(&session_id)
+        .ok_or_else(|| AppError::SessionDead(format!("Session {} not found", session_id)))?;
     
-    let claude_md_path = std::path::Path::new(&real_path).join("CLAUDE.md");
+    // Read the size before taking the recording lock; resize takes them in the other order
+    let size = session.pty_master.lock().await.get_size()
+        .map_err(|e| AppError::Other(format!("Failed to read terminal size: {}", e)))?;
     
-    if claude_md_path.exists() {
-        return Err("CLAUDE.md already exists".to_string());
+    let mut recording = session.recording.lock().await;
+    if recording.is_some() {
+        return Err(AppError::AlreadyExists(format!("Session {} is already being recorded", session_id)));
     }
     
-    let template = r#"# Project Instructions for Claude
-
-## Project Overview
-Brief description of what this project does and its main purpose.
-
-## Development Guidelines
-- Coding standards and conventions to follow
-- Preferred libraries and frameworks
-- Architecture patterns to maintain
-
-## Key Files and Directories
-- `src/` - Main source code
-- `tests/` - Test files
-- `docs/` - Documentation
-
-## Important Notes
-- Any specific requirements or constraints
-- Known issues or gotchas
-- Deployment considerations
+    let path = get_terminal_recording_path(&session_id)?;
+    *recording = Some(TerminalRecorder::create(&path, size.cols, size.rows, session.title.as_deref())?);
+    
+    println!("[INFO] Recording terminal session {} to {}", session_id, path.display());
+    Ok(path.to_string_lossy().to_string())
+}
 
-## Testing
-- How to run tests
-- Test coverage expectations
-- Any special testing requirements
+#[tauri::command]
+async fn stop_terminal_recording(session_id: String) -> Result<(), AppError> {
+    let sessions = TERMINAL_SESSIONS.read().await;
+    let session = sessions.get(&session_id)
+        .ok_or_else(|| AppError::SessionDead(format!("Session {} not found", session_id)))?;
+    
+    // Dropping the recorder closes the file
+    session.recording.lock().await.take();
+    Ok(())
+}
 
-## Build & Deployment
-- Build commands
-- Environment setup
-- Deployment process
-"#;
+// Save a copy of the recording where the user picks in a save dialog; returns the chosen
+// path, or None when the dialog was cancelled
+#[tauri::command]
+async fn export_terminal_recording(app: tauri::AppHandle, session_id: String) -> Result<Option<String>, AppError> {
+    let recording_path = get_terminal_recording_path(&session_id)?;
+    if !recording_path.exists() {
+        return Err(AppError::NotFound(format!("No recording for session {}", session_id)));
+    }
     
-    std::fs::write(&claude_md_path, template)
-        .map_err(|e| format!("Failed to create CLAUDE.md template: {}", e))?;
+    let file_name = recording_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let dest = match pick_save_path_natively(&app, "Export terminal recording", &file_name).await {
+        Some(dest) => dest,
+        None => return Ok(None),
+    };
+    std::fs::copy(&recording_path, &dest)
+        .map_err(|e| AppError::io("Failed to export recording", e))?;
+    Ok(Some(dest.to_string_lossy().to_string()))
+}
+
+#[tauri::command]
+async fn get_terminal_scrollback(session_id: String) -> Result<String, AppError> {
+    let scrollback = {
+        let sessions = TERMINAL_SESSIONS.read().await;
+        match sessions.get(&session_id) {
+            Some(session) => session.scrollback.clone(),
+            None => return Err(AppError::SessionDead(format!("Session {} not found", session_id))),
+        }
+    };
     
-    Ok(())
+    let contents = scrollback.lock().await.contents();
+    Ok(contents)
 }
 
 #[tauri::command]
-async fn open_file_in_system(file_path: String) -> Result<(), String> {
-    #[cfg(target_os = "macos")]
-    {
-        Command::new("open")
-            .arg(&file_path)
-            .spawn()
-            .map_err(|e| format!("Failed to open file: {}", e))?;
+async fn write_to_terminal(session_id: String, data: String) -> Result<(), AppError> {
+    println!("[DEBUG] Writing to terminal session: {} (data length: {})", session_id, data.len());
+    
+    // First check if the session is healthy
+    if !verify_claude_health(&session_id).await {
+        let error_msg = format!("Session {} is not healthy or has exited", session_id);
+        println!("[ERROR] {}", error_msg);
+        return Err(AppError::SessionDead(error_msg));
     }
     
-    #[cfg(target_os = "windows")]
-    {
-        Command::new("cmd")
-            .args(["/C", "start", "", &file_path])
-            .spawn()
-            .map_err(|e| format!("Failed to open file: {}", e))?;
+    // Large input (e.g. an unbracketed paste) goes out in paced chunks
+    if data.len() > TERMINAL_WRITE_CHUNK_BYTES {
+        return write_terminal_chunked(&session_id, data.as_bytes()).await;
+    }
+    
+    let sessions = TERMINAL_SESSIONS.read().await;
+    
+    if let Some(session) = sessions.get(&session_id) {
+        session.last_activity.store(now_millis(), std::sync::atomic::Ordering::Relaxed);
+        let mut writer_guard = session.pty_writer.lock().await;
+        
+        match writer_guard.write_all(data.as_bytes()) {
+            Ok(_) => {
+                match writer_guard.flush() {
+                    Ok(_) => {
+                        session.bytes_written.fetch_add(data.len() as u64, std::sync::atomic::Ordering::Relaxed);
+                        println!("[DEBUG] Successfully wrote and flushed data to session: {}", session_id);
+                        Ok(())
+                    }
+                    Err(e) => {
+                        let error_msg = format!("Failed to flush terminal {}: {}", session_id, e);
+                        println!("[ERROR] {}", error_msg);
+                        Err(AppError::Io(error_msg))
+                    }
+                }
+            }
+            Err(e) => {
+                let error_msg = format!("Failed to write to terminal {}: {}", session_id, e);
+                println!("[ERROR] {}", error_msg);
+                Err(AppError::Io(error_msg))
+            }
+        }
+    } else {
+        let error_msg = format!("Session {} not found. Available sessions: {:?}", session_id, sessions.keys().collect::<Vec<_>>());
+        println!("[ERROR] {}", error_msg);
+        Err(AppError::SessionDead(error_msg))
     }
+}
+
+const TERMINAL_WRITE_CHUNK_BYTES: usize = 1024;
+const DEFAULT_PASTE_BYTES_PER_SEC: u64 = 256 * 1024;
+
+// Write in small chunks at the configured rate so the child's input buffer isn't overrun.
+// The writer stays locked throughout so keystrokes can't land in the middle.
+async fn write_terminal_chunked(session_id: &str, data: &[u8]) -> Result<(), AppError> {
+    let (writer, last_activity, bytes_written) = {
+        let sessions = TERMINAL_SESSIONS.read().await;
+        let session = sessions.get(session_id)
+            .ok_or_else(|| AppError::SessionDead(format!("Session {} not found", session_id)))?;
+        (session.pty_writer.clone(), session.last_activity.clone(), session.bytes_written.clone())
+    };
     
-    #[cfg(target_os = "linux")]
-    {
-        Command::new("xdg-open")
-            .arg(&file_path)
-            .spawn()
-            .map_err(|e| format!("Failed to open file: {}", e))?;
+    let bytes_per_sec = read_app_settings().ok()
+        .and_then(|settings| settings.terminal_paste_bytes_per_sec)
+        .filter(|rate| *rate > 0)
+        .unwrap_or(DEFAULT_PASTE_BYTES_PER_SEC);
+    let delay = tokio::time::Duration::from_secs_f64(TERMINAL_WRITE_CHUNK_BYTES as f64 / bytes_per_sec as f64);
+    
+    let mut writer_guard = writer.lock().await;
+    for chunk in data.chunks(TERMINAL_WRITE_CHUNK_BYTES) {
+        writer_guard.write_all(chunk)
+            .and_then(|_| writer_guard.flush())
+            .map_err(|e| AppError::Io(format!("Failed to write to terminal {}: {}", session_id, e)))?;
+        last_activity.store(now_millis(), std::sync::atomic::Ordering::Relaxed);
+        bytes_written.fetch_add(chunk.len() as u64, std::sync::atomic::Ordering::Relaxed);
+        tokio::time::sleep(delay).await;
     }
     
+    println!("[DEBUG] Wrote {} bytes to session {} in chunks", data.len(), session_id);
     Ok(())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ProjectSetupOptions {
-    path: String,
-    project_name: String,
-    init_git: bool,
-    create_claude: bool,
-    project_type: String,
-    open_in_ide: bool,
-    selected_ide: Option<String>,
+// Paste text as a single unit, wrapped in bracketed-paste markers so the program
+// receiving it treats it as pasted content rather than typed keystrokes
+#[tauri::command]
+async fn paste_to_terminal(session_id: String, text: String, bracketed: Option<bool>) -> Result<(), AppError> {
+    if !verify_claude_health(&session_id).await {
+        return Err(AppError::SessionDead(format!("Session {} is not healthy or has exited", session_id)));
+    }
+    
+    let payload = if bracketed.unwrap_or(true) {
+        format!("\x1b[200~{}\x1b[201~", text)
+    } else {
+        text
+    };
+    
+    write_terminal_chunked(&session_id, payload.as_bytes()).await
 }
 
+// Deliver SIGINT/SIGTERM/SIGKILL to whatever is running in the PTY without closing the session
 #[tauri::command]
-async fn select_directory(app: tauri::AppHandle) -> Result<Option<String>, String> {
-    use tauri_plugin_dialog::DialogExt;
-    use std::sync::{Arc, Mutex};
-    use tokio::sync::oneshot;
+async fn signal_terminal(session_id: String, signal: String) -> Result<(), AppError> {
+    let sessions = TERMINAL_SESSIONS.read().await;
+    let session = sessions.get(&session_id)
+        .ok_or_else(|| AppError::SessionDead(format!("Session {} not found", session_id)))?;
     
-    let (tx, rx) = oneshot::channel();
-    let tx = Arc::new(Mutex::new(Some(tx)));
+    println!("[INFO] Sending {} to terminal session {}", signal, session_id);
     
-    app.dialog()
-        .file()
-        .set_directory(dirs::home_dir().unwrap_or_default())
-        .pick_folder(move |result| {
-            if let Some(tx) = tx.lock().unwrap().take() {
-                let _ = tx.send(result);
-            }
-        });
+    #[cfg(unix)]
+    {
+        let signal_number = match signal.to_uppercase().trim_start_matches("SIG") {
+            "INT" => libc::SIGINT,
+            "TERM" => libc::SIGTERM,
+            "KILL" => libc::SIGKILL,
+            _ => return Err(AppError::InvalidInput(format!("Unsupported signal: {}", signal))),
+        };
+        session.stopped_by_user.store(true, std::sync::atomic::Ordering::Relaxed);
+        
+        // Target the foreground process group, like a real terminal would,
+        // falling back to the process we spawned
+        let process_group = session.pty_master.lock().await.process_group_leader();
+        let result = match (process_group, session.pid) {
+            (Some(pgid), _) if pgid > 0 => unsafe { libc::killpg(pgid, signal_number) },
+            (_, Some(pid)) => unsafe { libc::kill(pid as libc::pid_t, signal_number) },
+            _ => return Err(AppError::ProcessFailed(format!("No process to signal in session {}", session_id))),
+        };
+        
+        if result != 0 {
+            return Err(AppError::ProcessFailed(format!(
+                "Failed to send {} to session {}: {}", signal, session_id, std::io::Error::last_os_error()
+            )));
+        }
+        Ok(())
+    }
     
-    match rx.await {
-        Ok(Some(path)) => Ok(Some(path.to_string())),
-        Ok(None) => Ok(None),
-        Err(_) => Err("Dialog was cancelled or failed".to_string())
+    #[cfg(not(unix))]
+    {
+        match signal.to_uppercase().trim_start_matches("SIG") {
+            // Ctrl+C through the console is the closest thing to SIGINT
+            "INT" => {
+                session.stopped_by_user.store(true, std::sync::atomic::Ordering::Relaxed);
+                let mut writer = session.pty_writer.lock().await;
+                writer.write_all(b"\x03")
+                    .and_then(|_| writer.flush())
+                    .map_err(|e| AppError::io("Failed to send interrupt", e))
+            }
+            "TERM" | "KILL" => {
+                session.stopped_by_user.store(true, std::sync::atomic::Ordering::Relaxed);
+                session.child_process.lock().await.kill()
+                    .map_err(|e| AppError::io("Failed to terminate process", e))
+            }
+            _ => Err(AppError::InvalidInput(format!("Unsupported signal: {}", signal))),
+        }
     }
 }
 
 #[tauri::command]
-async fn create_enhanced_project(options: ProjectSetupOptions) -> Result<String, String> {
-    let project_path = &options.path;
+async fn resize_terminal(session_id: String, rows: u16, cols: u16) -> Result<(), AppError> {
+    let sessions = TERMINAL_SESSIONS.read().await;
     
-    // Create directory if it doesn't exist
-    if !std::path::Path::new(project_path).exists() {
-        std::fs::create_dir_all(project_path)
-            .map_err(|e| format!("Failed to create directory: {}", e))?;
+    if let Some(session) = sessions.get(&session_id) {
+        let pty_master = session.pty_master.lock().await;
+        pty_master
+            .resize(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| AppError::Other(format!("Failed to resize terminal: {}", e)))?;
+        if let Some(recorder) = session.recording.lock().await.as_mut() {
+            recorder.record("r", &format!("{}x{}", cols, rows));
+        }
+        Ok(())
+    } else {
+        Err(AppError::SessionDead("Session not found".to_string()))
     }
+}
+
+#[tauri::command]
+async fn close_terminal_session(session_id: String) -> Result<(), AppError> {
+    println!("[INFO] Closing terminal session: {}", session_id);
+    let mut sessions = TERMINAL_SESSIONS.write().await;
     
-    // Initialize Git repository if requested
-    if options.init_git {
-        let git_output = Command::new("git")
-            .args(["init"])
-            .current_dir(project_path)
-            .output()
-            .map_err(|e| format!("Failed to initialize git: {}", e))?;
+    if let Some(session) = sessions.remove(&session_id) {
+        println!("[DEBUG] Found session to close: {}", session_id);
+        forget_terminal_session(&session_id);
         
-        if !git_output.status.success() {
-            eprintln!("Warning: Failed to initialize git repository");
+        // Gracefully terminate the child process
+        if let Ok(mut child) = session.child_process.try_lock() {
+            match child.kill() {
+                Ok(_) => println!("[DEBUG] Successfully killed child process for session: {}", session_id),
+                Err(e) => println!("[WARN] Failed to kill child process for session {}: {}", session_id, e)
+            }
+        } else {
+            println!("[WARN] Could not acquire lock on child process for session: {}", session_id);
+        }
+        
+        // Closing a tab explicitly ends the conversation, tmux backend or not
+        let tmux_name = tmux_session_name(&session_id);
+        if session.kind == "claude" && command_exists("tmux") && tmux_has_session(&tmux_name) {
+            tmux_kill_session(&tmux_name);
+        }
+        
+        println!("[INFO] Session {} closed successfully. Remaining sessions: {}", session_id, sessions.len());
+        Ok(())
+    } else {
+        let error_msg = format!("Session {} not found. Available sessions: {:?}", session_id, sessions.keys().collect::<Vec<_>>());
+        println!("[ERROR] {}", error_msg);
+        Err(AppError::SessionDead(error_msg))
+    }
+}
+
+async fn handle_pty_output(app: tauri::AppHandle, session_id: String) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!("[DEBUG] Starting PTY output handler for session: {}", session_id);
+    
+    // Check if output handler is already running for this session
+    {
+        let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
+        if handlers.contains(&session_id) {
+            println!("[WARN] Output handler already running for session {}, skipping", session_id);
+            return Ok(());
         }
+        handlers.insert(session_id.clone());
     }
     
-    // Create project based on type
-    match options.project_type.as_str() {
-        "react" => {
-            // Create React app with Vite
-            let output = Command::new("npm")
-                .args(["create", "vite@latest", ".", "--template", "react-ts"])
-                .current_dir(project_path)
-                .output()
-                .map_err(|e| format!("Failed to create React app: {}", e))?;
-            
-            if !output.status.success() {
-                return Err(String::from_utf8_lossy(&output.stderr).to_string());
-            }
-        },
-        "nextjs" => {
-            // Create Next.js app
-            let output = Command::new("npx")
-                .args(["create-next-app@latest", ".", "--typescript", "--tailwind", "--eslint"])
-                .current_dir(project_path)
-                .output()
-                .map_err(|e| format!("Failed to create Next.js app: {}", e))?;
-            
-            if !output.status.success() {
-                return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    handle_pty_output_no_check(app, session_id).await
+}
+
+// PTY output handler without duplicate check (assumes caller already registered)
+async fn handle_pty_output_no_check(app: tauri::AppHandle, session_id: String) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    println!("[DEBUG] Starting PTY output handler (no duplicate check) for session: {}", session_id);
+    
+    let sessions = TERMINAL_SESSIONS.read().await;
+    let session = sessions.get(&session_id).ok_or("Session not found")?;
+    let pty_master = session.pty_master.clone();
+    let scrollback = session.scrollback.clone();
+    let last_activity = session.last_activity.clone();
+    let recording = session.recording.clone();
+    let bytes_read = session.bytes_read.clone();
+    drop(sessions);
+    
+    // Take the reader once and do blocking reads on a dedicated thread,
+    // handing chunks to the async side as they arrive
+    let mut reader = pty_master.lock().await.try_clone_reader()
+        .map_err(|e| format!("Failed to clone PTY reader: {}", e))?;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Vec<u8>>();
+    let reader_session_id = session_id.clone();
+    std::thread::spawn(move || {
+        let mut buffer = [0u8; 8192];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => {
+                    println!("[DEBUG] PTY EOF for session: {}", reader_session_id);
+                    break;
+                }
+                Ok(n) => {
+                    if tx.send(buffer[..n].to_vec()).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("[ERROR] Failed to read from PTY for session {}: {}", reader_session_id, e);
+                    break;
+                }
             }
-        },
-        "python" => {
-            // Create Python project structure
-            let dirs = ["src", "tests", "docs"];
-            for dir in &dirs {
-                let dir_path = std::path::Path::new(project_path).join(dir);
-                std::fs::create_dir_all(&dir_path)
-                    .map_err(|e| format!("Failed to create directory {}: {}", dir, e))?;
+        }
+    });
+    
+    // Bytes of a UTF-8 character split across two reads
+    let mut pending = Vec::new();
+    let mut parser = PtyOutputParser::default();
+    let mut reader_done = false;
+    
+    while !reader_done {
+        match rx.recv().await {
+            Some(chunk) => pending.extend_from_slice(&chunk),
+            // Still flush whatever is left over below
+            None => reader_done = true,
+        }
+        
+        // Fast output arrives in many tiny reads; gather what comes in within a short
+        // window (or until the batch is large) so the webview gets one event per frame
+        let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_millis(PTY_COALESCE_WINDOW_MS);
+        while !reader_done && pending.len() < PTY_COALESCE_MAX_BYTES {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(chunk)) => pending.extend_from_slice(&chunk),
+                Ok(None) => {
+                    reader_done = true;
+                    break;
+                }
+                Err(_) => break,
             }
+        }
+        
+        // Hold back a character cut off at the end of the read, unless no more bytes are
+        // coming; then it's decoded lossily rather than dropped
+        let valid_up_to = match std::str::from_utf8(&pending) {
+            Err(e) if e.error_len().is_none() && !reader_done => e.valid_up_to(),
+            _ => pending.len(),
+        };
+        let bytes: Vec<u8> = pending.drain(..valid_up_to).collect();
+        if bytes.is_empty() {
+            continue;
+        }
+        bytes_read.fetch_add(bytes.len() as u64, std::sync::atomic::Ordering::Relaxed);
+        let data = String::from_utf8_lossy(&bytes);
+        
+        // Parse complete, escape-free lines for JSON events (including TodoWrite)
+        let mut saw_todo_list = false;
+        for line in parser.feed(&data) {
+            let line_trimmed = line.trim();
             
-            // Create requirements.txt
-            let requirements_path = std::path::Path::new(project_path).join("requirements.txt");
-            std::fs::write(&requirements_path, "# Add your dependencies here\n")
-                .map_err(|e| format!("Failed to create requirements.txt: {}", e))?;
-        },
-        "node" => {
-            // Initialize npm project
-            let output = Command::new("npm")
-                .args(["init", "-y"])
-                .current_dir(project_path)
-                .output()
-                .map_err(|e| format!("Failed to initialize npm project: {}", e))?;
+            // Debug: Log any line that mentions todos or TodoWrite
+            if line_trimmed.to_lowercase().contains("todo") {
+                println!("[DEBUG] Found todo-related line in session {}: {}", session_id, line_trimmed);
+            }
             
-            if !output.status.success() {
-                return Err(String::from_utf8_lossy(&output.stderr).to_string());
+            // Check if this line contains TodoWrite JSON
+            if line_trimmed.contains("TodoWrite") && line_trimmed.contains("tool_use") {
+                println!("[DEBUG] Detected TodoWrite tool usage in session {}", session_id);
+                if let Err(e) = handle_todowrite_in_terminal(&app, &session_id, line_trimmed).await {
+                    println!("[ERROR] Failed to handle TodoWrite in terminal session {}: {}", session_id, e);
+                } else {
+                    println!("[SUCCESS] Successfully processed TodoWrite in terminal session {}", session_id);
+                }
             }
-        },
-        "rust" => {
-            // Create Rust project with Cargo
-            let output = Command::new("cargo")
-                .args(["init", ".", "--name", &options.project_name])
-                .current_dir(project_path)
-                .output()
-                .map_err(|e| format!("Failed to create Rust project: {}", e))?;
             
-            if !output.status.success() {
-                return Err(String::from_utf8_lossy(&output.stderr).to_string());
+            // Also check for human-readable todo format from Claude
+            if line_trimmed.contains("Update Todos") || line_trimmed.starts_with("☐ ") {
+                saw_todo_list = true;
+            }
+        }
+        
+        // The list usually spans several reads, so parse it from the recent lines
+        if saw_todo_list {
+            if let Err(e) = handle_human_readable_todos(&app, &session_id, &parser.recent_text()).await {
+                println!("[ERROR] Failed to handle human-readable todos in session {}: {}", session_id, e);
             }
-        },
-        _ => {
-            // Empty project or custom - just create basic structure
         }
+        
+        scrollback.lock().await.push(&data);
+        last_activity.store(now_millis(), std::sync::atomic::Ordering::Relaxed);
+        if let Some(recorder) = recording.lock().await.as_mut() {
+            recorder.record("o", &data);
+        }
+        
+        // Emit data to frontend
+        let _ = app.emit("terminal_output", serde_json::json!({
+            "sessionId": session_id,
+            "data": data.to_string()
+        }));
     }
     
-    // Create CLAUDE.md template if requested
-    if options.create_claude {
-        let claude_md_path = std::path::Path::new(project_path).join("CLAUDE.md");
-        let template = format!(r#"# {} - Claude Instructions
-
-## Project Overview
-Brief description of what this project does and its main purpose.
-
-## Development Guidelines
-- Coding standards and conventions to follow
-- Preferred libraries and frameworks
-- Architecture patterns to maintain
-
-## Key Files and Directories
-- `src/` - Main source code
-- `tests/` - Test files
-- `docs/` - Documentation
-
-## Project Type
-This is a {} project.
-
-## Important Notes
-- Any specific requirements or constraints
-- Known issues or gotchas
-- Deployment considerations
-
-## Testing
-- How to run tests
-- Test coverage expectations
-- Any special testing requirements
+    // Remove from active handlers when done
+    {
+        let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
+        handlers.remove(&session_id);
+        println!("[DEBUG] Removed session {} from active handlers", session_id);
+    }
+    
+    println!("[DEBUG] PTY output handler (no check) ended for session: {}", session_id);
+    Ok(())
+}
 
-## Build & Deployment
-- Build commands
-- Environment setup
-- Deployment process
-"#, options.project_name, options.project_type);
-        
-        std::fs::write(&claude_md_path, template)
-            .map_err(|e| format!("Failed to create CLAUDE.md: {}", e))?;
+// Human-readable todo parsing
+async fn handle_human_readable_todos(
+    app: &tauri::AppHandle,
+    session_id: &str,
+    terminal_data: &str
+) -> Result<(), AppError> {
+    // Check if this looks like a todo update section
+    if !terminal_data.contains("Update Todos") {
+        return Ok(());
     }
     
-    // Execute claude --project to register the project
-    let claude_output = Command::new("claude")
-        .args(["--project", project_path])
-        .output()
-        .map_err(|e| format!("Failed to execute claude command: {}", e))?;
+    let mut todos = Vec::new();
+    let mut todo_counter = 1;
+    let mut in_block = false;
     
-    if !claude_output.status.success() {
-        eprintln!("Warning: Failed to register project with Claude");
+    // Parse todo items from the block following the "Update Todos" header only, so
+    // unrelated output mixed into the same chunk doesn't leak into the list
+    for line in terminal_data.lines() {
+        let line = line.trim();
+        
+        // A later header means the list was redrawn; the newest one wins
+        if line.contains("Update Todos") {
+            in_block = true;
+            todos.clear();
+            todo_counter = 1;
+            continue;
+        }
+        if !in_block {
+            continue;
+        }
+        
+        // Look for todo items starting with ☐ 
+        if line.starts_with("☐ ") || line.contains("☐ ") {
+            let content = line
+                .replace("☐ ", "")
+                .replace("     ", "")
+                .trim()
+                .to_string();
+            
+            if !content.is_empty() && content.len() > 10 { // Filter out very short items
+                let todo = Todo {
+                    id: format!("human-{}-{}", session_id, todo_counter),
+                    content,
+                    status: "pending".to_string(),
+                    priority: "medium".to_string(),
+                    created_at: chrono::Utc::now().to_rfc3339(),
+                    session_id: Some(session_id.to_string()),
+                };
+                todos.push(todo);
+                todo_counter += 1;
+            }
+        } else if !line.is_empty() && !line.starts_with('☒') && !todos.is_empty() {
+            // First line that isn't part of the list ends the block
+            in_block = false;
+        }
     }
     
-    // Open in IDE if requested
-    if options.open_in_ide {
-        if let Some(ide_command) = options.selected_ide {
-            let _ide_output = Command::new(&ide_command)
-                .arg(project_path)
-                .spawn();
-            // Don't fail if IDE opening fails
+    // Claude redraws the same list many times; skip it if this session already saw it
+    let block_hash = {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        for todo in &todos {
+            todo.content.hash(&mut hasher);
+        }
+        hasher.finish()
+    };
+    {
+        let sessions = TERMINAL_SESSIONS.read().await;
+        if let Some(session) = sessions.get(session_id) {
+            if session.last_todo_hash.swap(block_hash, std::sync::atomic::Ordering::Relaxed) == block_hash {
+                return Ok(());
+            }
         }
     }
     
-    Ok(format!("Project '{}' created successfully at {}", options.project_name, project_path))
-}
-
-#[tauri::command]
-async fn create_new_project(project_path: String) -> Result<String, String> {
-    // Execute claude --project /path/to/project to create a new project
-    let output = Command::new("claude")
-        .args(["--project", &project_path])
-        .output()
-        .map_err(|e| format!("Failed to execute claude command: {}", e))?;
+    println!("[INFO] Processing human-readable todos from session: {}", session_id);
     
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
+    if !todos.is_empty() {
+        println!("[INFO] Parsed {} human-readable todos", todos.len());
+        
+        // Get project path and save todos
+        let project_path = get_session_project_path(session_id).await?;
+        
+        // Save the todos directly to the project directory (bypass get_real_project_path)
+        if let Err(e) = save_todos_directly(&project_path, todos.clone()).await {
+            println!("[ERROR] Failed to save human-readable todos: {}", e);
+            return Err(e);
+        }
+        
+        // Emit update event
+        let _ = app.emit("todos_updated", serde_json::json!({
+            "projectPath": project_path,
+            "sessionId": session_id,
+            "todos": todos
+        }));
+        
+        println!("[SUCCESS] Successfully processed {} human-readable todos", todos.len());
     }
+    
+    Ok(())
 }
 
-#[tauri::command]
-async fn read_conversation_file(file_path: String) -> Result<Vec<ChatMessage>, String> {
-    let content = std::fs::read_to_string(&file_path)
-        .map_err(|e| format!("Failed to read file {}: {}", file_path, e))?;
-    
-    let mut messages = Vec::new();
+// TodoWrite tool handling
+async fn handle_todowrite_in_terminal(
+    app: &tauri::AppHandle,
+    session_id: &str,
+    json_line: &str
+) -> Result<(), AppError> {
+    println!("[INFO] Processing TodoWrite from terminal session: {}", session_id);
+    println!("[DEBUG] JSON line: {}", json_line);
     
-    for line in content.lines() {
-        if line.trim().is_empty() {
-            continue;
-        }
-        
-        match serde_json::from_str::<serde_json::Value>(line) {
-            Ok(json) => {
-                // Handle different Claude Code message formats
-                let mut role = "unknown".to_string();
-                let mut content = String::new();
-                let timestamp = json.get("timestamp")
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("")
-                    .to_string();
-
-                // Check if this is a user message
-                if json.get("type").and_then(|t| t.as_str()) == Some("user") {
-                    role = "user".to_string();
-                    if let Some(message) = json.get("message") {
-                        if let Some(content_str) = message.get("content").and_then(|c| c.as_str()) {
-                            content = content_str.to_string();
-                        }
-                    }
-                }
-                // Check if this is an assistant message
-                else if json.get("type").and_then(|t| t.as_str()) == Some("assistant") {
-                    role = "assistant".to_string();
-                    if let Some(message) = json.get("message") {
-                        // Handle content array format
-                        if let Some(content_array) = message.get("content").and_then(|c| c.as_array()) {
-                            for content_item in content_array {
-                                if let Some(text) = content_item.get("text").and_then(|t| t.as_str()) {
-                                    if !content.is_empty() {
-                                        content.push('\n');
+    // Parse the JSON line to extract TodoWrite data
+    if let Ok(claude_event) = serde_json::from_str::<ClaudeJsonEvent>(json_line) {
+        println!("[DEBUG] Successfully parsed Claude event: {}", claude_event.event_type);
+        if claude_event.event_type == "message_stream" {
+            if let Some(message) = &claude_event.message {
+                // Parse message content to extract tool usage
+                if let Some(content_array) = message.content_blocks() {
+                    for item in &content_array {
+                        if let Some(item_type) = item.get("type").and_then(|t| t.as_str()) {
+                            if item_type == "tool_use" {
+                                if let (Some(name), Some(input)) = (
+                                    item.get("name").and_then(|n| n.as_str()),
+                                    item.get("input")
+                                ) {
+                                    if name == "TodoWrite" {
+                                        if let Some(todos_data) = input.get("todos") {
+                                            // Get project path from session
+                                            let project_path = get_session_project_path(session_id).await?;
+                                            
+                                            // Process the todos
+                                            return handle_todowrite_tool(app, &project_path, session_id, todos_data).await;
+                                        }
                                     }
-                                    content.push_str(text);
                                 }
                             }
                         }
-                        // Handle direct string content
-                        else if let Some(content_str) = message.get("content").and_then(|c| c.as_str()) {
-                            content = content_str.to_string();
-                        }
-                    }
-                }
-                // Fallback for other message formats
-                else if let Some(message) = json.get("message") {
-                    if let Some(role_str) = message.get("role").and_then(|r| r.as_str()) {
-                        role = role_str.to_string();
-                    }
-                    
-                    if let Some(content_str) = message.get("content").and_then(|c| c.as_str()) {
-                        content = content_str.to_string();
                     }
                 }
-
-                // Only add messages that have actual content
-                if !content.trim().is_empty() && role != "unknown" {
-                    messages.push(ChatMessage {
-                        role,
-                        content,
-                        timestamp,
-                    });
-                }
             }
-            Err(_) => continue,
         }
+    } else {
+        println!("[DEBUG] Failed to parse JSON line as ClaudeJsonEvent: {}", json_line);
     }
     
-    Ok(messages)
+    Ok(())
 }
 
-async fn verify_claude_health(session_id: &str) -> bool {
-    if let Ok(sessions) = TERMINAL_SESSIONS.try_read() {
-        if let Some(session) = sessions.get(session_id) {
-            // Check if child process is still alive
-            if let Ok(mut child_guard) = session.child_process.try_lock() {
-                match child_guard.try_wait() {
-                    Ok(Some(_)) => {
-                        println!("[HEALTH] Session {} process has exited", session_id);
-                        return false;
-                    }
-                    Ok(None) => {
-                        println!("[HEALTH] Session {} process is still running", session_id);
-                        return true;
-                    }
-                    Err(e) => {
-                        println!("[HEALTH] Session {} process check failed: {}", session_id, e);
-                        return false;
-                    }
-                }
-            }
-        }
+async fn save_todos_directly(project_path: &str, todos: Vec<Todo>) -> Result<(), AppError> {
+    // Create todos file path directly without resolving through get_real_project_path
+    let todos_file_path = format!("{}/.claude-todos.json", project_path);
+    
+    println!("[DEBUG] Saving todos directly to: {}", todos_file_path);
+    
+    // Ensure directory exists
+    let project_dir = std::path::Path::new(project_path);
+    if !project_dir.exists() {
+        return Err(AppError::PathNotFound(format!("Project directory does not exist: {}", project_path)));
     }
-    false
-}
-
-#[tauri::command]
-async fn start_claude_session(app: tauri::AppHandle, project_path: String) -> Result<String, String> {
-    let session_id = Uuid::new_v4().to_string();
-    println!("[INFO] Starting new Claude session: {}", session_id);
     
-    // Get the real project path for the working directory
-    let working_dir = match get_real_project_path(project_path.clone()).await? {
-        Some(real_path) => real_path,
-        None => {
-            return Err("Could not find real project path".to_string());
+    // Load existing todos
+    let mut all_todos = if std::path::Path::new(&todos_file_path).exists() {
+        match std::fs::read_to_string(&todos_file_path) {
+            Ok(content) => {
+                serde_json::from_str::<Vec<Todo>>(&content).unwrap_or_else(|_| Vec::new())
+            }
+            Err(_) => Vec::new()
         }
+    } else {
+        Vec::new()
     };
-
-    // Create PTY system
-    let pty_system = native_pty_system();
     
-    // Create PTY with appropriate size
-    let pty_pair = pty_system
-        .openpty(PtySize {
-            rows: 24,
-            cols: 80,
-            pixel_width: 0,
-            pixel_height: 0,
-        })
-        .map_err(|e| format!("Failed to create PTY: {}", e))?;
-
-    // Set up Claude command
-    let mut cmd = CommandBuilder::new("claude");
-    cmd.cwd(&working_dir);
-    println!("[DEBUG] Starting Claude in directory: {}", working_dir);
+    // Add new todos (replace any with matching IDs)
+    for new_todo in todos {
+        // Remove any existing todo with the same ID
+        all_todos.retain(|existing| existing.id != new_todo.id);
+        // Add the new todo
+        all_todos.push(new_todo);
+    }
     
-    // Start the child process
-    let child = pty_pair
-        .slave
-        .spawn_command(cmd)
-        .map_err(|e| format!("Failed to spawn Claude process: {}", e))?;
-
-    // Get the writer ONCE and store it permanently
-    println!("[DEBUG] Getting PTY writer for session: {}", session_id);
-    let writer = pty_pair.master.take_writer()
-        .map_err(|e| {
-            let error_msg = format!("Failed to get PTY writer: {}", e);
-            println!("[ERROR] {}", error_msg);
-            error_msg
-        })?;
-    println!("[DEBUG] Successfully got PTY writer");
-        
-    // Create session with separate writer storage
-    let session = TerminalSession {
-        id: session_id.clone(),
-        pty_master: Arc::new(Mutex::new(pty_pair.master)),
-        pty_writer: Arc::new(Mutex::new(writer)),
-        child_process: Arc::new(Mutex::new(child)),
-        project_path: working_dir,
-        active: true,
-    };
+    // Save back to file
+    let json_content = serde_json::to_string_pretty(&all_todos)
+        .map_err(|e| AppError::parse("Failed to serialize todos", e))?;
+    
+    std::fs::write(&todos_file_path, json_content)
+        .map_err(|e| AppError::io("Failed to write todos file", e))?;
+    
+    println!("[INFO] Successfully saved {} todos to {}", all_todos.len(), todos_file_path);
+    Ok(())
+}
 
-    // Store session
-    {
-        let mut sessions = TERMINAL_SESSIONS.write().await;
-        println!("[DEBUG] Storing session with ID: {}", session_id);
-        sessions.insert(session_id.clone(), session);
-        println!("[DEBUG] Session stored. Total sessions: {}", sessions.len());
+async fn get_session_project_path(session_id: &str) -> Result<String, AppError> {
+    let sessions = TERMINAL_SESSIONS.read().await;
+    if let Some(session) = sessions.get(session_id) {
+        println!("[DEBUG] Found session project path: {}", session.project_path);
+        Ok(session.project_path.clone())
+    } else {
+        println!("[ERROR] Session {} not found in terminal sessions", session_id);
+        Err(AppError::SessionDead(format!("Session {} not found", session_id)))
     }
+}
 
-    // Start reading from PTY and sending output to frontend (only if not already running)
-    {
-        let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
-        if !handlers.contains(&session_id) {
-            // Reserve the handler slot immediately to prevent race conditions
-            handlers.insert(session_id.clone());
-            let session_id_clone = session_id.clone();
-            let session_id_for_cleanup = session_id.clone();
-            let app_clone = app.clone();
-            tokio::spawn(async move {
-                if let Err(e) = handle_pty_output_no_check(app_clone, session_id_clone).await {
-                    eprintln!("PTY output handler error: {}", e);
-                    // Remove from handlers on error
-                    let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
-                    handlers.remove(&session_id_for_cleanup);
-                }
-            });
-            println!("[DEBUG] Spawned new PTY handler for session: {}", session_id);
-        } else {
-            println!("[DEBUG] PTY handler already exists for session: {}", session_id);
+async fn handle_todowrite_tool(
+    app: &tauri::AppHandle,
+    project_path: &str,
+    session_id: &str,
+    todos_data: &serde_json::Value
+) -> Result<(), AppError> {
+    println!("[INFO] Processing TodoWrite tool for session: {}", session_id);
+    
+    if let Some(todos_array) = todos_data.as_array() {
+        let mut parsed_todos = Vec::new();
+        
+        for todo_item in todos_array {
+            if let (Some(content), Some(status), Some(priority), Some(id)) = (
+                todo_item.get("content").and_then(|c| c.as_str()),
+                todo_item.get("status").and_then(|s| s.as_str()),
+                todo_item.get("priority").and_then(|p| p.as_str()),
+                todo_item.get("id").and_then(|i| i.as_str())
+            ) {
+                let todo = Todo {
+                    id: id.to_string(),
+                    content: content.to_string(),
+                    status: status.to_string(),
+                    priority: priority.to_string(),
+                    created_at: chrono::Utc::now().to_rfc3339(),
+                    session_id: Some(session_id.to_string()),
+                };
+                parsed_todos.push(todo);
+            }
+        }
+        
+        // Save the todos
+        if let Err(e) = save_project_todos(project_path.to_string(), parsed_todos.clone()).await {
+            println!("[ERROR] Failed to save todos from TodoWrite: {}", e);
+            return Err(e);
         }
+        
+        // Emit event for real-time UI update
+        let _ = app.emit("todos_updated", serde_json::json!({
+            "sessionId": session_id,
+            "projectPath": project_path,
+            "todos": parsed_todos
+        }));
+        
+        println!("[INFO] Successfully processed {} todos from TodoWrite", parsed_todos.len());
     }
+    
+    Ok(())
+}
 
-    Ok(session_id)
+// Todo management functions
+async fn get_todos_file_path(project_path: String) -> Result<String, AppError> {
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    
+    Ok(format!("{}/.claude-todos.json", real_path))
 }
 
 #[tauri::command]
-async fn resume_claude_session(app: tauri::AppHandle, session_id: String, project_path: String) -> Result<String, String> {
-    println!("[INFO] Resume request for session: {}", session_id);
+async fn load_project_todos(project_path: String) -> Result<Vec<Todo>, AppError> {
+    println!("[DEBUG] load_project_todos called with path: {}", project_path);
     
-    // Check if session already exists and is healthy
-    {
-        let sessions = TERMINAL_SESSIONS.read().await;
-        if sessions.contains_key(&session_id) {
-            println!("[DEBUG] Session {} already exists, verifying health", session_id);
-            if verify_claude_health(&session_id).await {
-                println!("[DEBUG] Session {} is healthy, returning existing session", session_id);
-                return Ok(session_id);
+    // Try multiple possible locations for the todos file
+    let possible_paths = vec![
+        format!("{}/.claude-todos.json", project_path),
+        // If the project_path contains the transformed path, try to extract the real path
+        if project_path.contains("/.claude/projects/") {
+            // Extract real path from transformed path like: /home/user/.claude/projects/-home-user-repos-project
+            let parts: Vec<&str> = project_path.split("/.claude/projects/").collect();
+            if parts.len() == 2 {
+                let encoded_path = parts[1];
+                let real_path = encoded_path.replace("-", "/");
+                format!("{}/.claude-todos.json", real_path)
             } else {
-                println!("[DEBUG] Session {} is not healthy, will recreate", session_id);
-                // Don't return early - let it recreate the session
+                project_path.clone()
             }
+        } else {
+            project_path.clone()
         }
-    }
+    ];
     
-    // Clean up any existing unhealthy session
-    {
-        let mut sessions = TERMINAL_SESSIONS.write().await;
-        if let Some(old_session) = sessions.remove(&session_id) {
-            println!("[DEBUG] Removing unhealthy session and terminating process: {}", session_id);
+    for todos_file in possible_paths {
+        println!("[DEBUG] Trying to load todos from: {}", todos_file);
+        
+        if std::path::Path::new(&todos_file).exists() {
+            println!("[DEBUG] Found todos file at: {}", todos_file);
             
-            // Terminate the old Claude process
-            if let Ok(mut child) = old_session.child_process.try_lock() {
-                match child.kill() {
-                    Ok(_) => println!("[DEBUG] Successfully killed old Claude process for session: {}", session_id),
-                    Err(e) => println!("[WARN] Failed to kill old Claude process for session {}: {}", session_id, e)
-                }
-            } else {
-                println!("[WARN] Could not acquire lock on old Claude process for session: {}", session_id);
+            let content = std::fs::read_to_string(&todos_file)
+                .map_err(|e| AppError::io("Failed to read todos file", e))?;
+            
+            // Try to parse as direct Vec<Todo> first (new format)
+            if let Ok(todos) = serde_json::from_str::<Vec<Todo>>(&content) {
+                println!("[DEBUG] Loaded {} todos directly", todos.len());
+                return Ok(todos);
             }
             
-            // Remove from active handlers
-            {
-                let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
-                handlers.remove(&session_id);
-                println!("[DEBUG] Removed old session {} from active handlers during cleanup", session_id);
+            // Fallback to old ProjectTodos format
+            if let Ok(project_todos) = serde_json::from_str::<ProjectTodos>(&content) {
+                println!("[DEBUG] Loaded {} todos from ProjectTodos format", project_todos.todos.len());
+                return Ok(project_todos.todos);
             }
+            
+            return Err(AppError::Parse("Failed to parse todos file in any known format".to_string()));
         }
     }
     
-    // Get the real project path for the working directory
-    let working_dir = match get_real_project_path(project_path.clone()).await? {
-        Some(real_path) => real_path,
-        None => {
-            return Err("Could not find real project path".to_string());
-        }
-    };
-
-    // Create PTY system
-    let pty_system = native_pty_system();
-    
-    // Create PTY with appropriate size
-    let pty_pair = pty_system
-        .openpty(PtySize {
-            rows: 24,
-            cols: 80,
-            pixel_width: 0,
-            pixel_height: 0,
-        })
-        .map_err(|e| format!("Failed to create PTY: {}", e))?;
+    println!("[DEBUG] No todos file found in any of the attempted locations");
+    Ok(vec![])
+}
 
-    // Set up Claude command with resume flag
-    let mut cmd = CommandBuilder::new("claude");
-    cmd.cwd(&working_dir);
-    cmd.arg("--resume");
-    cmd.arg(&session_id);
-    println!("[DEBUG] Starting Claude with resume for session {} in directory: {}", session_id, working_dir);
+#[tauri::command]
+async fn save_project_todos(project_path: String, todos: Vec<Todo>) -> Result<(), AppError> {
+    let todos_file = get_todos_file_path(project_path).await?;
     
-    // Start the child process
-    let child = pty_pair
-        .slave
-        .spawn_command(cmd)
-        .map_err(|e| format!("Failed to spawn Claude process: {}", e))?;
-
-    // Get the writer ONCE and store it permanently
-    println!("[DEBUG] Getting PTY writer for session: {}", session_id);
-    let writer = pty_pair.master.take_writer()
-        .map_err(|e| {
-            let error_msg = format!("Failed to get PTY writer: {}", e);
-            println!("[ERROR] {}", error_msg);
-            error_msg
-        })?;
-    println!("[DEBUG] Successfully got PTY writer");
-        
-    // Create session with separate writer storage
-    let session = TerminalSession {
-        id: session_id.clone(),
-        pty_master: Arc::new(Mutex::new(pty_pair.master)),
-        pty_writer: Arc::new(Mutex::new(writer)),
-        child_process: Arc::new(Mutex::new(child)),
-        project_path: working_dir,
-        active: true,
+    let project_todos = ProjectTodos {
+        todos,
+        last_updated: chrono::Utc::now().to_rfc3339(),
     };
-
-    // Store session
-    {
-        let mut sessions = TERMINAL_SESSIONS.write().await;
-        println!("[DEBUG] Storing session with ID: {}", session_id);
-        sessions.insert(session_id.clone(), session);
-        println!("[DEBUG] Session stored. Total sessions: {}", sessions.len());
-    }
-
-    // Start reading from PTY and sending output to frontend (only if not already running)
-    {
-        let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
-        if !handlers.contains(&session_id) {
-            // Reserve the handler slot immediately to prevent race conditions
-            handlers.insert(session_id.clone());
-            let session_id_clone = session_id.clone();
-            let session_id_for_cleanup = session_id.clone();
-            let app_clone = app.clone();
-            tokio::spawn(async move {
-                if let Err(e) = handle_pty_output_no_check(app_clone, session_id_clone).await {
-                    eprintln!("PTY output handler error: {}", e);
-                    // Remove from handlers on error
-                    let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
-                    handlers.remove(&session_id_for_cleanup);
-                }
-            });
-            println!("[DEBUG] Spawned new PTY handler for session: {}", session_id);
-        } else {
-            println!("[DEBUG] PTY handler already exists for session: {}", session_id);
-        }
-    }
-
-    Ok(session_id)
+    
+    let content = serde_json::to_string_pretty(&project_todos)
+        .map_err(|e| AppError::parse("Failed to serialize todos", e))?;
+    
+    std::fs::write(&todos_file, content)
+        .map_err(|e| AppError::io("Failed to write todos file", e))?;
+    
+    Ok(())
 }
 
 #[tauri::command]
-async fn write_to_terminal(session_id: String, data: String) -> Result<(), String> {
-    println!("[DEBUG] Writing to terminal session: {} (data length: {})", session_id, data.len());
+async fn add_todo(
+    project_path: String, 
+    content: String, 
+    priority: String,
+    session_id: Option<String>
+) -> Result<Todo, AppError> {
+    let mut todos = load_project_todos(project_path.clone()).await?;
     
-    // First check if the session is healthy
-    if !verify_claude_health(&session_id).await {
-        let error_msg = format!("Session {} is not healthy or has exited", session_id);
-        println!("[ERROR] {}", error_msg);
-        return Err(error_msg);
-    }
+    let new_todo = Todo {
+        id: Uuid::new_v4().to_string(),
+        content,
+        status: "pending".to_string(),
+        priority,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        session_id,
+    };
     
-    let sessions = TERMINAL_SESSIONS.read().await;
+    todos.push(new_todo.clone());
+    save_project_todos(project_path, todos).await?;
     
-    if let Some(session) = sessions.get(&session_id) {
-        let mut writer_guard = session.pty_writer.lock().await;
-        
-        match writer_guard.write_all(data.as_bytes()) {
-            Ok(_) => {
-                match writer_guard.flush() {
-                    Ok(_) => {
-                        println!("[DEBUG] Successfully wrote and flushed data to session: {}", session_id);
-                        Ok(())
-                    }
-                    Err(e) => {
-                        let error_msg = format!("Failed to flush terminal {}: {}", session_id, e);
-                        println!("[ERROR] {}", error_msg);
-                        Err(error_msg)
-                    }
-                }
-            }
-            Err(e) => {
-                let error_msg = format!("Failed to write to terminal {}: {}", session_id, e);
-                println!("[ERROR] {}", error_msg);
-                Err(error_msg)
-            }
-        }
-    } else {
-        let error_msg = format!("Session {} not found. Available sessions: {:?}", session_id, sessions.keys().collect::<Vec<_>>());
-        println!("[ERROR] {}", error_msg);
-        Err(error_msg)
-    }
+    Ok(new_todo)
 }
 
 #[tauri::command]
-async fn resize_terminal(session_id: String, rows: u16, cols: u16) -> Result<(), String> {
-    let sessions = TERMINAL_SESSIONS.read().await;
+async fn update_todo_status(
+    project_path: String, 
+    todo_id: String, 
+    new_status: String
+) -> Result<(), AppError> {
+    let mut todos = load_project_todos(project_path.clone()).await?;
     
-    if let Some(session) = sessions.get(&session_id) {
-        let pty_master = session.pty_master.lock().await;
-        pty_master
-            .resize(PtySize {
-                rows,
-                cols,
-                pixel_width: 0,
-                pixel_height: 0,
-            })
-            .map_err(|e| format!("Failed to resize terminal: {}", e))?;
+    if let Some(todo) = todos.iter_mut().find(|t| t.id == todo_id) {
+        todo.status = new_status;
+        save_project_todos(project_path, todos).await?;
         Ok(())
     } else {
-        Err("Session not found".to_string())
+        Err(AppError::NotFound("Todo not found".to_string()))
     }
 }
 
 #[tauri::command]
-async fn close_terminal_session(session_id: String) -> Result<(), String> {
-    println!("[INFO] Closing terminal session: {}", session_id);
-    let mut sessions = TERMINAL_SESSIONS.write().await;
-    
-    if let Some(session) = sessions.remove(&session_id) {
-        println!("[DEBUG] Found session to close: {}", session_id);
-        
-        // Gracefully terminate the child process
-        if let Ok(mut child) = session.child_process.try_lock() {
-            match child.kill() {
-                Ok(_) => println!("[DEBUG] Successfully killed child process for session: {}", session_id),
-                Err(e) => println!("[WARN] Failed to kill child process for session {}: {}", session_id, e)
-            }
-        } else {
-            println!("[WARN] Could not acquire lock on child process for session: {}", session_id);
-        }
-        
-        println!("[INFO] Session {} closed successfully. Remaining sessions: {}", session_id, sessions.len());
-        Ok(())
-    } else {
-        let error_msg = format!("Session {} not found. Available sessions: {:?}", session_id, sessions.keys().collect::<Vec<_>>());
-        println!("[ERROR] {}", error_msg);
-        Err(error_msg)
-    }
+async fn delete_todo(project_path: String, todo_id: String) -> Result<(), AppError> {
+    let mut todos = load_project_todos(project_path.clone()).await?;
+    todos.retain(|t| t.id != todo_id);
+    save_project_todos(project_path, todos).await?;
+    Ok(())
 }
 
-async fn handle_pty_output(app: tauri::AppHandle, session_id: String) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    println!("[DEBUG] Starting PTY output handler for session: {}", session_id);
+// App-wide GUI settings, stored in ~/.claude/gui-app-settings.json
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct AppGuiSettings {
+    // Explicit claude binary, for installs discovery can't find
+    #[serde(default)]
+    claude_binary_path: Option<String>,
+    // Context window sizes keyed by (part of) a model name, overriding the built-in defaults
+    #[serde(default)]
+    context_window_sizes: HashMap<String, u64>,
+    // Close terminal sessions idle for this many minutes; unset disables the cleanup
+    #[serde(default)]
+    terminal_idle_timeout_minutes: Option<u64>,
+    // Write rate for pastes and other large terminal input
+    #[serde(default)]
+    terminal_paste_bytes_per_sec: Option<u64>,
+    // Run Claude terminal sessions inside tmux so they outlive the app
+    #[serde(default)]
+    tmux_sessions: bool,
+    // Per-model prices keyed by (part of) a model name, overriding the built-in table
+    #[serde(default)]
+    model_pricing: HashMap<String, ModelPricing>,
+    #[serde(default)]
+    usage_budgets: Vec<UsageBudget>,
+    // Directories outside any project that the file commands may still modify
+    #[serde(default)]
+    granted_paths: Vec<String>,
+}
+
+fn get_app_settings_file_path() -> Result<std::path::PathBuf, AppError> {
+    let home_dir = dirs::home_dir().ok_or(AppError::PathNotFound("Could not find home directory".to_string()))?;
+    Ok(home_dir.join(".claude").join("gui-app-settings.json"))
+}
+
+fn read_app_settings() -> Result<AppGuiSettings, AppError> {
+    let settings_file = get_app_settings_file_path()?;
     
-    // Check if output handler is already running for this session
-    {
-        let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
-        if handlers.contains(&session_id) {
-            println!("[WARN] Output handler already running for session {}, skipping", session_id);
-            return Ok(());
-        }
-        handlers.insert(session_id.clone());
+    if !settings_file.exists() {
+        return Ok(AppGuiSettings::default());
     }
     
-    let sessions = TERMINAL_SESSIONS.read().await;
-    let session = sessions.get(&session_id).ok_or("Session not found")?;
-    let pty_master = session.pty_master.clone();
-    drop(sessions);
+    let content = std::fs::read_to_string(&settings_file)
+        .map_err(|e| AppError::io("Failed to read app settings", e))?;
+    
+    serde_json::from_str(&content)
+        .map_err(|e| AppError::parse("Failed to parse app settings", e))
+}
 
-    let mut buffer = [0u8; 8192];
+fn write_app_settings(settings: &AppGuiSettings) -> Result<(), AppError> {
+    let settings_file = get_app_settings_file_path()?;
     
-    loop {
-        let pty = pty_master.lock().await;
-        match pty.try_clone_reader() {
-            Ok(mut reader) => {
-                drop(pty); // Release the lock before blocking read
-                
-                match reader.read(&mut buffer) {
-                    Ok(0) => {
-                        println!("[DEBUG] PTY EOF for session: {}", session_id);
-                        break; // EOF
-                    }
-                    Ok(n) => {
-                        let data = String::from_utf8_lossy(&buffer[..n]);
-                        
-                        // Parse for JSON events (including TodoWrite)
-                        let lines: Vec<&str> = data.lines().collect();
-                        for line in lines {
-                            let line_trimmed = line.trim();
-                            
-                            // Debug: Log any line that mentions todos or TodoWrite
-                            if line_trimmed.to_lowercase().contains("todo") {
-                                println!("[DEBUG] Found todo-related line in session {}: {}", session_id, line_trimmed);
-                            }
-                            
-                            // Check if this line contains TodoWrite JSON
-                            if line_trimmed.contains("TodoWrite") && line_trimmed.contains("tool_use") {
-                                println!("[DEBUG] Detected TodoWrite tool usage in session {}", session_id);
-                                if let Err(e) = handle_todowrite_in_terminal(&app, &session_id, line_trimmed).await {
-                                    println!("[ERROR] Failed to handle TodoWrite in terminal session {}: {}", session_id, e);
-                                } else {
-                                    println!("[SUCCESS] Successfully processed TodoWrite in terminal session {}", session_id);
-                                }
-                            }
-                            
-                            // Also check for human-readable todo format from Claude
-                            if line_trimmed.contains("Update Todos") || line_trimmed.starts_with("     ☐ ") {
-                                if let Err(e) = handle_human_readable_todos(&app, &session_id, &data).await {
-                                    println!("[ERROR] Failed to handle human-readable todos in session {}: {}", session_id, e);
-                                }
-                            }
-                        }
-                        
-                        let _ = app.emit("terminal_output", serde_json::json!({
-                            "sessionId": session_id,
-                            "data": data.to_string()
-                        }));
-                    }
-                    Err(e) => {
-                        println!("[ERROR] PTY read error for session {}: {}", session_id, e);
-                        break;
-                    }
-                }
-            }
-            Err(e) => {
-                println!("[ERROR] Failed to clone PTY reader for session {}: {}", session_id, e);
-                break;
-            }
-        }
-        
-        // Small delay to prevent busy loop
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+    if let Some(parent) = settings_file.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create settings directory", e))?;
     }
     
-    // Remove from active handlers when done
-    {
-        let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
-        handlers.remove(&session_id);
-        println!("[DEBUG] Removed session {} from active handlers", session_id);
-    }
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| AppError::parse("Failed to serialize app settings", e))?;
     
-    println!("[DEBUG] PTY output handler ended for session: {}", session_id);
+    std::fs::write(&settings_file, content)
+        .map_err(|e| AppError::io("Failed to write app settings", e))?;
+    // Reloaded on next use
+    *CONTEXT_WINDOW_SIZES.lock().unwrap() = None;
     Ok(())
 }
 
-// PTY output handler without duplicate check (assumes caller already registered)
-async fn handle_pty_output_no_check(app: tauri::AppHandle, session_id: String) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    println!("[DEBUG] Starting PTY output handler (no duplicate check) for session: {}", session_id);
+// Capture the login shell's environment once. An interactive login shell is used so
+// variables exported from .zshrc/.bashrc are picked up as well as .profile ones.
+fn load_login_shell_env() -> HashMap<String, String> {
+    if cfg!(target_os = "windows") {
+        return std::env::vars().collect();
+    }
     
-    let sessions = TERMINAL_SESSIONS.read().await;
-    let session = sessions.get(&session_id).ok_or("Session not found")?;
-    let pty_master = session.pty_master.clone();
-    drop(sessions);
-    let mut buffer = [0u8; 8192];
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| {
+        if cfg!(target_os = "macos") { "/bin/zsh".to_string() } else { "/bin/bash".to_string() }
+    });
     
-    loop {
-        let pty = pty_master.lock().await;
-        match pty.try_clone_reader() {
-            Ok(mut reader) => {
-                drop(pty); // Release the lock before blocking read
-                
-                match reader.read(&mut buffer) {
-                    Ok(0) => {
-                        println!("[DEBUG] PTY EOF for session: {}", session_id);
-                        break; // EOF
-                    }
-                    Ok(n) => {
-                        let data = String::from_utf8_lossy(&buffer[..n]);
-                        
-                        // Parse for JSON events (including TodoWrite)
-                        let lines: Vec<&str> = data.lines().collect();
-                        for line in lines {
-                            let line_trimmed = line.trim();
-                            
-                            // Debug: Log any line that mentions todos or TodoWrite
-                            if line_trimmed.to_lowercase().contains("todo") {
-                                println!("[DEBUG] Found todo-related line in session {}: {}", session_id, line_trimmed);
-                            }
-                            
-                            // Check if this line contains TodoWrite JSON
-                            if line_trimmed.contains("TodoWrite") && line_trimmed.contains("tool_use") {
-                                println!("[DEBUG] Detected TodoWrite tool usage in session {}", session_id);
-                                if let Err(e) = handle_todowrite_in_terminal(&app, &session_id, line_trimmed).await {
-                                    println!("[ERROR] Failed to handle TodoWrite in terminal session {}: {}", session_id, e);
-                                } else {
-                                    println!("[SUCCESS] Successfully processed TodoWrite in terminal session {}", session_id);
-                                }
-                            }
-                            
-                            // Also check for human-readable todo format from Claude
-                            if line_trimmed.contains("Update Todos") || line_trimmed.starts_with("     ☐ ") {
-                                if let Err(e) = handle_human_readable_todos(&app, &session_id, &data).await {
-                                    println!("[ERROR] Failed to handle human-readable todos in session {}: {}", session_id, e);
-                                }
-                            }
-                        }
-                        
-                        // Emit data to frontend
-                        let _ = app.emit("terminal_output", serde_json::json!({
-                            "sessionId": session_id,
-                            "data": data.to_string()
-                        }));
-                    }
-                    Err(e) => {
-                        eprintln!("[ERROR] Failed to read from PTY for session {}: {}", session_id, e);
-                        break;
-                    }
-                }
+    // The markers separate the env dump from anything the rc files print
+    let marker = "__CLAUDE_GUI_ENV__";
+    let child = Command::new(&shell)
+        .args(["-i", "-l", "-c", &format!("echo {}; env -0; echo; echo {}", marker, marker)])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+    
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            println!("[WARN] Could not start login shell {}: {}", shell, e);
+            return std::env::vars().collect();
+        }
+    };
+    
+    // Read on a separate thread until the closing marker, rather than EOF: a background job
+    // started by an rc file can keep the pipe open after the shell has exited
+    let mut pipe = child.stdout.take();
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let mut chunk = [0u8; 8192];
+        while let Some(pipe) = pipe.as_mut() {
+            match std::io::Read::read(pipe, &mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => buffer.extend_from_slice(&chunk[..read]),
             }
-            Err(e) => {
-                eprintln!("[ERROR] Failed to clone PTY reader for session {}: {}", session_id, e);
+            if String::from_utf8_lossy(&buffer).matches(marker).count() >= 2 {
                 break;
             }
         }
-        
-        // Small delay to prevent busy loop
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
-    }
+        let _ = tx.send(buffer);
+    });
     
-    // Remove from active handlers when done
-    {
-        let mut handlers = ACTIVE_OUTPUT_HANDLERS.write().await;
-        handlers.remove(&session_id);
-        println!("[DEBUG] Removed session {} from active handlers", session_id);
+    // Don't let a slow or stuck shell profile hang the app; the reader is left detached
+    let output = rx.recv_timeout(std::time::Duration::from_secs(5));
+    let _ = child.kill();
+    let _ = child.wait();
+    let output = match output {
+        Ok(output) => output,
+        Err(_) => {
+            println!("[WARN] Login shell {} timed out, using the app environment", shell);
+            return std::env::vars().collect();
+        }
+    };
+    
+    let stdout = String::from_utf8_lossy(&output);
+    let mut sections = stdout.split(marker);
+    let dump = match (sections.next(), sections.next()) {
+        (Some(_), Some(dump)) => dump.trim_start_matches('\n'),
+        _ => return std::env::vars().collect(),
+    };
+    
+    let mut env: HashMap<String, String> = std::env::vars().collect();
+    for entry in dump.split('\0') {
+        if let Some((key, value)) = entry.split_once('=') {
+            if !key.is_empty() && !key.contains('\n') {
+                env.insert(key.to_string(), value.to_string());
+            }
+        }
     }
     
-    println!("[DEBUG] PTY output handler (no check) ended for session: {}", session_id);
-    Ok(())
+    println!("[INFO] Loaded {} environment variables from {}", env.len(), shell);
+    env
 }
 
-// Human-readable todo parsing
-async fn handle_human_readable_todos(
-    app: &tauri::AppHandle,
-    session_id: &str,
-    terminal_data: &str
-) -> Result<(), String> {
-    static mut LAST_PROCESSED_CONTENT: Option<String> = None;
+// Every place the claude CLI might be installed. GUI apps on macOS don't get the
+// shell PATH, so we can't rely on a bare `claude` resolving.
+fn discover_claude_binaries() -> Vec<String> {
+    let mut candidates: Vec<std::path::PathBuf> = Vec::new();
     
-    // Prevent duplicate processing
-    unsafe {
-        if let Some(ref last_content) = LAST_PROCESSED_CONTENT {
-            if last_content == terminal_data {
-                return Ok(());
+    if let Some(found) = find_on_path("claude") {
+        candidates.push(std::path::PathBuf::from(found));
+    }
+    
+    if let Some(home_dir) = dirs::home_dir() {
+        candidates.push(home_dir.join(".claude").join("local").join("claude"));
+        
+        // nvm installs, newest node version first
+        if let Ok(entries) = std::fs::read_dir(home_dir.join(".nvm").join("versions").join("node")) {
+            let mut versions: Vec<std::path::PathBuf> = entries.flatten().map(|e| e.path()).collect();
+            versions.sort();
+            for version in versions.iter().rev() {
+                candidates.push(version.join("bin").join("claude"));
             }
         }
-        LAST_PROCESSED_CONTENT = Some(terminal_data.to_string());
+        
+        candidates.push(home_dir.join(".npm-global").join("bin").join("claude"));
+        candidates.push(home_dir.join(".local").join("bin").join("claude"));
+        candidates.push(home_dir.join(".bun").join("bin").join("claude"));
     }
     
-    println!("[INFO] Processing human-readable todos from session: {}", session_id);
-    
-    // Check if this looks like a todo update section
-    if !terminal_data.contains("Update Todos") {
-        return Ok(());
+    if cfg!(target_os = "windows") {
+        // npm puts .cmd shims straight into its prefix directory on Windows
+        if let Some(app_data) = std::env::var_os("APPDATA") {
+            candidates.push(std::path::Path::new(&app_data).join("npm").join("claude.cmd"));
+        }
+        if let Some(home_dir) = dirs::home_dir() {
+            candidates.push(home_dir.join(".local").join("bin").join("claude.exe"));
+        }
+    } else {
+        candidates.push(std::path::PathBuf::from("/usr/local/bin/claude"));
+        candidates.push(std::path::PathBuf::from("/opt/homebrew/bin/claude"));
+        candidates.push(std::path::PathBuf::from("/usr/bin/claude"));
     }
     
-    let mut todos = Vec::new();
-    let mut todo_counter = 1;
-    
-    // Parse todo items from the text
-    for line in terminal_data.lines() {
-        let line = line.trim();
-        
-        // Look for todo items starting with ☐ 
-        if line.starts_with("☐ ") || line.contains("☐ ") {
-            let content = line
-                .replace("☐ ", "")
-                .replace("     ", "")
-                .trim()
-                .to_string();
-            
-            if !content.is_empty() && content.len() > 10 { // Filter out very short items
-                let todo = Todo {
-                    id: format!("human-{}-{}", session_id, todo_counter),
-                    content,
-                    status: "pending".to_string(),
-                    priority: "medium".to_string(),
-                    created_at: chrono::Utc::now().to_rfc3339(),
-                    session_id: Some(session_id.to_string()),
-                };
-                todos.push(todo);
-                todo_counter += 1;
+    // Global npm prefix, wherever the user pointed it
+    let npm = if cfg!(target_os = "windows") { "npm.cmd" } else { "npm" };
+    if let Ok(output) = Command::new(npm).envs(LOGIN_SHELL_ENV.iter()).args(["prefix", "-g"]).output() {
+        if output.status.success() {
+            let prefix = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if !prefix.is_empty() {
+                if cfg!(target_os = "windows") {
+                    candidates.push(std::path::Path::new(&prefix).join("claude.cmd"));
+                } else {
+                    candidates.push(std::path::Path::new(&prefix).join("bin").join("claude"));
+                }
             }
         }
     }
     
-    if !todos.is_empty() {
-        println!("[INFO] Parsed {} human-readable todos", todos.len());
-        
-        // Get project path and save todos
-        let project_path = get_session_project_path(session_id).await?;
-        
-        // Save the todos directly to the project directory (bypass get_real_project_path)
-        if let Err(e) = save_todos_directly(&project_path, todos.clone()).await {
-            println!("[ERROR] Failed to save human-readable todos: {}", e);
-            return Err(e);
+    let mut found = Vec::new();
+    for candidate in candidates {
+        let path = candidate.to_string_lossy().to_string();
+        if candidate.is_file() && !found.contains(&path) {
+            found.push(path);
+        }
+    }
+    found
+}
+
+// PTY command for the claude CLI. On Windows npm installs a .cmd shim, which
+// ConPTY can't launch directly, so it goes through cmd.exe.
+fn claude_pty_command() -> CommandBuilder {
+    let binary = claude_binary();
+    let lower = binary.to_lowercase();
+    
+    if cfg!(target_os = "windows") && (lower.ends_with(".cmd") || lower.ends_with(".bat")) {
+        let mut cmd = CommandBuilder::new("cmd.exe");
+        cmd.arg("/C");
+        cmd.arg(binary);
+        cmd
+    } else {
+        CommandBuilder::new(binary)
+    }
+}
+
+// The claude binary to spawn: the configured path if set, else the first one discovered.
+// Resolved at startup; the lock is never held while discovery runs its subprocesses.
+fn claude_binary() -> String {
+    let cached = CLAUDE_BINARY.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(binary) = cached {
+        return binary;
+    }
+    
+    let binary = resolve_claude_binary();
+    *CLAUDE_BINARY.lock().unwrap_or_else(|e| e.into_inner()) = Some(binary.clone());
+    binary
+}
+
+fn resolve_claude_binary() -> String {
+    let configured = read_app_settings().ok()
+        .and_then(|settings| settings.claude_binary_path)
+        .filter(|path| std::path::Path::new(path).is_file());
+    
+    let binary = configured
+        .or_else(|| discover_claude_binaries().into_iter().next())
+        .unwrap_or_else(|| "claude".to_string());
+    
+    println!("[INFO] Using claude binary: {}", binary);
+    binary
+}
+
+#[tauri::command]
+async fn find_claude_binaries() -> Result<Vec<String>, AppError> {
+    Ok(discover_claude_binaries())
+}
+
+#[tauri::command]
+async fn get_claude_binary_path() -> Result<serde_json::Value, AppError> {
+    let settings = read_app_settings()?;
+    Ok(serde_json::json!({
+        "configuredPath": settings.claude_binary_path,
+        "resolvedPath": claude_binary(),
+    }))
+}
+
+#[tauri::command]
+async fn set_claude_binary_path(path: Option<String>) -> Result<(), AppError> {
+    let path = path.filter(|p| !p.trim().is_empty());
+    
+    if let Some(binary) = &path {
+        if !std::path::Path::new(binary).is_file() {
+            return Err(AppError::PathNotFound(format!("Claude binary not found: {}", binary)));
         }
-        
-        // Emit update event
-        let _ = app.emit("todos_updated", serde_json::json!({
-            "projectPath": project_path,
-            "sessionId": session_id,
-            "todos": todos
-        }));
-        
-        println!("[SUCCESS] Successfully processed {} human-readable todos", todos.len());
     }
     
+    let mut settings = read_app_settings()?;
+    settings.claude_binary_path = path;
+    write_app_settings(&settings)?;
+    
+    let binary = tokio::task::spawn_blocking(resolve_claude_binary).await
+        .map_err(|e| AppError::Other(format!("Resolving the claude binary failed: {}", e)))?;
+    *CLAUDE_BINARY.lock().unwrap_or_else(|e| e.into_inner()) = Some(binary);
     Ok(())
 }
 
-// TodoWrite tool handling
-async fn handle_todowrite_in_terminal(
-    app: &tauri::AppHandle,
-    session_id: &str,
-    json_line: &str
-) -> Result<(), String> {
-    println!("[INFO] Processing TodoWrite from terminal session: {}", session_id);
-    println!("[DEBUG] JSON line: {}", json_line);
+// The effective pricing table: user overrides plus the built-in prices they don't replace
+#[tauri::command]
+async fn get_model_pricing() -> Result<HashMap<String, ModelPricing>, AppError> {
+    let mut pricing: HashMap<String, ModelPricing> = DEFAULT_MODEL_PRICING.iter()
+        .map(|(name, pricing)| (name.to_string(), *pricing))
+        .collect();
+    pricing.extend(read_app_settings()?.model_pricing);
+    Ok(pricing)
+}
+
+#[tauri::command]
+async fn set_model_pricing(pricing: HashMap<String, ModelPricing>) -> Result<(), AppError> {
+    let invalid = pricing.values().any(|p| {
+        [p.input, p.output, p.cache_write, p.cache_read].iter().any(|rate| !rate.is_finite() || *rate < 0.0)
+    });
+    if invalid {
+        return Err(AppError::InvalidInput("Prices must be non-negative numbers".to_string()));
+    }
     
-    // Parse the JSON line to extract TodoWrite data
-    if let Ok(claude_event) = serde_json::from_str::<ClaudeJsonEvent>(json_line) {
-        println!("[DEBUG] Successfully parsed Claude event: {}", claude_event.event_type);
-        if claude_event.event_type == "message_stream" {
-            if let Some(message) = &claude_event.message {
-                // Parse message content to extract tool usage
-                if let Ok(content_value) = serde_json::from_str::<serde_json::Value>(&message.content) {
-                    if let Some(content_array) = content_value.as_array() {
-                        for item in content_array {
-                            if let Some(item_type) = item.get("type").and_then(|t| t.as_str()) {
-                                if item_type == "tool_use" {
-                                    if let (Some(name), Some(input)) = (
-                                        item.get("name").and_then(|n| n.as_str()),
-                                        item.get("input")
-                                    ) {
-                                        if name == "TodoWrite" {
-                                            if let Some(todos_data) = input.get("todos") {
-                                                // Get project path from session
-                                                let project_path = get_session_project_path(session_id).await?;
-                                                
-                                                // Process the todos
-                                                return handle_todowrite_tool(app, &project_path, session_id, todos_data).await;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+    let mut settings = read_app_settings()?;
+    settings.model_pricing = pricing;
+    write_app_settings(&settings)
+}
+
+#[tauri::command]
+async fn get_context_window_sizes() -> Result<HashMap<String, u64>, AppError> {
+    Ok(read_app_settings()?.context_window_sizes)
+}
+
+#[tauri::command]
+async fn set_context_window_sizes(sizes: HashMap<String, u64>) -> Result<(), AppError> {
+    if sizes.values().any(|size| *size == 0) {
+        return Err(AppError::InvalidInput("Context window sizes must be greater than zero".to_string()));
+    }
+    
+    let mut settings = read_app_settings()?;
+    settings.context_window_sizes = sizes;
+    write_app_settings(&settings)
+}
+
+#[tauri::command]
+async fn set_terminal_paste_rate(bytes_per_sec: Option<u64>) -> Result<(), AppError> {
+    let mut settings = read_app_settings()?;
+    settings.terminal_paste_bytes_per_sec = bytes_per_sec.filter(|rate| *rate > 0);
+    write_app_settings(&settings)
+}
+
+#[tauri::command]
+async fn get_terminal_idle_timeout() -> Result<Option<u64>, AppError> {
+    Ok(read_app_settings()?.terminal_idle_timeout_minutes)
+}
+
+#[tauri::command]
+async fn set_terminal_idle_timeout(minutes: Option<u64>) -> Result<(), AppError> {
+    let mut settings = read_app_settings()?;
+    settings.terminal_idle_timeout_minutes = minutes.filter(|m| *m > 0);
+    write_app_settings(&settings)
+}
+
+// Per-project GUI settings, stored in <project>/.claude/gui-settings.json
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct ProjectGuiSettings {
+    #[serde(default)]
+    default_model: Option<String>,
+    #[serde(default)]
+    tool_policy: ToolPolicy,
+    #[serde(default)]
+    extra_dirs: Vec<String>,
+    #[serde(default)]
+    system_prompt: Option<String>,
+    #[serde(default)]
+    append_system_prompt: Option<String>,
+    // Extra environment for terminal sessions in this project (e.g. ANTHROPIC_MODEL, HTTP_PROXY).
+    // Kept in the user-scoped env file, never in the repo; only read here to migrate old files
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    env: HashMap<String, String>,
+    // Respawn crashed Claude terminal sessions with --resume
+    #[serde(default)]
+    auto_restart_claude: bool,
+    // Run prompts in plan mode unless the request says otherwise
+    #[serde(default)]
+    plan_mode: bool,
+    // IDE command used to open this project (e.g. "code", "cursor")
+    #[serde(default)]
+    preferred_ide: Option<String>,
+}
+
+// Tools pre-authorized or blocked for Claude, e.g. "Bash(git *)" or "WebFetch"
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct ToolPolicy {
+    #[serde(default)]
+    allowed_tools: Vec<String>,
+    #[serde(default)]
+    disallowed_tools: Vec<String>,
+}
+
+impl ToolPolicy {
+    fn merged_with(&self, allowed: &[String], disallowed: &[String]) -> ToolPolicy {
+        let mut merged = self.clone();
+        for tool in allowed {
+            if !merged.allowed_tools.contains(tool) {
+                merged.allowed_tools.push(tool.clone());
             }
         }
+        for tool in disallowed {
+            if !merged.disallowed_tools.contains(tool) {
+                merged.disallowed_tools.push(tool.clone());
+            }
+        }
+        merged
+    }
+}
+
+// Real project path (the key for the user-scoped env file) and its settings file
+async fn get_project_settings_location(project_path: String) -> Result<(String, std::path::PathBuf), AppError> {
+    let real_path = match get_real_project_path(project_path).await? {
+        Some(path) => path,
+        None => return Err(AppError::PathNotFound("Could not find real project path".to_string()))
+    };
+    
+    let settings_file = std::path::Path::new(&real_path).join(".claude").join("gui-settings.json");
+    Ok((real_path, settings_file))
+}
+
+// Project env often holds API keys, so it lives in ~/.claude/gui-project-env.json (owner-only)
+// keyed by real project path, instead of in the project's .claude/ where it could be committed
+fn get_project_env_file_path() -> Result<std::path::PathBuf, AppError> {
+    let home_dir = dirs::home_dir().ok_or(AppError::PathNotFound("Could not find home directory".to_string()))?;
+    Ok(home_dir.join(".claude").join("gui-project-env.json"))
+}
+
+// Opens `path` for writing, readable only by the current user (0600 on unix). With
+// `create_new` an existing file is an error, which keeps shared temp dirs safe
+fn create_private_file(path: &std::path::Path, create_new: bool) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true);
+    if create_new {
+        options.create_new(true);
     } else {
-        println!("[DEBUG] Failed to parse JSON line as ClaudeJsonEvent: {}", json_line);
+        options.create(true).truncate(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(path)?;
+    // mode() only applies on creation, so tighten a file that already existed
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(file)
+}
+
+fn read_project_env_store() -> Result<HashMap<String, HashMap<String, String>>, AppError> {
+    let path = get_project_env_file_path()?;
+    if !path.exists() {
+        return Ok(HashMap::new());
     }
     
-    Ok(())
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| AppError::io("Failed to read project env", e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| AppError::parse("Failed to parse project env", e))
 }
 
-async fn save_todos_directly(project_path: &str, todos: Vec<Todo>) -> Result<(), String> {
-    // Create todos file path directly without resolving through get_real_project_path
-    let todos_file_path = format!("{}/.claude-todos.json", project_path);
+fn write_project_env_store(store: &HashMap<String, HashMap<String, String>>) -> Result<(), AppError> {
+    let path = get_project_env_file_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create settings directory", e))?;
+    }
     
-    println!("[DEBUG] Saving todos directly to: {}", todos_file_path);
+    let content = serde_json::to_string_pretty(store)
+        .map_err(|e| AppError::parse("Failed to serialize project env", e))?;
     
-    // Ensure directory exists
-    let project_dir = std::path::Path::new(project_path);
-    if !project_dir.exists() {
-        return Err(format!("Project directory does not exist: {}", project_path));
+    // Write a private temp file and rename it over, so the file is never readable by others
+    let temp_path = path.with_extension("json.tmp");
+    let mut file = create_private_file(&temp_path, false)
+        .map_err(|e| AppError::io("Failed to write project env", e))?;
+    file.write_all(content.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|e| AppError::io("Failed to write project env", e))?;
+    drop(file);
+    
+    std::fs::rename(&temp_path, &path)
+        .map_err(|e| AppError::io("Failed to write project env", e))
+}
+
+fn update_project_env(real_path: &str, update: impl FnOnce(&mut HashMap<String, String>)) -> Result<(), AppError> {
+    let _guard = PROJECT_ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut store = read_project_env_store()?;
+    let env = store.entry(real_path.to_string()).or_default();
+    update(env);
+    if env.is_empty() {
+        store.remove(real_path);
+    }
+    write_project_env_store(&store)
+}
+
+fn write_project_settings_file(settings_file: &std::path::Path, settings: &ProjectGuiSettings) -> Result<(), AppError> {
+    if let Some(parent) = settings_file.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::io("Failed to create settings directory", e))?;
     }
     
-    // Load existing todos
-    let mut all_todos = if std::path::Path::new(&todos_file_path).exists() {
-        match std::fs::read_to_string(&todos_file_path) {
-            Ok(content) => {
-                serde_json::from_str::<Vec<Todo>>(&content).unwrap_or_else(|_| Vec::new())
-            }
-            Err(_) => Vec::new()
-        }
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| AppError::parse("Failed to serialize project settings", e))?;
+    
+    std::fs::write(settings_file, content)
+        .map_err(|e| AppError::io("Failed to write project settings", e))
+}
+
+async fn read_project_settings(project_path: String) -> Result<ProjectGuiSettings, AppError> {
+    let (real_path, settings_file) = get_project_settings_location(project_path).await?;
+    
+    let mut settings: ProjectGuiSettings = if settings_file.exists() {
+        let content = std::fs::read_to_string(&settings_file)
+            .map_err(|e| AppError::io("Failed to read project settings", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| AppError::parse("Failed to parse project settings", e))?
     } else {
-        Vec::new()
+        ProjectGuiSettings::default()
     };
     
-    // Add new todos (replace any with matching IDs)
-    for new_todo in todos {
-        // Remove any existing todo with the same ID
-        all_todos.retain(|existing| existing.id != new_todo.id);
-        // Add the new todo
-        all_todos.push(new_todo);
+    // Older versions kept env in the repo file: move it out and scrub the file
+    let legacy_env = std::mem::take(&mut settings.env);
+    if !legacy_env.is_empty() {
+        update_project_env(&real_path, |env| {
+            for (key, value) in legacy_env {
+                env.entry(key).or_insert(value);
+            }
+        })?;
+        write_project_settings_file(&settings_file, &settings)?;
+        println!("[INFO] Moved project env out of {}", settings_file.display());
     }
     
-    // Save back to file
-    let json_content = serde_json::to_string_pretty(&all_todos)
-        .map_err(|e| format!("Failed to serialize todos: {}", e))?;
+    settings.env = read_project_env_store()?.remove(&real_path).unwrap_or_default();
+    Ok(settings)
+}
+
+async fn write_project_settings(project_path: String, settings: &ProjectGuiSettings) -> Result<(), AppError> {
+    let (real_path, settings_file) = get_project_settings_location(project_path).await?;
     
-    std::fs::write(&todos_file_path, json_content)
-        .map_err(|e| format!("Failed to write todos file: {}", e))?;
+    let mut settings = settings.clone();
+    let new_env = std::mem::take(&mut settings.env);
+    update_project_env(&real_path, |env| *env = new_env)?;
     
-    println!("[INFO] Successfully saved {} todos to {}", all_todos.len(), todos_file_path);
-    Ok(())
+    write_project_settings_file(&settings_file, &settings)
 }
 
-async fn get_session_project_path(session_id: &str) -> Result<String, String> {
-    let sessions = TERMINAL_SESSIONS.read().await;
-    if let Some(session) = sessions.get(session_id) {
-        println!("[DEBUG] Found session project path: {}", session.project_path);
-        Ok(session.project_path.clone())
-    } else {
-        println!("[ERROR] Session {} not found in terminal sessions", session_id);
-        Err(format!("Session {} not found", session_id))
-    }
+#[tauri::command]
+async fn get_project_settings(project_path: String) -> Result<ProjectGuiSettings, AppError> {
+    read_project_settings(project_path).await
 }
 
-async fn handle_todowrite_tool(
-    app: &tauri::AppHandle,
-    project_path: &str,
-    session_id: &str,
-    todos_data: &serde_json::Value
-) -> Result<(), String> {
-    println!("[INFO] Processing TodoWrite tool for session: {}", session_id);
-    
-    if let Some(todos_array) = todos_data.as_array() {
-        let mut parsed_todos = Vec::new();
-        
-        for todo_item in todos_array {
-            if let (Some(content), Some(status), Some(priority), Some(id)) = (
-                todo_item.get("content").and_then(|c| c.as_str()),
-                todo_item.get("status").and_then(|s| s.as_str()),
-                todo_item.get("priority").and_then(|p| p.as_str()),
-                todo_item.get("id").and_then(|i| i.as_str())
-            ) {
-                let todo = Todo {
-                    id: id.to_string(),
-                    content: content.to_string(),
-                    status: status.to_string(),
-                    priority: priority.to_string(),
-                    created_at: chrono::Utc::now().to_rfc3339(),
-                    session_id: Some(session_id.to_string()),
-                };
-                parsed_todos.push(todo);
-            }
-        }
-        
-        // Save the todos
-        if let Err(e) = save_project_todos(project_path.to_string(), parsed_todos.clone()).await {
-            println!("[ERROR] Failed to save todos from TodoWrite: {}", e);
-            return Err(e);
-        }
-        
-        // Emit event for real-time UI update
-        let _ = app.emit("todos_updated", serde_json::json!({
-            "sessionId": session_id,
-            "projectPath": project_path,
-            "todos": parsed_todos
-        }));
-        
-        println!("[INFO] Successfully processed {} todos from TodoWrite", parsed_todos.len());
+#[tauri::command]
+async fn save_project_settings(project_path: String, settings: ProjectGuiSettings) -> Result<(), AppError> {
+    if let Some(dir) = settings.extra_dirs.iter().find(|dir| !std::path::Path::new(dir).is_dir()) {
+        return Err(AppError::PathNotFound(format!("Directory does not exist: {}", dir)));
+    }
+    if let Some(key) = settings.env.keys().find(|key| key.is_empty() || key.contains('=')) {
+        return Err(AppError::InvalidInput(format!("Invalid environment variable name: {:?}", key)));
     }
     
-    Ok(())
+    let mut settings = settings;
+    settings.default_model = settings.default_model.filter(|m| !m.trim().is_empty());
+    settings.preferred_ide = settings.preferred_ide.filter(|ide| !ide.trim().is_empty());
+    settings.system_prompt = settings.system_prompt.filter(|p| !p.trim().is_empty());
+    settings.append_system_prompt = settings.append_system_prompt.filter(|p| !p.trim().is_empty());
+    write_project_settings(project_path, &settings).await
 }
 
-// Todo management functions
-async fn get_todos_file_path(project_path: String) -> Result<String, String> {
-    let real_path = match get_real_project_path(project_path).await? {
-        Some(path) => path,
-        None => return Err("Could not find real project path".to_string())
+#[tauri::command]
+async fn set_project_default_model(project_path: String, model: Option<String>) -> Result<(), AppError> {
+    let mut settings = read_project_settings(project_path.clone()).await?;
+    settings.default_model = model.filter(|m| !m.trim().is_empty());
+    write_project_settings(project_path, &settings).await
+}
+
+#[tauri::command]
+async fn get_project_tool_policy(project_path: String) -> Result<ToolPolicy, AppError> {
+    Ok(read_project_settings(project_path).await?.tool_policy)
+}
+
+#[tauri::command]
+async fn set_project_tool_policy(
+    project_path: String,
+    allowed_tools: Vec<String>,
+    disallowed_tools: Vec<String>
+) -> Result<(), AppError> {
+    let mut settings = read_project_settings(project_path.clone()).await?;
+    settings.tool_policy = ToolPolicy {
+        allowed_tools: allowed_tools.into_iter().filter(|t| !t.trim().is_empty()).collect(),
+        disallowed_tools: disallowed_tools.into_iter().filter(|t| !t.trim().is_empty()).collect(),
     };
-    
-    Ok(format!("{}/.claude-todos.json", real_path))
+    write_project_settings(project_path, &settings).await
 }
 
 #[tauri::command]
-async fn load_project_todos(project_path: String) -> Result<Vec<Todo>, String> {
-    println!("[DEBUG] load_project_todos called with path: {}", project_path);
-    
-    // Try multiple possible locations for the todos file
-    let possible_paths = vec![
-        format!("{}/.claude-todos.json", project_path),
-        // If the project_path contains the transformed path, try to extract the real path
-        if project_path.contains("/.claude/projects/") {
-            // Extract real path from transformed path like: /home/user/.claude/projects/-home-user-repos-project
-            let parts: Vec<&str> = project_path.split("/.claude/projects/").collect();
-            if parts.len() == 2 {
-                let encoded_path = parts[1];
-                let real_path = encoded_path.replace("-", "/");
-                format!("{}/.claude-todos.json", real_path)
-            } else {
-                project_path.clone()
-            }
-        } else {
-            project_path.clone()
-        }
-    ];
-    
-    for todos_file in possible_paths {
-        println!("[DEBUG] Trying to load todos from: {}", todos_file);
-        
-        if std::path::Path::new(&todos_file).exists() {
-            println!("[DEBUG] Found todos file at: {}", todos_file);
-            
-            let content = std::fs::read_to_string(&todos_file)
-                .map_err(|e| format!("Failed to read todos file: {}", e))?;
-            
-            // Try to parse as direct Vec<Todo> first (new format)
-            if let Ok(todos) = serde_json::from_str::<Vec<Todo>>(&content) {
-                println!("[DEBUG] Loaded {} todos directly", todos.len());
-                return Ok(todos);
-            }
-            
-            // Fallback to old ProjectTodos format
-            if let Ok(project_todos) = serde_json::from_str::<ProjectTodos>(&content) {
-                println!("[DEBUG] Loaded {} todos from ProjectTodos format", project_todos.todos.len());
-                return Ok(project_todos.todos);
-            }
-            
-            return Err("Failed to parse todos file in any known format".to_string());
+async fn get_project_extra_dirs(project_path: String) -> Result<Vec<String>, AppError> {
+    Ok(read_project_settings(project_path).await?.extra_dirs)
+}
+
+#[tauri::command]
+async fn set_project_extra_dirs(project_path: String, extra_dirs: Vec<String>) -> Result<(), AppError> {
+    for dir in &extra_dirs {
+        if !std::path::Path::new(dir).is_dir() {
+            return Err(AppError::PathNotFound(format!("Directory does not exist: {}", dir)));
         }
     }
     
-    println!("[DEBUG] No todos file found in any of the attempted locations");
-    Ok(vec![])
+    let mut settings = read_project_settings(project_path.clone()).await?;
+    settings.extra_dirs = extra_dirs;
+    write_project_settings(project_path, &settings).await
 }
 
 #[tauri::command]
-async fn save_project_todos(project_path: String, todos: Vec<Todo>) -> Result<(), String> {
-    let todos_file = get_todos_file_path(project_path).await?;
-    
-    let project_todos = ProjectTodos {
-        todos,
-        last_updated: chrono::Utc::now().to_rfc3339(),
-    };
-    
-    let content = serde_json::to_string_pretty(&project_todos)
-        .map_err(|e| format!("Failed to serialize todos: {}", e))?;
-    
-    std::fs::write(&todos_file, content)
-        .map_err(|e| format!("Failed to write todos file: {}", e))?;
-    
-    Ok(())
+async fn get_project_system_prompts(project_path: String) -> Result<serde_json::Value, AppError> {
+    let settings = read_project_settings(project_path).await?;
+    Ok(serde_json::json!({
+        "systemPrompt": settings.system_prompt,
+        "appendSystemPrompt": settings.append_system_prompt
+    }))
 }
 
 #[tauri::command]
-async fn add_todo(
-    project_path: String, 
-    content: String, 
-    priority: String,
-    session_id: Option<String>
-) -> Result<Todo, String> {
-    let mut todos = load_project_todos(project_path.clone()).await?;
-    
-    let new_todo = Todo {
-        id: Uuid::new_v4().to_string(),
-        content,
-        status: "pending".to_string(),
-        priority,
-        created_at: chrono::Utc::now().to_rfc3339(),
-        session_id,
-    };
-    
-    todos.push(new_todo.clone());
-    save_project_todos(project_path, todos).await?;
-    
-    Ok(new_todo)
+async fn set_project_system_prompts(
+    project_path: String,
+    system_prompt: Option<String>,
+    append_system_prompt: Option<String>
+) -> Result<(), AppError> {
+    let mut settings = read_project_settings(project_path.clone()).await?;
+    settings.system_prompt = system_prompt.filter(|p| !p.trim().is_empty());
+    settings.append_system_prompt = append_system_prompt.filter(|p| !p.trim().is_empty());
+    write_project_settings(project_path, &settings).await
 }
 
 #[tauri::command]
-async fn update_todo_status(
-    project_path: String, 
-    todo_id: String, 
-    new_status: String
-) -> Result<(), String> {
-    let mut todos = load_project_todos(project_path.clone()).await?;
-    
-    if let Some(todo) = todos.iter_mut().find(|t| t.id == todo_id) {
-        todo.status = new_status;
-        save_project_todos(project_path, todos).await?;
-        Ok(())
-    } else {
-        Err("Todo not found".to_string())
-    }
+async fn set_project_auto_restart(project_path: String, enabled: bool) -> Result<(), AppError> {
+    let mut settings = read_project_settings(project_path.clone()).await?;
+    settings.auto_restart_claude = enabled;
+    write_project_settings(project_path, &settings).await
 }
 
 #[tauri::command]
-async fn delete_todo(project_path: String, todo_id: String) -> Result<(), String> {
-    let mut todos = load_project_todos(project_path.clone()).await?;
-    todos.retain(|t| t.id != todo_id);
-    save_project_todos(project_path, todos).await?;
-    Ok(())
+async fn get_project_env(project_path: String) -> Result<HashMap<String, String>, AppError> {
+    Ok(read_project_settings(project_path).await?.env)
+}
+
+#[tauri::command]
+async fn set_project_env(project_path: String, env: HashMap<String, String>) -> Result<(), AppError> {
+    if let Some(key) = env.keys().find(|key| key.is_empty() || key.contains('=')) {
+        return Err(AppError::InvalidInput(format!("Invalid environment variable name: {:?}", key)));
+    }
+    
+    let mut settings = read_project_settings(project_path.clone()).await?;
+    settings.env = env;
+    write_project_settings(project_path, &settings).await
 }
 
 fn main() {
@@ -2976,52 +11824,194 @@ fn main() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_notification::init())
+        .setup(|app| {
+            // Capturing the login shell environment and finding claude can take seconds;
+            // do both off the async runtime before the first command needs them
+            tauri::async_runtime::spawn_blocking(|| {
+                lazy_static::initialize(&LOGIN_SHELL_ENV);
+                claude_binary();
+            });
+            tauri::async_runtime::spawn(monitor_terminal_sessions(app.handle().clone()));
+            tauri::async_runtime::spawn(monitor_usage_budgets(app.handle().clone()));
+            tauri::async_runtime::spawn(monitor_project_usage(app.handle().clone()));
+            tauri::async_runtime::spawn(run_usage_indexer(app.handle().clone()));
+            tauri::async_runtime::spawn(sweep_write_streams());
+            let watcher_app = app.handle().clone();
+            std::thread::spawn(move || watch_claude_projects(watcher_app));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_claude_projects,
+            get_project_registry,
+            get_project_overview,
+            list_workspaces,
+            save_workspace,
+            delete_workspace,
+            pin_project,
+            set_project_display_name,
+            mark_project_opened,
+            get_recent_projects,
+            archive_project,
+            unarchive_project,
+            list_archived_projects,
+            import_project,
+            prepare_project_history_deletion,
+            delete_project_history,
             get_claude_version,
             get_claude_config,
             get_system_info,
             get_usage_statistics,
+            get_usage_blocks,
+            query_usage,
+            compare_usage,
+            get_usage_budgets,
+            set_usage_budgets,
+            watch_project_usage,
             update_claude_config,
             check_claude_updates,
             execute_claude_command,
             execute_claude_command_with_files,
             execute_claude_command_streaming,
+            fork_session,
+            get_session_forks,
+            approve_plan,
+            reject_plan,
+            execute_prompt_multi,
+            get_raw_json_outputs,
+            find_claude_binaries,
+            get_claude_binary_path,
+            set_claude_binary_path,
+            get_context_window_sizes,
+            set_context_window_sizes,
+            get_model_pricing,
+            set_model_pricing,
+            get_terminal_idle_timeout,
+            set_terminal_idle_timeout,
+            set_terminal_paste_rate,
+            set_tmux_sessions_enabled,
+            list_active_executions,
+            enqueue_prompt,
+            list_prompt_queue,
+            remove_queued_prompt,
+            get_session_cost,
+            get_chat_session_id,
+            clear_chat_session,
             read_conversation_file,
             get_project_sessions,
+            list_resumable_sessions,
             open_file_in_system,
             detect_available_ides,
             open_file_in_ide,
             open_project_in_ide,
+            open_project_in_terminal,
             get_file_info,
             get_project_files,
             get_claude_md_content,
             save_claude_md_content,
+            get_project_stats,
+            list_env_files,
+            read_env_file,
+            reveal_env_value,
+            update_env_file,
             check_claude_md_exists,
             create_claude_md_template,
             debug_project_path,
             get_real_project_path,
             create_new_project,
             create_enhanced_project,
+            create_project_from_git,
             select_directory,
             start_claude_session,
+            start_terminal_session,
             resume_claude_session,
             write_to_terminal,
+            paste_to_terminal,
+            list_terminal_sessions,
+            rename_terminal_session,
+            get_terminal_session_info,
+            get_terminal_scrollback,
+            restore_terminal_sessions,
+            start_terminal_recording,
+            stop_terminal_recording,
+            export_terminal_recording,
             resize_terminal,
+            signal_terminal,
             close_terminal_session,
             load_project_todos,
             save_project_todos,
             add_todo,
             update_todo_status,
             delete_todo,
+            set_project_default_model,
+            get_project_tool_policy,
+            set_project_tool_policy,
+            get_project_extra_dirs,
+            set_project_extra_dirs,
+            get_project_system_prompts,
+            set_project_system_prompts,
+            get_project_settings,
+            save_project_settings,
+            get_project_env,
+            set_project_env,
+            set_project_auto_restart,
             read_file_content,
             write_file_content,
             create_file,
             create_directory,
             delete_file,
             rename_file,
-            get_directory_tree
+            get_directory_tree,
+            get_directory_children,
+            watch_project_files,
+            unwatch_project_files,
+            search_project_content,
+            fuzzy_find_files,
+            read_file_range,
+            read_file_chunked,
+            read_file_binary,
+            get_granted_paths,
+            grant_path_access,
+            revoke_path_access,
+            copy_path,
+            apply_file_operations,
+            undo_last_file_operation,
+            get_directory_size,
+            diff_file,
+            diff_paths,
+            apply_patch,
+            get_recently_modified_files,
+            append_file_content,
+            open_write_stream,
+            write_stream_chunk,
+            close_write_stream,
+            abort_write_stream,
+            read_file_with_encoding,
+            get_image_thumbnail,
+            export_project_archive,
+            watch_claude_md,
+            unwatch_claude_md,
+            read_conversation_entries
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
\ No newline at end of file
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_app, event| {
+            // Don't leave claude processes orphaned when the app quits
+            if let tauri::RunEvent::Exit = event {
+                tauri::async_runtime::block_on(shutdown_terminal_sessions());
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn env_values_round_trip() {
+        for value in ["plain", "", "C:\\new", "two words", "a \"quoted\" # value", "line one\nline two", "trailing\\", "x=y", "it's"] {
+            let line = format_env_line("KEY", value);
+            assert_eq!(parse_env_assignment(&line), Some(("KEY".to_string(), value.to_string())), "line: {}", line);
+        }
+    }
+}
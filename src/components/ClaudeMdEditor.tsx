@@ -1,7 +1,9 @@
 'use client'
 
-import { useState, useEffect } from 'react'
+import { useState, useEffect, useRef } from 'react'
 import { invoke } from '@tauri-apps/api/core'
+import { getCurrentWindow } from '@tauri-apps/api/window'
+import { errorMessage } from '@/lib/appError'
 import { 
   FileText, 
   Edit3, 
@@ -39,6 +41,35 @@ export function ClaudeMdEditor({ selectedProject }: ClaudeMdEditorProps) {
     }
   }, [selectedProject])
 
+  // Read by the change listener without re-subscribing on every keystroke
+  const hasChangesRef = useRef(hasChanges)
+  hasChangesRef.current = hasChanges
+
+  // Pick up edits made outside the GUI (e.g. by Claude), unless there are unsaved edits here
+  useEffect(() => {
+    if (!selectedProject) return
+
+    const projectPath = selectedProject.path
+    const setupClaudeMdListener = async () => {
+      const unlisten = await getCurrentWindow().listen<any>('claude_md_changed', (event) => {
+        if (event.payload.projectPath !== projectPath || hasChangesRef.current) return
+        setExists(event.payload.content != null)
+        setContent(event.payload.content || '')
+      })
+      invoke('watch_claude_md', { projectPath })
+        .catch((error) => console.error('Failed to watch CLAUDE.md:', error))
+      return unlisten
+    }
+
+    let unlisten: (() => void) | null = null
+    setupClaudeMdListener().then((fn) => { unlisten = fn })
+
+    return () => {
+      if (unlisten) unlisten()
+      invoke('unwatch_claude_md', { projectPath }).catch(() => {})
+    }
+  }, [selectedProject])
+
   const loadClaudeMd = async () => {
     if (!selectedProject) return
 
@@ -72,7 +103,7 @@ export function ClaudeMdEditor({ selectedProject }: ClaudeMdEditorProps) {
       setHasChanges(false)
     } catch (error) {
       console.error('Failed to load CLAUDE.md:', error)
-      setDebugInfo(`Error: ${error}`)
+      setDebugInfo(`Error: ${errorMessage(error)}`)
     } finally {
       setLoading(false)
     }
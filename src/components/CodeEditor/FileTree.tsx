@@ -2,6 +2,7 @@
 
 import { useState, useEffect, useMemo } from 'react'
 import { invoke } from '@tauri-apps/api/core'
+import { errorMessage } from '@/lib/appError'
 import { 
   File, 
   Folder, 
@@ -97,7 +98,7 @@ export function FileTree({
       // Auto-expand the root directory
       setExpandedPaths(new Set([data.path]))
     } catch (err) {
-      setError(err as string)
+      setError(errorMessage(err))
       console.error('Failed to load directory tree:', err)
     } finally {
       setLoading(false)
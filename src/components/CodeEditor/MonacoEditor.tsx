@@ -3,6 +3,7 @@
 import { useEffect, useRef, useState } from 'react'
 import Editor, { Monaco } from '@monaco-editor/react'
 import { invoke } from '@tauri-apps/api/core'
+import { errorMessage } from '@/lib/appError'
 import { Save, RotateCcw, Type, Search, Settings, Maximize, Minimize } from 'lucide-react'
 import { useHotkeys } from 'react-hotkeys-hook'
 
@@ -15,6 +16,14 @@ interface MonacoEditorProps {
   onSave?: (content: string) => void
 }
 
+// read_file_with_encoding result
+interface DecodedFile {
+  content: string
+  encoding: string
+  has_bom: boolean
+  had_errors: boolean
+}
+
 export function MonacoEditor({ 
   filePath, 
   language, 
@@ -25,6 +34,8 @@ export function MonacoEditor({
 }: MonacoEditorProps) {
   const [content, setContent] = useState<string>('')
   const [originalContent, setOriginalContent] = useState<string>('')
+  // Encoding the file was read in, so saving keeps it (and its BOM) unchanged
+  const [fileEncoding, setFileEncoding] = useState<DecodedFile | null>(null)
   const [loading, setLoading] = useState(true)
   const [saving, setSaving] = useState(false)
   const [error, setError] = useState<string | null>(null)
@@ -129,12 +140,13 @@ export function MonacoEditor({
     try {
       setLoading(true)
       setError(null)
-      const fileContent = await invoke<string>('read_file_content', { filePath })
-      setContent(fileContent)
-      setOriginalContent(fileContent)
+      const file = await invoke<DecodedFile>('read_file_with_encoding', { filePath })
+      setFileEncoding(file)
+      setContent(file.content)
+      setOriginalContent(file.content)
       setHasUnsavedChanges(false)
     } catch (err) {
-      setError(err as string)
+      setError(errorMessage(err))
       console.error('Failed to load file:', err)
     } finally {
       setLoading(false)
@@ -145,13 +157,22 @@ export function MonacoEditor({
     if (!filePath || saving) return
     
     try {
+      if (fileEncoding?.had_errors) {
+        setError(`This file isn't valid ${fileEncoding.encoding}; saving it would change bytes that couldn't be decoded`)
+        return
+      }
       setSaving(true)
-      await invoke('write_file_content', { filePath, content })
+      await invoke('write_file_content', {
+        filePath,
+        content,
+        encoding: fileEncoding?.encoding ?? null,
+        bom: fileEncoding?.has_bom ?? null
+      })
       setOriginalContent(content)
       setHasUnsavedChanges(false)
       onSave?.(content)
     } catch (err) {
-      setError(err as string)
+      setError(errorMessage(err))
       console.error('Failed to save file:', err)
     } finally {
       setSaving(false)
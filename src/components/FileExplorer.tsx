@@ -2,6 +2,7 @@
 
 import { useState, useEffect, useCallback } from 'react'
 import { invoke } from '@tauri-apps/api/core'
+import { errorMessage } from '@/lib/appError'
 import { FileText, Loader2, AlertCircle } from 'lucide-react'
 import { FileTree } from './CodeEditor/FileTree'
 import { MonacoEditor } from './CodeEditor/MonacoEditor'
@@ -121,7 +122,7 @@ export function FileExplorer({ selectedProject }: FileExplorerProps) {
       setNewFileName('')
     } catch (error) {
       console.error('Failed to create file/directory:', error)
-      alert(`Failed to create ${isCreatingFile.isDirectory ? 'directory' : 'file'}: ${error}`)
+      alert(`Failed to create ${isCreatingFile.isDirectory ? 'directory' : 'file'}: ${errorMessage(error)}`)
     }
   }
 
@@ -147,7 +148,7 @@ export function FileExplorer({ selectedProject }: FileExplorerProps) {
       }
     } catch (error) {
       console.error('Failed to delete file:', error)
-      alert(`Failed to delete file: ${error}`)
+      alert(`Failed to delete file: ${errorMessage(error)}`)
     }
   }
 
@@ -176,7 +177,7 @@ export function FileExplorer({ selectedProject }: FileExplorerProps) {
       }
     } catch (error) {
       console.error('Failed to rename file:', error)
-      alert(`Failed to rename file: ${error}`)
+      alert(`Failed to rename file: ${errorMessage(error)}`)
     }
   }
 
@@ -302,7 +302,7 @@ export function SystemSettings({ selectedProject }: SystemSettingsProps) {
               {Object.entries(usageStats.models_used).map(([model, count]) => (
                 <div key={model} className="flex items-center justify-between">
                   <span className="text-foreground">{model || 'Unknown'}</span>
-                  <span className="text-muted-foreground">{count} sessions</span>
+                  <span className="text-muted-foreground">{count} messages</span>
                 </div>
               ))}
             </div>
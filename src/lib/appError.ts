@@ -0,0 +1,32 @@
+// Mirrors the Rust `AppError` enum returned by every Tauri command
+export type AppErrorKind =
+  | 'ClaudeNotInstalled'
+  | 'AuthRequired'
+  | 'RateLimited'
+  | 'Overloaded'
+  | 'BudgetExceeded'
+  | 'PathNotFound'
+  | 'AlreadyExists'
+  | 'AccessDenied'
+  | 'InvalidInput'
+  | 'NotFound'
+  | 'SessionDead'
+  | 'ProcessFailed'
+  | 'Io'
+  | 'Parse'
+  | 'Other'
+
+export interface AppError {
+  kind: AppErrorKind
+  message: string
+}
+
+export function isAppError(error: unknown): error is AppError {
+  return typeof error === 'object' && error !== null && 'kind' in error && 'message' in error
+}
+
+export function errorMessage(error: unknown): string {
+  if (isAppError(error)) return error.message
+  if (error instanceof Error) return error.message
+  return String(error)
+}